@@ -1,75 +1,369 @@
-use clap::{Parser, Subcommand};
+use crate::env_store::EnvStore;
+use crate::models::{
+    ClusterSpec, OffsetSpec, PartitionOffsets, SaslConfig, SaslMechanism, SslConfig, parse_brokers,
+};
+use crate::output::{OutputFormat, TimeFormat};
+use crate::query::{Pattern, SelectQuery};
+use anyhow::{Context, Result, anyhow};
+use clap::parser::ValueSource;
+use clap::{
+    Arg, ArgMatches, Command, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+};
+use rdkafka::config::ClientConfig;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// librdkafka's default receive.message.max.bytes.
+const DEFAULT_RECEIVE_MESSAGE_MAX_BYTES: u64 = 100_000_000;
+/// librdkafka caps fetch sizes (and receive.message.max.bytes) at 2^31 - 1.
+const MAX_FETCH_BYTES: u64 = i32::MAX as u64 - 512;
 
 #[derive(Parser, Debug, Clone)]
 #[command(name = "rkl")]
 #[command(about = "Search Kafka topics without committing offsets", long_about = None)]
+#[command(disable_version_flag = true)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Commands>,
+
+    /// Print the rkl and linked librdkafka versions
+    #[arg(short = 'V', long)]
+    pub version: bool,
+
+    /// Open the TUI with a saved query (by name) loaded into the editor
+    #[arg(long)]
+    pub saved: Option<String>,
+
+    /// Run TUI queries against an NDJSON dump instead of a broker (see `rkl run --from-file`)
+    #[arg(long)]
+    pub from_file: Option<PathBuf>,
+
+    /// Truncate each value the TUI keeps to this many bytes (see `rkl run --max-value-bytes`)
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_value_bytes: Option<u64>,
+
+    /// Show the TUI run's message count in the terminal title (an OSC escape
+    /// some terminals ignore)
+    #[arg(long)]
+    pub terminal_title: bool,
+}
+
+/// Which transactional messages consumers see (librdkafka `isolation.level`).
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IsolationLevel {
+    /// Skip messages from aborted or still-open transactions (librdkafka's default)
+    #[default]
+    #[value(name = "read_committed")]
+    ReadCommitted,
+    /// Also show uncommitted and aborted transactional messages
+    #[value(name = "read_uncommitted")]
+    ReadUncommitted,
+}
+
+impl IsolationLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IsolationLevel::ReadCommitted => "read_committed",
+            IsolationLevel::ReadUncommitted => "read_uncommitted",
+        }
+    }
 }
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum Commands {
     /// Run once with a query or search, printing a table
-    Run(RunArgs),
+    Run(Box<RunArgs>),
+    /// List a topic's partitions with their leader and low/high watermarks
+    TopicInfo(TopicInfoArgs),
+    /// Print the rkl and linked librdkafka versions
+    Version,
 }
 
 #[derive(Parser, Debug, Clone)]
-pub struct RunArgs {
-    /// Kafka broker address
+pub struct TopicInfoArgs {
+    /// Kafka broker address: `host:port` or `@env` for a saved TUI environment
     #[arg(short, long, default_value = "localhost:9092")]
     pub broker: String,
 
-    /// Topic to search (required unless --query is provided)
-    #[arg(short, long, required_unless_present = "query")]
+    /// Topic to describe
+    #[arg(short, long)]
+    pub topic: String,
+
+    /// Timeout in ms for fetching topic metadata and each partition's watermarks
+    #[arg(long, default_value_t = 10_000)]
+    pub metadata_timeout_ms: u64,
+
+    /// SSL: CA PEM inline (librdkafka: ssl.ca.pem)
+    #[arg(long)]
+    pub ssl_ca_pem: Option<String>,
+
+    /// SSL: Certificate PEM inline (librdkafka: ssl.certificate.pem)
+    #[arg(long)]
+    pub ssl_certificate_pem: Option<String>,
+
+    /// SSL: Private key PEM inline (librdkafka: ssl.key.pem)
+    #[arg(long)]
+    pub ssl_key_pem: Option<String>,
+
+    /// SSL: Passphrase for an encrypted private key (librdkafka: ssl.key.password)
+    #[arg(long)]
+    pub ssl_key_password: Option<String>,
+
+    /// SASL mechanism; requires --sasl-username and --sasl-password
+    #[arg(long, value_enum, requires_all = ["sasl_username", "sasl_password"])]
+    pub sasl_mechanism: Option<SaslMechanism>,
+
+    /// SASL username (librdkafka: sasl.username)
+    #[arg(long, requires = "sasl_mechanism")]
+    pub sasl_username: Option<String>,
+
+    /// SASL password (librdkafka: sasl.password)
+    #[arg(long, requires = "sasl_mechanism")]
+    pub sasl_password: Option<String>,
+
+    /// Use sasl_plaintext instead of the default sasl_ssl (e.g. for a local broker)
+    #[arg(long, requires = "sasl_mechanism", conflicts_with_all = ["ssl_ca_pem", "ssl_certificate_pem", "ssl_key_pem"])]
+    pub sasl_plaintext: bool,
+}
+
+impl TopicInfoArgs {
+    /// The equivalent `rkl run` flags, so topic-info connects exactly like a run.
+    pub fn run_args(self) -> RunArgs {
+        RunArgs {
+            broker: vec![self.broker],
+            topic: Some(self.topic),
+            metadata_timeout_ms: self.metadata_timeout_ms,
+            ssl_ca_pem: self.ssl_ca_pem,
+            ssl_certificate_pem: self.ssl_certificate_pem,
+            ssl_key_pem: self.ssl_key_pem,
+            ssl_key_password: self.ssl_key_password,
+            sasl_mechanism: self.sasl_mechanism,
+            sasl_username: self.sasl_username,
+            sasl_password: self.sasl_password,
+            sasl_plaintext: self.sasl_plaintext,
+            ..RunArgs::default()
+        }
+    }
+}
+
+/// `rkl --version`: crate version plus the librdkafka actually linked, for bug reports.
+pub fn version_text() -> String {
+    let (hex, librdkafka) = rdkafka::util::get_rdkafka_version();
+    format!(
+        "rkl {}\nlibrdkafka {} (0x{:08x})",
+        env!("CARGO_PKG_VERSION"),
+        librdkafka,
+        hex
+    )
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct RunArgs {
+    /// Kafka brokers as `host:port`, or a comma list of one cluster's brokers
+    /// (`host1:9092,host2:9092`). Repeat to read the same topic from several clusters:
+    /// `label=host:port,...`, or `@env` for a saved TUI environment (with its SSL).
+    #[arg(short, long, default_value = "localhost:9092")]
+    pub broker: Vec<String>,
+
+    /// Topic to search (required unless --query or --saved is provided)
+    #[arg(short, long, required_unless_present_any = ["query", "saved"])]
     pub topic: Option<String>,
 
     /// Search term (applies to key and JSON/text value). Conflicts with --query.
     #[arg(short, long, conflicts_with = "query")]
     pub search: Option<String>,
 
+    /// Make --search case-insensitive
+    #[arg(long, requires = "search")]
+    pub search_ignore_case: bool,
+
+    /// Regex searched for in the key and the value, like --search but with
+    /// pattern syntax (prefix with (?i) to ignore case). Conflicts with --search and --query.
+    #[arg(long, value_parser = Pattern::new, conflicts_with_all = ["query", "search"])]
+    pub search_regex: Option<Pattern>,
+
     /// SQL-like query. When provided, topic is taken from FROM and overrides --offset/--max-messages/--keys-only as applicable.
     /// Example:
     ///   SELECT key, value FROM my.topic WHERE value->payload->method = 'PUT' ORDER BY timestamp DESC LIMIT 10
     #[arg(long)]
     pub query: Option<String>,
 
-    /// Maximum number of messages to read (default: all)
+    /// Run a saved query by name (see ~/.rkl/saved_queries.json). Conflicts with --query.
+    #[arg(long, conflicts_with_all = ["query", "search", "search_regex"])]
+    pub saved: Option<String>,
+
+    /// Maximum number of messages to print across all partitions (default: all)
     #[arg(short, long)]
     pub max_messages: Option<usize>,
 
+    /// Stop reading each partition after this many matches (default: no cap)
+    #[arg(long)]
+    pub limit_per_partition: Option<usize>,
+
     /// Specific partition to read from (default: all partitions)
     #[arg(short, long)]
     pub partition: Option<i32>,
 
-    /// Starting offset: "beginning" | "end" | <number>
-    #[arg(short, long, default_value = "beginning")]
-    pub offset: String,
+    /// Starting offset: "beginning" | "end" | "end-N" (last N per partition) |
+    /// <number> | an RFC 3339 time ("2024-01-01T00:00:00Z") | "@<epoch ms>";
+    /// a time starts each partition at its first message from then on
+    #[arg(short, long, default_value = "beginning", value_parser = OffsetSpec::from_str)]
+    pub offset: OffsetSpec,
+
+    /// Stream new messages as they arrive, starting at the end of each
+    /// partition, until interrupted. Ignores --max-messages and LIMIT.
+    #[arg(long, conflicts_with_all = ["offset", "partition_offset", "from_file", "dedup_key", "schema"])]
+    pub follow: bool,
+
+    /// Per-partition starting offsets, e.g. "0:100,1:250,2:end"; unlisted partitions use --offset
+    #[arg(long, value_parser = PartitionOffsets::parse)]
+    pub partition_offset: Option<PartitionOffsets>,
 
     /// Show only keys (omit value column)
     #[arg(long)]
     pub keys_only: bool,
 
+    /// Keep JSON values exactly as stored (no re-indenting or key reordering)
+    #[arg(long)]
+    pub no_value_pretty: bool,
+
+    /// Re-indent keys that are JSON (e.g. composite keys) like values; other
+    /// keys are printed as stored
+    #[arg(long)]
+    pub pretty_key: bool,
+
+    /// Print only message values, one per line, without table formatting
+    #[arg(long)]
+    pub raw: bool,
+
+    /// Instead of printing rows, report the JSON field paths observed in the
+    /// matched values with their types (flagging fields of mixed type)
+    #[arg(long, conflicts_with_all = ["raw", "produce_to"])]
+    pub schema: bool,
+
+    /// How to print rows: table, json (one array), ndjson (one object per line), csv,
+    /// or raw (only the value, or the key when no value is selected)
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table, conflicts_with_all = ["raw", "schema", "produce_to"])]
+    pub format: OutputFormat,
+
+    /// Keep only the newest message per key (as `GROUP BY key` does); ordering
+    /// and the limit then apply to those survivors
+    #[arg(long)]
+    pub dedup_key: bool,
+
+    /// Write results to this file (truncated first) instead of stdout
+    #[arg(long, conflicts_with = "produce_to")]
+    pub output_file: Option<PathBuf>,
+
+    /// With --raw or --format raw: text printed for tombstones (null values);
+    /// default is an empty line
+    #[arg(long, default_value = "")]
+    pub tombstone_marker: String,
+
+    /// Fail the run on the first key/value that is not valid UTF-8, reporting its
+    /// partition and offset (default: decode lossily)
+    #[arg(long)]
+    pub strict_decode: bool,
+
+    /// Confluent Schema Registry base URL: values framed with its magic byte
+    /// and schema id are decoded from Avro to JSON before filtering
+    #[arg(long)]
+    pub schema_registry_url: Option<String>,
+
+    /// Keep at most this many bytes of each value (plus a "…[truncated]" marker)
+    /// to bound memory on topics with huge payloads. WHERE still sees the full value.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_value_bytes: Option<u64>,
+
+    /// Exit code to use when the run succeeds but matches nothing (default 0)
+    #[arg(long, default_value_t = 0)]
+    pub no_results_exit_code: i32,
+
+    /// Report a failure on stderr as one JSON object ({"error", "kind", "topic"})
+    /// instead of a colored message
+    #[arg(long, overrides_with = "no_errors_json")]
+    pub errors_json: bool,
+
+    /// Report failures as colored text even with `errors_json = true` in the config file
+    #[arg(long, overrides_with = "errors_json")]
+    pub no_errors_json: bool,
+
+    /// How to render the timestamp column: iso (UTC), local, relative ("5m ago") or epoch (ms)
+    #[arg(long, value_enum, default_value_t = TimeFormat::Iso)]
+    pub time_format: TimeFormat,
+
     /// Disable terminal colors
-    #[arg(long, default_value_t = false)]
+    #[arg(long, default_value_t = false, overrides_with = "color")]
     pub no_color: bool,
 
+    /// Keep terminal colors even with `no_color = true` in the config file
+    #[arg(long, overrides_with = "no_color")]
+    pub color: bool,
+
     /// Max cell width for table wrapping (0 = no wrap, default 120)
     #[arg(long, default_value_t = 120)]
     pub max_cell_width: usize,
 
+    /// Broker connection setup timeout in ms (librdkafka: socket.connection.setup.timeout.ms).
+    /// Lower it to fail fast on dead brokers.
+    #[arg(long)]
+    pub connect_timeout_ms: Option<u64>,
+
+    /// Max bytes per fetch request across partitions (librdkafka: fetch.max.bytes).
+    /// Raise for topics with multi-MB messages.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..=MAX_FETCH_BYTES))]
+    pub fetch_max_bytes: Option<u64>,
+
+    /// Max bytes fetched per partition (librdkafka: max.partition.fetch.bytes)
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..=MAX_FETCH_BYTES))]
+    pub max_partition_fetch_bytes: Option<u64>,
+
+    /// Transactional isolation for consumers (librdkafka: isolation.level)
+    #[arg(long, value_enum, default_value_t = IsolationLevel::ReadCommitted)]
+    pub isolation_level: IsolationLevel,
+
+    /// Republish every emitted message (key, value, headers, timestamp) to this
+    /// topic instead of printing it. Requires --yes-produce.
+    #[arg(long, conflicts_with_all = ["raw", "max_value_bytes"])]
+    pub produce_to: Option<String>,
+
+    /// Broker for --produce-to, using the --ssl-* flags (default: the first --broker)
+    #[arg(long, requires = "produce_to")]
+    pub produce_broker: Option<String>,
+
+    /// Confirm that --produce-to may write to the target topic
+    #[arg(long, requires = "produce_to")]
+    pub yes_produce: bool,
+
+    /// Read messages from an NDJSON file (one `{"partition", "offset", "timestamp_ms",
+    /// "key", "value", "headers"}` object per line) instead of a broker
+    #[arg(long)]
+    pub from_file: Option<PathBuf>,
+
+    /// Timeout in ms for fetching topic metadata
+    #[arg(long, default_value_t = 10_000)]
+    pub metadata_timeout_ms: u64,
+
     /// Channel capacity (messages buffered between consumers and merger)
     #[arg(long, default_value_t = 2048)]
     pub channel_capacity: usize,
 
-    /// Watermark (min-heap size before we flush oldest-by-timestamp)
-    #[arg(long, default_value_t = 256)]
+    /// Merger heap size that forces out its oldest half, even ahead of a
+    /// lagging partition (bounds memory at the cost of strict ordering)
+    #[arg(long, default_value_t = 10_000)]
     pub watermark: usize,
 
     /// Flush interval in milliseconds (drains heap on tick)
     #[arg(long, default_value_t = 250)]
     pub flush_interval_ms: u64,
 
+    /// TUI: show the run's message count in the terminal title; set from the
+    /// top-level `--terminal-title`
+    #[arg(skip)]
+    pub terminal_title: bool,
+
     /// SSL: CA PEM inline (librdkafka: ssl.ca.pem)
     #[arg(long)]
     pub ssl_ca_pem: Option<String>,
@@ -81,33 +375,804 @@ pub struct RunArgs {
     /// SSL: Private key PEM inline (librdkafka: ssl.key.pem)
     #[arg(long)]
     pub ssl_key_pem: Option<String>,
+
+    /// SSL: Passphrase for an encrypted private key (librdkafka: ssl.key.password)
+    #[arg(long)]
+    pub ssl_key_password: Option<String>,
+
+    /// SASL mechanism; requires --sasl-username and --sasl-password
+    #[arg(long, value_enum, requires_all = ["sasl_username", "sasl_password"])]
+    pub sasl_mechanism: Option<SaslMechanism>,
+
+    /// SASL username (librdkafka: sasl.username)
+    #[arg(long, requires = "sasl_mechanism")]
+    pub sasl_username: Option<String>,
+
+    /// SASL password (librdkafka: sasl.password)
+    #[arg(long, requires = "sasl_mechanism")]
+    pub sasl_password: Option<String>,
+
+    /// Use sasl_plaintext instead of the default sasl_ssl (e.g. for a local broker)
+    #[arg(long, requires = "sasl_mechanism", conflicts_with_all = ["ssl_ca_pem", "ssl_certificate_pem", "ssl_key_pem"])]
+    pub sasl_plaintext: bool,
 }
 
 impl Cli {
-    pub fn parse_cli() -> Self {
-        Self::parse()
+    /// The parsed command line, with the matches `RunArgs::with_config_defaults`
+    /// uses to tell typed flags from clap defaults.
+    pub fn parse_cli() -> (Self, ArgMatches) {
+        let matches = Self::command().get_matches();
+        let cli = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        (cli, matches)
+    }
+}
+
+/// Defaults for `rkl` flags from ~/.rkl/config.toml, e.g.
+///
+/// ```toml
+/// mode = "cli"
+/// broker = ["prod=kafka-1:9092", "dr=kafka-dr:9092"]
+/// channel_capacity = 4096
+/// format = "ndjson"
+/// ```
+///
+/// Keys are long flag names with `_` for `-`. A flag given on the command line,
+/// or one it conflicts with, wins over the file, and `RKL_MODE` wins over `mode`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConfigDefaults {
+    /// `tui` or `cli`, used when `RKL_MODE` is unset.
+    pub mode: Option<String>,
+    pub broker: Option<Vec<String>>,
+    pub offset: Option<OffsetSpec>,
+    pub format: Option<OutputFormat>,
+    pub time_format: Option<TimeFormat>,
+    pub isolation_level: Option<IsolationLevel>,
+    pub max_cell_width: Option<usize>,
+    pub no_color: Option<bool>,
+    pub errors_json: Option<bool>,
+    pub metadata_timeout_ms: Option<u64>,
+    pub channel_capacity: Option<usize>,
+    pub watermark: Option<usize>,
+    pub flush_interval_ms: Option<u64>,
+    pub connect_timeout_ms: Option<u64>,
+    pub fetch_max_bytes: Option<u64>,
+    pub max_partition_fetch_bytes: Option<u64>,
+    pub schema_registry_url: Option<String>,
+}
+
+/// config.toml as written; unknown keys are rejected so a typo is not
+/// silently ignored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    mode: Option<String>,
+    broker: Option<Brokers>,
+    offset: Option<String>,
+    format: Option<String>,
+    time_format: Option<String>,
+    isolation_level: Option<String>,
+    max_cell_width: Option<usize>,
+    no_color: Option<bool>,
+    errors_json: Option<bool>,
+    metadata_timeout_ms: Option<u64>,
+    channel_capacity: Option<usize>,
+    watermark: Option<usize>,
+    flush_interval_ms: Option<u64>,
+    connect_timeout_ms: Option<u64>,
+    fetch_max_bytes: Option<u64>,
+    max_partition_fetch_bytes: Option<u64>,
+    schema_registry_url: Option<String>,
+    /// TUI preferences, read and written by `tui::settings`.
+    #[allow(dead_code)]
+    tui: Option<toml::Table>,
+}
+
+/// `broker` takes one address or a list, like repeating `--broker`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Brokers {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl ConfigDefaults {
+    pub fn load() -> Result<Self> {
+        Self::load_from(&config_path())
+    }
+
+    /// No file means no defaults; a file that does not parse is an error
+    /// naming it.
+    pub fn load_from(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::from_toml(&text).with_context(|| format!("{}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let file: ConfigFile = toml::from_str(text)?;
+        if let Some(mode) = &file.mode
+            && !matches!(mode.as_str(), "tui" | "cli")
+        {
+            return Err(anyhow!("mode must be \"tui\" or \"cli\", not {:?}", mode));
+        }
+        for (key, bytes) in [
+            ("fetch_max_bytes", file.fetch_max_bytes),
+            ("max_partition_fetch_bytes", file.max_partition_fetch_bytes),
+        ] {
+            if bytes.is_some_and(|b| !(1..=MAX_FETCH_BYTES).contains(&b)) {
+                return Err(anyhow!("{} must be between 1 and {}", key, MAX_FETCH_BYTES));
+            }
+        }
+        Ok(Self {
+            mode: file.mode,
+            broker: file.broker.map(|b| match b {
+                Brokers::One(broker) => vec![broker],
+                Brokers::Many(brokers) => brokers,
+            }),
+            offset: file
+                .offset
+                .map(|o| OffsetSpec::from_str(&o).map_err(|e| anyhow!("offset: {}", e)))
+                .transpose()?,
+            format: config_enum("format", file.format)?,
+            time_format: config_enum("time_format", file.time_format)?,
+            isolation_level: config_enum("isolation_level", file.isolation_level)?,
+            max_cell_width: file.max_cell_width,
+            no_color: file.no_color,
+            errors_json: file.errors_json,
+            metadata_timeout_ms: file.metadata_timeout_ms,
+            channel_capacity: file.channel_capacity,
+            watermark: file.watermark,
+            flush_interval_ms: file.flush_interval_ms,
+            connect_timeout_ms: file.connect_timeout_ms,
+            fetch_max_bytes: file.fetch_max_bytes,
+            max_partition_fetch_bytes: file.max_partition_fetch_bytes,
+            schema_registry_url: file.schema_registry_url,
+        })
+    }
+
+    /// The mode to start in: `RKL_MODE` (passed as `env`), else `mode`, else the TUI.
+    pub fn mode(&self, env: Option<String>) -> String {
+        env.or_else(|| self.mode.clone())
+            .unwrap_or_else(|| "tui".to_string())
+    }
+}
+
+/// A `--format`-style value from the config, named by its flag value.
+fn config_enum<T: ValueEnum>(key: &str, value: Option<String>) -> Result<Option<T>> {
+    value
+        .map(|v| T::from_str(&v, true).map_err(|_| anyhow!("{} has no value {:?}", key, v)))
+        .transpose()
+}
+
+pub fn config_path() -> PathBuf {
+    std::env::var("HOME")
+        .map(|h| PathBuf::from(h).join(".rkl").join("config.toml"))
+        .unwrap_or_else(|_| PathBuf::from(".rkl").join("config.toml"))
+}
+
+/// Whether the flag `id` was typed on the command line (not a clap default).
+fn given_on_command_line(matches: &ArgMatches, id: &str) -> bool {
+    matches.ids().any(|i| i.as_str() == id)
+        && matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+/// Whether `id`, or a flag that conflicts with it either way, was typed, so a
+/// config value for `id` would override or clash with the command line.
+fn typed_or_conflicting(command: &Command, matches: &ArgMatches, id: &str) -> bool {
+    let typed = |arg: &Arg| given_on_command_line(matches, arg.get_id().as_str());
+    let Some(arg) = command.get_arguments().find(|a| a.get_id() == id) else {
+        return given_on_command_line(matches, id);
+    };
+    typed(arg)
+        || command.get_arg_conflicts_with(arg).into_iter().any(typed)
+        || command.get_arguments().filter(|a| typed(a)).any(|a| {
+            command
+                .get_arg_conflicts_with(a)
+                .iter()
+                .any(|c| c.get_id() == id)
+        })
+}
+
+/// Set `field` to the config file's `value`, if it has one and `typed` is false.
+fn fill<T: Clone>(field: &mut T, value: &Option<T>, typed: bool) {
+    if let Some(value) = value
+        && !typed
+    {
+        *field = value.clone();
+    }
+}
+
+impl RunArgs {
+    /// Fill the flags not typed on the command line (per `matches`; `None`
+    /// counts none as typed) from `config`.
+    pub fn with_config_defaults(
+        mut self,
+        config: &ConfigDefaults,
+        matches: Option<&ArgMatches>,
+    ) -> Self {
+        let mut command = RunArgs::command();
+        command.build();
+        let typed = |id: &str| matches.is_some_and(|m| typed_or_conflicting(&command, m, id));
+        fill(&mut self.broker, &config.broker, typed("broker"));
+        fill(&mut self.offset, &config.offset, typed("offset"));
+        fill(&mut self.format, &config.format, typed("format"));
+        fill(
+            &mut self.time_format,
+            &config.time_format,
+            typed("time_format"),
+        );
+        fill(
+            &mut self.isolation_level,
+            &config.isolation_level,
+            typed("isolation_level"),
+        );
+        fill(
+            &mut self.max_cell_width,
+            &config.max_cell_width,
+            typed("max_cell_width"),
+        );
+        // `--color` and `--no-errors-json` turn the file's settings back off
+        fill(
+            &mut self.no_color,
+            &config.no_color,
+            typed("no_color") || self.color,
+        );
+        fill(
+            &mut self.errors_json,
+            &config.errors_json,
+            typed("errors_json") || self.no_errors_json,
+        );
+        fill(
+            &mut self.metadata_timeout_ms,
+            &config.metadata_timeout_ms,
+            typed("metadata_timeout_ms"),
+        );
+        fill(
+            &mut self.channel_capacity,
+            &config.channel_capacity,
+            typed("channel_capacity"),
+        );
+        fill(&mut self.watermark, &config.watermark, typed("watermark"));
+        fill(
+            &mut self.flush_interval_ms,
+            &config.flush_interval_ms,
+            typed("flush_interval_ms"),
+        );
+        fill(
+            &mut self.connect_timeout_ms,
+            &config.connect_timeout_ms.map(Some),
+            typed("connect_timeout_ms"),
+        );
+        fill(
+            &mut self.fetch_max_bytes,
+            &config.fetch_max_bytes.map(Some),
+            typed("fetch_max_bytes"),
+        );
+        fill(
+            &mut self.max_partition_fetch_bytes,
+            &config.max_partition_fetch_bytes.map(Some),
+            typed("max_partition_fetch_bytes"),
+        );
+        fill(
+            &mut self.schema_registry_url,
+            &config.schema_registry_url.clone().map(Some),
+            typed("schema_registry_url"),
+        );
+        self
+    }
+
+    /// SSL settings from the `--ssl-*` flags; `None` when no PEM material was given.
+    pub fn ssl_config(&self) -> Option<SslConfig> {
+        let ssl = SslConfig {
+            ca_pem: self.ssl_ca_pem.clone(),
+            cert_pem: self.ssl_certificate_pem.clone(),
+            key_pem: self.ssl_key_pem.clone(),
+            key_password: self.ssl_key_password.clone(),
+        };
+        if ssl.is_enabled() { Some(ssl) } else { None }
+    }
+
+    /// SASL settings from the `--sasl-*` flags; `None` without `--sasl-mechanism`.
+    pub fn sasl_config(&self) -> Option<SaslConfig> {
+        Some(SaslConfig {
+            mechanism: self.sasl_mechanism?,
+            username: self.sasl_username.clone().unwrap_or_default(),
+            password: self.sasl_password.clone().unwrap_or_default(),
+            tls: !self.sasl_plaintext,
+        })
+    }
+
+    /// One entry per `--broker`. Plain brokers use the `--ssl-*` and `--sasl-*`
+    /// flags; `@env` entries bring their own host, SSL and SASL. Envelopes are only labelled when
+    /// there is more than one cluster or a label was given explicitly.
+    pub fn clusters(&self) -> Result<Vec<ClusterSpec>> {
+        let envs = if self.broker.iter().any(|b| b.starts_with('@')) {
+            EnvStore::load()
+        } else {
+            EnvStore::default()
+        };
+        self.clusters_in(&envs)
+    }
+
+    /// `clusters` against an already loaded environment store.
+    fn clusters_in(&self, envs: &EnvStore) -> Result<Vec<ClusterSpec>> {
+        let multi = self.broker.len() > 1;
+        self.broker
+            .iter()
+            .map(|spec| {
+                if let Some(name) = spec.strip_prefix('@') {
+                    let env =
+                        envs.envs.iter().find(|e| e.name == name).ok_or_else(|| {
+                            match envs.load_error_summary() {
+                                Some(note) => {
+                                    anyhow!("Unknown environment in --broker: {} ({})", name, note)
+                                }
+                                None => anyhow!("Unknown environment in --broker: {}", name),
+                            }
+                        })?;
+                    let ssl = env.ssl_config();
+                    let brokers = parse_brokers(&env.host)
+                        .map_err(|e| anyhow!("Environment {} in --broker: {}", name, e))?;
+                    return Ok(ClusterSpec {
+                        label: Some(env.name.clone()),
+                        bootstrap: brokers.join(","),
+                        ssl: ssl.is_enabled().then_some(ssl),
+                        sasl: env.sasl.clone(),
+                    });
+                }
+                let (label, hosts) = match spec.split_once('=') {
+                    Some((label, hosts)) => (Some(label.to_string()), hosts),
+                    None => (multi.then(|| spec.clone()), spec.as_str()),
+                };
+                let brokers =
+                    parse_brokers(hosts).map_err(|e| anyhow!("Invalid --broker: {}", e))?;
+                Ok(ClusterSpec {
+                    label,
+                    bootstrap: brokers.join(","),
+                    ssl: self.ssl_config(),
+                    sasl: self.sasl_config(),
+                })
+            })
+            .collect()
+    }
+
+    /// Args for one partition consumer. The global `--max-messages`/`LIMIT`
+    /// is enforced by the merger only; consumers just honor
+    /// `--limit-per-partition`.
+    pub fn for_consumer(&self, topic: &str, keys_only: bool) -> RunArgs {
+        let mut a = self.clone();
+        a.topic = Some(topic.to_string());
+        a.keys_only = keys_only;
+        a.max_messages = None;
+        a
+    }
+
+    /// Apply timeout, fetch-size and isolation flags to a client config; unset
+    /// flags keep librdkafka defaults.
+    pub fn apply_client_options(&self, cfg: &mut ClientConfig) {
+        cfg.set("isolation.level", self.isolation_level.as_str());
+        if let Some(ms) = self.connect_timeout_ms {
+            cfg.set("socket.connection.setup.timeout.ms", ms.to_string());
+        }
+        if let Some(bytes) = self.fetch_max_bytes {
+            cfg.set("fetch.max.bytes", bytes.to_string());
+            // librdkafka rejects fetch.max.bytes above receive.message.max.bytes - 512
+            if bytes + 512 > DEFAULT_RECEIVE_MESSAGE_MAX_BYTES {
+                cfg.set("receive.message.max.bytes", (bytes + 512).to_string());
+            }
+        }
+        if let Some(bytes) = self.max_partition_fetch_bytes {
+            cfg.set("max.partition.fetch.bytes", bytes.to_string());
+        }
+    }
+
+    /// Where partition readers start: `--offset`, or the end with `--follow`.
+    pub fn offset_spec(&self) -> OffsetSpec {
+        if self.follow {
+            return OffsetSpec::End;
+        }
+        self.offset
+    }
+
+    /// The run's global row limit: the query's `LIMIT`, else `--max-messages`;
+    /// none with `--follow`, which streams until interrupted, or for
+    /// `COUNT(*)`, which counts every match.
+    pub fn row_limit(&self, query: Option<&SelectQuery>) -> Option<usize> {
+        if self.follow || query.is_some_and(SelectQuery::is_count) {
+            return None;
+        }
+        query.and_then(|q| q.limit).or(self.max_messages)
+    }
+
+    pub fn metadata_timeout(&self) -> Duration {
+        Duration::from_millis(self.metadata_timeout_ms)
+    }
+
+    /// Whether stdout carries only data (`--raw` or a machine `--format`, and
+    /// no `--output-file`), so status chatter must go to stderr.
+    pub fn stdout_is_data(&self) -> bool {
+        self.output_file.is_none() && (self.raw || self.format != OutputFormat::Table)
+    }
+
+    /// Whether rows print as bare values or keys, one per line (`--raw` or
+    /// `--format raw`): values and keys are then printed as stored.
+    pub fn raw_output(&self) -> bool {
+        self.raw || self.format == OutputFormat::Raw
     }
 }
 
 impl Default for RunArgs {
     fn default() -> Self {
         Self {
-            broker: "localhost:9092".to_string(),
+            broker: vec!["localhost:9092".to_string()],
             topic: None,
             search: None,
+            search_ignore_case: false,
+            search_regex: None,
             query: None,
+            saved: None,
             max_messages: None,
+            limit_per_partition: None,
             partition: None,
-            offset: "beginning".to_string(),
+            offset: OffsetSpec::Beginning,
+            follow: false,
+            partition_offset: None,
             keys_only: false,
+            no_value_pretty: false,
+            pretty_key: false,
+            raw: false,
+            schema: false,
+            format: OutputFormat::Table,
+            dedup_key: false,
+            output_file: None,
+            tombstone_marker: String::new(),
+            strict_decode: false,
+            schema_registry_url: None,
+            max_value_bytes: None,
+            no_results_exit_code: 0,
+            errors_json: false,
+            no_errors_json: false,
+            time_format: TimeFormat::Iso,
             no_color: false,
+            color: false,
             max_cell_width: 120,
+            connect_timeout_ms: None,
+            fetch_max_bytes: None,
+            max_partition_fetch_bytes: None,
+            isolation_level: IsolationLevel::ReadCommitted,
+            produce_to: None,
+            produce_broker: None,
+            yes_produce: false,
+            from_file: None,
+            metadata_timeout_ms: 10_000,
             channel_capacity: 2048,
-            watermark: 256,
+            watermark: 10_000,
             flush_interval_ms: 250,
+            terminal_title: false,
             ssl_ca_pem: None,
             ssl_certificate_pem: None,
             ssl_key_pem: None,
+            ssl_key_password: None,
+            sasl_mechanism: None,
+            sasl_username: None,
+            sasl_password: None,
+            sasl_plaintext: false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_defaults_yield_to_typed_flags() {
+        let config = ConfigDefaults::from_toml(
+            r#"
+            mode = "cli"
+            broker = "kafka-1:9092"
+            channel_capacity = 4096
+            watermark = 500
+            format = "ndjson"
+            connect_timeout_ms = 3000
+            offset = "end-10"
+            no_color = true
+            errors_json = true
+            "#,
+        )
+        .unwrap();
+        let parse = |argv: &[&str]| {
+            let matches = RunArgs::command().get_matches_from(argv);
+            RunArgs::from_arg_matches(&matches)
+                .unwrap()
+                .with_config_defaults(&config, Some(&matches))
+        };
+
+        let args = parse(&["rkl", "--topic", "t"]);
+        assert_eq!(args.broker, ["kafka-1:9092"]);
+        assert_eq!((args.channel_capacity, args.watermark), (4096, 500));
+        assert_eq!(args.format, OutputFormat::Ndjson);
+        assert_eq!(args.connect_timeout_ms, Some(3000));
+        assert_eq!(args.offset, OffsetSpec::FromEnd(10));
+        assert!(args.no_color && args.errors_json);
+        // Keys the file leaves out keep clap's defaults
+        assert_eq!(args.flush_interval_ms, 250);
+
+        // A typed flag that conflicts with a key keeps the key out
+        let args = parse(&["rkl", "--topic", "t", "--raw", "--follow"]);
+        assert_eq!(args.format, OutputFormat::Table);
+        assert_eq!(args.offset, OffsetSpec::Beginning);
+        // and the switches the file turns on can be turned off again
+        let args = parse(&["rkl", "--topic", "t", "--color", "--no-errors-json"]);
+        assert!(!args.no_color && !args.errors_json);
+
+        let args = parse(&[
+            "rkl",
+            "--topic",
+            "t",
+            "-b",
+            "local:9092",
+            "--channel-capacity",
+            "64",
+            "--format",
+            "csv",
+            "--connect-timeout-ms",
+            "10",
+        ]);
+        assert_eq!(args.broker, ["local:9092"]);
+        assert_eq!((args.channel_capacity, args.watermark), (64, 500));
+        assert_eq!(args.format, OutputFormat::Csv);
+        assert_eq!(args.connect_timeout_ms, Some(10));
+
+        // RKL_MODE beats the file, which beats the TUI default
+        assert_eq!(config.mode(None), "cli");
+        assert_eq!(config.mode(Some("tui".into())), "tui");
+        assert_eq!(ConfigDefaults::default().mode(None), "tui");
+    }
+
+    #[test]
+    fn bad_config_files_are_reported() {
+        assert!(ConfigDefaults::from_toml("chanel_capacity = 1").is_err());
+        assert!(ConfigDefaults::from_toml("format = \"xml\"").is_err());
+        assert!(ConfigDefaults::from_toml("mode = \"gui\"").is_err());
+        assert!(ConfigDefaults::from_toml("fetch_max_bytes = 0").is_err());
+        assert!(ConfigDefaults::from_toml("offset = \"start\"").is_err());
+        let brokers = ConfigDefaults::from_toml("broker = [\"a:1\", \"b=c:2\"]").unwrap();
+        assert_eq!(brokers.broker.unwrap(), ["a:1", "b=c:2"]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("config.toml");
+        assert_eq!(
+            ConfigDefaults::load_from(&missing).unwrap(),
+            ConfigDefaults::default()
+        );
+    }
+
+    #[test]
+    fn search_regex_is_compiled_at_startup() {
+        let args = RunArgs::parse_from(["rkl", "--topic", "t", "--search-regex", "ord(er)?-\\d+"]);
+        assert_eq!(
+            args.search_regex.map(|p| p.as_str().to_string()),
+            Some("ord(er)?-\\d+".to_string())
+        );
+        let invalid = RunArgs::try_parse_from(["rkl", "--topic", "t", "--search-regex", "(open"]);
+        assert!(invalid.is_err());
+        let both = RunArgs::try_parse_from([
+            "rkl",
+            "--topic",
+            "t",
+            "--search",
+            "x",
+            "--search-regex",
+            "x",
+        ]);
+        assert!(both.is_err());
+    }
+
+    #[test]
+    fn fetch_size_flags_set_fetch_properties() {
+        let args = RunArgs::parse_from([
+            "rkl",
+            "--topic",
+            "t",
+            "--fetch-max-bytes",
+            "8388608",
+            "--max-partition-fetch-bytes",
+            "4194304",
+        ]);
+        let mut cfg = ClientConfig::new();
+        args.apply_client_options(&mut cfg);
+        assert_eq!(cfg.get("fetch.max.bytes"), Some("8388608"));
+        assert_eq!(cfg.get("max.partition.fetch.bytes"), Some("4194304"));
+        assert_eq!(cfg.get("receive.message.max.bytes"), None);
+
+        // Very large fetches also raise the receive limit so librdkafka accepts them
+        let big = RunArgs::parse_from(["rkl", "--topic", "t", "--fetch-max-bytes", "200000000"]);
+        let mut cfg = ClientConfig::new();
+        big.apply_client_options(&mut cfg);
+        assert_eq!(cfg.get("receive.message.max.bytes"), Some("200000512"));
+
+        assert!(
+            RunArgs::try_parse_from(["rkl", "--topic", "t", "--fetch-max-bytes", "0"]).is_err()
+        );
+    }
+
+    #[test]
+    fn bad_offsets_are_rejected_by_clap() {
+        let parse =
+            |offset: &str| RunArgs::try_parse_from(["rkl", "--topic", "t", "--offset", offset]);
+        assert_eq!(
+            parse("end-25").unwrap().offset_spec(),
+            OffsetSpec::FromEnd(25)
+        );
+        for offset in ["end-x", "end--5", "end-", "soon"] {
+            let err = parse(offset).unwrap_err().to_string();
+            assert!(err.contains("invalid offset"), "{offset}: {err}");
+        }
+    }
+
+    #[test]
+    fn repeated_broker_flags_become_labelled_clusters() {
+        let single = RunArgs::parse_from(["rkl", "--topic", "t"]);
+        let clusters = single.clusters().unwrap();
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].bootstrap, "localhost:9092");
+        assert_eq!(clusters[0].label, None);
+
+        let args = RunArgs::parse_from([
+            "rkl",
+            "--topic",
+            "t",
+            "--broker",
+            "source=a:9092,b:9092",
+            "--broker",
+            "c:9092",
+        ]);
+        let clusters = args.clusters().unwrap();
+        assert_eq!(clusters[0].label.as_deref(), Some("source"));
+        assert_eq!(clusters[0].bootstrap, "a:9092,b:9092");
+        assert_eq!(clusters[1].label.as_deref(), Some("c:9092"));
+        assert_eq!(clusters[1].bootstrap, "c:9092");
+
+        let spaced = RunArgs::parse_from(["rkl", "--topic", "t", "-b", "a:9092, b:9092"]);
+        assert_eq!(spaced.clusters().unwrap()[0].bootstrap, "a:9092,b:9092");
+        let broken = RunArgs::parse_from(["rkl", "--topic", "t", "-b", "src=a:9092,,b:9092"]);
+        let err = broken.clusters().unwrap_err().to_string();
+        assert!(err.starts_with("Invalid --broker: empty entry"), "{err}");
+    }
+
+    #[test]
+    fn env_brokers_bring_their_own_host_and_credentials() {
+        let envs = EnvStore {
+            envs: vec![crate::env_store::Environment {
+                name: "prod".into(),
+                host: "p1:9093, p2:9093".into(),
+                ssl_ca_pem: Some("-----BEGIN CERTIFICATE-----\\nQ0E=".into()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let args = RunArgs::parse_from([
+            "rkl",
+            "--topic",
+            "t",
+            "-b",
+            "@prod",
+            "-b",
+            "local:9092",
+            "--sasl-mechanism",
+            "plain",
+            "--sasl-username",
+            "u",
+            "--sasl-password",
+            "p",
+        ]);
+        let clusters = args.clusters_in(&envs).unwrap();
+        assert_eq!(clusters[0].label.as_deref(), Some("prod"));
+        assert_eq!(clusters[0].bootstrap, "p1:9093,p2:9093");
+        let ssl = clusters[0].ssl.as_ref().unwrap();
+        assert_eq!(
+            ssl.ca_pem.as_deref(),
+            Some("-----BEGIN CERTIFICATE-----\nQ0E=")
+        );
+        // The env's own (absent) SASL wins over the flags meant for plain brokers
+        assert!(clusters[0].sasl.is_none());
+        assert!(clusters[1].sasl.is_some());
+
+        let missing = RunArgs::parse_from(["rkl", "--topic", "t", "-b", "@staging"]);
+        let err = missing.clusters_in(&envs).unwrap_err().to_string();
+        assert_eq!(err, "Unknown environment in --broker: staging");
+    }
+
+    #[test]
+    fn consumers_get_per_partition_cap_but_not_global_limit() {
+        let args = RunArgs::parse_from([
+            "rkl",
+            "--topic",
+            "t",
+            "--max-messages",
+            "10",
+            "--limit-per-partition",
+            "3",
+        ]);
+        let consumer = args.for_consumer("orders", true);
+        assert_eq!(consumer.max_messages, None);
+        assert_eq!(consumer.limit_per_partition, Some(3));
+        assert_eq!(consumer.topic.as_deref(), Some("orders"));
+        assert!(consumer.keys_only);
+        assert_eq!(args.max_messages, Some(10));
+    }
+
+    #[test]
+    fn version_text_names_linked_librdkafka() {
+        let text = version_text();
+        let mut lines = text.lines();
+        assert_eq!(
+            lines.next(),
+            Some(format!("rkl {}", env!("CARGO_PKG_VERSION")).as_str())
+        );
+        let librdkafka = lines.next().and_then(|l| l.strip_prefix("librdkafka "));
+        let version = librdkafka.and_then(|l| l.split(' ').next()).unwrap_or("");
+        assert!(!version.is_empty(), "{text}");
+
+        assert!(Cli::try_parse_from(["rkl", "--version"]).unwrap().version);
+        assert!(matches!(
+            Cli::try_parse_from(["rkl", "version"]).unwrap().command,
+            Some(Commands::Version)
+        ));
+    }
+
+    #[test]
+    fn terminal_title_is_a_top_level_tui_flag() {
+        assert!(
+            Cli::try_parse_from(["rkl", "--terminal-title"])
+                .unwrap()
+                .terminal_title
+        );
+        assert!(!Cli::try_parse_from(["rkl"]).unwrap().terminal_title);
+        // It only means something to the TUI, so `run` does not take it
+        assert!(Cli::try_parse_from(["rkl", "run", "--topic", "t", "--terminal-title"]).is_err());
+    }
+
+    #[test]
+    fn schema_report_rejects_other_formats() {
+        for format in ["json", "ndjson", "csv", "raw", "table"] {
+            let args = ["rkl", "--topic", "t", "--schema", "--format", format];
+            assert!(RunArgs::try_parse_from(args).is_err(), "{format}");
+        }
+        assert!(RunArgs::try_parse_from(["rkl", "--topic", "t", "--schema"]).is_ok());
+    }
+
+    #[test]
+    fn isolation_level_flag_sets_isolation_property() {
+        let mut cfg = ClientConfig::new();
+        RunArgs::parse_from(["rkl", "--topic", "t"]).apply_client_options(&mut cfg);
+        assert_eq!(cfg.get("isolation.level"), Some("read_committed"));
+
+        let args = RunArgs::parse_from([
+            "rkl",
+            "--topic",
+            "t",
+            "--isolation-level",
+            "read_uncommitted",
+        ]);
+        let mut cfg = ClientConfig::new();
+        args.apply_client_options(&mut cfg);
+        assert_eq!(cfg.get("isolation.level"), Some("read_uncommitted"));
+    }
+
+    #[test]
+    fn connect_timeout_flag_sets_connection_setup_timeout() {
+        let args = RunArgs::parse_from(["rkl", "--topic", "t", "--connect-timeout-ms", "1500"]);
+        let mut cfg = ClientConfig::new();
+        args.apply_client_options(&mut cfg);
+        assert_eq!(cfg.get("socket.connection.setup.timeout.ms"), Some("1500"));
+        assert_eq!(args.metadata_timeout(), Duration::from_secs(10));
+
+        let mut untouched = ClientConfig::new();
+        RunArgs::default().apply_client_options(&mut untouched);
+        assert_eq!(untouched.get("socket.connection.setup.timeout.ms"), None);
+    }
+}
@@ -1,48 +1,62 @@
 use crate::args::RunArgs;
-use crate::models::{MessageEnvelope, OffsetSpec, SslConfig};
-use crate::query::SelectQuery;
-use anyhow::{Context, Result};
+use crate::merger::{ReaderEvent, ReaderTx};
+use crate::models::{
+    ClusterSpec, MessageEnvelope, MessageHeader, OffsetSpec, PartitionSummary, ScanProgress,
+    StopSignal,
+};
+use crate::query::{MessageView, SelectQuery};
+use crate::schema_registry::SchemaCache;
+use anyhow::{Context, Result, anyhow};
+use rdkafka::Offset;
 use rdkafka::config::ClientConfig;
 use rdkafka::consumer::{Consumer, StreamConsumer};
-use rdkafka::message::Message;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::message::{Headers, Message};
 use rdkafka::topic_partition_list::TopicPartitionList;
 use serde_json::Value;
 use std::io::Write as _;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc::Sender;
 
+/// Run state shared by the partition readers of one run, passed next to
+/// their `RunArgs`.
+#[derive(Debug, Clone, Default)]
+pub struct ReaderContext {
+    /// Raised by the run once its global LIMIT is met; readers stop on it
+    pub stop: Option<StopSignal>,
+    /// Stop at the end of the partition instead of tailing; set for the
+    /// latest-N plan
+    pub stop_at_eof: bool,
+    /// Avro schemas fetched from --schema-registry-url, shared by every reader
+    pub schemas: SchemaCache,
+    /// Where readers count scanned and matched messages
+    pub progress: Option<Arc<ScanProgress>>,
+}
+
 pub async fn spawn_partition_consumer(
     args: RunArgs,
     partition: i32,
     offset_spec: OffsetSpec,
-    tx: Sender<MessageEnvelope>,
+    tx: Sender<ReaderEvent>,
     query: Option<std::sync::Arc<SelectQuery>>,
-    ssl: Option<SslConfig>,
-) -> Result<()> {
+    cluster: ClusterSpec,
+    ctx: ReaderContext,
+) -> Result<PartitionSummary> {
     // unique group id (we never commit)
     let group_id = format!("rkl-{}-p{}", uuid::Uuid::new_v4(), partition);
 
     let mut cfg = ClientConfig::new();
-    cfg.set("bootstrap.servers", &args.broker)
+    cfg.set("bootstrap.servers", &cluster.bootstrap)
         .set("group.id", group_id)
         .set("enable.auto.commit", "false")
         .set("auto.offset.reset", "earliest")
         .set("enable.partition.eof", "true");
-    if let Some(ssl) = &ssl {
-        if ssl.ca_pem.is_some() || ssl.cert_pem.is_some() || ssl.key_pem.is_some() {
-            cfg.set("security.protocol", "ssl");
-            if let Some(ref s) = ssl.ca_pem {
-                cfg.set("ssl.ca.pem", s);
-            }
-            if let Some(ref s) = ssl.cert_pem {
-                cfg.set("ssl.certificate.pem", s);
-            }
-            if let Some(ref s) = ssl.key_pem {
-                cfg.set("ssl.key.pem", s);
-            }
-        }
-    }
-    let consumer: StreamConsumer = cfg.create().context("Failed to create consumer")?;
+    cluster.apply_security(&mut cfg);
+    args.apply_client_options(&mut cfg);
+    // Shared so the blocking broker lookups below can run off the runtime
+    let consumer: Arc<StreamConsumer> =
+        Arc::new(cfg.create().context("Failed to create consumer")?);
 
     // Manual assignment to this specific partition + offset
     let mut tpl = TopicPartitionList::new();
@@ -50,17 +64,60 @@ pub async fn spawn_partition_consumer(
         .topic
         .as_ref()
         .expect("topic should be set by main before spawning consumers");
-    tpl.add_partition_offset(topic, partition, offset_spec.to_rdkafka())?;
+    let start = {
+        let lookup = Arc::clone(&consumer);
+        let (topic, timeout) = (topic.clone(), args.metadata_timeout());
+        tokio::task::spawn_blocking(move || {
+            start_offset(lookup.as_ref(), &topic, partition, offset_spec, timeout)
+        })
+        .await
+        .context("Starting offset lookup failed")??
+    };
+    tpl.add_partition_offset(topic, partition, start)?;
+    // Note the partition's end so the UI can show how far the scan has read
+    let progress = ctx.progress.clone();
+    let position = match &progress {
+        Some(p) => {
+            let lookup = Arc::clone(&consumer);
+            let (topic, timeout) = (topic.clone(), args.metadata_timeout());
+            tokio::task::spawn_blocking(move || lookup.fetch_watermarks(&topic, partition, timeout))
+                .await
+                .ok()
+                .and_then(Result::ok)
+                .map(|(low, high)| p.track_partition(first_offset(start, low, high), high))
+        }
+        None => None,
+    };
     consumer
         .assign(&tpl)
         .context("Failed to assign partition")?;
 
     let mut processed: usize = 0;
+    let mut stop = ctx.stop.clone();
+    let mut lane = ReaderTx::new(
+        tx,
+        cluster.label.clone(),
+        partition,
+        Duration::from_millis(args.flush_interval_ms),
+    );
 
     loop {
-        // Backpressure-friendly, async receive
-        match consumer.recv().await {
+        // Backpressure-friendly, async receive; a met global LIMIT ends the scan
+        let received = match stop.as_mut() {
+            Some(stop) => tokio::select! {
+                _ = stop.raised() => break,
+                received = consumer.recv() => received,
+            },
+            None => consumer.recv().await,
+        };
+        match received {
             Ok(msg) => {
+                if let Some(ref p) = progress {
+                    p.record_scanned();
+                }
+                if let Some(ref position) = position {
+                    position.record_offset(msg.offset());
+                }
                 // End-of-partition marker
                 if msg.payload().is_none()
                     && msg.key().is_none()
@@ -69,76 +126,69 @@ pub async fn spawn_partition_consumer(
                     // Keep reading; librdkafka emits EOFs—don’t break, we want “tail” as well if offset=end
                 }
 
-                let key = msg
-                    .key()
-                    .map(|k| String::from_utf8_lossy(k).to_string())
-                    .unwrap_or_else(|| "null".to_string());
-
-                // Prepare payload as String and JSON
-                let payload_str = msg
-                    .payload()
-                    .map(|p| String::from_utf8_lossy(p).to_string());
-                let payload_json: serde_json::Value = payload_str
-                    .as_deref()
-                    .and_then(|s| serde_json::from_str::<serde_json::Value>(s).ok())
-                    .unwrap_or(serde_json::Value::Null);
-
-                // Apply query WHERE if provided; else fallback to simple --search
-                let matches = if let Some(ref q) = query {
-                    if let Some(ref expr) = q.r#where {
-                        expr.matches(
-                            &key,
-                            &payload_json,
-                            payload_str.as_deref(),
-                            msg.timestamp().to_millis().unwrap_or(0),
-                        )
-                    } else {
-                        true
+                let strict = args.strict_decode;
+                let key = match msg.key() {
+                    Some(k) => decode_text(k, strict, "key", partition, msg.offset())?,
+                    None => "null".to_string(),
+                };
+
+                let payload_str = match msg.payload() {
+                    Some(p) => {
+                        Some(decode_value(&args, &ctx.schemas, p, partition, msg.offset()).await?)
                     }
-                } else if let Some(ref needle) = args.search {
-                    let hay1 = &key;
-                    let hay2 = if let Some(ref s) = payload_str { s } else { "" };
-                    hay1.contains(needle) || hay2.contains(needle)
-                } else {
-                    true
+                    None => None,
+                };
+                let decoded = MessageEnvelope {
+                    topic: topic.clone(),
+                    partition,
+                    offset: msg.offset(),
+                    timestamp_ms: msg.timestamp().to_millis().unwrap_or(0),
+                    key,
+                    value: payload_str,
+                    headers: msg
+                        .headers()
+                        .map(|hs| {
+                            hs.iter()
+                                .map(|h| MessageHeader {
+                                    key: h.key.to_string(),
+                                    value: h.value.map(<[u8]>::to_vec),
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    cluster: cluster.label.clone(),
+                    count: None,
                 };
 
-                if matches {
-                    // If keys_only -> set value None, else pretty-print JSON if possible
-                    let keys_only = args.keys_only; // effective keys_only computed in main when using query
-                    let value_print = if keys_only {
-                        None
-                    } else if let Some(ref s) = payload_str {
-                        if let Ok(json) = serde_json::from_str::<Value>(s) {
-                            Some(serde_json::to_string_pretty(&json).unwrap())
-                        } else {
-                            Some(s.clone())
-                        }
-                    } else {
-                        Some("null".to_string())
-                    };
-
-                    let env = MessageEnvelope {
-                        partition,
-                        offset: msg.offset(),
-                        timestamp_ms: msg.timestamp().to_millis().unwrap_or(0),
-                        key,
-                        value: value_print,
-                    };
-
-                    if tx.send(env).await.is_err() {
-                        // merger dropped—shut down gracefully
+                let timestamp_ms = decoded.timestamp_ms;
+                let Some(env) = filter_message(&args, query.as_deref(), decoded) else {
+                    if lane.skipped(timestamp_ms).await.is_err() {
                         break;
                     }
-                    processed += 1;
+                    continue;
+                };
+                if lane.row(env).await.is_err() {
+                    // merger dropped—shut down gracefully
+                    break;
+                }
+                if let Some(ref p) = progress {
+                    p.record_matched();
+                }
+                processed += 1;
 
-                    if let Some(max) = args.max_messages {
-                        if processed >= max {
-                            break;
-                        }
+                if let Some(max) = args.limit_per_partition {
+                    if processed >= max {
+                        break;
                     }
                 }
             }
+            Err(KafkaError::PartitionEOF(_)) if stops_at_eof(&args, &ctx) => break,
+            // Caught up: stop holding back the merge until new messages arrive
+            Err(KafkaError::PartitionEOF(_)) => {
+                if lane.idle().await.is_err() {
+                    break;
+                }
+            }
             Err(e) => {
                 // Log errors to ~/.rkl/logs instead of printing over the TUI
                 if let Some(home) = std::env::var_os("HOME") {
@@ -156,6 +206,13 @@ pub async fn spawn_partition_consumer(
                             .format(&time::format_description::well_known::Rfc3339)
                             .unwrap_or_else(|_| "".into());
                         let _ = writeln!(f, "{} [partition {}] {}", ts, partition, e);
+                        if e.rdkafka_error_code() == Some(RDKafkaErrorCode::MessageSizeTooLarge) {
+                            let _ = writeln!(
+                                f,
+                                "{} [partition {}] hint: a message exceeds the fetch limits; raise --max-partition-fetch-bytes / --fetch-max-bytes",
+                                ts, partition
+                            );
+                        }
                     }
                 }
                 // Keep going; transient errors happen
@@ -164,5 +221,554 @@ pub async fn spawn_partition_consumer(
         }
     }
 
-    Ok(())
+    let _ = lane.idle().await;
+    Ok(PartitionSummary {
+        partition,
+        matched: processed as u64,
+    })
+}
+
+/// Whether a reader ends at its partition's end; `--follow` keeps tailing.
+pub fn stops_at_eof(args: &RunArgs, ctx: &ReaderContext) -> bool {
+    ctx.stop_at_eof && !args.follow
+}
+
+/// Broker lookups behind `start_offset`; a trait so tests can fake the broker.
+pub trait OffsetLookup {
+    /// A partition's first offset whose timestamp is at or after `ms`
+    /// (librdkafka `offsets_for_times`); `None` when no message is that recent.
+    fn offset_for_time(
+        &self,
+        topic: &str,
+        partition: i32,
+        ms: i64,
+        timeout: Duration,
+    ) -> Result<Option<i64>>;
+
+    /// A partition's `(low, high)` watermarks.
+    fn watermarks(&self, topic: &str, partition: i32, timeout: Duration) -> Result<(i64, i64)>;
+}
+
+impl OffsetLookup for StreamConsumer {
+    fn offset_for_time(
+        &self,
+        topic: &str,
+        partition: i32,
+        ms: i64,
+        timeout: Duration,
+    ) -> Result<Option<i64>> {
+        let mut tpl = TopicPartitionList::new();
+        tpl.add_partition_offset(topic, partition, Offset::Offset(ms))?;
+        let found = self
+            .offsets_for_times(tpl, timeout)
+            .context("Failed to look up offsets for the start time")?;
+        Ok(
+            match found.find_partition(topic, partition).map(|e| e.offset()) {
+                Some(Offset::Offset(n)) => Some(n),
+                _ => None,
+            },
+        )
+    }
+
+    fn watermarks(&self, topic: &str, partition: i32, timeout: Duration) -> Result<(i64, i64)> {
+        self.fetch_watermarks(topic, partition, timeout)
+            .context("Failed to fetch watermarks")
+    }
+}
+
+/// Offset to assign a partition for `spec`. A timestamp is looked up, and a
+/// partition with nothing at or after it starts at the end (new messages only).
+/// `end-N` starts N before the partition's high watermark, but never before
+/// its low one.
+pub fn start_offset(
+    lookup: &impl OffsetLookup,
+    topic: &str,
+    partition: i32,
+    spec: OffsetSpec,
+    timeout: Duration,
+) -> Result<Offset> {
+    match spec {
+        OffsetSpec::Beginning | OffsetSpec::End | OffsetSpec::Absolute(_) => spec
+            .to_rdkafka()
+            .ok_or_else(|| anyhow!("no direct offset for {:?}", spec)),
+        OffsetSpec::Timestamp(ms) => Ok(lookup
+            .offset_for_time(topic, partition, ms, timeout)?
+            .map_or(Offset::End, Offset::Offset)),
+        OffsetSpec::FromEnd(n) => {
+            let (low, high) = lookup.watermarks(topic, partition, timeout)?;
+            Ok(Offset::Offset(tail_window(low, high, n as usize).0))
+        }
+    }
+}
+
+/// The first offset a reader assigned `start` reads, given the partition's
+/// watermarks.
+pub fn first_offset(start: Offset, low: i64, high: i64) -> i64 {
+    match start {
+        Offset::Offset(n) => n.clamp(low, high),
+        Offset::End => high,
+        _ => low,
+    }
+}
+
+/// The latest-N plan: `n` when `query` wants the newest `n` messages
+/// (`SelectQuery::latest_n`) and the scan starts from the default offsets, so
+/// each partition only needs reading from `n` before its end. `--dedup-key`
+/// needs every message, like `GROUP BY key`.
+pub fn tail_plan(args: &RunArgs, query: Option<&SelectQuery>) -> Option<usize> {
+    let default_offsets = matches!(args.offset, OffsetSpec::Beginning | OffsetSpec::End)
+        && args.partition_offset.is_none()
+        && args.from_file.is_none();
+    if !default_offsets || args.dedup_key || args.follow {
+        return None;
+    }
+    query?.latest_n()
+}
+
+/// Where to start reading a partition's newest `n` messages given its
+/// watermarks, and how many messages there are from there.
+pub fn tail_window(low: i64, high: i64, n: usize) -> (i64, usize) {
+    let start = high.saturating_sub(n as i64).max(low);
+    (start, (high - start).max(0) as usize)
+}
+
+/// Adjust a partition reader's args and offset for the latest-N plan: start
+/// `n` before the partition's end, which the reader looks up from its own
+/// watermarks, and stop after `n` messages or at the end.
+pub fn tail_reader(args: &mut RunArgs, ctx: &mut ReaderContext, n: usize) -> OffsetSpec {
+    args.limit_per_partition = Some(args.limit_per_partition.map_or(n, |m| m.min(n)));
+    ctx.stop_at_eof = true;
+    OffsetSpec::FromEnd(n as i64)
+}
+
+/// Apply the query's WHERE (or the legacy `--search`/`--search-regex`) to a decoded message
+/// whose `value` is the raw payload text, and shape it for the sinks: `None`
+/// when it doesn't match. Shared by the Kafka consumers and `--from-file`.
+pub fn filter_message(
+    args: &RunArgs,
+    query: Option<&SelectQuery>,
+    mut env: MessageEnvelope,
+) -> Option<MessageEnvelope> {
+    let payload_json = env
+        .value
+        .as_deref()
+        .and_then(|s| serde_json::from_str::<Value>(s).ok())
+        .unwrap_or(Value::Null);
+
+    let matches = if let Some(q) = query {
+        q.r#where
+            .as_ref()
+            .is_none_or(|expr| expr.matches(&MessageView::of(&env, &payload_json)))
+    } else if let Some(ref needle) = args.search {
+        search_matches(
+            needle,
+            &env.key,
+            env.value.as_deref(),
+            args.search_ignore_case,
+        )
+    } else if let Some(ref pattern) = args.search_regex {
+        pattern.is_match(&env.key) || env.value.as_deref().is_some_and(|v| pattern.is_match(v))
+    } else {
+        true
+    };
+    if !matches {
+        return None;
+    }
+
+    // keys_only (computed in main from the query) drops the value; tombstones
+    // stay None, which sinks render as "null" (or --tombstone-marker).
+    // Raw output and --produce-to promise the stored text, not a re-indented copy.
+    let pretty = !(args.no_value_pretty || args.raw_output() || args.produce_to.is_some());
+    if args.pretty_key && !args.raw_output() && args.produce_to.is_none() {
+        env.key = pretty_key(env.key);
+    }
+    env.value = if args.keys_only {
+        None
+    } else {
+        env.value.map(|s| {
+            let shown = display_value(&s, &payload_json, pretty);
+            match args.max_value_bytes {
+                Some(max) => truncate_value(shown, max as usize),
+                None => shown,
+            }
+        })
+    };
+    Some(env)
+}
+
+/// `--pretty-key`: a key holding a JSON object or array, re-indented; any
+/// other key unchanged.
+fn pretty_key(key: String) -> String {
+    match serde_json::from_str::<Value>(&key) {
+        Ok(json @ (Value::Object(_) | Value::Array(_))) => display_value(&key, &json, true),
+        _ => key,
+    }
+}
+
+/// Marker appended to values cut by `--max-value-bytes`.
+const TRUNCATED_MARKER: &str = "…[truncated]";
+
+/// Cut `value` to at most `max` bytes (backing off to a char boundary) and
+/// mark it, so a single huge payload can't dominate memory or rendering.
+fn truncate_value(mut value: String, max: usize) -> String {
+    if value.len() <= max {
+        return value;
+    }
+    let mut end = max;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    value.truncate(end);
+    value.push_str(TRUNCATED_MARKER);
+    value
+}
+
+/// Text stored on the envelope for a payload: re-indented JSON when `pretty`
+/// and the payload parsed (`json` is its parse, Null otherwise), else the
+/// payload verbatim.
+fn display_value(payload: &str, json: &Value, pretty: bool) -> String {
+    if pretty && !json.is_null() {
+        serde_json::to_string_pretty(json).unwrap_or_else(|_| payload.to_string())
+    } else {
+        payload.to_string()
+    }
+}
+
+/// The value as text; with --schema-registry-url, registry-framed Avro values
+/// are decoded to JSON and failing to decode one fails the run.
+async fn decode_value(
+    args: &RunArgs,
+    schemas: &SchemaCache,
+    bytes: &[u8],
+    partition: i32,
+    offset: i64,
+) -> Result<String> {
+    let avro = match &args.schema_registry_url {
+        Some(url) => schemas.decode(url, bytes, args.metadata_timeout()).await,
+        None => None,
+    };
+    match avro {
+        Some(json) => json.with_context(|| format!("partition {} offset {}", partition, offset)),
+        None => decode_text(bytes, args.strict_decode, "value", partition, offset),
+    }
+}
+
+/// Decode a key/payload as UTF-8 text. Invalid bytes are replaced lossily,
+/// unless `strict` (`--strict-decode`), where they abort the scan with the
+/// message's location.
+fn decode_text(
+    bytes: &[u8],
+    strict: bool,
+    what: &str,
+    partition: i32,
+    offset: i64,
+) -> Result<String> {
+    if strict {
+        let text = std::str::from_utf8(bytes).map_err(|e| {
+            anyhow::anyhow!(
+                "partition {} offset {}: {} is not valid UTF-8 ({})",
+                partition,
+                offset,
+                what,
+                e
+            )
+        })?;
+        Ok(text.to_string())
+    } else {
+        Ok(String::from_utf8_lossy(bytes).to_string())
+    }
+}
+
+/// Legacy `--search`: substring match against the key or the raw payload.
+fn search_matches(needle: &str, key: &str, payload: Option<&str>, ignore_case: bool) -> bool {
+    let payload = payload.unwrap_or("");
+    if ignore_case {
+        let needle = needle.to_lowercase();
+        key.to_lowercase().contains(&needle) || payload.to_lowercase().contains(&needle)
+    } else {
+        key.contains(needle) || payload.contains(needle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latest_n_switches_to_a_tail_plan() {
+        let args = RunArgs::default();
+        let plan =
+            |q: &str, args: &RunArgs| tail_plan(args, Some(&crate::query::parse_query(q).unwrap()));
+        assert_eq!(
+            plan("SELECT key FROM t ORDER BY timestamp DESC LIMIT 20", &args),
+            Some(20)
+        );
+        // Ascending, unlimited, filtered or grouped queries still scan
+        assert_eq!(
+            plan("SELECT key FROM t ORDER BY timestamp ASC LIMIT 20", &args),
+            None
+        );
+        assert_eq!(
+            plan("SELECT key FROM t ORDER BY timestamp DESC", &args),
+            None
+        );
+        assert_eq!(
+            plan(
+                "SELECT key FROM t WHERE key = 'a' ORDER BY timestamp DESC LIMIT 5",
+                &args
+            ),
+            None
+        );
+        assert_eq!(
+            plan(
+                "SELECT key FROM t GROUP BY key ORDER BY timestamp DESC LIMIT 5",
+                &args
+            ),
+            None
+        );
+        assert_eq!(
+            plan("SELECT key FROM t ORDER BY value->n DESC LIMIT 5", &args),
+            None
+        );
+        // An explicit starting offset is honoured
+        let from_offset = RunArgs {
+            offset: OffsetSpec::Absolute(1000),
+            ..RunArgs::default()
+        };
+        assert_eq!(
+            plan(
+                "SELECT key FROM t ORDER BY timestamp DESC LIMIT 20",
+                &from_offset
+            ),
+            None
+        );
+        assert_eq!(tail_plan(&args, None), None);
+        let deduped = RunArgs {
+            dedup_key: true,
+            ..RunArgs::default()
+        };
+        assert_eq!(
+            plan(
+                "SELECT key FROM t ORDER BY timestamp DESC LIMIT 20",
+                &deduped
+            ),
+            None
+        );
+
+        assert_eq!(tail_window(0, 100, 20), (80, 20));
+        assert_eq!(tail_window(95, 100, 20), (95, 5));
+        assert_eq!(tail_window(7, 7, 20), (7, 0));
+
+        let mut reader = RunArgs {
+            limit_per_partition: Some(3),
+            ..RunArgs::default()
+        };
+        let mut ctx = ReaderContext::default();
+        assert!(matches!(
+            tail_reader(&mut reader, &mut ctx, 20),
+            OffsetSpec::FromEnd(20)
+        ));
+        assert_eq!(reader.limit_per_partition, Some(3));
+        assert!(ctx.stop_at_eof);
+    }
+
+    #[test]
+    fn follow_keeps_readers_past_eof() {
+        use clap::Parser;
+
+        let follow = RunArgs::parse_from(["rkl", "--topic", "t", "--follow", "-m", "5"]);
+        assert!(matches!(follow.offset_spec(), OffsetSpec::End));
+        let limited = crate::query::parse_query("SELECT key FROM t LIMIT 10").unwrap();
+        assert_eq!(follow.row_limit(Some(&limited)), None);
+        assert_eq!(
+            tail_plan(
+                &follow,
+                Some(
+                    &crate::query::parse_query(
+                        "SELECT key FROM t ORDER BY timestamp DESC LIMIT 20"
+                    )
+                    .unwrap()
+                )
+            ),
+            None
+        );
+        // Even a reader set up to stop at the end keeps tailing
+        let mut reader = follow.for_consumer("t", true);
+        let mut ctx = ReaderContext::default();
+        tail_reader(&mut reader, &mut ctx, 10);
+        assert!(!stops_at_eof(&reader, &ctx));
+
+        let scan = RunArgs::parse_from(["rkl", "--topic", "t", "-m", "5"]);
+        assert_eq!(scan.row_limit(None), Some(5));
+        assert_eq!(scan.row_limit(Some(&limited)), Some(10));
+        // --max-messages doesn't cap a count
+        let count = crate::query::parse_query("SELECT COUNT(*) FROM t").unwrap();
+        assert_eq!(scan.row_limit(Some(&count)), None);
+        let mut reader = scan.for_consumer("t", true);
+        let mut ctx = ReaderContext::default();
+        assert!(!stops_at_eof(&reader, &ctx));
+        tail_reader(&mut reader, &mut ctx, 10);
+        assert!(stops_at_eof(&reader, &ctx));
+
+        // --follow chooses where to start itself
+        assert!(
+            RunArgs::try_parse_from(["rkl", "--topic", "t", "--follow", "--offset", "end"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn timestamp_and_end_minus_n_starts_are_looked_up_per_partition() {
+        // Partition 0 has a message at/after the time; partition 1 doesn't
+        struct Broker;
+        impl OffsetLookup for Broker {
+            fn offset_for_time(
+                &self,
+                topic: &str,
+                partition: i32,
+                ms: i64,
+                _timeout: Duration,
+            ) -> Result<Option<i64>> {
+                assert_eq!((topic, ms), ("orders", 1_704_067_200_000));
+                Ok((partition == 0).then_some(42))
+            }
+
+            fn watermarks(
+                &self,
+                _topic: &str,
+                partition: i32,
+                _timeout: Duration,
+            ) -> Result<(i64, i64)> {
+                Ok(if partition == 0 { (0, 1000) } else { (95, 100) })
+            }
+        }
+        let start = |partition, spec| {
+            start_offset(&Broker, "orders", partition, spec, Duration::from_secs(1)).unwrap()
+        };
+        let at = OffsetSpec::Timestamp(1_704_067_200_000);
+        assert_eq!(start(0, at), Offset::Offset(42));
+        assert_eq!(start(1, at), Offset::End);
+        // end-N: each partition counts back from its own high watermark
+        assert_eq!(start(0, OffsetSpec::FromEnd(100)), Offset::Offset(900));
+        assert_eq!(start(1, OffsetSpec::FromEnd(100)), Offset::Offset(95));
+        // Other specs never hit the broker
+        assert_eq!(start(1, OffsetSpec::Absolute(7)), Offset::Offset(7));
+        assert_eq!(start(0, OffsetSpec::Beginning), Offset::Beginning);
+
+        // Progress counts from where the reader really begins
+        assert_eq!(first_offset(Offset::Beginning, 5, 50), 5);
+        assert_eq!(first_offset(Offset::End, 5, 50), 50);
+        assert_eq!(first_offset(Offset::Offset(2), 5, 50), 5);
+        assert_eq!(first_offset(Offset::Offset(20), 5, 50), 20);
+    }
+
+    #[test]
+    fn search_regex_matches_key_or_value() {
+        let args = RunArgs {
+            search_regex: Some(crate::query::Pattern::new(r"^order-\d+$|\bfailed\b").unwrap()),
+            ..RunArgs::default()
+        };
+        let env = |key: &str, value| MessageEnvelope::at(0).with_key(key).with_value(value);
+        assert!(filter_message(&args, None, env("order-42", Some("{}"))).is_some());
+        assert!(filter_message(&args, None, env("k", Some(r#"{"state":"failed"}"#))).is_some());
+        assert!(filter_message(&args, None, env("order-42x", Some("unfailed"))).is_none());
+        assert!(filter_message(&args, None, env("k", None)).is_none());
+    }
+
+    #[test]
+    fn search_ignore_case_matches_upper_case_haystack() {
+        let payload = Some(r#"{"level":"ERROR","msg":"boom"}"#);
+        assert!(!search_matches("error", "k1", payload, false));
+        assert!(search_matches("error", "k1", payload, true));
+        assert!(search_matches("ERROR", "k1", payload, false));
+        assert!(search_matches("order", "ORDER-42", None, true));
+        assert!(!search_matches("missing", "k1", payload, true));
+    }
+
+    #[test]
+    fn strict_decode_rejects_malformed_payload_with_location() {
+        let bad = b"{\"a\": \xff\xfe}";
+        assert_eq!(
+            decode_text(bad, false, "value", 3, 42).unwrap(),
+            "{\"a\": \u{fffd}\u{fffd}}"
+        );
+        let err = decode_text(bad, true, "value", 3, 42)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("partition 3 offset 42: value is not valid UTF-8"));
+        assert_eq!(decode_text(b"ok", true, "key", 0, 0).unwrap(), "ok");
+    }
+
+    #[test]
+    fn max_value_bytes_truncates_after_filtering() {
+        assert_eq!(truncate_value("short".into(), 10), "short");
+        assert_eq!(truncate_value("abcdef".into(), 4), "abcd…[truncated]");
+        // Never splits a multi-byte char: "é" is 2 bytes
+        assert_eq!(truncate_value("aé".into(), 2), "a…[truncated]");
+
+        let args = RunArgs {
+            max_value_bytes: Some(8),
+            no_value_pretty: true,
+            ..RunArgs::default()
+        };
+        let payload = r#"{"pad":"xxxxxxxxxxxxxxxx","tail":"needle"}"#;
+        let query =
+            crate::query::parse_query("SELECT key, value FROM t WHERE value->tail = 'needle'")
+                .unwrap();
+        let env = MessageEnvelope::at(0).with_key("k").with_value(payload);
+        // The WHERE sees the field past the cut; the stored value is bounded
+        let out = filter_message(&args, Some(&query), env).unwrap();
+        let value = out.value.unwrap();
+        assert_eq!(value, format!("{}{}", &payload[..8], TRUNCATED_MARKER));
+    }
+
+    #[test]
+    fn raw_format_keeps_values_and_keys_on_one_line() {
+        use clap::Parser;
+
+        let payload = r#"{"b": 1, "a": [1, 2]}"#;
+        let env = MessageEnvelope::at(0)
+            .with_key(r#"{"tenant": 7}"#)
+            .with_value(payload);
+        for args in [
+            RunArgs::parse_from(["rkl", "--topic", "t", "--format", "raw", "--pretty-key"]),
+            RunArgs::parse_from(["rkl", "--topic", "t", "--raw", "--pretty-key"]),
+        ] {
+            assert!(args.raw_output());
+            let out = filter_message(&args, None, env.clone()).unwrap();
+            assert_eq!(out.value.as_deref(), Some(payload));
+            assert_eq!(out.key, env.key);
+        }
+        // The table re-indents
+        let table = RunArgs::parse_from(["rkl", "--topic", "t", "--pretty-key"]);
+        let out = filter_message(&table, None, env).unwrap();
+        assert!(out.value.unwrap().contains('\n'));
+        assert!(out.key.contains('\n'));
+    }
+
+    #[test]
+    fn no_value_pretty_keeps_payload_verbatim() {
+        let raw = r#"{"z": 1,  "a": [1,2]}"#;
+        let json: Value = serde_json::from_str(raw).unwrap();
+        assert_eq!(display_value(raw, &json, false), raw);
+        assert_eq!(
+            display_value(raw, &json, true),
+            serde_json::to_string_pretty(&json).unwrap()
+        );
+        // Non-JSON text is never rewritten
+        assert_eq!(display_value("plain", &Value::Null, true), "plain");
+    }
+
+    #[test]
+    fn pretty_key_reindents_only_structured_keys() {
+        assert_eq!(
+            pretty_key(r#"{"b":1,"a":2}"#.into()),
+            "{\n  \"b\": 1,\n  \"a\": 2\n}"
+        );
+        assert_eq!(pretty_key("[1,2]".into()), "[\n  1,\n  2\n]");
+        // Scalars that happen to parse as JSON stay as stored
+        for key in ["123", "true", "\"quoted\"", "user-1", "null"] {
+            assert_eq!(pretty_key(key.into()), key);
+        }
+    }
 }
@@ -0,0 +1,111 @@
+//! Typed CLI failures and the process exit codes scripts can rely on.
+use std::fmt;
+
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_PARSE: i32 = 2;
+pub const EXIT_CONNECTION: i32 = 3;
+pub const EXIT_TOPIC_NOT_FOUND: i32 = 4;
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+#[derive(Debug)]
+pub enum CliError {
+    Parse(String),
+    Connection(String),
+    TopicNotFound(String),
+    Interrupted,
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Parse(e) => write!(f, "Failed to parse --query: {}", e),
+            CliError::Connection(e) => write!(f, "Connection error: {}", e),
+            CliError::TopicNotFound(t) => write!(f, "Topic not found: {}", t),
+            CliError::Interrupted => write!(f, "Interrupted"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Stable name of a failure's category for `--errors-json`; untyped errors are "other".
+pub fn error_kind(err: &anyhow::Error) -> &'static str {
+    match err.downcast_ref::<CliError>() {
+        Some(CliError::Parse(_)) => "parse",
+        Some(CliError::Connection(_)) => "connection",
+        Some(CliError::TopicNotFound(_)) => "topic_not_found",
+        Some(CliError::Interrupted) => "interrupted",
+        None => "other",
+    }
+}
+
+/// The `--errors-json` report: `{"error": .., "kind": .., "topic": ..}`, where
+/// `topic` is the requested topic (null when unknown).
+pub fn error_json(err: &anyhow::Error, topic: Option<&str>) -> serde_json::Value {
+    let topic = match err.downcast_ref::<CliError>() {
+        Some(CliError::TopicNotFound(t)) => Some(t.as_str()),
+        _ => topic,
+    };
+    serde_json::json!({
+        "error": format!("{:#}", err),
+        "kind": error_kind(err),
+        "topic": topic,
+    })
+}
+
+/// Exit code for a failed run; untyped errors map to 1.
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    match err.downcast_ref::<CliError>() {
+        Some(CliError::Parse(_)) => EXIT_PARSE,
+        Some(CliError::Connection(_)) => EXIT_CONNECTION,
+        Some(CliError::TopicNotFound(_)) => EXIT_TOPIC_NOT_FOUND,
+        Some(CliError::Interrupted) => EXIT_INTERRUPTED,
+        None => EXIT_FAILURE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn failures_map_to_documented_codes() {
+        let code = |e: CliError| exit_code(&anyhow::Error::new(e));
+        assert_eq!(code(CliError::Parse("expected FROM".into())), 2);
+        assert_eq!(code(CliError::Connection("broker down".into())), 3);
+        assert_eq!(code(CliError::TopicNotFound("orders".into())), 4);
+        assert_eq!(code(CliError::Interrupted), 130);
+        assert_eq!(exit_code(&anyhow::anyhow!("disk full")), 1);
+
+        // Added context doesn't hide the typed cause
+        let wrapped: anyhow::Result<()> =
+            Err(CliError::TopicNotFound("orders".into())).context("while probing");
+        assert_eq!(exit_code(&wrapped.unwrap_err()), 4);
+    }
+
+    #[test]
+    fn errors_serialize_with_kind_and_topic() {
+        let parse = anyhow::Error::new(CliError::Parse("expected FROM".into()));
+        assert_eq!(
+            error_json(&parse, Some("orders")),
+            serde_json::json!({
+                "error": "Failed to parse --query: expected FROM",
+                "kind": "parse",
+                "topic": "orders",
+            })
+        );
+
+        // The missing topic comes from the error itself; context stays in the message
+        let missing: anyhow::Result<()> =
+            Err(CliError::TopicNotFound("payments".into())).context("while probing");
+        let report = error_json(&missing.unwrap_err(), None);
+        assert_eq!(report["kind"], "topic_not_found");
+        assert_eq!(report["topic"], "payments");
+        assert_eq!(report["error"], "while probing: Topic not found: payments");
+
+        let other = error_json(&anyhow::anyhow!("disk full"), None);
+        assert_eq!(other["kind"], "other");
+        assert!(other["topic"].is_null());
+    }
+}
@@ -1,188 +1,799 @@
 mod args;
 mod consumer;
+mod env_store;
+mod exit_code;
 mod merger;
 mod models;
 mod output;
+mod produce;
 mod query;
+mod replay;
+mod saved_queries;
+mod schema_registry;
 mod tui;
 
-use anyhow::{Context, Result};
-use args::{Cli, Commands, RunArgs};
-use clap::Parser;
+use anyhow::{Context, Result, anyhow};
+use args::{Cli, Commands, ConfigDefaults, RunArgs};
+use clap::{CommandFactory, FromArgMatches};
 use colored::*;
-use consumer::spawn_partition_consumer;
-use merger::run_merger;
-use models::{MessageEnvelope, OffsetSpec, SslConfig};
-use output::TableOutput;
-use query::{OrderDir, SelectItem, parse_query};
+use consumer::{ReaderContext, spawn_partition_consumer};
+use exit_code::{CliError, EXIT_INTERRUPTED, error_json, exit_code};
+use merger::{MergeBuffer, ReaderEvent, RowWindow, merge_order, run_merger};
+use models::{
+    ClusterSpec, MergeOrder, PartitionSummary, ScanProgress, StopSignal, leader_summary,
+    partition_info, partition_leaders, partition_skew_warning,
+};
+use output::{
+    CsvOutput, JsonOutput, LatestPerKey, OutputFormat, OutputSink, RawOutput, SchemaOutput,
+    TableOutput, apply_query_stages,
+};
+use query::{Command, GroupBy, OrderDir, SelectItem, SelectQuery, parse_command, parse_query};
 use rdkafka::config::ClientConfig;
 use rdkafka::consumer::{Consumer, StreamConsumer};
-use std::io::Write as _;
-use std::time::Duration;
+use saved_queries::SavedQueryStore;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 use tokio::sync::mpsc;
 use tokio::task::JoinSet;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::parse_cli();
-    let mode = std::env::var("RKL_MODE").unwrap_or_else(|_| "tui".to_string());
+    let (cli, matches) = Cli::parse_cli();
+    if cli.version {
+        println!("{}", args::version_text());
+        return Ok(());
+    }
+    let env_mode = std::env::var("RKL_MODE").ok();
+    // A bad config file fails a CLI run; the TUI starts without it and says
+    // so in its status panel
+    let config = match ConfigDefaults::load() {
+        Ok(config) => config,
+        Err(e) if cli.command.is_some() || env_mode.as_deref() == Some("cli") => return Err(e),
+        Err(_) => ConfigDefaults::default(),
+    };
+    let mode = config.mode(env_mode);
     match (mode.as_str(), cli.command) {
         ("tui", None) => {
             // TUI mode by default when no subcommand
-            return tui::run(RunArgs::default()).await;
+            let args = tui_args(
+                cli.saved,
+                cli.from_file,
+                cli.max_value_bytes,
+                cli.terminal_title,
+            )?;
+            return tui::run(args.with_config_defaults(&config, None)).await;
         }
         ("cli", None) => {
             // CLI mode without subcommand: parse RunArgs directly from argv
-            let run_args = parse_runargs_from_argv();
-            return run_once_cli(run_args).await;
+            let run_args = parse_runargs_from_argv(&config);
+            let report = ErrorReport::for_args(&run_args);
+            exit_with(run_once_cli(run_args, interrupt_signal()).await, report)
         }
         (_, None) => {
             // Fallback to TUI for unknown mode
-            return tui::run(RunArgs::default()).await;
+            let args = tui_args(
+                cli.saved,
+                cli.from_file,
+                cli.max_value_bytes,
+                cli.terminal_title,
+            )?;
+            return tui::run(args.with_config_defaults(&config, None)).await;
         }
         (_, Some(Commands::Run(args))) => {
-            let args = args;
+            let args = (*args).with_config_defaults(&config, matches.subcommand_matches("run"));
+            let report = ErrorReport::for_args(&args);
+            exit_with(run_subcommand(args, interrupt_signal()).await, report)
+        }
+        (_, Some(Commands::TopicInfo(args))) => {
+            let args = args
+                .run_args()
+                .with_config_defaults(&config, matches.subcommand_matches("topic-info"));
+            let report = ErrorReport::for_args(&args);
+            exit_with(topic_info(&args), report)
+        }
+        (_, Some(Commands::Version)) => {
+            println!("{}", args::version_text());
+            Ok(())
+        }
+    }
+}
 
-            // Parse --query if provided and compute effective settings
-            println!(
-                "{}",
-                format!("Connecting to Kafka broker: {}", args.broker).cyan()
-            );
-            let (query_ast, topic, columns, max_messages, order_desc) =
-                if let Some(ref q) = args.query {
-                    let ast = parse_query(q).context("Failed to parse --query")?;
-                    let columns = ast.select.clone();
-                    let max_messages = ast.limit.or(args.max_messages);
-                    let order_desc = ast
-                        .order
-                        .as_ref()
-                        .map(|o| matches!(o.dir, OrderDir::Desc))
-                        .unwrap_or(false);
-                    println!("{}", format!("Using query: {}", q).cyan());
-                    println!("{}", format!("Topic: {}", ast.from).cyan());
-                    let topic_name = ast.from.clone();
-                    (Some(ast), topic_name, columns, max_messages, order_desc)
-                } else {
-                    let topic_value = args
-                        .topic
-                        .clone()
-                        .expect("topic is required unless --query is provided");
-                    println!("{}", format!("Topic: {}", topic_value).cyan());
-                    let columns = SelectItem::standard(!args.keys_only);
-                    (None, topic_value, columns, args.max_messages, false)
-                };
-
-            let keys_only = !columns.iter().any(|c| matches!(c, SelectItem::Value));
-
-            // One-time consumer just to fetch metadata / partitions
-            let mut probe_cfg = ClientConfig::new();
-            probe_cfg
-                .set("bootstrap.servers", &args.broker)
-                .set("group.id", format!("rkl-probe-{}", uuid::Uuid::new_v4()))
-                .set("enable.auto.commit", "false")
-                .set("auto.offset.reset", "earliest")
-                .set("enable.partition.eof", "true");
-            if args.ssl_ca_pem.is_some()
-                || args.ssl_certificate_pem.is_some()
-                || args.ssl_key_pem.is_some()
-            {
-                probe_cfg.set("security.protocol", "ssl");
-                if let Some(ref s) = args.ssl_ca_pem {
-                    probe_cfg.set("ssl.ca.pem", s);
-                }
-                if let Some(ref s) = args.ssl_certificate_pem {
-                    probe_cfg.set("ssl.certificate.pem", s);
-                }
-                if let Some(ref s) = args.ssl_key_pem {
-                    probe_cfg.set("ssl.key.pem", s);
-                }
+/// Raised by Ctrl-C during a CLI run. The run then stops its readers and lets
+/// the merger drain into the sink, so output is completed rather than cut off.
+/// A second Ctrl-C exits at once, for a run stuck before it reads anything.
+fn interrupt_signal() -> StopSignal {
+    let (raise, interrupt) = StopSignal::channel();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            raise.send_replace(true);
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(EXIT_INTERRUPTED);
             }
-            let probe_consumer: StreamConsumer = probe_cfg
-                .create()
-                .context("Failed to create probe consumer")?;
-
-            let metadata = probe_consumer
-                .fetch_metadata(Some(&topic), Duration::from_secs(10))
-                .context("Failed to fetch metadata")?;
-
-            let topic_md = metadata
-                .topics()
-                .iter()
-                .find(|t| t.name() == topic)
-                .context("Topic not found")?;
-
-            let partitions: Vec<i32> = if let Some(p) = args.partition {
-                vec![p]
+        }
+    });
+    interrupt
+}
+
+/// How a finished CLI run reports itself; captured before the args are consumed.
+struct ErrorReport {
+    no_results_code: i32,
+    json: bool,
+    topic: Option<String>,
+}
+
+impl ErrorReport {
+    fn for_args(args: &RunArgs) -> Self {
+        // Best effort: the topic named by --topic or the query's FROM
+        let topic = args.topic.clone().or_else(|| {
+            args.query
+                .as_deref()
+                .and_then(|q| parse_query(q).ok())
+                .map(|q| q.from)
+        });
+        Self {
+            no_results_code: args.no_results_exit_code,
+            json: args.errors_json,
+            topic,
+        }
+    }
+}
+
+/// Turn a CLI run's outcome into the process exit code (see README "Exit codes").
+fn exit_with(res: Result<u64>, report: ErrorReport) -> Result<()> {
+    match res {
+        Ok(0) if report.no_results_code != 0 => std::process::exit(report.no_results_code),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            if report.json {
+                eprintln!("{}", error_json(&e, report.topic.as_deref()));
             } else {
-                topic_md.partitions().iter().map(|p| p.id()).collect()
-            };
+                eprintln!("{}", format!("Error: {:#}", e).red());
+            }
+            std::process::exit(exit_code(&e));
+        }
+    }
+}
 
-            println!(
-                "{}",
-                format!("Found {} partition(s): {:?}", partitions.len(), partitions).green()
+/// `rkl run`: one pass over the topic, printed as a table (or raw values).
+/// Returns the number of matched messages.
+async fn run_subcommand(mut args: RunArgs, interrupt: StopSignal) -> Result<u64> {
+    resolve_saved_query(&mut args)?;
+    let list_topics_query = args
+        .query
+        .as_deref()
+        .is_some_and(|q| matches!(parse_command(q), Ok(Command::ListTopics)));
+    if list_topics_query {
+        return list_topics(&args);
+    }
+
+    // Parse --query if provided and compute effective settings
+    let source = match &args.from_file {
+        Some(path) => format!("Reading messages from file: {}", path.display()),
+        None => format!("Connecting to Kafka broker: {}", args.broker.join(", ")),
+    };
+    info_line(args.stdout_is_data(), source.cyan());
+    let (query_ast, topic, columns, max_messages, order_desc) = if let Some(ref q) = args.query {
+        let ast = parse_query(q).map_err(|e| CliError::Parse(e.annotated(q)))?;
+        check_follow(&args, &ast)?;
+        let columns = ast.select.clone();
+        let max_messages = args.row_limit(Some(&ast));
+        let order_desc = ast
+            .primary_order()
+            .map(|o| matches!(o.dir, OrderDir::Desc))
+            .unwrap_or(false);
+        info_line(args.stdout_is_data(), format!("Using query: {}", q).cyan());
+        info_line(args.stdout_is_data(), format!("Topic: {}", ast.from).cyan());
+        let topic_name = ast.from.clone();
+        (Some(ast), topic_name, columns, max_messages, order_desc)
+    } else {
+        let topic_value = args
+            .topic
+            .clone()
+            .expect("topic is required unless --query is provided");
+        info_line(
+            args.stdout_is_data(),
+            format!("Topic: {}", topic_value).cyan(),
+        );
+        let columns = SelectItem::standard(!args.keys_only);
+        (None, topic_value, columns, args.row_limit(None), false)
+    };
+
+    // Raw output always needs the value, whatever the SELECT list says
+    let keys_only = !args.raw_output()
+        && args.produce_to.is_none()
+        && !columns.iter().any(SelectItem::needs_value)
+        && !query_ast.as_ref().is_some_and(SelectQuery::needs_value);
+    let clusters = args.clusters()?;
+    let columns = with_cluster_column(columns, &clusters);
+    let (produce, produce_sink) = start_produce(&args, &clusters)?.unzip();
+
+    // Message channel: producers = partition tasks, consumer = merger task
+    let (tx, rx) = mpsc::channel::<ReaderEvent>(args.channel_capacity);
+    let mut joinset = JoinSet::new();
+    let query_arc = query_ast.clone().map(std::sync::Arc::new);
+    let (stop_readers, stop) = StopSignal::channel();
+    let progress = std::sync::Arc::new(ScanProgress::default());
+    let ctx = ReaderContext {
+        stop: Some(stop),
+        progress: Some(progress.clone()),
+        ..ReaderContext::default()
+    };
+    let readers = spawn_readers(
+        &args.for_consumer(&topic, keys_only),
+        &clusters,
+        &topic,
+        query_arc,
+        &ctx,
+        tx,
+        &mut joinset,
+    )?;
+    for ClusterReaders {
+        label,
+        partitions,
+        leaders,
+    } in &readers
+    {
+        let prefix = label
+            .as_deref()
+            .map(|l| format!("[{}] ", l))
+            .unwrap_or_default();
+        info_line(
+            args.stdout_is_data(),
+            format!(
+                "{}Found {} partition(s): {:?}",
+                prefix,
+                partitions.len(),
+                partitions
+            )
+            .green(),
+        );
+        if !leaders.is_empty() {
+            info_line(
+                args.stdout_is_data(),
+                format!("{}Leaders: {}", prefix, leader_summary(leaders)).green(),
             );
-            println!("{}", "Starting readers (one per partition)...".yellow());
-
-            // Message channel: producers = partition tasks, consumer = merger task
-            let (tx, rx) = mpsc::channel::<MessageEnvelope>(args.channel_capacity);
-
-            // Spawn per-partition consumers
-            let mut joinset = JoinSet::new();
-            let offset_spec =
-                OffsetSpec::from_str(&args.offset).unwrap_or_else(|_| OffsetSpec::Beginning);
-            let query_arc = query_ast.clone().map(std::sync::Arc::new);
-            for &p in &partitions {
-                let txp = tx.clone();
-                let mut a = args.clone();
-                // Override effective args when using a query
-                a.topic = Some(topic.clone());
-                a.keys_only = keys_only;
-                if query_ast.is_some() {
-                    a.max_messages = None;
+        }
+    }
+    if let Some(n) = consumer::tail_plan(&args, query_ast.as_ref()) {
+        info_line(
+            args.stdout_is_data(),
+            format!(
+                "Latest-{} plan: reading only the newest {} per partition",
+                n, n
+            )
+            .yellow(),
+        );
+    }
+    info_line(
+        args.stdout_is_data(),
+        "Starting readers (one per partition)...".yellow(),
+    );
+    // merger will know when producers are done once every reader drops its sender
+
+    // Output sink (table, bare values with --raw, --schema, or --produce-to); grouping
+    // and path ordering need every message, so LIMIT then applies after them
+    let (mut out, window) = make_sink(
+        &args,
+        &columns,
+        query_ast.as_ref(),
+        order_desc,
+        max_messages,
+        produce_sink,
+    )?;
+
+    // Merge + print
+    let summaries = {
+        let merging = merge_rows(
+            rx,
+            out.as_mut(),
+            &args,
+            window,
+            merge_order(query_ast.as_ref()),
+            joinset,
+            &stop_readers,
+        );
+        tokio::pin!(merging);
+        let mut waiting = interrupt.clone();
+        let mut interrupted = false;
+        loop {
+            tokio::select! {
+                res = &mut merging => break res?,
+                // Ctrl-C stops the readers as a met LIMIT does: they drop their
+                // senders, and the merger drains what it holds into the sink
+                _ = waiting.raised(), if !interrupted => {
+                    interrupted = true;
+                    stop_readers.send_replace(true);
                 }
-                let q = query_arc.clone();
-                let ssl = if args.ssl_ca_pem.is_some()
-                    || args.ssl_certificate_pem.is_some()
-                    || args.ssl_key_pem.is_some()
-                {
-                    Some(SslConfig {
-                        ca_pem: args.ssl_ca_pem.clone(),
-                        cert_pem: args.ssl_certificate_pem.clone(),
-                        key_pem: args.ssl_key_pem.clone(),
-                    })
-                } else {
-                    None
-                };
-                joinset.spawn(async move {
-                    spawn_partition_consumer(a, p, offset_spec, txp, q, ssl).await
-                });
             }
-            drop(tx); // merger will know when producers are done
-
-            // Output sink (table)
-            let mut table_out =
-                TableOutput::new(args.no_color, columns.clone(), args.max_cell_width);
-
-            // Merge + print
-            run_merger(
-                rx,
-                &mut table_out,
-                args.watermark,
-                args.flush_interval_ms,
-                max_messages,
-                order_desc,
-            )
-            .await?;
+        }
+    };
+    if let Some(warning) = partition_skew_warning(&summaries) {
+        info_line(args.stdout_is_data(), warning.yellow());
+    }
+    report_read(&args, &progress);
+    if let Some(produce) = produce {
+        drop(out);
+        finish_produce(produce).await?;
+    }
+    // Ctrl-C is how --follow ends; other runs still exit as interrupted
+    if interrupt.is_raised() && !args.follow {
+        return Err(CliError::Interrupted.into());
+    }
+
+    Ok(summaries.iter().map(|s| s.matched).sum())
+}
+
+/// `--follow` never reaches the end of the scan, so queries that only emit
+/// there would print nothing.
+fn check_follow(args: &RunArgs, query: &SelectQuery) -> Result<()> {
+    if args.follow && query.buffers_until_end() {
+        return Err(anyhow!(
+            "--follow streams rows as they arrive; GROUP BY key, ORDER BY (other than timestamp ASC) and COUNT(*) need the scan to end"
+        ));
+    }
+    Ok(())
+}
 
-            // Await all consumer tasks (and surface errors if any)
-            while let Some(res) = joinset.join_next().await {
-                res??;
+/// What `spawn_readers` started for one cluster.
+struct ClusterReaders {
+    label: Option<String>,
+    partitions: Vec<i32>,
+    /// Leader broker per partition; empty for `--from-file`
+    leaders: BTreeMap<i32, i32>,
+}
+
+/// Probe each cluster's metadata for `topic` and spawn one consumer per
+/// partition per cluster, all feeding `tx`. `args` are the readers' own (see
+/// `RunArgs::for_consumer`); `ctx` is shared by all of them.
+fn spawn_readers(
+    args: &RunArgs,
+    clusters: &[ClusterSpec],
+    topic: &str,
+    query: Option<std::sync::Arc<SelectQuery>>,
+    ctx: &ReaderContext,
+    tx: mpsc::Sender<ReaderEvent>,
+    joinset: &mut JoinSet<Result<PartitionSummary>>,
+) -> Result<Vec<ClusterReaders>> {
+    if let Some(path) = &args.from_file {
+        let partitions = replay::spawn_replay(args, path, topic, query, tx, joinset, ctx.clone())?;
+        return Ok(vec![ClusterReaders {
+            label: None,
+            partitions,
+            leaders: BTreeMap::new(),
+        }]);
+    }
+    let offset_spec = args.offset_spec();
+    let tail = consumer::tail_plan(args, query.as_deref());
+    let mut readers = Vec::with_capacity(clusters.len());
+    for cluster in clusters {
+        let on = |what: &str, e: rdkafka::error::KafkaError| match &cluster.label {
+            Some(label) => CliError::Connection(format!("{} ({}): {}", what, label, e)),
+            None => CliError::Connection(format!("{}: {}", what, e)),
+        };
+        // One-time consumer just to fetch metadata / partitions
+        let probe_consumer: StreamConsumer = probe_config(cluster, args)
+            .create()
+            .map_err(|e| on("failed to create probe consumer", e))?;
+
+        let metadata = probe_consumer
+            .fetch_metadata(Some(topic), args.metadata_timeout())
+            .map_err(|e| on("failed to fetch metadata", e))?;
+
+        let topic_md = metadata
+            .topics()
+            .iter()
+            .find(|t| t.name() == topic)
+            .ok_or_else(|| CliError::TopicNotFound(topic.to_string()))?;
+
+        let partitions: Vec<i32> = if let Some(p) = args.partition {
+            vec![p]
+        } else {
+            topic_md.partitions().iter().map(|p| p.id()).collect()
+        };
+        if let Some(map) = &args.partition_offset {
+            let all: Vec<i32> = topic_md.partitions().iter().map(|p| p.id()).collect();
+            let unknown = map.unknown_partitions(&all);
+            if !unknown.is_empty() {
+                return Err(anyhow!(
+                    "--partition-offset names partition(s) {:?} that topic {} does not have (partitions: {:?})",
+                    unknown,
+                    topic,
+                    all
+                ));
             }
+        }
 
-            table_out.finish();
-            return Ok(());
+        for &p in &partitions {
+            let mut offset_spec = args
+                .partition_offset
+                .as_ref()
+                .map_or(offset_spec, |map| map.resolve(p, offset_spec));
+            let mut a = args.clone();
+            let mut reader_ctx = ctx.clone();
+            // Latest-N: read only each partition's newest n, then stop
+            if let Some(n) = tail {
+                offset_spec = consumer::tail_reader(&mut a, &mut reader_ctx, n);
+            }
+            let txp = tx.clone();
+            let q = query.clone();
+            let c = cluster.clone();
+            joinset.spawn(async move {
+                spawn_partition_consumer(a, p, offset_spec, txp, q, c, reader_ctx).await
+            });
         }
+        let leaders = partition_leaders(
+            topic_md.partitions().iter().map(|p| (p.id(), p.leader())),
+            &partitions,
+        );
+        readers.push(ClusterReaders {
+            label: cluster.label.clone(),
+            partitions,
+            leaders,
+        });
     }
+    Ok(readers)
+}
+
+/// Config for a throwaway consumer that only fetches metadata and watermarks.
+fn probe_config(cluster: &ClusterSpec, args: &RunArgs) -> ClientConfig {
+    let mut cfg = ClientConfig::new();
+    cfg.set("bootstrap.servers", &cluster.bootstrap)
+        .set("group.id", format!("rkl-probe-{}", uuid::Uuid::new_v4()))
+        .set("enable.auto.commit", "false")
+        .set("auto.offset.reset", "earliest")
+        .set("enable.partition.eof", "true");
+    cluster.apply_security(&mut cfg);
+    args.apply_client_options(&mut cfg);
+    cfg
+}
+
+/// `--query "LIST topics"`: print every topic with its partition count.
+/// Returns the number of topics.
+fn list_topics(args: &RunArgs) -> Result<u64> {
+    if args.from_file.is_some() {
+        anyhow::bail!("LIST topics needs a broker; it can't be used with --from-file");
+    }
+    let cluster = args
+        .clusters()?
+        .into_iter()
+        .next()
+        .context("--broker is required")?;
+    let on = |what: &str, e: rdkafka::error::KafkaError| {
+        CliError::Connection(format!("{}: {}", what, e))
+    };
+    let probe: StreamConsumer = probe_config(&cluster, args)
+        .create()
+        .map_err(|e| on("failed to create probe consumer", e))?;
+    let metadata = probe
+        .fetch_metadata(None, args.metadata_timeout())
+        .map_err(|e| on("failed to fetch metadata", e))?;
+    let mut topics: Vec<(String, usize)> = metadata
+        .topics()
+        .iter()
+        .map(|t| (t.name().to_string(), t.partitions().len()))
+        .collect();
+    topics.sort();
+    println!("{}", output::topic_list_table(&topics, args.no_color));
+    Ok(topics.len() as u64)
+}
+
+/// `rkl topic-info`: print each partition's leader and watermarks.
+/// Returns the number of partitions.
+fn topic_info(args: &RunArgs) -> Result<u64> {
+    let topic = args.topic.as_deref().context("--topic is required")?;
+    let cluster = args
+        .clusters()?
+        .into_iter()
+        .next()
+        .context("--broker is required")?;
+    let on = |what: &str, e: rdkafka::error::KafkaError| {
+        CliError::Connection(format!("{}: {}", what, e))
+    };
+    let probe: StreamConsumer = probe_config(&cluster, args)
+        .create()
+        .map_err(|e| on("failed to create probe consumer", e))?;
+    let metadata = probe
+        .fetch_metadata(Some(topic), args.metadata_timeout())
+        .map_err(|e| on("failed to fetch metadata", e))?;
+    let topic_md = metadata
+        .topics()
+        .iter()
+        .find(|t| t.name() == topic)
+        .ok_or_else(|| CliError::TopicNotFound(topic.to_string()))?;
+    let mut watermarks = BTreeMap::new();
+    for p in topic_md.partitions() {
+        let marks = probe
+            .fetch_watermarks(topic, p.id(), args.metadata_timeout())
+            .map_err(|e| on("failed to fetch watermarks", e))?;
+        watermarks.insert(p.id(), marks);
+    }
+    let rows = partition_info(
+        topic_md.partitions().iter().map(|p| (p.id(), p.leader())),
+        &watermarks,
+    );
+    println!("{}", output::partition_info_table(&rows, args.no_color));
+    Ok(rows.len() as u64)
+}
+
+/// Reading several clusters adds a leading Cluster column unless the query
+/// already selects one.
+fn with_cluster_column(mut columns: Vec<SelectItem>, clusters: &[ClusterSpec]) -> Vec<SelectItem> {
+    // A COUNT(*) total spans every cluster
+    if clusters.len() > 1
+        && !columns.contains(&SelectItem::Cluster)
+        && columns != [SelectItem::Count]
+    {
+        columns.insert(0, SelectItem::Cluster);
+    }
+    columns
+}
+
+/// Run the merger while watching the partition consumers, so a fatal consumer
+/// error (e.g. `--strict-decode`) stops the run instead of waiting on the
+/// other partitions. Once the merger is done the readers are told to stop, and
+/// every partition's summary is returned.
+async fn merge_rows(
+    rx: mpsc::Receiver<ReaderEvent>,
+    out: &mut (dyn OutputSink + Send),
+    args: &RunArgs,
+    window: RowWindow,
+    order: MergeOrder,
+    mut joinset: JoinSet<Result<PartitionSummary>>,
+    stop_readers: &tokio::sync::watch::Sender<bool>,
+) -> Result<Vec<PartitionSummary>> {
+    let mut summaries = Vec::new();
+    let buffer = MergeBuffer {
+        readers: joinset.len(),
+        watermark: args.watermark,
+        flush_interval_ms: args.flush_interval_ms,
+    };
+    let merged = run_merger(rx, out, buffer, window, order, None);
+    tokio::pin!(merged);
+    let mut consumers_done = false;
+    loop {
+        tokio::select! {
+            res = &mut merged => {
+                res?;
+                break;
+            }
+            next = joinset.join_next(), if !consumers_done => match next {
+                Some(res) => summaries.push(res??),
+                None => consumers_done = true,
+            },
+        }
+    }
+    // A global limit stops the merger while readers may still be scanning for
+    // matches they can no longer deliver; they stop and report what they sent.
+    stop_readers.send_replace(true);
+    while let Some(res) = joinset.join_next().await {
+        summaries.push(res??);
+    }
+    Ok(summaries)
+}
+
+/// `--produce-to` in flight: the target and what `finish_produce` reports on.
+struct ProduceRun {
+    target: String,
+    enqueued: produce::Enqueued,
+    failed: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+/// With `--produce-to` (and `--yes-produce`), connect a producer to
+/// `--produce-broker` or the first source cluster and return its sink.
+fn start_produce(
+    args: &RunArgs,
+    clusters: &[ClusterSpec],
+) -> Result<Option<(ProduceRun, Box<dyn OutputSink + Send>)>> {
+    let Some(target) = &args.produce_to else {
+        return Ok(None);
+    };
+    let cluster = match &args.produce_broker {
+        Some(bootstrap) => ClusterSpec {
+            label: None,
+            bootstrap: bootstrap.clone(),
+            ssl: args.ssl_config(),
+            sasl: args.sasl_config(),
+        },
+        None => clusters
+            .first()
+            .cloned()
+            .context("--produce-to needs --produce-broker when reading from a file")?,
+    };
+    if !args.yes_produce {
+        return Err(anyhow!(
+            "--produce-to would write every emitted message to topic {} on {}; pass --yes-produce to confirm",
+            target,
+            cluster.bootstrap
+        ));
+    }
+    let (sender, enqueued) = produce::KafkaSender::new(&cluster, |cfg| {
+        if let Some(ms) = args.connect_timeout_ms {
+            cfg.set("socket.connection.setup.timeout.ms", ms.to_string());
+        }
+    })?;
+    info_line(
+        args.stdout_is_data(),
+        format!(
+            "Producing matches to topic {} on {}",
+            target, cluster.bootstrap
+        )
+        .yellow(),
+    );
+    let failed = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let run = ProduceRun {
+        target: target.clone(),
+        enqueued,
+        failed: failed.clone(),
+    };
+    Ok(Some((
+        run,
+        Box::new(produce::ProduceSink::new(sender, target, failed)),
+    )))
+}
+
+/// Wait for every produced record to be acknowledged and report the count;
+/// any failed record fails the run. The sink must be dropped first, which
+/// lets the enqueueing task finish.
+async fn finish_produce(run: ProduceRun) -> Result<()> {
+    let (deliveries, mut failed) = run.enqueued.await.context("producer task failed")?;
+    let (delivered, mut delivery_errors) = produce::wait_for_deliveries(deliveries).await;
+    failed.append(&mut delivery_errors);
+    if let Ok(mut send_errors) = run.failed.lock() {
+        failed.append(&mut send_errors);
+    }
+    println!(
+        "{}",
+        format!("Produced {} record(s) to topic {}", delivered, run.target).green()
+    );
+    if failed.is_empty() {
+        return Ok(());
+    }
+    for err in failed.iter().take(5) {
+        eprintln!("{}", format!("  {}", err).red());
+    }
+    Err(anyhow!(
+        "{} record(s) failed to produce to {}",
+        failed.len(),
+        run.target
+    ))
+}
+
+/// Pick the CLI output sink: a bordered table, or bare values with `--raw`,
+/// wrapped in the buffering stages for `GROUP BY key` / `ORDER BY <path>`.
+/// Returns the sink and the limit the merger should enforce.
+fn make_sink(
+    args: &RunArgs,
+    columns: &[SelectItem],
+    query: Option<&SelectQuery>,
+    order_desc: bool,
+    limit: Option<usize>,
+    produce: Option<Box<dyn OutputSink + Send>>,
+) -> Result<(Box<dyn OutputSink + Send>, RowWindow)> {
+    // --output-file: buffered, flushed by the sinks after every block
+    let out: Box<dyn Write + Send> = match &args.output_file {
+        Some(path) => {
+            Box::new(BufWriter::new(File::create(path).with_context(|| {
+                format!("Failed to create output file {}", path.display())
+            })?))
+        }
+        None => Box::new(io::stdout()),
+    };
+    // No color escapes in files
+    let no_color = args.no_color || args.output_file.is_some();
+    let sink: Box<dyn OutputSink + Send> = if let Some(produce) = produce {
+        produce
+    } else if args.raw_output() {
+        let raw = RawOutput::new(out, args.tombstone_marker.clone());
+        // --format raw follows the SELECT list; a COUNT(*) row prints its total
+        if args.raw || columns.contains(&SelectItem::Value) {
+            Box::new(raw)
+        } else {
+            Box::new(raw.printing_keys())
+        }
+    } else if args.schema {
+        Box::new(SchemaOutput::new(out, no_color))
+    } else if args.format == OutputFormat::Csv {
+        let aliases = query.map(|q| q.aliases.clone()).unwrap_or_default();
+        Box::new(
+            CsvOutput::new(out, columns.to_vec(), args.time_format, output::now_ms())
+                .with_aliases(aliases),
+        )
+    } else if args.format != OutputFormat::Table {
+        let aliases = query.map(|q| q.aliases.clone()).unwrap_or_default();
+        let array = args.format == OutputFormat::Json;
+        Box::new(JsonOutput::new(out, columns.to_vec(), array).with_aliases(aliases))
+    } else {
+        let aliases = query.map(|q| q.aliases.clone()).unwrap_or_default();
+        Box::new(
+            TableOutput::new(
+                out,
+                no_color,
+                columns.to_vec(),
+                args.max_cell_width,
+                args.time_format,
+                output::now_ms(),
+            )
+            .with_aliases(aliases),
+        )
+    };
+    // Latest-N: the merger can't order descending as rows stream in, so
+    // buffer the (already bounded) tail and emit it newest first
+    if let Some(n) = consumer::tail_plan(args, query) {
+        return Ok((output::newest_first(sink, n), RowWindow::default()));
+    }
+    // --dedup-key runs as `GROUP BY key`: dedup, then order, then limit
+    let deduped;
+    let query = match query {
+        Some(q) if args.dedup_key => {
+            deduped = SelectQuery {
+                group_by: Some(GroupBy::Key),
+                ..q.clone()
+            };
+            Some(&deduped)
+        }
+        q => q,
+    };
+    Ok(match query {
+        Some(q) => apply_query_stages(sink, q, order_desc, limit),
+        None if args.dedup_key => (
+            Box::new(LatestPerKey::new(sink, order_desc, limit)),
+            RowWindow::default(),
+        ),
+        None => (sink, RowWindow { skip: 0, limit }),
+    })
+}
+
+/// The closing "read X of Y" line: how far the readers got against the
+/// partitions' high watermarks. Nothing for `--from-file`, which has none.
+fn report_read(args: &RunArgs, progress: &ScanProgress) {
+    let read = progress.read_progress();
+    if let Some(percent) = read.percent() {
+        info_line(
+            args.stdout_is_data(),
+            format!(
+                "Read {} of {} messages up to the partitions' ends ({:.1}%)",
+                read.read, read.total, percent
+            )
+            .green(),
+        );
+    }
+}
+
+/// Status chatter goes to stderr when stdout carries data, so it stays pipeable.
+fn info_line(to_stderr: bool, line: ColoredString) {
+    if to_stderr {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Build the TUI's initial args, seeding the editor from a saved query if requested.
+fn tui_args(
+    saved: Option<String>,
+    from_file: Option<std::path::PathBuf>,
+    max_value_bytes: Option<u64>,
+    terminal_title: bool,
+) -> Result<RunArgs> {
+    let mut args = RunArgs {
+        saved,
+        from_file,
+        max_value_bytes,
+        terminal_title,
+        ..RunArgs::default()
+    };
+    resolve_saved_query(&mut args)?;
+    Ok(args)
+}
+
+/// Replace `--saved <name>` with the stored query text.
+fn resolve_saved_query(args: &mut RunArgs) -> Result<()> {
+    if let Some(ref name) = args.saved {
+        let store = SavedQueryStore::load();
+        args.query = Some(store.lookup(name)?.to_string());
+    }
+    Ok(())
 }
 
 fn logs_dir() -> std::path::PathBuf {
@@ -206,7 +817,7 @@ fn log_cli_error(err: &str) {
     }
 }
 
-fn parse_runargs_from_argv() -> RunArgs {
+fn parse_runargs_from_argv(config: &ConfigDefaults) -> RunArgs {
     let argv: Vec<String> = std::env::args().collect();
     // Accept either: rkl --query "..." or rkl "..."
     // Reuse clap by pretending we're parsing RunArgs as a top-level command
@@ -237,130 +848,24 @@ fn parse_runargs_from_argv() -> RunArgs {
                 args.push(a);
             }
         }
-        RunArgs::parse_from(args)
+        parse_runargs(args, config)
     } else {
-        RunArgs::parse_from(argv)
+        parse_runargs(argv, config)
     }
 }
 
-async fn run_once_cli(args: RunArgs) -> Result<()> {
-    // Run the same pipeline as the Run subcommand and log errors
-    let res = async {
-        // One-time consumer just to fetch metadata / partitions
-        let (query_ast, topic, columns, max_messages, order_desc) = if let Some(ref q) = args.query
-        {
-            let ast = parse_query(q).context("Failed to parse --query")?;
-            let columns = ast.select.clone();
-            let max_messages = ast.limit.or(args.max_messages);
-            let order_desc = ast
-                .order
-                .as_ref()
-                .map(|o| matches!(o.dir, OrderDir::Desc))
-                .unwrap_or(false);
-            let topic_name = ast.from.clone();
-            (Some(ast), topic_name, columns, max_messages, order_desc)
-        } else {
-            let topic_value = args
-                .topic
-                .clone()
-                .context("topic is required unless --query is provided")?;
-            let columns = SelectItem::standard(!args.keys_only);
-            (None, topic_value, columns, args.max_messages, false)
-        };
-
-        let keys_only = !columns.iter().any(|c| matches!(c, SelectItem::Value));
-
-        let mut probe_cfg = ClientConfig::new();
-        probe_cfg
-            .set("bootstrap.servers", &args.broker)
-            .set("group.id", format!("rkl-probe-{}", uuid::Uuid::new_v4()))
-            .set("enable.auto.commit", "false")
-            .set("auto.offset.reset", "earliest")
-            .set("enable.partition.eof", "true");
-        if args.ssl_ca_pem.is_some()
-            || args.ssl_certificate_pem.is_some()
-            || args.ssl_key_pem.is_some()
-        {
-            probe_cfg.set("security.protocol", "ssl");
-            if let Some(ref s) = args.ssl_ca_pem {
-                probe_cfg.set("ssl.ca.pem", s);
-            }
-            if let Some(ref s) = args.ssl_certificate_pem {
-                probe_cfg.set("ssl.certificate.pem", s);
-            }
-            if let Some(ref s) = args.ssl_key_pem {
-                probe_cfg.set("ssl.key.pem", s);
-            }
-        }
-        let probe_consumer: StreamConsumer = probe_cfg
-            .create()
-            .context("Failed to create probe consumer")?;
-
-        let metadata = probe_consumer
-            .fetch_metadata(Some(&topic), Duration::from_secs(10))
-            .context("Failed to fetch metadata")?;
-
-        let topic_md = metadata
-            .topics()
-            .iter()
-            .find(|t| t.name() == topic)
-            .context("Topic not found")?;
-
-        let partitions: Vec<i32> = if let Some(p) = args.partition {
-            vec![p]
-        } else {
-            topic_md.partitions().iter().map(|p| p.id()).collect()
-        };
-
-        let (tx, rx) = mpsc::channel::<MessageEnvelope>(args.channel_capacity);
-        let mut joinset = JoinSet::new();
-        let offset_spec =
-            OffsetSpec::from_str(&args.offset).unwrap_or_else(|_| OffsetSpec::Beginning);
-        let query_arc = query_ast.clone().map(std::sync::Arc::new);
-        for &p in &partitions {
-            let txp = tx.clone();
-            let mut a = args.clone();
-            a.topic = Some(topic.clone());
-            a.keys_only = keys_only;
-            if query_ast.is_some() {
-                a.max_messages = None;
-            }
-            let q = query_arc.clone();
-            let ssl = if args.ssl_ca_pem.is_some()
-                || args.ssl_certificate_pem.is_some()
-                || args.ssl_key_pem.is_some()
-            {
-                Some(SslConfig {
-                    ca_pem: args.ssl_ca_pem.clone(),
-                    cert_pem: args.ssl_certificate_pem.clone(),
-                    key_pem: args.ssl_key_pem.clone(),
-                })
-            } else {
-                None
-            };
-            joinset.spawn(
-                async move { spawn_partition_consumer(a, p, offset_spec, txp, q, ssl).await },
-            );
-        }
-        drop(tx);
-        let mut table_out = TableOutput::new(args.no_color, columns.clone(), args.max_cell_width);
-        run_merger(
-            rx,
-            &mut table_out,
-            args.watermark,
-            args.flush_interval_ms,
-            max_messages,
-            order_desc,
-        )
-        .await?;
-        while let Some(res) = joinset.join_next().await {
-            res??;
-        }
-        table_out.finish();
-        Ok(())
-    }
-    .await;
+/// `RunArgs` from `argv`, with `config` filling the flags it does not give.
+fn parse_runargs(argv: Vec<String>, config: &ConfigDefaults) -> RunArgs {
+    let matches = RunArgs::command().get_matches_from(argv);
+    RunArgs::from_arg_matches(&matches)
+        .unwrap_or_else(|e| e.exit())
+        .with_config_defaults(config, Some(&matches))
+}
 
+/// `RKL_MODE=cli` without a subcommand: the same pass as `rkl run`, with
+/// errors also logged to cli-error.log.
+async fn run_once_cli(args: RunArgs, interrupt: StopSignal) -> Result<u64> {
+    let res = run_subcommand(args, interrupt).await;
     if let Err(ref e) = res {
         log_cli_error(&format!("{}", e));
     }
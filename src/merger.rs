@@ -1,106 +1,321 @@
-use crate::models::{MessageEnvelope, SortableEnvelope};
+use crate::models::{
+    MergeOrder, MessageEnvelope, ScanProgress, SortField, SortKey, SortableEnvelope,
+};
 use crate::output::OutputSink;
+use crate::query::{OrderDir, OrderField, SelectQuery};
 use anyhow::Result;
 use std::cmp::Reverse;
-use std::collections::BinaryHeap;
-use tokio::sync::mpsc::Receiver;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc::error::SendError;
+use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::time::{Duration, interval};
 
-enum HeapKind {
-    Asc(BinaryHeap<Reverse<SortableEnvelope>>),
-    Desc(BinaryHeap<SortableEnvelope>),
+/// A partition reader: its cluster label and partition.
+pub type ReaderId = (Option<String>, i32);
+
+/// What partition readers send the merger.
+#[derive(Debug)]
+pub enum ReaderEvent {
+    /// A matched message; its timestamp also marks how far its reader has read
+    Row(MessageEnvelope),
+    /// The reader has read up to `timestamp_ms` without a match since its last event
+    Progress { reader: ReaderId, timestamp_ms: i64 },
+    /// The reader caught up with the end of its partition, or finished; it
+    /// holds no rows back until it sends again
+    Idle(ReaderId),
 }
 
-impl HeapKind {
-    fn new(desc: bool) -> Self {
-        if desc {
-            HeapKind::Desc(BinaryHeap::new())
-        } else {
-            HeapKind::Asc(BinaryHeap::new())
+/// A reader's end of the merger channel. Messages that were read but filtered
+/// out are reported as progress at most once per `every`, so a reader with no
+/// matches still lets the merge move past it.
+pub struct ReaderTx {
+    tx: Sender<ReaderEvent>,
+    id: ReaderId,
+    every: Duration,
+    last_sent: Instant,
+}
+
+impl ReaderTx {
+    pub fn new(
+        tx: Sender<ReaderEvent>,
+        cluster: Option<String>,
+        partition: i32,
+        every: Duration,
+    ) -> Self {
+        Self {
+            tx,
+            id: (cluster, partition),
+            every,
+            last_sent: Instant::now(),
         }
     }
-    fn len(&self) -> usize {
-        match self {
-            HeapKind::Asc(h) => h.len(),
-            HeapKind::Desc(h) => h.len(),
+
+    /// Send a matched row; fails once the merger is gone.
+    pub async fn row(&mut self, env: MessageEnvelope) -> Result<(), SendError<ReaderEvent>> {
+        self.last_sent = Instant::now();
+        self.tx.send(ReaderEvent::Row(env)).await
+    }
+
+    /// Note a message that was read but did not match.
+    pub async fn skipped(&mut self, timestamp_ms: i64) -> Result<(), SendError<ReaderEvent>> {
+        if self.last_sent.elapsed() < self.every {
+            return Ok(());
         }
+        self.last_sent = Instant::now();
+        let reader = self.id.clone();
+        self.tx
+            .send(ReaderEvent::Progress {
+                reader,
+                timestamp_ms,
+            })
+            .await
     }
-    fn push(&mut self, env: MessageEnvelope) {
-        match self {
-            HeapKind::Asc(h) => h.push(Reverse(SortableEnvelope(env))),
-            HeapKind::Desc(h) => h.push(SortableEnvelope(env)),
+
+    /// Report that the reader is at the end of its partition (or done).
+    pub async fn idle(&mut self) -> Result<(), SendError<ReaderEvent>> {
+        self.last_sent = Instant::now();
+        self.tx.send(ReaderEvent::Idle(self.id.clone())).await
+    }
+}
+
+/// How far each reader has read, to tell which buffered rows no reader can
+/// still precede. Assumes timestamps rise within a partition, as they do for
+/// broker (log-append) time and, near enough, for producer time.
+struct ReaderClock {
+    expected: usize,
+    /// Latest timestamp read per reader; `None` while it is idle
+    read_to: HashMap<ReaderId, Option<i64>>,
+}
+
+impl ReaderClock {
+    fn new(expected: usize) -> Self {
+        Self {
+            expected,
+            read_to: HashMap::new(),
         }
     }
+
+    fn advance(&mut self, reader: ReaderId, timestamp_ms: i64) {
+        self.read_to.insert(reader, Some(timestamp_ms));
+    }
+
+    fn idle(&mut self, reader: ReaderId) {
+        self.read_to.insert(reader, None);
+    }
+
+    /// Rows older than this are final. `None` until every reader has been
+    /// heard from; `i64::MAX` once all of them are idle.
+    fn horizon(&self) -> Option<i64> {
+        if self.read_to.len() < self.expected {
+            return None;
+        }
+        Some(
+            self.read_to
+                .values()
+                .flatten()
+                .copied()
+                .min()
+                .unwrap_or(i64::MAX),
+        )
+    }
+}
+
+/// Min-heap on the merge order: pops the row that prints next.
+struct SortHeap {
+    heap: BinaryHeap<Reverse<SortableEnvelope>>,
+    keys: Arc<[SortKey]>,
+}
+
+impl SortHeap {
+    fn new(order: MergeOrder) -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+            keys: order.keys.into(),
+        }
+    }
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+    fn push(&mut self, env: MessageEnvelope) {
+        self.heap
+            .push(Reverse(SortableEnvelope(env, self.keys.clone())));
+    }
     fn pop(&mut self) -> Option<MessageEnvelope> {
-        match self {
-            HeapKind::Asc(h) => h.pop().map(|Reverse(se)| se.0),
-            HeapKind::Desc(h) => h.pop().map(|se| se.0),
+        self.heap.pop().map(|Reverse(se)| se.0)
+    }
+    /// Pop the next row if it is older than `before` (any row when `None`).
+    fn pop_before(&mut self, before: Option<i64>) -> Option<MessageEnvelope> {
+        let Reverse(next) = self.heap.peek()?;
+        if before.is_some_and(|b| next.0.timestamp_ms >= b) {
+            return None;
         }
+        self.pop()
+    }
+}
+
+/// The merger's ordering for a query. `ORDER BY <path>` is sorted later by its
+/// own stage, so the merger keeps timestamp order for it. A count needs none.
+pub fn merge_order(query: Option<&SelectQuery>) -> MergeOrder {
+    if query.is_some_and(SelectQuery::is_count) {
+        return MergeOrder {
+            keys: Vec::new(),
+            unordered: true,
+        };
+    }
+    let specs = query.and_then(|q| q.order.as_deref()).unwrap_or_default();
+    let keys = specs
+        .iter()
+        .map(|spec| SortKey {
+            field: match spec.field {
+                OrderField::Timestamp | OrderField::Path(_) => SortField::Timestamp,
+                OrderField::Offset => SortField::Offset,
+                OrderField::Partition => SortField::Partition,
+                OrderField::Key => SortField::Key,
+            },
+            desc: spec.dir == OrderDir::Desc,
+        })
+        .collect();
+    MergeOrder {
+        keys,
+        unordered: false,
     }
 }
 
-/// Receives envelopes from all partitions, maintains a min-heap by timestamp,
-/// and periodically flushes in-order rows to the output sink.
-pub async fn run_merger<S: OutputSink + Send>(
-    mut rx: Receiver<MessageEnvelope>,
+/// Which merged rows reach the sink: `skip` are dropped first (`OFFSET`), then
+/// at most `limit` are emitted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RowWindow {
+    pub skip: usize,
+    pub limit: Option<usize>,
+}
+
+/// How the merger buffers rows between flushes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergeBuffer {
+    /// How many readers feed the channel
+    pub readers: usize,
+    /// Heap size that forces out the oldest half, even ahead of a lagging reader
+    pub watermark: usize,
+    pub flush_interval_ms: u64,
+}
+
+/// Receives rows from all partitions, maintains a heap keyed on the `order`
+/// keys, and periodically flushes in-order rows to the output sink. Oldest-first
+/// merges are k-way: a tick only emits rows older than what every active reader
+/// has read, so a slow partition can't be overtaken. When `progress` is given,
+/// a heartbeat is sent to the sink on each tick where the scanned count moved.
+/// Returns how many rows reached the sink.
+pub async fn run_merger<S: OutputSink + Send + ?Sized>(
+    mut rx: Receiver<ReaderEvent>,
     out: &mut S,
-    watermark: usize,
-    flush_interval_ms: u64,
-    max_messages: Option<usize>,
-    order_desc: bool,
-) -> Result<()> {
-    let mut heap = HeapKind::new(order_desc);
-    let mut tick = interval(Duration::from_millis(flush_interval_ms));
+    buffer: MergeBuffer,
+    window: RowWindow,
+    order: MergeOrder,
+    progress: Option<Arc<ScanProgress>>,
+) -> Result<usize> {
+    let RowWindow {
+        mut skip,
+        limit: max_messages,
+    } = window;
+    let gated = order.oldest_first();
+    let unordered = order.unordered;
+    let mut heap = SortHeap::new(order);
+    let mut clock = ReaderClock::new(buffer.readers);
+    let mut tick = interval(Duration::from_millis(buffer.flush_interval_ms));
     let mut emitted: usize = 0;
+    let mut last_scanned: u64 = 0;
 
     loop {
         tokio::select! {
             biased;
 
             _ = tick.tick() => {
-                // periodic flush
-                drain_heap(&mut heap, out, usize::MAX, &mut emitted, max_messages);
+                // periodic flush, of what no reader can still precede
+                let horizon = if gated { clock.horizon() } else { Some(i64::MAX) };
+                if let Some(horizon) = horizon {
+                    drain_heap(&mut heap, out, usize::MAX, Some(horizon), &mut emitted, &mut skip, max_messages);
+                }
+                if let Some(ref p) = progress {
+                    let (scanned, matched) = p.snapshot();
+                    if scanned != last_scanned {
+                        last_scanned = scanned;
+                        out.heartbeat(scanned, matched);
+                    }
+                }
                 if done(emitted, max_messages) { break; }
             }
 
-            maybe_msg = rx.recv() => {
-                if let Some(env) = maybe_msg {
+            maybe_event = rx.recv() => match maybe_event {
+                Some(ReaderEvent::Row(env)) if unordered => {
+                    if skip > 0 {
+                        skip -= 1;
+                    } else {
+                        out.push(&env);
+                        emitted += 1;
+                    }
+                    if done(emitted, max_messages) { break; }
+                }
+                Some(ReaderEvent::Row(env)) => {
+                    clock.advance((env.cluster.clone(), env.partition), env.timestamp_ms);
                     heap.push(env);
-                    if heap.len() >= watermark {
-                        // flush oldest ~half to keep latency low
+                    if heap.len() >= buffer.watermark {
+                        // bound memory: flush the oldest ~half even past a lagging reader
                         let target = heap.len() / 2;
-                        drain_heap(&mut heap, out, target, &mut emitted, max_messages);
+                        drain_heap(&mut heap, out, target, None, &mut emitted, &mut skip, max_messages);
                         if done(emitted, max_messages) { break; }
                     }
-                } else {
+                }
+                Some(ReaderEvent::Progress { reader, timestamp_ms }) => clock.advance(reader, timestamp_ms),
+                Some(ReaderEvent::Idle(reader)) => clock.idle(reader),
+                None => {
                     // producers finished; drain all remaining
-                    drain_heap(&mut heap, out, usize::MAX, &mut emitted, max_messages);
+                    drain_heap(&mut heap, out, usize::MAX, None, &mut emitted, &mut skip, max_messages);
+                    if let Some(ref p) = progress {
+                        let (scanned, matched) = p.snapshot();
+                        if scanned != last_scanned {
+                            out.heartbeat(scanned, matched);
+                        }
+                    }
                     break;
                 }
             }
         }
     }
 
-    Ok(())
+    out.finish();
+    Ok(emitted)
 }
 
-fn drain_heap<S: OutputSink>(
-    heap: &mut HeapKind,
+/// Emit up to `max_rows` rows in order, stopping at the first row that is not
+/// older than `before` (if given).
+fn drain_heap<S: OutputSink + ?Sized>(
+    heap: &mut SortHeap,
     out: &mut S,
     max_rows: usize,
+    before: Option<i64>,
     emitted: &mut usize,
+    skip: &mut usize,
     max_messages: Option<usize>,
 ) {
     let mut n = 0usize;
-    while let Some(env) = heap.pop() {
-        out.push(&env);
-        *emitted += 1;
+    let mut pushed = false;
+    while let Some(env) = heap.pop_before(before) {
         n += 1;
+        if *skip > 0 {
+            // OFFSET: dropped rows don't count toward the limit
+            *skip -= 1;
+        } else {
+            out.push(&env);
+            pushed = true;
+            *emitted += 1;
+        }
         if n >= max_rows || done(*emitted, max_messages) {
             break;
         }
     }
-    if n > 0 {
+    if pushed {
         out.flush_block();
     }
 }
@@ -109,3 +324,538 @@ fn drain_heap<S: OutputSink>(
 fn done(emitted: usize, max: Option<usize>) -> bool {
     max.map(|m| emitted >= m).unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tokio::sync::mpsc;
+
+    /// One reader; the tests close the channel, which drains everything.
+    fn buffer(watermark: usize, flush_interval_ms: u64) -> MergeBuffer {
+        MergeBuffer {
+            readers: 1,
+            watermark,
+            flush_interval_ms,
+        }
+    }
+
+    /// Records what reaches it. Clones share the record, so a test can keep
+    /// one while a buffering stage owns another.
+    #[derive(Clone, Default)]
+    struct RecordingSink {
+        rows: Arc<Mutex<Vec<MessageEnvelope>>>,
+        heartbeats: Arc<Mutex<Vec<(u64, u64)>>>,
+    }
+
+    impl RecordingSink {
+        fn rows(&self) -> Vec<MessageEnvelope> {
+            self.rows.lock().unwrap().clone()
+        }
+        fn heartbeats(&self) -> Vec<(u64, u64)> {
+            self.heartbeats.lock().unwrap().clone()
+        }
+    }
+
+    impl OutputSink for RecordingSink {
+        fn push(&mut self, env: &MessageEnvelope) {
+            self.rows.lock().unwrap().push(env.clone());
+        }
+        fn heartbeat(&mut self, scanned: u64, matched: u64) {
+            self.heartbeats.lock().unwrap().push((scanned, matched));
+        }
+    }
+
+    #[tokio::test]
+    async fn heartbeat_reports_scanned_without_matches() {
+        let (tx, rx) = mpsc::channel::<ReaderEvent>(8);
+        let progress = Arc::new(ScanProgress::default());
+        let producer = {
+            let progress = progress.clone();
+            tokio::spawn(async move {
+                for _ in 0..3 {
+                    progress.record_scanned();
+                    tokio::time::sleep(Duration::from_millis(15)).await;
+                }
+                drop(tx);
+            })
+        };
+        let mut sink = RecordingSink::default();
+        run_merger(
+            rx,
+            &mut sink,
+            buffer(16, 5),
+            RowWindow::default(),
+            MergeOrder::default(),
+            Some(progress),
+        )
+        .await
+        .unwrap();
+        producer.await.unwrap();
+
+        let heartbeats = sink.heartbeats();
+        assert!(sink.rows().is_empty());
+        assert!(!heartbeats.is_empty());
+        assert!(heartbeats.iter().all(|&(_, matched)| matched == 0));
+        let scanned: Vec<u64> = heartbeats.iter().map(|&(s, _)| s).collect();
+        assert!(scanned.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(scanned.last(), Some(&3));
+    }
+
+    #[tokio::test]
+    async fn json_sink_closes_its_array_after_the_stop_signal() {
+        use crate::models::StopSignal;
+        use crate::output::JsonOutput;
+        use crate::query::SelectItem;
+
+        let (tx, rx) = mpsc::channel::<ReaderEvent>(8);
+        let (raise, mut stop) = StopSignal::channel();
+        // A followed reader: rows, then nothing until it is told to stop
+        let reader = tokio::spawn(async move {
+            for i in 0..3 {
+                let env = MessageEnvelope::at(i).with_key(&format!("k{}", i));
+                tx.send(ReaderEvent::Row(env)).await.unwrap();
+            }
+            stop.raised().await;
+            drop(tx);
+        });
+        let mut buf = Vec::new();
+        {
+            let mut sink = JsonOutput::new(&mut buf, vec![SelectItem::Key], true);
+            let merging = run_merger(
+                rx,
+                &mut sink,
+                buffer(16, 10_000),
+                RowWindow::default(),
+                MergeOrder::default(),
+                None,
+            );
+            tokio::pin!(merging);
+            tokio::select! {
+                _ = &mut merging => panic!("merger ended before the stop signal"),
+                _ = tokio::time::sleep(Duration::from_millis(30)) => {}
+            }
+            raise.send_replace(true);
+            merging.await.unwrap();
+        }
+        reader.await.unwrap();
+
+        let out = String::from_utf8(buf).unwrap();
+        assert!(out.trim_end().ends_with(']'));
+        let parsed: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(parsed.as_array().map(Vec::len), Some(3));
+    }
+
+    #[tokio::test]
+    async fn envelopes_from_two_clusters_reach_sink_with_tags() {
+        let (tx, rx) = mpsc::channel::<ReaderEvent>(8);
+        let env = |cluster: &str, offset: i64, ts: i64| {
+            MessageEnvelope::at(offset)
+                .with_key(&format!("k{}", offset))
+                .with_timestamp(ts)
+                .with_cluster(cluster)
+        };
+        let producers: Vec<_> = [("source", 100), ("mirror", 150)]
+            .into_iter()
+            .map(|(cluster, base)| {
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    for i in 0..2 {
+                        tx.send(ReaderEvent::Row(env(cluster, i, base + i * 100)))
+                            .await
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+        let mut sink = RecordingSink::default();
+        run_merger(
+            rx,
+            &mut sink,
+            buffer(16, 10_000),
+            RowWindow::default(),
+            MergeOrder::default(),
+            None,
+        )
+        .await
+        .unwrap();
+        for p in producers {
+            p.await.unwrap();
+        }
+
+        let rows = sink.rows();
+        let tags: Vec<(&str, i64)> = rows
+            .iter()
+            .map(|e| (e.cluster.as_deref().unwrap(), e.timestamp_ms))
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                ("source", 100),
+                ("mirror", 150),
+                ("source", 200),
+                ("mirror", 250)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn global_limit_caps_rows_across_partitions() {
+        let (tx, rx) = mpsc::channel::<ReaderEvent>(16);
+        for partition in 0..3 {
+            for offset in 0..4 {
+                let env = MessageEnvelope::at(offset)
+                    .on_partition(partition)
+                    .with_timestamp(offset * 10 + partition as i64);
+                tx.send(ReaderEvent::Row(env)).await.unwrap();
+            }
+        }
+        drop(tx);
+        let mut sink = RecordingSink::default();
+        run_merger(
+            rx,
+            &mut sink,
+            buffer(64, 10_000),
+            RowWindow {
+                skip: 0,
+                limit: Some(5),
+            },
+            MergeOrder::default(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Five rows in total, oldest first, drawn from every partition
+        let got: Vec<(i32, i64)> = sink
+            .rows()
+            .iter()
+            .map(|e| (e.partition, e.offset))
+            .collect();
+        assert_eq!(got, vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1)]);
+    }
+
+    async fn merged(rows: &[(i32, i64, i64, &str)], query: &str) -> Vec<(i32, i64)> {
+        let (tx, rx) = mpsc::channel::<ReaderEvent>(rows.len());
+        for &(partition, offset, timestamp_ms, key) in rows {
+            let env = MessageEnvelope::at(offset)
+                .on_partition(partition)
+                .with_timestamp(timestamp_ms)
+                .with_key(key);
+            tx.send(ReaderEvent::Row(env)).await.unwrap();
+        }
+        drop(tx);
+        let query = crate::query::parse_query(query).unwrap();
+        let mut sink = RecordingSink::default();
+        run_merger(
+            rx,
+            &mut sink,
+            buffer(64, 10_000),
+            RowWindow {
+                skip: query.skip.unwrap_or(0),
+                limit: query.limit,
+            },
+            merge_order(Some(&query)),
+            None,
+        )
+        .await
+        .unwrap();
+        sink.rows()
+            .iter()
+            .map(|e| (e.partition, e.offset))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn order_by_offset_and_key_sort_on_that_field() {
+        let rows = [
+            (0, 4, 100, "carol"),
+            (1, 9, 200, "alice"),
+            (0, 7, 300, "bob"),
+            (1, 2, 400, "alice"),
+        ];
+        assert_eq!(
+            merged(&rows, "SELECT key FROM t ORDER BY offset DESC").await,
+            vec![(1, 9), (0, 7), (0, 4), (1, 2)]
+        );
+        // Equal keys fall back to the oldest first
+        assert_eq!(
+            merged(&rows, "SELECT key FROM t ORDER BY key ASC").await,
+            vec![(1, 9), (1, 2), (0, 7), (0, 4)]
+        );
+        assert_eq!(
+            merged(&rows, "SELECT key FROM t ORDER BY partition").await,
+            vec![(0, 4), (0, 7), (1, 9), (1, 2)]
+        );
+    }
+
+    #[tokio::test]
+    async fn offset_skips_rows_before_the_limit_counts() {
+        let rows: Vec<(i32, i64, i64, &str)> = (0..20).map(|i| (0, i, i * 10, "k")).collect();
+        let offsets = |got: Vec<(i32, i64)>| got.into_iter().map(|(_, o)| o).collect::<Vec<_>>();
+        assert_eq!(
+            offsets(merged(&rows, "SELECT key FROM t LIMIT 10 OFFSET 5").await),
+            (5..15).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            offsets(merged(&rows, "SELECT key FROM t OFFSET 17").await),
+            vec![17, 18, 19]
+        );
+        assert!(
+            merged(
+                &rows,
+                "SELECT key FROM t ORDER BY offset DESC LIMIT 3 OFFSET 30"
+            )
+            .await
+            .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn later_order_by_keys_break_ties_in_their_own_direction() {
+        let rows = [
+            (0, 4, 100, "carol"),
+            (1, 9, 200, "alice"),
+            (0, 7, 300, "bob"),
+            (1, 2, 400, "alice"),
+            (0, 1, 500, "alice"),
+        ];
+        assert_eq!(
+            merged(
+                &rows,
+                "SELECT key FROM t ORDER BY partition ASC, offset DESC"
+            )
+            .await,
+            vec![(0, 7), (0, 4), (0, 1), (1, 9), (1, 2)]
+        );
+        assert_eq!(
+            merged(
+                &rows,
+                "SELECT key FROM t ORDER BY partition DESC, offset ASC"
+            )
+            .await,
+            vec![(1, 2), (1, 9), (0, 1), (0, 4), (0, 7)]
+        );
+        assert_eq!(
+            merged(
+                &rows,
+                "SELECT key FROM t ORDER BY key ASC, partition DESC, timestamp DESC"
+            )
+            .await,
+            vec![(1, 2), (1, 9), (0, 1), (0, 7), (0, 4)]
+        );
+    }
+
+    #[tokio::test]
+    async fn order_by_path_sorts_buffered_rows_numerically() {
+        use crate::output::{SortBy, SortedRows};
+        use crate::query::{JsonPath, RootPath};
+
+        let (tx, rx) = mpsc::channel::<ReaderEvent>(16);
+        let latencies = [Some("120"), Some("5"), None, Some("980"), Some("42.5")];
+        for (offset, latency) in latencies.iter().enumerate() {
+            let value = match latency {
+                Some(n) => format!(r#"{{"latency": {}}}"#, n),
+                None => r#"{"latency": "n/a"}"#.to_string(),
+            };
+            let env = MessageEnvelope::at(offset as i64)
+                .with_timestamp(offset as i64)
+                .with_value(value.as_str());
+            tx.send(ReaderEvent::Row(env)).await.unwrap();
+        }
+        drop(tx);
+
+        let emitted = RecordingSink::default();
+        let path = JsonPath {
+            root: RootPath::Value,
+            segments: vec!["latency".to_string()],
+        };
+        // Watermark 2 forces several streaming flushes; the sink still sorts globally
+        let by = SortBy::Path { path, desc: true };
+        let mut sink = SortedRows::new(Box::new(emitted.clone()), by, Some(4));
+        run_merger(
+            rx,
+            &mut sink,
+            buffer(2, 10_000),
+            RowWindow::default(),
+            MergeOrder::default(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        // Slowest first; the non-numeric latency sorts last and falls off the limit
+        let offsets: Vec<i64> = emitted.rows().iter().map(|e| e.offset).collect();
+        assert_eq!(offsets, vec![3, 0, 4, 1]);
+    }
+
+    fn row(partition: i32, timestamp_ms: i64) -> MessageEnvelope {
+        MessageEnvelope::at(timestamp_ms)
+            .on_partition(partition)
+            .with_timestamp(timestamp_ms)
+    }
+
+    #[tokio::test]
+    async fn slow_partition_is_not_overtaken() {
+        let (tx, rx) = mpsc::channel::<ReaderEvent>(16);
+        let every = Duration::from_millis(1);
+        let mut fast = ReaderTx::new(tx.clone(), None, 0, every);
+        let mut slow = ReaderTx::new(tx, None, 1, every);
+        let readers = async move {
+            for ts in [10, 30, 50] {
+                fast.row(row(0, ts)).await.unwrap();
+            }
+            // Many flush ticks pass before the slow partition's older rows arrive
+            tokio::time::sleep(Duration::from_millis(60)).await;
+            for ts in [20, 40, 60] {
+                slow.row(row(1, ts)).await.unwrap();
+            }
+            fast.idle().await.unwrap();
+            slow.idle().await.unwrap();
+        };
+        let mut sink = RecordingSink::default();
+        let buffer = MergeBuffer {
+            readers: 2,
+            watermark: 64,
+            flush_interval_ms: 5,
+        };
+        let merged = run_merger(
+            rx,
+            &mut sink,
+            buffer,
+            RowWindow::default(),
+            MergeOrder::default(),
+            None,
+        );
+        let (res, ()) = tokio::join!(merged, readers);
+        res.unwrap();
+
+        let order: Vec<i64> = sink.rows().iter().map(|e| e.timestamp_ms).collect();
+        assert_eq!(order, vec![10, 20, 30, 40, 50, 60]);
+    }
+
+    #[tokio::test]
+    async fn progress_and_idle_release_held_rows() {
+        let (tx, rx) = mpsc::channel::<ReaderEvent>(16);
+        let mut sink = RecordingSink::default();
+        let seen = sink.clone();
+        let timestamps =
+            |s: &RecordingSink| -> Vec<i64> { s.rows().iter().map(|e| e.timestamp_ms).collect() };
+        let readers = async move {
+            let settle = || tokio::time::sleep(Duration::from_millis(30));
+            tx.send(ReaderEvent::Row(row(0, 10))).await.unwrap();
+            tx.send(ReaderEvent::Row(row(0, 20))).await.unwrap();
+            settle().await;
+            // Partition 1 has not been heard from: nothing is final yet
+            assert!(seen.rows().is_empty());
+
+            // It read up to 25 without a match: rows before partition 0's 20 are final
+            tx.send(ReaderEvent::Progress {
+                reader: (None, 1),
+                timestamp_ms: 25,
+            })
+            .await
+            .unwrap();
+            settle().await;
+            assert_eq!(timestamps(&seen), vec![10]);
+
+            // Once both are caught up, the rest goes out while the channel stays open
+            tx.send(ReaderEvent::Idle((None, 1))).await.unwrap();
+            tx.send(ReaderEvent::Idle((None, 0))).await.unwrap();
+            settle().await;
+            assert_eq!(timestamps(&seen), vec![10, 20]);
+        };
+        let buffer = MergeBuffer {
+            readers: 2,
+            watermark: 64,
+            flush_interval_ms: 5,
+        };
+        let merged = run_merger(
+            rx,
+            &mut sink,
+            buffer,
+            RowWindow::default(),
+            MergeOrder::default(),
+            None,
+        );
+        let (res, ()) = tokio::join!(merged, readers);
+        res.unwrap();
+        assert_eq!(timestamps(&sink), vec![10, 20]);
+    }
+
+    #[tokio::test]
+    async fn order_by_key_limit_picks_the_top_rows_across_flushes() {
+        use crate::output::apply_query_stages;
+
+        let query = crate::query::parse_query("SELECT key FROM t ORDER BY key LIMIT 2").unwrap();
+        let emitted = RecordingSink::default();
+        let (mut sink, window) =
+            apply_query_stages(Box::new(emitted.clone()), &query, false, query.limit);
+        let (tx, rx) = mpsc::channel::<ReaderEvent>(8);
+        let readers = async move {
+            // Far enough apart that every row lands in its own flush
+            for (ts, key) in [(10, "dave"), (20, "carol"), (30, "bob"), (40, "alice")] {
+                let env = row(0, ts).with_key(key);
+                tx.send(ReaderEvent::Row(env)).await.unwrap();
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        };
+        let merged = run_merger(
+            rx,
+            sink.as_mut(),
+            buffer(64, 5),
+            window,
+            merge_order(Some(&query)),
+            None,
+        );
+        let (res, ()) = tokio::join!(merged, readers);
+        res.unwrap();
+        let keys: Vec<String> = emitted.rows().into_iter().map(|e| e.key).collect();
+        assert_eq!(keys, ["alice", "bob"]);
+    }
+
+    #[tokio::test]
+    async fn count_query_emits_one_row_with_the_match_total() {
+        use crate::args::RunArgs;
+        use crate::output::apply_query_stages;
+        use crate::query::parse_query;
+        use clap::Parser;
+
+        for sql in [
+            "SELECT COUNT(*) FROM t",
+            "SELECT COUNT(*) FROM t GROUP BY key",
+        ] {
+            let query = parse_query(sql).unwrap();
+            // A --max-messages default caps printed rows, not the count
+            let args = RunArgs::parse_from(["rkl", "--topic", "t", "-m", "3"]);
+            let emitted = RecordingSink::default();
+            let (mut sink, window) = apply_query_stages(
+                Box::new(emitted.clone()),
+                &query,
+                false,
+                args.row_limit(Some(&query)),
+            );
+            let (tx, rx) = mpsc::channel::<ReaderEvent>(8);
+            let readers = async move {
+                // Out of timestamp order across partitions: a count doesn't sort
+                for (partition, ts) in [(1, 50), (0, 10), (1, 30), (0, 40), (0, 20)] {
+                    let env = row(partition, ts).with_key(&format!("k{}", ts));
+                    tx.send(ReaderEvent::Row(env)).await.unwrap();
+                }
+            };
+            let merged = run_merger(
+                rx,
+                sink.as_mut(),
+                buffer(2, 10_000),
+                window,
+                merge_order(Some(&query)),
+                None,
+            );
+            let (res, ()) = tokio::join!(merged, readers);
+            res.unwrap();
+            let totals: Vec<Option<u64>> = emitted.rows().iter().map(|e| e.count).collect();
+            assert_eq!(totals, vec![Some(5)], "{}", sql);
+        }
+    }
+}
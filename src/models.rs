@@ -1,52 +1,273 @@
 use rdkafka::Offset;
-use serde::Serialize;
+use rdkafka::config::ClientConfig;
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering as AtomicOrdering};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio::sync::watch;
 
 /// What to assign for each partition.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum OffsetSpec {
     Beginning,
     End,
     Absolute(i64),
+    /// First message at or after this time (epoch ms); resolved per partition
+    /// by `consumer::start_offset`.
+    Timestamp(i64),
+    /// `end-N`: the last N messages of each partition, resolved from its
+    /// watermarks by `consumer::start_offset`.
+    FromEnd(i64),
 }
 
 impl OffsetSpec {
-    pub fn to_rdkafka(self) -> Offset {
+    /// The librdkafka offset, or `None` for a timestamp or `end-N`, which need a lookup.
+    pub fn to_rdkafka(self) -> Option<Offset> {
         match self {
-            OffsetSpec::Beginning => Offset::Beginning,
-            OffsetSpec::End => Offset::End,
-            OffsetSpec::Absolute(n) => Offset::Offset(n),
+            OffsetSpec::Beginning => Some(Offset::Beginning),
+            OffsetSpec::End => Some(Offset::End),
+            OffsetSpec::Absolute(n) => Some(Offset::Offset(n)),
+            OffsetSpec::Timestamp(_) | OffsetSpec::FromEnd(_) => None,
         }
     }
+}
+
+/// The forms `OffsetSpec` parses, for error messages.
+const OFFSET_FORMS: &str = "beginning | end | end-N | <number> | <RFC 3339 time> | @<epoch ms>";
+
+impl FromStr for OffsetSpec {
+    type Err = String;
+
+    /// `beginning`, `end`, `end-N`, an offset number, an RFC 3339 time
+    /// (`2024-01-01T00:00:00Z`) or `@<epoch ms>`.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let parsed = match s {
+            "beginning" => Some(Self::Beginning),
+            "end" => Some(Self::End),
+            _ if s.starts_with("end-") => s[4..]
+                .parse::<i64>()
+                .ok()
+                .filter(|n| *n >= 0)
+                .map(Self::FromEnd),
+            _ if s.starts_with('@') => s[1..].parse::<i64>().ok().map(Self::Timestamp),
+            _ => s.parse::<i64>().map(Self::Absolute).ok().or_else(|| {
+                OffsetDateTime::parse(s, &Rfc3339)
+                    .ok()
+                    .map(|t| Self::Timestamp((t.unix_timestamp_nanos() / 1_000_000) as i64))
+            }),
+        };
+        parsed.ok_or_else(|| format!("invalid offset '{}' (expected {})", s, OFFSET_FORMS))
+    }
+}
+
+/// `--partition-offset 0:100,1:250,2:end`: a starting offset per partition;
+/// partitions not listed use the global `--offset`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PartitionOffsets(BTreeMap<i32, OffsetSpec>);
 
-    pub fn from_str(s: &str) -> Result<Self, ()> {
-        match s {
-            "beginning" => Ok(Self::Beginning),
-            "end" => Ok(Self::End),
-            _ => s.parse::<i64>().map(Self::Absolute).map_err(|_| ()),
+impl PartitionOffsets {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut map = BTreeMap::new();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (partition, offset) = entry
+                .split_once(':')
+                .ok_or_else(|| format!("expected <partition>:<offset>, got '{}'", entry))?;
+            let partition: i32 = partition
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid partition '{}'", partition.trim()))?;
+            let offset = OffsetSpec::from_str(offset.trim()).map_err(|_| {
+                format!(
+                    "invalid offset '{}' for partition {} ({})",
+                    offset.trim(),
+                    partition,
+                    OFFSET_FORMS
+                )
+            })?;
+            if map.insert(partition, offset).is_some() {
+                return Err(format!("partition {} is listed twice", partition));
+            }
         }
+        if map.is_empty() {
+            return Err("no partition offsets given".to_string());
+        }
+        Ok(Self(map))
+    }
+
+    /// Starting offset for `partition`, or `default` when it isn't listed.
+    pub fn resolve(&self, partition: i32, default: OffsetSpec) -> OffsetSpec {
+        self.0.get(&partition).copied().unwrap_or(default)
+    }
+
+    /// Listed partitions the topic doesn't have.
+    pub fn unknown_partitions(&self, partitions: &[i32]) -> Vec<i32> {
+        self.0
+            .keys()
+            .copied()
+            .filter(|p| !partitions.contains(p))
+            .collect()
     }
 }
 
 /// Data sent from partition tasks to the merger.
 #[derive(Debug, Clone, Serialize)]
 pub struct MessageEnvelope {
+    pub topic: String,
     pub partition: i32,
     pub offset: i64,
     pub timestamp_ms: i64, // 0 if unknown
     pub key: String,
-    pub value: Option<String>, // None if the Value column is omitted
+    pub value: Option<String>, // None if the Value column is omitted or the message is a tombstone
+    pub headers: Vec<MessageHeader>,
+    /// Label of the source cluster; only set when reading from several brokers.
+    pub cluster: Option<String>,
+    /// The total of a `SELECT COUNT(*)` result row (see `output::CountRows`);
+    /// such a row is not a Kafka message and its other fields are empty.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
+}
+
+/// Test messages: `MessageEnvelope::at(7).with_key("k")` and so on, starting
+/// from topic `t`, partition 0, timestamp 0, an empty key and no value.
+#[cfg(test)]
+impl MessageEnvelope {
+    pub fn at(offset: i64) -> Self {
+        Self {
+            topic: "t".to_string(),
+            partition: 0,
+            offset,
+            timestamp_ms: 0,
+            key: String::new(),
+            value: None,
+            headers: Vec::new(),
+            cluster: None,
+            count: None,
+        }
+    }
+
+    pub fn on_topic(mut self, topic: &str) -> Self {
+        self.topic = topic.to_string();
+        self
+    }
+
+    pub fn on_partition(mut self, partition: i32) -> Self {
+        self.partition = partition;
+        self
+    }
+
+    pub fn with_timestamp(mut self, timestamp_ms: i64) -> Self {
+        self.timestamp_ms = timestamp_ms;
+        self
+    }
+
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.key = key.to_string();
+        self
+    }
+
+    pub fn with_value<'a>(mut self, value: impl Into<Option<&'a str>>) -> Self {
+        self.value = value.into().map(str::to_string);
+        self
+    }
+
+    pub fn with_cluster(mut self, cluster: &str) -> Self {
+        self.cluster = Some(cluster.to_string());
+        self
+    }
 }
 
-/// Wrapper that gives us total ordering by (timestamp, partition, offset)
+/// A Kafka record header; `value` is None for null-valued headers.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MessageHeader {
+    pub key: String,
+    pub value: Option<Vec<u8>>,
+}
+
+impl MessageHeader {
+    /// The value for display: UTF-8 text as-is, binary as `0x`-prefixed hex.
+    pub fn display_value(&self) -> String {
+        match self.value.as_deref() {
+            None => "null".to_string(),
+            Some(bytes) => match std::str::from_utf8(bytes) {
+                Ok(s) if !s.chars().any(|c| c.is_control() && c != '\t') => s.to_string(),
+                _ => {
+                    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+                    format!("0x{} ({} bytes)", hex, bytes.len())
+                }
+            },
+        }
+    }
+}
+
+/// The `headers` column: every header as `k=v`, joined with `; `.
+pub fn headers_compact(headers: &[MessageHeader]) -> String {
+    headers
+        .iter()
+        .map(|h| format!("{}={}", h.key, h.display_value()))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// What the merger orders rows by: `ORDER BY timestamp|offset|partition|key`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortField {
+    #[default]
+    Timestamp,
+    Offset,
+    Partition,
+    Key,
+}
+
+/// One `ORDER BY` key as the merger applies it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SortKey {
+    pub field: SortField,
+    pub desc: bool,
+}
+
+impl SortKey {
+    fn cmp(&self, a: &MessageEnvelope, b: &MessageEnvelope) -> Ordering {
+        let ord = match self.field {
+            SortField::Timestamp => a.timestamp_ms.cmp(&b.timestamp_ms),
+            SortField::Offset => a.offset.cmp(&b.offset),
+            SortField::Partition => a.partition.cmp(&b.partition),
+            SortField::Key => a.key.cmp(&b.key),
+        };
+        if self.desc { ord.reverse() } else { ord }
+    }
+}
+
+/// The merger's ordering: `ORDER BY` keys applied in turn, later keys
+/// breaking ties. No keys means oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeOrder {
+    pub keys: Vec<SortKey>,
+    /// Any order will do (`COUNT(*)`): rows go straight to the sink unsorted.
+    pub unordered: bool,
+}
+
+impl MergeOrder {
+    /// Whether rows print oldest first, so the merger can hold each row back
+    /// until every reader has read past its timestamp.
+    pub fn oldest_first(&self) -> bool {
+        self.keys
+            .first()
+            .is_none_or(|k| k.field == SortField::Timestamp && !k.desc)
+    }
+}
+
+/// Wrapper that gives us total ordering by the `ORDER BY` keys, then by
+/// (timestamp, partition, offset) in the first key's direction
 #[derive(Debug, Clone)]
-pub struct SortableEnvelope(pub MessageEnvelope);
+pub struct SortableEnvelope(pub MessageEnvelope, pub Arc<[SortKey]>);
 
 impl PartialEq for SortableEnvelope {
     fn eq(&self, other: &Self) -> bool {
-        self.0.timestamp_ms == other.0.timestamp_ms
-            && self.0.partition == other.0.partition
-            && self.0.offset == other.0.offset
+        self.cmp(other) == Ordering::Equal
     }
 }
 impl Eq for SortableEnvelope {}
@@ -58,15 +279,26 @@ impl PartialOrd for SortableEnvelope {
 }
 impl Ord for SortableEnvelope {
     fn cmp(&self, other: &Self) -> Ordering {
-        // natural ordering: smaller timestamp first
-        match self.0.timestamp_ms.cmp(&other.0.timestamp_ms) {
-            Ordering::Equal => match self.0.partition.cmp(&other.0.partition) {
-                Ordering::Equal => self.0.offset.cmp(&other.0.offset),
-                x => x,
-            },
-            x => x,
+        compare_by_keys(&self.1, &self.0, &other.0)
+    }
+}
+
+/// The order of two rows under `keys`, then by (timestamp, partition, offset)
+/// in the first key's direction. The first in output sorts smallest.
+pub fn compare_by_keys(keys: &[SortKey], a: &MessageEnvelope, b: &MessageEnvelope) -> Ordering {
+    for key in keys {
+        let ord = key.cmp(a, b);
+        if ord != Ordering::Equal {
+            return ord;
         }
     }
+    let position =
+        (a.timestamp_ms, a.partition, a.offset).cmp(&(b.timestamp_ms, b.partition, b.offset));
+    if keys.first().is_some_and(|k| k.desc) {
+        position.reverse()
+    } else {
+        position
+    }
 }
 
 /// SSL configuration for Kafka connections (PEM contents).
@@ -75,4 +307,838 @@ pub struct SslConfig {
     pub ca_pem: Option<String>,
     pub cert_pem: Option<String>,
     pub key_pem: Option<String>,
+    /// Passphrase for an encrypted private key (librdkafka: ssl.key.password)
+    #[serde(skip_serializing)]
+    pub key_password: Option<String>,
+}
+
+impl SslConfig {
+    /// True when any PEM material is present, i.e. the connection should use SSL.
+    pub fn is_enabled(&self) -> bool {
+        self.ca_pem.is_some() || self.cert_pem.is_some() || self.key_pem.is_some()
+    }
+
+    /// Set `security.protocol=ssl` and the PEM/password properties on `cfg`.
+    /// Does nothing when no PEM material is configured.
+    pub fn apply_to(&self, cfg: &mut ClientConfig) {
+        if !self.is_enabled() {
+            return;
+        }
+        cfg.set("security.protocol", "ssl");
+        if let Some(ref s) = self.ca_pem {
+            cfg.set("ssl.ca.pem", s);
+        }
+        if let Some(ref s) = self.cert_pem {
+            cfg.set("ssl.certificate.pem", s);
+        }
+        if let Some(ref s) = self.key_pem {
+            cfg.set("ssl.key.pem", s);
+        }
+        if let Some(ref s) = self.key_password {
+            cfg.set("ssl.key.password", s);
+        }
+    }
+}
+
+/// SASL mechanism (librdkafka `sasl.mechanism`).
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SaslMechanism {
+    #[value(name = "plain")]
+    #[serde(rename = "plain")]
+    Plain,
+    #[value(name = "scram-sha-256")]
+    #[serde(rename = "scram-sha-256")]
+    ScramSha256,
+    #[value(name = "scram-sha-512")]
+    #[serde(rename = "scram-sha-512")]
+    ScramSha512,
+}
+
+impl SaslMechanism {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SaslMechanism::Plain => "PLAIN",
+            SaslMechanism::ScramSha256 => "SCRAM-SHA-256",
+            SaslMechanism::ScramSha512 => "SCRAM-SHA-512",
+        }
+    }
+}
+
+/// SASL username/password authentication for Kafka connections.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaslConfig {
+    pub mechanism: SaslMechanism,
+    pub username: String,
+    pub password: String,
+    /// Run SASL over TLS (`sasl_ssl`, the default) or in the clear (`sasl_plaintext`).
+    #[serde(default = "default_sasl_tls")]
+    pub tls: bool,
+}
+
+fn default_sasl_tls() -> bool {
+    true
+}
+
+impl SaslConfig {
+    /// Set `security.protocol` and the `sasl.*` properties on `cfg`. Runs after
+    /// `SslConfig::apply_to`, whose PEM properties still apply over `sasl_ssl`.
+    pub fn apply_to(&self, cfg: &mut ClientConfig) {
+        let protocol = if self.tls {
+            "sasl_ssl"
+        } else {
+            "sasl_plaintext"
+        };
+        cfg.set("security.protocol", protocol)
+            .set("sasl.mechanism", self.mechanism.as_str())
+            .set("sasl.username", &self.username)
+            .set("sasl.password", &self.password);
+    }
+}
+
+/// Apply SSL PEM material and then SASL (which picks the final protocol) to `cfg`.
+pub fn apply_security(cfg: &mut ClientConfig, ssl: Option<&SslConfig>, sasl: Option<&SaslConfig>) {
+    if let Some(ssl) = ssl {
+        ssl.apply_to(cfg);
+    }
+    if let Some(sasl) = sasl {
+        sasl.apply_to(cfg);
+    }
+}
+
+/// The `host:port` entries of a comma-separated broker list (a `--broker`
+/// value or an environment's host), trimmed. Every entry needs a host and a
+/// port from 1 to 65535; IPv6 hosts go in brackets (`[::1]:9092`), and a
+/// `PLAINTEXT://`-style prefix is allowed, as librdkafka does.
+pub fn parse_brokers(list: &str) -> Result<Vec<String>, String> {
+    if list.trim().is_empty() {
+        return Err("no broker given; expected host:port[,host:port...]".to_string());
+    }
+    list.split(',')
+        .map(str::trim)
+        .map(|entry| {
+            if entry.is_empty() {
+                return Err(format!("empty entry in broker list '{}'", list.trim()));
+            }
+            let address = entry.split_once("://").map_or(entry, |(_, rest)| rest);
+            let (host, port) = address
+                .rsplit_once(':')
+                .ok_or_else(|| format!("broker '{}' needs a port, e.g. {}:9092", entry, address))?;
+            let bracketed = host.len() > 2 && host.starts_with('[') && host.ends_with(']');
+            if host.is_empty()
+                || host.contains(char::is_whitespace)
+                || (!bracketed && host.contains([':', '[', ']']))
+            {
+                let hint = if host.contains(':') && !bracketed {
+                    " (put an IPv6 address in brackets, e.g. [::1]:9092)"
+                } else {
+                    ""
+                };
+                return Err(format!("invalid host in broker '{}'{}", entry, hint));
+            }
+            match port.parse::<u16>() {
+                Ok(p) if p > 0 => Ok(entry.to_string()),
+                _ => Err(format!(
+                    "invalid port '{}' in broker '{}' (expected 1-65535)",
+                    port, entry
+                )),
+            }
+        })
+        .collect()
+}
+
+/// One cluster to read from: its bootstrap servers, SSL and SASL settings, and
+/// the label stamped on its envelopes (`None` for a single-cluster run).
+#[derive(Debug, Clone, Default)]
+pub struct ClusterSpec {
+    pub label: Option<String>,
+    pub bootstrap: String,
+    pub ssl: Option<SslConfig>,
+    pub sasl: Option<SaslConfig>,
+}
+
+impl ClusterSpec {
+    pub fn apply_security(&self, cfg: &mut ClientConfig) {
+        apply_security(cfg, self.ssl.as_ref(), self.sasl.as_ref());
+    }
+}
+
+/// Completion record returned by each partition consumer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionSummary {
+    pub partition: i32,
+    pub matched: u64,
+}
+
+/// Below this many matches the split is too noisy to call skewed.
+const SKEW_MIN_MATCHES: u64 = 50;
+
+/// Warn when one partition holds a disproportionate share of the matches,
+/// which usually points at a hot key or a bad partitioner. "Disproportionate"
+/// means more than halfway between its fair share (1/n) and all of them.
+pub fn partition_skew_warning(summaries: &[PartitionSummary]) -> Option<String> {
+    let total: u64 = summaries.iter().map(|s| s.matched).sum();
+    if summaries.len() < 2 || total < SKEW_MIN_MATCHES {
+        return None;
+    }
+    let top = summaries.iter().max_by_key(|s| s.matched)?;
+    let share = top.matched as f64 / total as f64;
+    let fair = 1.0 / summaries.len() as f64;
+    if share <= (1.0 + fair) / 2.0 {
+        return None;
+    }
+    Some(format!(
+        "Partition skew: partition {} holds {:.0}% of matches ({}/{}) across {} partitions",
+        top.partition,
+        share * 100.0,
+        top.matched,
+        total,
+        summaries.len()
+    ))
+}
+
+/// Leader broker id of each partition being read, from topic metadata's
+/// `(partition, leader)` pairs. librdkafka reports `-1` for a partition
+/// without a leader.
+pub fn partition_leaders(
+    metadata: impl IntoIterator<Item = (i32, i32)>,
+    partitions: &[i32],
+) -> BTreeMap<i32, i32> {
+    metadata
+        .into_iter()
+        .filter(|(p, _)| partitions.contains(p))
+        .collect()
+}
+
+/// One line grouping partitions by leader, e.g. `broker 1: p0, p2; broker 3: p1`.
+pub fn leader_summary(leaders: &BTreeMap<i32, i32>) -> String {
+    let mut by_broker: BTreeMap<i32, Vec<String>> = BTreeMap::new();
+    for (&partition, &leader) in leaders {
+        by_broker
+            .entry(leader)
+            .or_default()
+            .push(format!("p{}", partition));
+    }
+    by_broker
+        .into_iter()
+        .map(|(leader, parts)| {
+            let broker = if leader < 0 {
+                "no leader".to_string()
+            } else {
+                format!("broker {}", leader)
+            };
+            format!("{}: {}", broker, parts.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// One row of `rkl topic-info`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartitionInfo {
+    pub partition: i32,
+    /// Leader broker id; `-1` when the partition has no leader.
+    pub leader: i32,
+    pub low: i64,
+    pub high: i64,
+}
+
+impl PartitionInfo {
+    /// Messages currently retained: high minus low watermark.
+    pub fn messages(&self) -> i64 {
+        (self.high - self.low).max(0)
+    }
+}
+
+/// Join topic metadata's `(partition, leader)` pairs with each partition's
+/// `(low, high)` watermarks, ordered by partition. Partitions without
+/// watermarks are left out.
+pub fn partition_info(
+    metadata: impl IntoIterator<Item = (i32, i32)>,
+    watermarks: &BTreeMap<i32, (i64, i64)>,
+) -> Vec<PartitionInfo> {
+    let mut rows: Vec<PartitionInfo> = metadata
+        .into_iter()
+        .filter_map(|(partition, leader)| {
+            let &(low, high) = watermarks.get(&partition)?;
+            Some(PartitionInfo {
+                partition,
+                leader,
+                low,
+                high,
+            })
+        })
+        .collect();
+    rows.sort_by_key(|r| r.partition);
+    rows
+}
+
+/// JSON type of a value seen by `--schema`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum JsonType {
+    String,
+    Number,
+    Bool,
+    Object,
+    Array,
+    Null,
+}
+
+impl JsonType {
+    pub fn of(value: &serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::String(_) => JsonType::String,
+            serde_json::Value::Number(_) => JsonType::Number,
+            serde_json::Value::Bool(_) => JsonType::Bool,
+            serde_json::Value::Object(_) => JsonType::Object,
+            serde_json::Value::Array(_) => JsonType::Array,
+            serde_json::Value::Null => JsonType::Null,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JsonType::String => "string",
+            JsonType::Number => "number",
+            JsonType::Bool => "bool",
+            JsonType::Object => "object",
+            JsonType::Array => "array",
+            JsonType::Null => "null",
+        }
+    }
+}
+
+/// Types observed at one value path, and in how many messages it appeared.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldSchema {
+    pub types: BTreeSet<JsonType>,
+    pub seen: u64,
+}
+
+impl FieldSchema {
+    /// The field was not always the same type.
+    pub fn mixed(&self) -> bool {
+        self.types.len() > 1
+    }
+}
+
+/// Union of the field paths and types observed across message values
+/// (`--schema`). Object members become `a.b` paths; arrays are recorded as
+/// `array` without descending. A value that is not a JSON object is recorded
+/// under `ROOT_FIELD`, and one that is not JSON at all only bumps `non_json`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValueSchema {
+    pub fields: BTreeMap<String, FieldSchema>,
+    pub messages: u64,
+    pub non_json: u64,
+}
+
+/// `--schema` path for values whose top level is not an object.
+pub const ROOT_FIELD: &str = "(value)";
+
+impl ValueSchema {
+    /// Fold one message value in; tombstones are skipped.
+    pub fn observe(&mut self, value: Option<&str>) {
+        let Some(text) = value else { return };
+        self.messages += 1;
+        match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(serde_json::Value::Object(map)) => self.observe_members("", &map),
+            Ok(other) => self.record(ROOT_FIELD.to_string(), &other),
+            Err(_) => self.non_json += 1,
+        }
+    }
+
+    fn observe_members(&mut self, prefix: &str, map: &serde_json::Map<String, serde_json::Value>) {
+        for (name, value) in map {
+            let path = if prefix.is_empty() {
+                name.clone()
+            } else {
+                format!("{}.{}", prefix, name)
+            };
+            if let serde_json::Value::Object(inner) = value {
+                self.observe_members(&path, inner);
+            }
+            self.record(path, value);
+        }
+    }
+
+    fn record(&mut self, path: String, value: &serde_json::Value) {
+        let field = self.fields.entry(path).or_default();
+        field.types.insert(JsonType::of(value));
+        field.seen += 1;
+    }
+}
+
+/// Scan counters shared between partition consumers and the merger so the
+/// UI can show progress even when nothing matches.
+#[derive(Debug, Default)]
+pub struct ScanProgress {
+    scanned: AtomicU64,
+    matched: AtomicU64,
+    /// One entry per partition reader that found its watermarks.
+    reads: std::sync::Mutex<Vec<Arc<PartitionRead>>>,
+}
+
+/// Where one partition reader is: the offset it started from, the partition's
+/// high watermark at that time, and the offset it will read next.
+#[derive(Debug)]
+pub struct PartitionRead {
+    start: i64,
+    high: i64,
+    next: AtomicI64,
+}
+
+impl PartitionRead {
+    pub fn record_offset(&self, offset: i64) {
+        self.next.store(offset + 1, AtomicOrdering::Relaxed);
+    }
+}
+
+/// How far a run has read: messages from each reader's start offset to its
+/// next one, out of those up to each high watermark.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReadProgress {
+    pub read: u64,
+    pub total: u64,
+}
+
+impl ReadProgress {
+    /// Sum `(start, next, high)` per partition. A reader past the watermark it
+    /// started with (`--follow`) counts as done rather than over 100%.
+    pub fn from_positions(positions: impl IntoIterator<Item = (i64, i64, i64)>) -> Self {
+        positions
+            .into_iter()
+            .fold(Self::default(), |acc, (start, next, high)| {
+                let total = (high - start).max(0);
+                let read = (next - start).clamp(0, total);
+                Self {
+                    read: acc.read + read as u64,
+                    total: acc.total + total as u64,
+                }
+            })
+    }
+
+    /// Messages left to read before every partition reaches its watermark.
+    pub fn lag(&self) -> u64 {
+        self.total - self.read
+    }
+
+    /// Share read so far; `None` when there was nothing to read.
+    pub fn percent(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.read as f64 * 100.0 / self.total as f64)
+    }
+}
+
+impl ScanProgress {
+    pub fn record_scanned(&self) {
+        self.scanned.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+
+    pub fn record_matched(&self) {
+        self.matched.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+
+    /// Returns `(scanned, matched)`.
+    pub fn snapshot(&self) -> (u64, u64) {
+        (
+            self.scanned.load(AtomicOrdering::Relaxed),
+            self.matched.load(AtomicOrdering::Relaxed),
+        )
+    }
+
+    /// Register a reader starting at `start` on a partition ending at `high`;
+    /// it reports each offset it reads on the returned handle.
+    pub fn track_partition(&self, start: i64, high: i64) -> Arc<PartitionRead> {
+        let read = Arc::new(PartitionRead {
+            start,
+            high,
+            next: AtomicI64::new(start),
+        });
+        if let Ok(mut reads) = self.reads.lock() {
+            reads.push(read.clone());
+        }
+        read
+    }
+
+    /// Position of every tracked reader, summed.
+    pub fn read_progress(&self) -> ReadProgress {
+        let Ok(reads) = self.reads.lock() else {
+            return ReadProgress::default();
+        };
+        ReadProgress::from_positions(
+            reads
+                .iter()
+                .map(|r| (r.start, r.next.load(AtomicOrdering::Relaxed), r.high)),
+        )
+    }
+}
+
+/// Raised once the merger needs no more rows (a global LIMIT was met), so
+/// partition readers stop scanning instead of waiting for a match they can
+/// no longer deliver.
+#[derive(Debug, Clone)]
+pub struct StopSignal(watch::Receiver<bool>);
+
+impl StopSignal {
+    /// The sender raises the signal with `send_replace(true)`.
+    pub fn channel() -> (watch::Sender<bool>, StopSignal) {
+        let (tx, rx) = watch::channel(false);
+        (tx, StopSignal(rx))
+    }
+
+    pub fn is_raised(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once raised; never, if the sender is dropped without raising.
+    pub async fn raised(&mut self) {
+        if self.0.wait_for(|raised| *raised).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broker_lists_are_split_and_validated() {
+        assert_eq!(parse_brokers("localhost:9092").unwrap(), ["localhost:9092"]);
+        assert_eq!(
+            parse_brokers(" k1:9092, k2:9093 ,10.0.0.7:19092").unwrap(),
+            ["k1:9092", "k2:9093", "10.0.0.7:19092"]
+        );
+        assert_eq!(
+            parse_brokers("SSL://k1:9093,[::1]:9092").unwrap(),
+            ["SSL://k1:9093", "[::1]:9092"]
+        );
+
+        let err = |list: &str| parse_brokers(list).unwrap_err();
+        assert!(err("").starts_with("no broker given"));
+        assert!(err("  ").starts_with("no broker given"));
+        assert!(err("k1:9092,,k2:9092").starts_with("empty entry"));
+        assert!(err("k1:9092,").starts_with("empty entry"));
+        assert!(err("k1").contains("needs a port, e.g. k1:9092"));
+        assert!(err(":9092").starts_with("invalid host"));
+        assert!(err("my host:9092").starts_with("invalid host"));
+        assert!(err("::1:9092").contains("brackets"));
+        assert!(err("k1:0").starts_with("invalid port '0'"));
+        assert!(err("k1:70000").starts_with("invalid port"));
+        assert!(err("k1:abc").starts_with("invalid port"));
+    }
+
+    #[test]
+    fn read_progress_sums_partitions_against_watermarks() {
+        // Half of a 100-message partition, all of a 10-message one, and an
+        // empty one
+        let progress = ReadProgress::from_positions([(0, 50, 100), (40, 50, 50), (7, 7, 7)]);
+        assert_eq!(
+            progress,
+            ReadProgress {
+                read: 60,
+                total: 110
+            }
+        );
+        assert_eq!(progress.lag(), 50);
+        assert_eq!(progress.percent().map(|p| p.round()), Some(55.0));
+        // Following past the starting watermark caps at done
+        let followed = ReadProgress::from_positions([(90, 130, 100)]);
+        assert_eq!((followed.read, followed.lag()), (10, 0));
+        assert_eq!(followed.percent(), Some(100.0));
+        // Nothing to read has no percentage
+        assert_eq!(ReadProgress::from_positions([(5, 5, 5)]).percent(), None);
+        assert_eq!(ReadProgress::default().lag(), 0);
+
+        let scan = ScanProgress::default();
+        let p0 = scan.track_partition(0, 20);
+        scan.track_partition(20, 30);
+        p0.record_offset(4);
+        assert_eq!(scan.read_progress(), ReadProgress { read: 5, total: 30 });
+    }
+
+    #[test]
+    fn value_schema_merges_types_across_messages() {
+        let mut schema = ValueSchema::default();
+        schema.observe(Some(r#"{"id":7,"user":{"name":"a"},"tags":["x"]}"#));
+        schema.observe(Some(r#"{"id":"7-b","user":{"name":"b","age":3}}"#));
+        schema.observe(None);
+        schema.observe(Some("not json"));
+
+        assert_eq!(schema.messages, 3);
+        assert_eq!(schema.non_json, 1);
+        let id = &schema.fields["id"];
+        assert!(id.mixed());
+        assert_eq!(
+            id.types.iter().map(|t| t.as_str()).collect::<Vec<_>>(),
+            ["string", "number"]
+        );
+        assert_eq!(id.seen, 2);
+        let name = &schema.fields["user.name"];
+        assert!(!name.mixed());
+        assert_eq!(name.seen, 2);
+        assert_eq!(
+            schema.fields["user"].types,
+            BTreeSet::from([JsonType::Object])
+        );
+        assert_eq!(schema.fields["user.age"].seen, 1);
+        assert_eq!(
+            schema.fields["tags"].types,
+            BTreeSet::from([JsonType::Array])
+        );
+        assert!(!schema.fields.contains_key(ROOT_FIELD));
+    }
+
+    #[tokio::test]
+    async fn stop_signal_resolves_only_once_raised() {
+        let wait = |mut stop: StopSignal| async move {
+            tokio::time::timeout(std::time::Duration::from_millis(20), stop.raised())
+                .await
+                .is_ok()
+        };
+        let (tx, stop) = StopSignal::channel();
+        assert!(!stop.is_raised());
+        assert!(!wait(stop.clone()).await);
+        tx.send_replace(true);
+        assert!(stop.is_raised());
+        assert!(wait(stop).await);
+
+        // A run that ends without hitting its limit never stops readers
+        let (tx, stop) = StopSignal::channel();
+        drop(tx);
+        assert!(!wait(stop).await);
+    }
+
+    fn summaries(counts: &[u64]) -> Vec<PartitionSummary> {
+        counts
+            .iter()
+            .enumerate()
+            .map(|(p, &matched)| PartitionSummary {
+                partition: p as i32,
+                matched,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn leaders_come_from_metadata_for_read_partitions() {
+        let metadata = [(0, 1), (1, 3), (2, 1), (3, -1), (4, 2)];
+        let leaders = partition_leaders(metadata, &[0, 1, 2, 3]);
+        assert_eq!(leaders, BTreeMap::from([(0, 1), (1, 3), (2, 1), (3, -1)]));
+        assert_eq!(
+            leader_summary(&leaders),
+            "no leader: p3; broker 1: p0, p2; broker 3: p1"
+        );
+        assert_eq!(leader_summary(&BTreeMap::new()), "");
+    }
+
+    #[test]
+    fn partition_info_joins_leaders_with_watermarks() {
+        let metadata = [(2, 1), (0, 3), (1, -1), (3, 2)];
+        let watermarks = BTreeMap::from([(0, (0, 120)), (1, (40, 40)), (2, (7, 10))]);
+        let rows = partition_info(metadata, &watermarks);
+        assert_eq!(
+            rows,
+            vec![
+                PartitionInfo {
+                    partition: 0,
+                    leader: 3,
+                    low: 0,
+                    high: 120
+                },
+                PartitionInfo {
+                    partition: 1,
+                    leader: -1,
+                    low: 40,
+                    high: 40
+                },
+                PartitionInfo {
+                    partition: 2,
+                    leader: 1,
+                    low: 7,
+                    high: 10
+                },
+            ]
+        );
+        let counts: Vec<i64> = rows.iter().map(PartitionInfo::messages).collect();
+        assert_eq!(counts, [120, 0, 3]);
+        // A fetch that reports low past high never yields a negative count
+        let odd = PartitionInfo {
+            partition: 0,
+            leader: 1,
+            low: 9,
+            high: 5,
+        };
+        assert_eq!(odd.messages(), 0);
+    }
+
+    #[test]
+    fn partition_offsets_parse_and_resolve() {
+        let map = PartitionOffsets::parse("0:100, 1:250,2:end").unwrap();
+        assert_eq!(
+            map.resolve(0, OffsetSpec::Beginning),
+            OffsetSpec::Absolute(100)
+        );
+        assert_eq!(
+            map.resolve(1, OffsetSpec::Beginning),
+            OffsetSpec::Absolute(250)
+        );
+        assert_eq!(map.resolve(2, OffsetSpec::Beginning), OffsetSpec::End);
+        // Unlisted partitions fall back to --offset
+        assert_eq!(
+            map.resolve(3, OffsetSpec::Absolute(7)),
+            OffsetSpec::Absolute(7)
+        );
+        assert_eq!(map.unknown_partitions(&[0, 1, 3]), vec![2]);
+
+        assert!(PartitionOffsets::parse("0=100").is_err());
+        assert!(PartitionOffsets::parse("x:1").is_err());
+        assert!(PartitionOffsets::parse("0:later").is_err());
+        assert!(PartitionOffsets::parse("0:1,0:2").is_err());
+        assert!(PartitionOffsets::parse("").is_err());
+    }
+
+    #[test]
+    fn offset_spec_parses_times() {
+        assert_eq!(
+            OffsetSpec::from_str("2024-01-01T00:00:00Z"),
+            Ok(OffsetSpec::Timestamp(1_704_067_200_000))
+        );
+        assert_eq!(
+            OffsetSpec::from_str("2024-01-01T02:00:00.250+02:00"),
+            Ok(OffsetSpec::Timestamp(1_704_067_200_250))
+        );
+        assert_eq!(
+            OffsetSpec::from_str("@1704067200000"),
+            Ok(OffsetSpec::Timestamp(1_704_067_200_000))
+        );
+        // Plain numbers stay offsets
+        assert_eq!(
+            OffsetSpec::from_str("1704067200000"),
+            Ok(OffsetSpec::Absolute(1_704_067_200_000))
+        );
+        assert!(OffsetSpec::from_str("2024-01-01").is_err());
+        assert!(OffsetSpec::from_str("@soon").is_err());
+
+        // Per-partition times: only the first ':' separates the partition
+        let map = PartitionOffsets::parse("0:2024-01-01T00:00:00Z,1:end").unwrap();
+        assert_eq!(
+            map.resolve(0, OffsetSpec::Beginning),
+            OffsetSpec::Timestamp(1_704_067_200_000)
+        );
+        assert_eq!(OffsetSpec::Timestamp(0).to_rdkafka(), None);
+    }
+
+    #[test]
+    fn sasl_sets_protocol_and_credentials() {
+        let sasl = SaslConfig {
+            mechanism: SaslMechanism::ScramSha512,
+            username: "svc".to_string(),
+            password: "s3cret".to_string(),
+            tls: true,
+        };
+        let mut cfg = ClientConfig::new();
+        let cluster = ClusterSpec {
+            sasl: Some(sasl.clone()),
+            ..ClusterSpec::default()
+        };
+        cluster.apply_security(&mut cfg);
+        assert_eq!(cfg.get("security.protocol"), Some("sasl_ssl"));
+        assert_eq!(cfg.get("sasl.mechanism"), Some("SCRAM-SHA-512"));
+        assert_eq!(cfg.get("sasl.username"), Some("svc"));
+        assert_eq!(cfg.get("sasl.password"), Some("s3cret"));
+
+        // PEM material stays configured underneath sasl_ssl
+        let ssl = SslConfig {
+            ca_pem: Some("CA".to_string()),
+            ..SslConfig::default()
+        };
+        let mut cfg = ClientConfig::new();
+        apply_security(&mut cfg, Some(&ssl), Some(&sasl));
+        assert_eq!(cfg.get("security.protocol"), Some("sasl_ssl"));
+        assert_eq!(cfg.get("ssl.ca.pem"), Some("CA"));
+
+        let plaintext = SaslConfig {
+            mechanism: SaslMechanism::Plain,
+            tls: false,
+            ..sasl
+        };
+        let mut cfg = ClientConfig::new();
+        apply_security(&mut cfg, None, Some(&plaintext));
+        assert_eq!(cfg.get("security.protocol"), Some("sasl_plaintext"));
+        assert_eq!(cfg.get("sasl.mechanism"), Some("PLAIN"));
+
+        // Environment files spell the mechanism in lower case; TLS is the default
+        let parsed: SaslConfig = serde_json::from_str(
+            r#"{"mechanism": "scram-sha-256", "username": "u", "password": "p"}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.mechanism, SaslMechanism::ScramSha256);
+        assert!(parsed.tls);
+    }
+
+    #[test]
+    fn offset_spec_parses_end_minus_n() {
+        assert_eq!(
+            OffsetSpec::from_str("end-100"),
+            Ok(OffsetSpec::FromEnd(100))
+        );
+        assert_eq!(OffsetSpec::from_str("end-0"), Ok(OffsetSpec::FromEnd(0)));
+        assert!(OffsetSpec::from_str("end-").is_err());
+        assert!(OffsetSpec::from_str("end--5").is_err());
+        assert!(OffsetSpec::from_str("end-ten").is_err());
+        assert_eq!(OffsetSpec::FromEnd(5).to_rdkafka(), None);
+        let map = PartitionOffsets::parse("0:end-10,1:beginning").unwrap();
+        assert_eq!(map.resolve(0, OffsetSpec::End), OffsetSpec::FromEnd(10));
+    }
+
+    #[test]
+    fn skew_warning_flags_dominant_partition() {
+        // Even-ish spread: no warning
+        assert_eq!(partition_skew_warning(&summaries(&[30, 25, 35, 28])), None);
+        // Too few matches to judge
+        assert_eq!(partition_skew_warning(&summaries(&[9, 0, 0])), None);
+        // Single partition can't be skewed
+        assert_eq!(partition_skew_warning(&summaries(&[500])), None);
+        // 2 partitions: threshold is 75%
+        assert_eq!(partition_skew_warning(&summaries(&[70, 30])), None);
+        let warning = partition_skew_warning(&summaries(&[10, 90])).unwrap();
+        assert!(warning.contains("partition 1 holds 90%"), "{warning}");
+        // 4 partitions: threshold is 62.5%
+        let warning = partition_skew_warning(&summaries(&[5, 5, 70, 20])).unwrap();
+        assert!(warning.contains("partition 2"), "{warning}");
+        assert!(
+            warning.contains("(70/100) across 4 partitions"),
+            "{warning}"
+        );
+    }
+
+    fn ssl_with_key(key_password: Option<&str>) -> SslConfig {
+        SslConfig {
+            ca_pem: Some("ca".to_string()),
+            cert_pem: Some("cert".to_string()),
+            key_pem: Some("key".to_string()),
+            key_password: key_password.map(|s| s.to_string()),
+        }
+    }
+
+    #[test]
+    fn sets_key_password_when_present() {
+        let mut cfg = ClientConfig::new();
+        ssl_with_key(Some("s3cret")).apply_to(&mut cfg);
+        assert_eq!(cfg.get("security.protocol"), Some("ssl"));
+        assert_eq!(cfg.get("ssl.key.pem"), Some("key"));
+        assert_eq!(cfg.get("ssl.key.password"), Some("s3cret"));
+    }
+
+    #[test]
+    fn omits_key_password_when_absent() {
+        let mut cfg = ClientConfig::new();
+        ssl_with_key(None).apply_to(&mut cfg);
+        assert_eq!(cfg.get("ssl.key.pem"), Some("key"));
+        assert_eq!(cfg.get("ssl.key.password"), None);
+
+        let mut plain = ClientConfig::new();
+        SslConfig::default().apply_to(&mut plain);
+        assert_eq!(plain.get("security.protocol"), None);
+    }
 }
@@ -1,24 +1,380 @@
-use crate::models::MessageEnvelope;
-use crate::query::SelectItem;
+use crate::merger::{RowWindow, merge_order};
+use crate::models::{
+    MessageEnvelope, PartitionInfo, SortKey, ValueSchema, compare_by_keys, headers_compact,
+};
+use crate::query::{
+    GroupBy, JsonPath, MessageView, RootPath, SelectItem, SelectQuery, column_alias,
+    derived_column_text, value_fields, value_size,
+};
 use comfy_table::{Attribute, Cell, ContentArrangement, Table, presets::UTF8_FULL};
-use time::{OffsetDateTime, format_description::well_known::Iso8601};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+use time::{OffsetDateTime, UtcOffset, format_description::well_known::Iso8601};
 
 /// Generic sink trait used by the merger to emit rows in batches.
 pub trait OutputSink {
     fn push(&mut self, env: &MessageEnvelope);
-    fn flush_block(&mut self);
+    /// The rows pushed since the last call form a block and may be written
+    /// out. Does nothing by default, which suits stages that buffer until
+    /// `finish` because nothing is final until every message has been seen.
+    fn flush_block(&mut self) {}
+    /// Periodic "scanned N, matched M" progress; sinks may ignore it.
+    fn heartbeat(&mut self, _scanned: u64, _matched: u64) {}
+    /// Called once by the merger after the last row.
+    fn finish(&mut self) {
+        self.flush_block();
+    }
+}
+
+/// `GROUP BY key`: keeps only the newest message per key (max timestamp,
+/// ties broken by offset) and emits the survivors, ordered by timestamp and
+/// limited, when the scan finishes.
+pub struct LatestPerKey {
+    inner: Box<dyn OutputSink + Send>,
+    latest: HashMap<String, MessageEnvelope>,
+    order_desc: bool,
+    limit: Option<usize>,
+}
+
+impl LatestPerKey {
+    pub fn new(inner: Box<dyn OutputSink + Send>, order_desc: bool, limit: Option<usize>) -> Self {
+        Self {
+            inner,
+            latest: HashMap::new(),
+            order_desc,
+            limit,
+        }
+    }
+}
+
+impl OutputSink for LatestPerKey {
+    fn push(&mut self, env: &MessageEnvelope) {
+        match self.latest.get(&env.key) {
+            Some(cur) if (cur.timestamp_ms, cur.offset) >= (env.timestamp_ms, env.offset) => {}
+            _ => {
+                self.latest.insert(env.key.clone(), env.clone());
+            }
+        }
+    }
+
+    fn heartbeat(&mut self, scanned: u64, matched: u64) {
+        self.inner.heartbeat(scanned, matched);
+    }
+
+    fn finish(&mut self) {
+        let mut rows: Vec<MessageEnvelope> = self.latest.drain().map(|(_, env)| env).collect();
+        rows.sort_by_key(|e| (e.timestamp_ms, e.partition, e.offset));
+        if self.order_desc {
+            rows.reverse();
+        }
+        for env in rows.iter().take(self.limit.unwrap_or(usize::MAX)) {
+            self.inner.push(env);
+        }
+        self.inner.finish();
+    }
+}
+
+/// `SELECT COUNT(*)`: counts the rows it is given and, when the scan
+/// finishes, emits a single result row whose `count` holds the total.
+pub struct CountRows {
+    inner: Box<dyn OutputSink + Send>,
+    topic: String,
+    count: u64,
+}
+
+impl CountRows {
+    pub fn new(inner: Box<dyn OutputSink + Send>, topic: &str) -> Self {
+        Self {
+            inner,
+            topic: topic.to_string(),
+            count: 0,
+        }
+    }
+}
+
+impl OutputSink for CountRows {
+    fn push(&mut self, _env: &MessageEnvelope) {
+        self.count += 1;
+    }
+
+    fn heartbeat(&mut self, scanned: u64, matched: u64) {
+        self.inner.heartbeat(scanned, matched);
+    }
+
+    fn finish(&mut self) {
+        self.inner.push(&MessageEnvelope {
+            topic: self.topic.clone(),
+            partition: 0,
+            offset: 0,
+            timestamp_ms: 0,
+            key: String::new(),
+            value: None,
+            headers: Vec::new(),
+            cluster: None,
+            count: Some(self.count),
+        });
+        self.inner.finish();
+    }
+}
+
+/// How the CLI renders the `timestamp` column (`--time-format`).
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// ISO-8601 in UTC with milliseconds
+    #[default]
+    Iso,
+    /// ISO-8601 in the machine's local time zone
+    Local,
+    /// Age relative to when the run started, e.g. `5m ago`
+    Relative,
+    /// Raw epoch milliseconds
+    Epoch,
+}
+
+/// How `rkl run` prints matched rows (`--format`).
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A table, printed in blocks as rows arrive
+    #[default]
+    Table,
+    /// One JSON array of row objects, streamed
+    Json,
+    /// One JSON row object per line
+    Ndjson,
+    /// A header row, then one RFC 4180 record per row
+    Csv,
+    /// Only each value (or the key, when no value is selected), one per line
+    Raw,
 }
 
-pub struct TableOutput {
+/// What a `SortedRows` stage orders rows by.
+pub enum SortBy {
+    /// `ORDER BY` offset, partition, key or `timestamp DESC`: the keys in turn,
+    /// as the merger compares them (see `SortableEnvelope`)
+    Keys(Arc<[SortKey]>),
+    /// `ORDER BY <path>`: the path's numeric value, non-numbers last, ties
+    /// oldest first
+    Path { path: JsonPath, desc: bool },
+}
+
+impl SortBy {
+    /// The number a row sorts on, worked out once when it is buffered.
+    fn number(&self, env: &MessageEnvelope) -> Option<f64> {
+        let SortBy::Path { path, .. } = self else {
+            return None;
+        };
+        let json = env
+            .value
+            .as_deref()
+            .and_then(|s| serde_json::from_str::<Value>(s).ok())
+            .unwrap_or(Value::Null);
+        path.resolve(&MessageView::of(env, &json)).as_f64()
+    }
+
+    fn compare(&self, (a, ea): &SortRow, (b, eb): &SortRow) -> Ordering {
+        match self {
+            SortBy::Keys(keys) => compare_by_keys(keys, ea, eb),
+            SortBy::Path { desc, .. } => {
+                let by_value = match (a, b) {
+                    (Some(a), Some(b)) if *desc => b.total_cmp(a),
+                    (Some(a), Some(b)) => a.total_cmp(b),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                };
+                by_value.then_with(|| {
+                    (ea.timestamp_ms, ea.partition, ea.offset).cmp(&(
+                        eb.timestamp_ms,
+                        eb.partition,
+                        eb.offset,
+                    ))
+                })
+            }
+        }
+    }
+}
+
+/// A buffered row and the number it sorts on, if any.
+type SortRow = (Option<f64>, MessageEnvelope);
+
+/// `ORDER BY` anything but oldest first: the merger streams only oldest-first
+/// output in order, so these rows are buffered and emitted sorted when the
+/// scan finishes. With a limit only the first `limit` rows are kept.
+pub struct SortedRows {
+    inner: Box<dyn OutputSink + Send>,
+    by: SortBy,
+    limit: Option<usize>,
+    rows: Vec<SortRow>,
+}
+
+impl SortedRows {
+    pub fn new(inner: Box<dyn OutputSink + Send>, by: SortBy, limit: Option<usize>) -> Self {
+        Self {
+            inner,
+            by,
+            limit,
+            rows: Vec::new(),
+        }
+    }
+
+    fn sort(&mut self) {
+        let by = &self.by;
+        self.rows.sort_by(|a, b| by.compare(a, b));
+        if let Some(limit) = self.limit {
+            self.rows.truncate(limit);
+        }
+    }
+}
+
+impl OutputSink for SortedRows {
+    fn push(&mut self, env: &MessageEnvelope) {
+        self.rows.push((self.by.number(env), env.clone()));
+        // Keep memory bounded under LIMIT: prune to the first rows now and then
+        if let Some(limit) = self.limit
+            && self.rows.len() >= limit.saturating_mul(2).max(1024)
+        {
+            self.sort();
+        }
+    }
+
+    fn heartbeat(&mut self, scanned: u64, matched: u64) {
+        self.inner.heartbeat(scanned, matched);
+    }
+
+    fn finish(&mut self) {
+        self.sort();
+        for (_, env) in self.rows.drain(..) {
+            self.inner.push(&env);
+        }
+        self.inner.finish();
+    }
+}
+
+/// Final stage of the latest-N plan: readers deliver each partition's newest
+/// `n`, and this keeps the newest `n` of those across partitions.
+pub fn newest_first(sink: Box<dyn OutputSink + Send>, n: usize) -> Box<dyn OutputSink + Send> {
+    let timestamp = JsonPath {
+        root: RootPath::Timestamp,
+        segments: Vec::new(),
+    };
+    let by = SortBy::Path {
+        path: timestamp,
+        desc: true,
+    };
+    Box::new(SortedRows::new(sink, by, Some(n)))
+}
+
+/// Drops the first `remaining` rows it is given: `OFFSET` after a buffering stage.
+pub struct SkipRows {
+    inner: Box<dyn OutputSink + Send>,
+    remaining: usize,
+}
+
+impl SkipRows {
+    pub fn new(inner: Box<dyn OutputSink + Send>, skip: usize) -> Self {
+        Self {
+            inner,
+            remaining: skip,
+        }
+    }
+}
+
+impl OutputSink for SkipRows {
+    fn push(&mut self, env: &MessageEnvelope) {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+        } else {
+            self.inner.push(env);
+        }
+    }
+
+    fn flush_block(&mut self) {
+        self.inner.flush_block();
+    }
+
+    fn heartbeat(&mut self, scanned: u64, matched: u64) {
+        self.inner.heartbeat(scanned, matched);
+    }
+
+    fn finish(&mut self) {
+        self.inner.finish();
+    }
+}
+
+/// Wrap `sink` in the buffering stages `query` needs: newest-per-key for
+/// `GROUP BY key`, then sorting for any `ORDER BY` but oldest first. `COUNT(*)`
+/// counts every match (or distinct key, under `GROUP BY key`) instead, so it
+/// skips sorting and takes no limit. Also returns the OFFSET/LIMIT window the
+/// merger may still enforce while streaming; empty once rows are buffered,
+/// since the window then applies to the buffered result.
+pub fn apply_query_stages(
+    sink: Box<dyn OutputSink + Send>,
+    query: &SelectQuery,
+    order_desc: bool,
+    limit: Option<usize>,
+) -> (Box<dyn OutputSink + Send>, RowWindow) {
+    if query.is_count() {
+        let count: Box<dyn OutputSink + Send> = Box::new(CountRows::new(sink, &query.from));
+        let sink: Box<dyn OutputSink + Send> = match query.group_by {
+            Some(GroupBy::Key) => Box::new(LatestPerKey::new(count, order_desc, None)),
+            None => count,
+        };
+        return (sink, RowWindow::default());
+    }
+    let skip = query.skip.unwrap_or(0);
+    if !query.sorts_at_end() && query.group_by.is_none() {
+        return (sink, RowWindow { skip, limit });
+    }
+    // Stages keep `skip` extra rows, dropped on the way out
+    let mut sink: Box<dyn OutputSink + Send> = if skip > 0 {
+        Box::new(SkipRows::new(sink, skip))
+    } else {
+        sink
+    };
+    let mut stage_limit = limit.map(|n| n + skip);
+    let sort_by = match query.order_path() {
+        Some(path) => Some(SortBy::Path {
+            path: path.clone(),
+            desc: order_desc,
+        }),
+        None if query.sorts_at_end() => Some(SortBy::Keys(merge_order(Some(query)).keys.into())),
+        None => None,
+    };
+    if let Some(by) = sort_by {
+        sink = Box::new(SortedRows::new(sink, by, stage_limit));
+        stage_limit = None;
+    }
+    if let Some(GroupBy::Key) = query.group_by {
+        sink = Box::new(LatestPerKey::new(sink, order_desc, stage_limit));
+    }
+    (sink, RowWindow::default())
+}
+
+pub struct TableOutput<W: Write> {
+    out: W,
     table: Table,
     no_color: bool,
     columns: Vec<SelectItem>,
+    aliases: Vec<(SelectItem, String)>,
+    time_format: TimeFormat,
+    /// Captured once per run so relative ages are consistent across blocks.
+    now_ms: i64,
     max_cell_width: usize, // used as an approximate table width hint
     rows_buffered: usize,
 }
 
-impl TableOutput {
-    pub fn new(no_color: bool, columns: Vec<SelectItem>, max_cell_width: usize) -> Self {
+impl<W: Write> TableOutput<W> {
+    pub fn new(
+        out: W,
+        no_color: bool,
+        columns: Vec<SelectItem>,
+        max_cell_width: usize,
+        time_format: TimeFormat,
+        now_ms: i64,
+    ) -> Self {
         let mut table = Table::new();
         table
             .load_preset(UTF8_FULL)
@@ -29,29 +385,51 @@ impl TableOutput {
             table.set_width((max_cell_width * 2) as u16);
         }
 
-        table.set_header(make_header(&columns, no_color));
+        table.set_header(make_header(&columns, &[], no_color));
 
         Self {
+            out,
             table,
             no_color,
             columns,
+            aliases: Vec::new(),
+            time_format,
+            now_ms,
             max_cell_width,
             rows_buffered: 0,
         }
     }
+
+    /// Label headers with the query's `AS` aliases instead of the column names.
+    pub fn with_aliases(mut self, aliases: Vec<(SelectItem, String)>) -> Self {
+        self.table
+            .set_header(make_header(&self.columns, &aliases, self.no_color));
+        self.aliases = aliases;
+        self
+    }
 }
 
-impl OutputSink for TableOutput {
+impl<W: Write> OutputSink for TableOutput<W> {
     fn push(&mut self, env: &MessageEnvelope) {
         let row = self
             .columns
             .iter()
             .map(|col| match col {
+                SelectItem::Cluster => cell(env.cluster.as_deref().unwrap_or(""), self.no_color),
                 SelectItem::Partition => cell(env.partition, self.no_color),
                 SelectItem::Offset => cell(env.offset, self.no_color),
-                SelectItem::Timestamp => cell(fmt_ts(env.timestamp_ms), self.no_color),
+                SelectItem::Timestamp => cell(
+                    fmt_ts(env.timestamp_ms, self.time_format, self.now_ms),
+                    self.no_color,
+                ),
                 SelectItem::Key => cell(&env.key, self.no_color),
                 SelectItem::Value => cell(env.value.as_deref().unwrap_or("null"), self.no_color),
+                derived @ (SelectItem::ValueSize | SelectItem::ValueFields) => cell(
+                    derived_column_text(*derived, env.value.as_deref()),
+                    self.no_color,
+                ),
+                SelectItem::Headers => cell(headers_compact(&env.headers), self.no_color),
+                SelectItem::Count => cell(env.count.unwrap_or_default(), self.no_color),
             })
             .collect::<Vec<_>>();
         self.table.add_row(row);
@@ -62,7 +440,8 @@ impl OutputSink for TableOutput {
         if self.rows_buffered == 0 {
             return;
         }
-        println!("{}", self.table);
+        let _ = writeln!(self.out, "{}", self.table);
+        let _ = self.out.flush();
 
         // Recreate table with same header so each block prints a header
         self.table = Table::new();
@@ -74,31 +453,471 @@ impl OutputSink for TableOutput {
             self.table.set_width((self.max_cell_width * 2) as u16);
         }
         self.table
-            .set_header(make_header(&self.columns, self.no_color));
+            .set_header(make_header(&self.columns, &self.aliases, self.no_color));
         self.rows_buffered = 0;
     }
 }
 
-impl TableOutput {
-    pub fn finish(&mut self) {
+/// Prints only each message's value, verbatim, one per line. Tombstones
+/// (null values) print `tombstone` instead (empty line by default). With
+/// `print_keys` the key is printed instead of the value.
+pub struct RawOutput<W: Write> {
+    out: W,
+    tombstone: String,
+    print_keys: bool,
+}
+
+impl<W: Write> RawOutput<W> {
+    pub fn new(out: W, tombstone: String) -> Self {
+        Self {
+            out,
+            tombstone,
+            print_keys: false,
+        }
+    }
+
+    /// Print keys rather than values, for queries that select no value.
+    pub fn printing_keys(mut self) -> Self {
+        self.print_keys = true;
+        self
+    }
+
+    #[cfg(test)]
+    fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> OutputSink for RawOutput<W> {
+    fn push(&mut self, env: &MessageEnvelope) {
+        let count = env.count.map(|n| n.to_string());
+        let text = if let Some(count) = &count {
+            count
+        } else if self.print_keys {
+            &env.key
+        } else {
+            env.value.as_deref().unwrap_or(&self.tombstone)
+        };
+        let _ = writeln!(self.out, "{}", text);
+    }
+
+    fn flush_block(&mut self) {
+        let _ = self.out.flush();
+    }
+}
+
+/// `--format json|ndjson`: each row as a JSON object keyed by column name (or
+/// its `AS` alias). Values that parse as JSON are embedded as JSON, keeping
+/// their key order and number digits; the timestamp is epoch milliseconds.
+/// With `array`, rows form one JSON array that `finish` closes.
+pub struct JsonOutput<W: Write> {
+    out: W,
+    columns: Vec<SelectItem>,
+    aliases: Vec<(SelectItem, String)>,
+    array: bool,
+    value_text: bool,
+    rows: usize,
+}
+
+impl<W: Write> JsonOutput<W> {
+    pub fn new(out: W, columns: Vec<SelectItem>, array: bool) -> Self {
+        Self {
+            out,
+            columns,
+            aliases: Vec::new(),
+            array,
+            value_text: false,
+            rows: 0,
+        }
+    }
+
+    pub fn with_aliases(mut self, aliases: Vec<(SelectItem, String)>) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Embed JSON values as their own text, indentation included, instead of
+    /// compacting them. Only `array` output takes this, as NDJSON rows must
+    /// stay on one line.
+    pub fn keeping_value_text(mut self) -> Self {
+        self.value_text = self.array;
+        self
+    }
+
+    #[cfg(test)]
+    fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// One row as a JSON object, keys in `SELECT` order; compact unless
+/// `value_text` keeps a JSON value's own text. See [`JsonOutput`].
+fn json_row(
+    env: &MessageEnvelope,
+    columns: &[SelectItem],
+    aliases: &[(SelectItem, String)],
+    value_text: bool,
+) -> String {
+    let parsed = env
+        .value
+        .as_deref()
+        .and_then(|s| serde_json::from_str::<Value>(s).ok());
+    let json = parsed.clone().unwrap_or(Value::Null);
+    let fields = columns
+        .iter()
+        .map(|&col| {
+            let name = column_alias(aliases, col).unwrap_or(col.name()).to_string();
+            if let (SelectItem::Value, true, Some(_), Some(text)) =
+                (col, value_text, &parsed, env.value.as_deref())
+            {
+                // Already valid JSON, so it embeds as is
+                return format!("{}:{}", Value::String(name), text.trim());
+            }
+            let value = match col {
+                SelectItem::Cluster => env.cluster.clone().map_or(Value::Null, Value::String),
+                SelectItem::Partition => Value::from(env.partition),
+                SelectItem::Offset => Value::from(env.offset),
+                SelectItem::Timestamp => Value::from(env.timestamp_ms),
+                SelectItem::Key => Value::String(env.key.clone()),
+                SelectItem::Value => match (&parsed, &env.value) {
+                    (Some(v), _) => v.clone(),
+                    (None, Some(text)) => Value::String(text.clone()),
+                    (None, None) => Value::Null,
+                },
+                SelectItem::ValueSize => value_size(&json, env.value.as_deref()).into(),
+                SelectItem::ValueFields => value_fields(&json).into(),
+                SelectItem::Headers => {
+                    let mut headers = serde_json::Map::new();
+                    for h in &env.headers {
+                        // Duplicate names keep the first, as `header->name` does
+                        headers
+                            .entry(h.key.clone())
+                            .or_insert_with(|| match h.value {
+                                Some(_) => Value::String(h.display_value()),
+                                None => Value::Null,
+                            });
+                    }
+                    Value::Object(headers)
+                }
+                SelectItem::Count => env.count.map_or(Value::Null, Value::from),
+            };
+            format!("{}:{}", Value::String(name), value)
+        })
+        .collect::<Vec<_>>();
+    format!("{{{}}}", fields.join(","))
+}
+
+impl<W: Write> OutputSink for JsonOutput<W> {
+    fn push(&mut self, env: &MessageEnvelope) {
+        let row = json_row(env, &self.columns, &self.aliases, self.value_text);
+        if self.array {
+            let sep = if self.rows == 0 { "[" } else { "," };
+            let _ = writeln!(self.out, "{}", sep);
+            let _ = write!(self.out, "{}", row);
+        } else {
+            let _ = writeln!(self.out, "{}", row);
+        }
+        self.rows += 1;
+    }
+
+    fn flush_block(&mut self) {
+        let _ = self.out.flush();
+    }
+
+    fn finish(&mut self) {
+        if self.array {
+            let close = if self.rows == 0 { "[]" } else { "\n]" };
+            let _ = writeln!(self.out, "{}", close);
+        }
+        self.flush_block();
+    }
+}
+
+/// `--format csv`: a header row of column names (or `AS` aliases), written
+/// once before the first record, then one record per row. JSON values are
+/// minified onto one line unless `keeping_value_text`; fields are quoted per
+/// RFC 4180 when needed.
+pub struct CsvOutput<W: Write> {
+    out: W,
+    columns: Vec<SelectItem>,
+    aliases: Vec<(SelectItem, String)>,
+    time_format: TimeFormat,
+    now_ms: i64,
+    value_text: bool,
+    header_written: bool,
+}
+
+impl<W: Write> CsvOutput<W> {
+    pub fn new(out: W, columns: Vec<SelectItem>, time_format: TimeFormat, now_ms: i64) -> Self {
+        Self {
+            out,
+            columns,
+            aliases: Vec::new(),
+            time_format,
+            now_ms,
+            value_text: false,
+            header_written: false,
+        }
+    }
+
+    pub fn with_aliases(mut self, aliases: Vec<(SelectItem, String)>) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Write values as their own text, line breaks included, without
+    /// minifying JSON.
+    pub fn keeping_value_text(mut self) -> Self {
+        self.value_text = true;
+        self
+    }
+
+    fn write_header(&mut self) {
+        if self.header_written {
+            return;
+        }
+        self.header_written = true;
+        let names: Vec<String> = self
+            .columns
+            .iter()
+            .map(|&col| csv_field(column_alias(&self.aliases, col).unwrap_or(col.name())))
+            .collect();
+        let _ = writeln!(self.out, "{}", names.join(","));
+    }
+
+    #[cfg(test)]
+    fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// Quote `s` if it holds a comma, quote or line break, doubling inner quotes.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+impl<W: Write> OutputSink for CsvOutput<W> {
+    fn push(&mut self, env: &MessageEnvelope) {
+        self.write_header();
+        let record: Vec<String> = self
+            .columns
+            .iter()
+            .map(|&col| {
+                let text = match col {
+                    SelectItem::Cluster => env.cluster.clone().unwrap_or_default(),
+                    SelectItem::Partition => env.partition.to_string(),
+                    SelectItem::Offset => env.offset.to_string(),
+                    SelectItem::Timestamp => {
+                        fmt_ts(env.timestamp_ms, self.time_format, self.now_ms)
+                    }
+                    SelectItem::Key => env.key.clone(),
+                    // Minified when it is JSON; other text is kept verbatim
+                    SelectItem::Value => match env.value.as_deref() {
+                        Some(raw) if self.value_text => raw.to_string(),
+                        Some(raw) => serde_json::from_str::<Value>(raw)
+                            .map(|v| v.to_string())
+                            .unwrap_or_else(|_| raw.to_string()),
+                        None => String::new(),
+                    },
+                    SelectItem::ValueSize | SelectItem::ValueFields => {
+                        derived_column_text(col, env.value.as_deref())
+                    }
+                    SelectItem::Headers => headers_compact(&env.headers),
+                    SelectItem::Count => env.count.map(|n| n.to_string()).unwrap_or_default(),
+                };
+                csv_field(&text)
+            })
+            .collect();
+        let _ = writeln!(self.out, "{}", record.join(","));
+    }
+
+    fn flush_block(&mut self) {
+        let _ = self.out.flush();
+    }
+
+    fn finish(&mut self) {
+        // Even an empty result gets its header row
+        self.write_header();
         self.flush_block();
     }
 }
 
-fn fmt_ts(ms: i64) -> String {
+pub fn now_ms() -> i64 {
+    (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000) as i64
+}
+
+fn fmt_ts(ms: i64, format: TimeFormat, now_ms: i64) -> String {
     if ms <= 0 {
         return "0".to_string();
     }
-    let secs = ms / 1000;
-    let nanos = ((ms % 1000) * 1_000_000) as i128;
-    if let Ok(dt) =
-        OffsetDateTime::from_unix_timestamp_nanos((secs as i128) * 1_000_000_000 + nanos)
-    {
-        dt.format(&Iso8601::DEFAULT)
-            .unwrap_or_else(|_| ms.to_string())
+    match format {
+        TimeFormat::Iso => fmt_iso(ms, UtcOffset::UTC),
+        TimeFormat::Local => fmt_iso(ms, local_offset_at(ms)),
+        TimeFormat::Relative => fmt_relative(ms, now_ms),
+        TimeFormat::Epoch => ms.to_string(),
+    }
+}
+
+fn fmt_iso(ms: i64, offset: UtcOffset) -> String {
+    match OffsetDateTime::from_unix_timestamp_nanos(ms as i128 * 1_000_000) {
+        Ok(dt) => dt
+            .to_offset(offset)
+            .format(&Iso8601::DEFAULT)
+            .unwrap_or_else(|_| ms.to_string()),
+        Err(_) => ms.to_string(),
+    }
+}
+
+fn fmt_relative(ms: i64, now_ms: i64) -> String {
+    let delta = now_ms - ms;
+    let secs = delta.unsigned_abs() / 1000;
+    let amount = match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86_400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86_400),
+    };
+    if delta < 0 {
+        format!("in {}", amount)
     } else {
-        ms.to_string()
+        format!("{} ago", amount)
+    }
+}
+
+/// Local UTC offset in effect at `ms`. `time`'s own lookup refuses to run in
+/// multi-threaded processes, so ask libc directly; falls back to UTC.
+fn local_offset_at(ms: i64) -> UtcOffset {
+    let t = (ms / 1000) as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    let res = unsafe { libc::localtime_r(&t, &mut tm) };
+    if res.is_null() {
+        return UtcOffset::UTC;
     }
+    UtcOffset::from_whole_seconds(tm.tm_gmtoff as i32).unwrap_or(UtcOffset::UTC)
+}
+
+/// The `rkl topic-info` table: one row per partition plus a total.
+pub fn partition_info_table(rows: &[PartitionInfo], no_color: bool) -> Table {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(
+            ["Partition", "Leader", "Low", "High", "Messages"]
+                .into_iter()
+                .map(|h| hdr(h, no_color)),
+        );
+    for r in rows {
+        let leader = if r.leader < 0 {
+            "none".to_string()
+        } else {
+            r.leader.to_string()
+        };
+        table.add_row(vec![
+            cell(r.partition, no_color),
+            cell(leader, no_color),
+            cell(r.low, no_color),
+            cell(r.high, no_color),
+            cell(r.messages(), no_color),
+        ]);
+    }
+    let total: i64 = rows.iter().map(PartitionInfo::messages).sum();
+    table.add_row(vec![
+        hdr("Total", no_color),
+        cell("", no_color),
+        cell("", no_color),
+        cell("", no_color),
+        cell(total, no_color),
+    ]);
+    table
+}
+
+/// `--schema`: observes every emitted value and prints the inferred field
+/// table once the scan finishes, instead of the rows themselves.
+pub struct SchemaOutput<W: Write> {
+    out: W,
+    no_color: bool,
+    schema: ValueSchema,
+}
+
+impl<W: Write> SchemaOutput<W> {
+    pub fn new(out: W, no_color: bool) -> Self {
+        Self {
+            out,
+            no_color,
+            schema: ValueSchema::default(),
+        }
+    }
+}
+
+impl<W: Write> OutputSink for SchemaOutput<W> {
+    fn push(&mut self, env: &MessageEnvelope) {
+        self.schema.observe(env.value.as_deref());
+    }
+
+    fn finish(&mut self) {
+        let _ = writeln!(self.out, "{}", schema_table(&self.schema, self.no_color));
+        if self.schema.non_json > 0 {
+            let _ = writeln!(
+                self.out,
+                "{} of {} values were not JSON",
+                self.schema.non_json, self.schema.messages
+            );
+        }
+        let _ = self.out.flush();
+    }
+}
+
+/// The `--schema` table: one row per observed value path, marking fields
+/// whose type varied between messages.
+pub fn schema_table(schema: &ValueSchema, no_color: bool) -> Table {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(
+            ["Field", "Types", "Seen"]
+                .into_iter()
+                .map(|h| hdr(h, no_color)),
+        );
+    for (path, field) in &schema.fields {
+        let mut types = field
+            .types
+            .iter()
+            .map(|t| t.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if field.mixed() {
+            types.push_str(" (mixed)");
+        }
+        table.add_row(vec![
+            cell(path, no_color),
+            cell(types, no_color),
+            cell(format!("{}/{}", field.seen, schema.messages), no_color),
+        ]);
+    }
+    table
+}
+
+/// `LIST topics` from the CLI: topic names with their partition counts.
+pub fn topic_list_table(topics: &[(String, usize)], no_color: bool) -> Table {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(
+            ["Topic", "Partitions"]
+                .into_iter()
+                .map(|h| hdr(h, no_color)),
+        );
+    for (topic, partitions) in topics {
+        table.add_row(vec![cell(topic, no_color), cell(partitions, no_color)]);
+    }
+    table
 }
 
 fn hdr(text: &str, _no_color: bool) -> Cell {
@@ -109,18 +928,330 @@ fn cell<T: std::fmt::Display>(v: T, _no_color: bool) -> Cell {
     Cell::new(v)
 }
 
-fn make_header(columns: &[SelectItem], no_color: bool) -> Vec<Cell> {
+fn make_header(
+    columns: &[SelectItem],
+    aliases: &[(SelectItem, String)],
+    no_color: bool,
+) -> Vec<Cell> {
     columns
         .iter()
-        .map(|col| {
+        .map(|&col| {
+            if let Some(alias) = column_alias(aliases, col) {
+                return hdr(alias, no_color);
+            }
             let label = match col {
+                SelectItem::Cluster => "Cluster",
                 SelectItem::Partition => "Partition",
                 SelectItem::Offset => "Offset",
                 SelectItem::Timestamp => "Timestamp",
                 SelectItem::Key => "Key",
                 SelectItem::Value => "Value (JSON / Text)",
+                SelectItem::ValueSize => "Value size",
+                SelectItem::ValueFields => "Value fields",
+                SelectItem::Headers => "Headers",
+                SelectItem::Count => "Count",
             };
             hdr(label, no_color)
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(offset: i64, value: Option<&str>) -> MessageEnvelope {
+        MessageEnvelope::at(offset)
+            .with_timestamp(offset)
+            .with_key(&format!("k{}", offset))
+            .with_value(value)
+    }
+
+    #[derive(Default)]
+    struct Collect(std::sync::Arc<std::sync::Mutex<Vec<(String, i64)>>>);
+
+    impl OutputSink for Collect {
+        fn push(&mut self, env: &MessageEnvelope) {
+            self.0.lock().unwrap().push((env.key.clone(), env.offset));
+        }
+    }
+
+    #[test]
+    fn latest_per_key_keeps_newest_by_timestamp() {
+        let rows = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut sink = LatestPerKey::new(Box::new(Collect(rows.clone())), true, None);
+        let msg = |key: &str, offset: i64, ts: i64| {
+            env(offset, Some("{}")).with_key(key).with_timestamp(ts)
+        };
+        sink.push(&msg("a", 1, 100));
+        sink.push(&msg("b", 2, 150));
+        sink.push(&msg("a", 3, 300));
+        sink.push(&msg("a", 4, 200)); // older timestamp, higher offset: dropped
+        sink.push(&msg("b", 5, 150)); // same timestamp: higher offset wins
+        sink.flush_block();
+        assert!(rows.lock().unwrap().is_empty());
+        sink.finish();
+        assert_eq!(
+            *rows.lock().unwrap(),
+            vec![("a".to_string(), 3), ("b".to_string(), 5)]
+        );
+    }
+
+    #[test]
+    fn latest_per_key_handles_out_of_order_and_null_keys() {
+        let rows = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut sink = LatestPerKey::new(Box::new(Collect(rows.clone())), false, Some(2));
+        let msg = |key: &str, offset: i64, ts: i64| {
+            env(offset, Some("{}")).with_key(key).with_timestamp(ts)
+        };
+        // Arrival order is not timestamp order, as across partitions
+        sink.push(&msg("a", 10, 500));
+        sink.push(&msg("null", 11, 100));
+        sink.push(&msg("a", 12, 50));
+        sink.push(&msg("c", 13, 900));
+        sink.push(&msg("null", 14, 300));
+        sink.push(&msg("c", 15, 200));
+        sink.finish();
+        // Keyless messages share the `null` key; the limit applies after dedup
+        assert_eq!(
+            *rows.lock().unwrap(),
+            vec![("null".to_string(), 14), ("a".to_string(), 10)]
+        );
+    }
+
+    #[test]
+    fn offset_after_path_ordering_skips_the_best_rows() {
+        let rows = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let query =
+            crate::query::parse_query("SELECT key FROM t ORDER BY value->n DESC LIMIT 2 OFFSET 1")
+                .unwrap();
+        let (mut sink, window) =
+            apply_query_stages(Box::new(Collect(rows.clone())), &query, true, query.limit);
+        // Buffered: the merger streams everything and the stages apply the window
+        assert_eq!(window, RowWindow::default());
+        for (offset, n) in [(0, 5), (1, 9), (2, 1), (3, 7)] {
+            sink.push(&env(offset, Some(&format!(r#"{{"n": {}}}"#, n))));
+        }
+        sink.finish();
+        let offsets: Vec<i64> = rows.lock().unwrap().iter().map(|r| r.1).collect();
+        assert_eq!(offsets, [3, 0]);
+
+        let plain = crate::query::parse_query("SELECT key FROM t LIMIT 2 OFFSET 1").unwrap();
+        let (_, window) = apply_query_stages(Box::new(Collect::default()), &plain, false, Some(2));
+        assert_eq!(
+            window,
+            RowWindow {
+                skip: 1,
+                limit: Some(2)
+            }
+        );
+    }
+
+    #[test]
+    fn json_sinks_emit_rows_in_select_order() {
+        let columns = vec![SelectItem::Key, SelectItem::Offset, SelectItem::Value];
+        let aliases = vec![(SelectItem::Key, "id".to_string())];
+        // Keys keep their order and big numbers their digits
+        let rows = [
+            env(1, Some(r#"{"b": 18446744073709551617, "a": [true]}"#)),
+            env(2, None),
+            env(3, Some("plain text")),
+        ];
+        let lines = [
+            r#"{"id":"k1","offset":1,"value":{"b":18446744073709551617,"a":[true]}}"#,
+            r#"{"id":"k2","offset":2,"value":null}"#,
+            r#"{"id":"k3","offset":3,"value":"plain text"}"#,
+        ];
+
+        let mut ndjson = JsonOutput::new(Vec::new(), columns.clone(), false).with_aliases(aliases);
+        rows.iter().for_each(|r| ndjson.push(r));
+        ndjson.finish();
+        let out = String::from_utf8(ndjson.into_inner()).unwrap();
+        assert_eq!(out, format!("{}\n", lines.join("\n")));
+
+        let mut json = JsonOutput::new(Vec::new(), columns.clone(), true);
+        rows.iter().for_each(|r| json.push(r));
+        json.finish();
+        let out = String::from_utf8(json.into_inner()).unwrap();
+        let parsed: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(parsed.as_array().map(Vec::len), Some(3));
+        assert_eq!(parsed[0]["value"]["a"][0], Value::Bool(true));
+        assert!(out.starts_with("[\n{\"key\":\"k1\""));
+
+        // No rows is still a valid document
+        let mut empty = JsonOutput::new(Vec::new(), columns, true);
+        empty.finish();
+        assert_eq!(String::from_utf8(empty.into_inner()).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn csv_quotes_fields_that_need_it() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field(r#"say "hi""#), r#""say ""hi""""#);
+        assert_eq!(csv_field("line\r\nbreak"), "\"line\r\nbreak\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn csv_sink_writes_one_header_and_keeps_newlines_quoted() {
+        let columns = vec![SelectItem::Offset, SelectItem::Key, SelectItem::Value];
+        let aliases = vec![(SelectItem::Key, "order, id".to_string())];
+        let mut sink =
+            CsvOutput::new(Vec::new(), columns, TimeFormat::Epoch, 0).with_aliases(aliases);
+        sink.push(&env(1, Some("{\n  \"a\": \"x,y\"\n}")));
+        sink.flush_block();
+        sink.push(&env(2, Some("first line\nsecond line")));
+        sink.flush_block();
+        sink.push(&env(3, None));
+        sink.finish();
+        let out = String::from_utf8(sink.into_inner()).unwrap();
+        assert_eq!(
+            out,
+            "offset,\"order, id\",value\n\
+             1,k1,\"{\"\"a\"\":\"\"x,y\"\"}\"\n\
+             2,k2,\"first line\nsecond line\"\n\
+             3,k3,\n"
+        );
+    }
+
+    #[test]
+    fn raw_sink_emits_only_values_in_order() {
+        let mut sink = RawOutput::new(Vec::new(), "<tombstone>".to_string());
+        sink.push(&env(1, Some(r#"{"a": 1}"#)));
+        sink.push(&env(2, None));
+        sink.push(&env(3, Some("plain text")));
+        sink.flush_block();
+        let out = String::from_utf8(sink.into_inner()).unwrap();
+        assert_eq!(out, "{\"a\": 1}\n<tombstone>\nplain text\n");
+    }
+
+    #[test]
+    fn raw_format_prints_tombstone_markers_or_keys() {
+        let rows = [
+            env(1, Some("one")),
+            env(2, None),
+            env(3, Some(r#"{"n": 3}"#)),
+        ];
+        let mut values = RawOutput::new(Vec::new(), "null".to_string());
+        rows.iter().for_each(|r| values.push(r));
+        values.finish();
+        let out = String::from_utf8(values.into_inner()).unwrap();
+        assert_eq!(out, "one\nnull\n{\"n\": 3}\n");
+
+        let mut keys = RawOutput::new(Vec::new(), "null".to_string()).printing_keys();
+        rows.iter().for_each(|r| keys.push(r));
+        keys.finish();
+        let out = String::from_utf8(keys.into_inner()).unwrap();
+        assert_eq!(out, "k1\nk2\nk3\n");
+    }
+
+    #[test]
+    fn count_rows_print_their_total_not_a_message() {
+        let rows = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut sink = CountRows::new(Box::new(Collect(rows.clone())), "t");
+        (0..3).for_each(|o| sink.push(&env(o, Some("{}"))));
+        sink.finish();
+        assert_eq!(*rows.lock().unwrap(), vec![(String::new(), 0)]);
+
+        let total = MessageEnvelope {
+            count: Some(3),
+            ..MessageEnvelope::at(0)
+        };
+        let mut raw = RawOutput::new(Vec::new(), String::new()).printing_keys();
+        raw.push(&total);
+        assert_eq!(String::from_utf8(raw.into_inner()).unwrap(), "3\n");
+        let mut json = JsonOutput::new(Vec::new(), vec![SelectItem::Count], false);
+        json.push(&total);
+        assert_eq!(
+            String::from_utf8(json.into_inner()).unwrap(),
+            "{\"count\":3}\n"
+        );
+    }
+
+    #[test]
+    fn timestamps_render_per_time_format() {
+        // 2024-01-02T03:04:05.678Z
+        let ts = 1_704_164_645_678;
+        assert_eq!(
+            fmt_ts(ts, TimeFormat::Iso, 0),
+            "2024-01-02T03:04:05.678000000Z"
+        );
+        assert_eq!(fmt_ts(ts, TimeFormat::Epoch, 0), "1704164645678");
+        assert_eq!(fmt_ts(ts, TimeFormat::Relative, ts + 42_000), "42s ago");
+        assert_eq!(
+            fmt_ts(ts, TimeFormat::Relative, ts + 3 * 3_600_000),
+            "3h ago"
+        );
+        assert_eq!(
+            fmt_ts(ts, TimeFormat::Relative, ts + 2 * 86_400_000),
+            "2d ago"
+        );
+        assert_eq!(fmt_ts(ts, TimeFormat::Relative, ts - 5 * 60_000), "in 5m");
+        let ist = UtcOffset::from_hms(5, 30, 0).unwrap();
+        assert_eq!(fmt_iso(ts, ist), "2024-01-02T08:34:05.678000000+05:30");
+        assert!(!fmt_ts(ts, TimeFormat::Local, 0).is_empty());
+        // Missing timestamps stay "0" in every mode
+        assert_eq!(fmt_ts(0, TimeFormat::Relative, ts), "0");
+    }
+
+    #[test]
+    fn table_header_uses_column_aliases() {
+        let columns = vec![SelectItem::Partition, SelectItem::Offset, SelectItem::Key];
+        let aliases = vec![(SelectItem::Key, "order_id".to_string())];
+        let out = TableOutput::new(Vec::new(), true, columns, 0, TimeFormat::Iso, 0)
+            .with_aliases(aliases);
+        let header = out.table.to_string();
+        assert!(header.contains("order_id"));
+        assert!(header.contains("Partition") && header.contains("Offset"));
+        assert!(!header.contains("Key"));
+    }
+
+    #[test]
+    fn table_sink_writes_each_block_to_its_writer() {
+        let mut out = TableOutput::new(
+            Vec::new(),
+            true,
+            vec![SelectItem::Offset, SelectItem::Value],
+            0,
+            TimeFormat::Iso,
+            0,
+        );
+        out.push(&env(1, Some("first")));
+        out.flush_block();
+        // An empty block writes nothing
+        out.flush_block();
+        out.push(&env(2, Some("second")));
+        out.flush_block();
+        let text = String::from_utf8(out.out).unwrap();
+        assert_eq!(text.matches("Offset").count(), 2);
+        let (first, second) = (text.find("first").unwrap(), text.find("second").unwrap());
+        assert!(first < second);
+    }
+
+    #[test]
+    fn headers_column_renders_compact_pairs() {
+        let mut out = TableOutput::new(
+            Vec::new(),
+            true,
+            vec![SelectItem::Headers],
+            0,
+            TimeFormat::Iso,
+            0,
+        );
+        let mut with_headers = env(1, None);
+        with_headers.headers = vec![
+            crate::models::MessageHeader {
+                key: "trace-id".to_string(),
+                value: Some(b"abc".to_vec()),
+            },
+            crate::models::MessageHeader {
+                key: "blob".to_string(),
+                value: Some(vec![0, 1]),
+            },
+        ];
+        out.push(&with_headers);
+        let rendered = out.table.to_string();
+        assert!(rendered.contains("trace-id=abc; blob=0x0001 (2 bytes)"));
+    }
+}
@@ -0,0 +1,253 @@
+//! `--produce-to`: republish the messages a run emits to another topic, for
+//! topic surgery and replay.
+use crate::models::{ClusterSpec, MessageEnvelope, MessageHeader};
+use crate::output::OutputSink;
+use anyhow::{Context, Result, anyhow};
+use rdkafka::config::ClientConfig;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::message::{Header, OwnedHeaders};
+use rdkafka::producer::{DeliveryFuture, FutureProducer, FutureRecord};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// What gets written for one emitted message. Keys and values are the decoded
+/// text the run read (so non-UTF-8 bytes were replaced unless `--strict-decode`);
+/// a `"null"` key and a missing value are produced as null.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProducedRecord {
+    pub topic: String,
+    pub key: Option<String>,
+    pub payload: Option<String>,
+    pub headers: Vec<MessageHeader>,
+    /// Original timestamp; `None` lets the broker stamp it.
+    pub timestamp_ms: Option<i64>,
+}
+
+impl ProducedRecord {
+    pub fn from_envelope(env: &MessageEnvelope, topic: &str) -> Self {
+        Self {
+            topic: topic.to_string(),
+            key: (env.key != "null").then(|| env.key.clone()),
+            payload: env.value.clone(),
+            headers: env.headers.clone(),
+            timestamp_ms: (env.timestamp_ms > 0).then_some(env.timestamp_ms),
+        }
+    }
+}
+
+/// Where produced records go; Kafka in practice, a stub in tests.
+pub trait RecordSender: Send {
+    fn send(&mut self, record: ProducedRecord) -> Result<()>;
+}
+
+/// Output sink that hands every emitted row to a `RecordSender`. Send errors
+/// are counted rather than aborting the merger.
+pub struct ProduceSink<S: RecordSender> {
+    sender: S,
+    topic: String,
+    failed: Arc<Mutex<Vec<String>>>,
+}
+
+impl<S: RecordSender> ProduceSink<S> {
+    pub fn new(sender: S, topic: &str, failed: Arc<Mutex<Vec<String>>>) -> Self {
+        Self {
+            sender,
+            topic: topic.to_string(),
+            failed,
+        }
+    }
+}
+
+impl<S: RecordSender> OutputSink for ProduceSink<S> {
+    fn push(&mut self, env: &MessageEnvelope) {
+        let record = ProducedRecord::from_envelope(env, &self.topic);
+        if let Err(e) = self.sender.send(record)
+            && let Ok(mut failed) = self.failed.lock()
+        {
+            failed.push(format!("p{}@{}: {:#}", env.partition, env.offset, e));
+        }
+    }
+}
+
+/// Hands records to a task that enqueues them on a `FutureProducer`, so a full
+/// librdkafka queue is waited out without blocking the merger's thread.
+pub struct KafkaSender {
+    records: mpsc::UnboundedSender<ProducedRecord>,
+}
+
+/// The enqueueing task; once every `KafkaSender` is dropped it resolves to
+/// the delivery futures and the records that could not be enqueued.
+pub type Enqueued = JoinHandle<(Vec<DeliveryFuture>, Vec<String>)>;
+
+impl KafkaSender {
+    pub fn new(
+        cluster: &ClusterSpec,
+        client_options: impl FnOnce(&mut ClientConfig),
+    ) -> Result<(Self, Enqueued)> {
+        let mut cfg = ClientConfig::new();
+        cfg.set("bootstrap.servers", &cluster.bootstrap);
+        cluster.apply_security(&mut cfg);
+        client_options(&mut cfg);
+        let producer = cfg.create().context("Failed to create producer")?;
+        let (records, rx) = mpsc::unbounded_channel();
+        Ok((
+            Self { records },
+            tokio::spawn(enqueue_records(producer, rx)),
+        ))
+    }
+}
+
+impl RecordSender for KafkaSender {
+    fn send(&mut self, record: ProducedRecord) -> Result<()> {
+        self.records
+            .send(record)
+            .map_err(|_| anyhow!("the producer task has stopped"))
+    }
+}
+
+async fn enqueue_records(
+    producer: FutureProducer,
+    mut records: mpsc::UnboundedReceiver<ProducedRecord>,
+) -> (Vec<DeliveryFuture>, Vec<String>) {
+    let mut deliveries = Vec::new();
+    let mut failed = Vec::new();
+    while let Some(record) = records.recv().await {
+        match enqueue(&producer, &record).await {
+            Ok(delivery) => deliveries.push(delivery),
+            Err(e) => failed.push(format!(
+                "key {}: {}",
+                record.key.as_deref().unwrap_or("null"),
+                e
+            )),
+        }
+    }
+    (deliveries, failed)
+}
+
+/// Enqueue one record, sleeping without blocking while librdkafka's local
+/// queue is full.
+async fn enqueue(
+    producer: &FutureProducer,
+    record: &ProducedRecord,
+) -> Result<DeliveryFuture, KafkaError> {
+    let mut headers = OwnedHeaders::new_with_capacity(record.headers.len());
+    for h in &record.headers {
+        headers = headers.insert(Header {
+            key: &h.key,
+            value: h.value.as_deref(),
+        });
+    }
+    let mut fr: FutureRecord<'_, str, str> = FutureRecord::to(&record.topic).headers(headers);
+    if let Some(key) = record.key.as_deref() {
+        fr = fr.key(key);
+    }
+    if let Some(payload) = record.payload.as_deref() {
+        fr = fr.payload(payload);
+    }
+    if let Some(ts) = record.timestamp_ms {
+        fr = fr.timestamp(ts);
+    }
+    loop {
+        match producer.send_result(fr) {
+            Ok(delivery) => return Ok(delivery),
+            // The local queue is full: let librdkafka drain it, then retry
+            Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), back)) => {
+                fr = back;
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            Err((e, _)) => return Err(e),
+        }
+    }
+}
+
+/// Await the queued deliveries: `(delivered, failure messages)`.
+pub async fn wait_for_deliveries(deliveries: Vec<DeliveryFuture>) -> (u64, Vec<String>) {
+    let mut delivered = 0;
+    let mut failed = Vec::new();
+    for delivery in deliveries {
+        match delivery.await {
+            Ok(Ok(_)) => delivered += 1,
+            Ok(Err((e, _))) => failed.push(e.to_string()),
+            Err(_) => failed.push("delivery cancelled".to_string()),
+        }
+    }
+    (delivered, failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Arc<Mutex<Vec<ProducedRecord>>>);
+
+    impl RecordSender for Recorder {
+        fn send(&mut self, record: ProducedRecord) -> Result<()> {
+            if record.key.as_deref() == Some("reject") {
+                anyhow::bail!("broker said no");
+            }
+            self.0.lock().unwrap().push(record);
+            Ok(())
+        }
+    }
+
+    fn env(key: &str, value: Option<&str>, timestamp_ms: i64) -> MessageEnvelope {
+        MessageEnvelope {
+            headers: vec![MessageHeader {
+                key: "trace".to_string(),
+                value: Some(b"\x01\x02".to_vec()),
+            }],
+            ..MessageEnvelope::at(9)
+                .on_topic("source")
+                .on_partition(2)
+                .with_timestamp(timestamp_ms)
+                .with_key(key)
+                .with_value(value)
+        }
+    }
+
+    #[test]
+    fn envelopes_map_to_produced_records() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let failed = Arc::new(Mutex::new(Vec::new()));
+        let mut sink = ProduceSink::new(Recorder(sent.clone()), "target", failed.clone());
+        sink.push(&env("k1", Some(r#"{"a":1}"#), 1_700_000_000_000));
+        // Null key, tombstone, unknown timestamp
+        sink.push(&env("null", None, 0));
+        sink.push(&env("reject", Some("x"), 5));
+        sink.finish();
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(
+            sent[0],
+            ProducedRecord {
+                topic: "target".to_string(),
+                key: Some("k1".to_string()),
+                payload: Some(r#"{"a":1}"#.to_string()),
+                headers: vec![MessageHeader {
+                    key: "trace".to_string(),
+                    value: Some(b"\x01\x02".to_vec()),
+                }],
+                timestamp_ms: Some(1_700_000_000_000),
+            }
+        );
+        assert_eq!(
+            (
+                sent[1].key.clone(),
+                sent[1].payload.clone(),
+                sent[1].timestamp_ms
+            ),
+            (None, None, None)
+        );
+        assert_eq!(sent.len(), 2);
+        let failed = failed.lock().unwrap();
+        assert_eq!(failed.len(), 1);
+        assert!(
+            failed[0].starts_with("p2@9: broker said no"),
+            "{}",
+            failed[0]
+        );
+    }
+}
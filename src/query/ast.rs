@@ -1,10 +1,20 @@
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SelectItem {
+    Cluster,
     Partition,
     Offset,
     Timestamp,
     Key,
     Value,
+    /// Derived: length of the value (see [`value_size`])
+    ValueSize,
+    /// Derived: top-level field count of a JSON object value (see [`value_fields`])
+    ValueFields,
+    /// Record headers, rendered as `k=v; k=v`
+    Headers,
+    /// Aggregate `COUNT(*)`: the number of rows the query would return,
+    /// emitted as one row at the end of the scan (see `output::CountRows`)
+    Count,
 }
 
 impl SelectItem {
@@ -20,6 +30,38 @@ impl SelectItem {
         }
         cols
     }
+
+    /// The column's name in the query language, e.g. `value_size`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            SelectItem::Cluster => "cluster",
+            SelectItem::Partition => "partition",
+            SelectItem::Offset => "offset",
+            SelectItem::Timestamp => "timestamp",
+            SelectItem::Key => "key",
+            SelectItem::Value => "value",
+            SelectItem::ValueSize => "value_size",
+            SelectItem::ValueFields => "value_fields",
+            SelectItem::Headers => "headers",
+            SelectItem::Count => "count",
+        }
+    }
+
+    /// Whether rendering this column needs the message value fetched.
+    pub fn needs_value(&self) -> bool {
+        matches!(
+            self,
+            SelectItem::Value | SelectItem::ValueSize | SelectItem::ValueFields
+        )
+    }
+}
+
+/// The `AS` label given to `col`, if any.
+pub fn column_alias(aliases: &[(SelectItem, String)], col: SelectItem) -> Option<&str> {
+    aliases
+        .iter()
+        .find(|(c, _)| *c == col)
+        .map(|(_, name)| name.as_str())
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -27,6 +69,14 @@ pub enum RootPath {
     Key,
     Value,
     Timestamp,
+    /// `topic`: the topic the message was read from; takes no sub-path
+    Topic,
+    /// `value_size`: see [`value_size`]; takes no sub-path
+    ValueSize,
+    /// `value_fields`: see [`value_fields`]; takes no sub-path
+    ValueFields,
+    /// `header->name`: the first record header called `name`; one segment
+    Header,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -41,6 +91,86 @@ pub enum Literal {
     Number(f64),
     Bool(bool),
     Null,
+    /// Right-hand side of `MATCHES` or `LIKE`, compiled once when the query is parsed
+    Pattern(Pattern),
+}
+
+/// A compiled `MATCHES` regex (or translated `LIKE` pattern, with the
+/// pattern as written kept in `like`). Two patterns are equal when their
+/// regex source is.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    regex: regex::Regex,
+    like: Option<String>,
+}
+
+impl Pattern {
+    pub fn new(source: &str) -> Result<Self, regex::Error> {
+        regex::Regex::new(source).map(|regex| Pattern { regex, like: None })
+    }
+
+    /// SQL `LIKE`: `%` is any sequence, `_` any single character, `\%` / `\_`
+    /// literal; everything else matches itself and the whole text must match.
+    pub fn like(pattern: &str) -> Result<Self, regex::Error> {
+        let mut source = String::from("(?s)^");
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '%' => source.push_str(".*"),
+                '_' => source.push('.'),
+                '\\' => match chars.next() {
+                    Some(escaped) => source.push_str(&regex::escape(&escaped.to_string())),
+                    None => source.push_str(&regex::escape("\\")),
+                },
+                _ => source.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        source.push('$');
+        let mut compiled = Self::new(&source)?;
+        compiled.like = Some(pattern.to_string());
+        Ok(compiled)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    /// The literal runs of a pattern built by `like`, split at its wildcards:
+    /// `%time_out%` gives `["time", "out"]`. Empty for a `MATCHES` regex.
+    pub fn like_literals(&self) -> Vec<String> {
+        let Some(like) = &self.like else {
+            return Vec::new();
+        };
+        let mut out = Vec::new();
+        let mut run = String::new();
+        let mut chars = like.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                // Same escapes as `like`: a trailing `\` is itself
+                '\\' => run.push(chars.next().unwrap_or('\\')),
+                '%' | '_' => {
+                    if !run.is_empty() {
+                        out.push(std::mem::take(&mut run));
+                    }
+                }
+                _ => run.push(c),
+            }
+        }
+        if !run.is_empty() {
+            out.push(run);
+        }
+        out
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -48,7 +178,27 @@ pub enum CmpOp {
     Eq,
     Neq,
     Contains,
-    // Future: Lt, Gt, Le, Ge, Like, In, etc.
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    /// `MATCHES '<regex>'` against the stringified path (unanchored)
+    Regex,
+    /// `LIKE '<pattern>'` with `%` / `_` wildcards against the whole stringified path
+    Like,
+}
+
+impl CmpOp {
+    /// The operator with its operands swapped: `a < b` is `b > a`.
+    pub fn flipped(self) -> CmpOp {
+        match self {
+            CmpOp::Lt => CmpOp::Gt,
+            CmpOp::Gt => CmpOp::Lt,
+            CmpOp::Le => CmpOp::Ge,
+            CmpOp::Ge => CmpOp::Le,
+            other => other,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -59,9 +209,30 @@ pub enum Expr {
         op: CmpOp,
         right: Literal,
     },
+    /// `ANY(value->items)->status = 'failed'`: `predicate` is evaluated against
+    /// each element of the array at `array`, with its `value` root bound to the element.
+    Quantified {
+        quantifier: Quantifier,
+        array: JsonPath,
+        predicate: Box<Expr>,
+    },
     And(Box<Expr>, Box<Expr>),
     Or(Box<Expr>, Box<Expr>),
-    // Future: Not(...)
+    /// `NOT <expr>`, and negated infix forms such as `value NOT CONTAINS 'x'`
+    Not(Box<Expr>),
+    /// `EXISTS(value->error)`: the path is present, whatever its value (even null)
+    Exists(JsonPath),
+    /// `key IN ('a', 'b')`: `left = item` holds for some item (never empty)
+    In {
+        left: JsonPath,
+        list: Vec<Literal>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantifier {
+    Any,
+    All,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -70,9 +241,15 @@ pub enum OrderDir {
     Desc,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum OrderField {
     Timestamp,
+    Offset,
+    Partition,
+    /// Lexicographic on the key string.
+    Key,
+    /// `ORDER BY value->latency`: numeric sort on a path; non-numbers sort last.
+    Path(JsonPath),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -81,72 +258,329 @@ pub struct OrderSpec {
     pub dir: OrderDir,
 }
 
+/// `GROUP BY` target. Grouping keeps the newest message per group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Key,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct SelectQuery {
     pub select: Vec<SelectItem>,
+    /// `AS <name>` labels from the select list, shown as column headers.
+    pub aliases: Vec<(SelectItem, String)>,
     pub from: String, // Kafka topic (raw string for now)
     pub r#where: Option<Expr>,
-    pub order: Option<OrderSpec>,
+    pub group_by: Option<GroupBy>,
+    /// `ORDER BY` keys in priority order; never empty when present.
+    pub order: Option<Vec<OrderSpec>>,
     pub limit: Option<usize>,
+    /// `OFFSET n`: matching rows to skip before `limit` starts counting.
+    pub skip: Option<usize>,
+}
+
+impl SelectQuery {
+    /// The first `ORDER BY` key; its direction also orders buffered stages.
+    pub fn primary_order(&self) -> Option<&OrderSpec> {
+        self.order.as_ref()?.first()
+    }
+
+    /// The path from `ORDER BY <path>`, when ordering by something other than timestamp.
+    pub fn order_path(&self) -> Option<&JsonPath> {
+        match self.primary_order().map(|o| &o.field) {
+            Some(OrderField::Path(path)) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// `n` for `ORDER BY timestamp DESC LIMIT n` with no WHERE or GROUP BY:
+    /// every message matches, so the newest `n` overall are among each
+    /// partition's last `n` and readers can start near the end.
+    pub fn latest_n(&self) -> Option<usize> {
+        let [order] = self.order.as_deref()? else {
+            return None;
+        };
+        let newest_first = order.field == OrderField::Timestamp && order.dir == OrderDir::Desc;
+        if !newest_first
+            || self.r#where.is_some()
+            || self.group_by.is_some()
+            || self.skip.is_some()
+            || self.is_count()
+        {
+            return None;
+        }
+        self.limit
+    }
+
+    /// `SELECT COUNT(*)`: the result is one row holding the number of matches.
+    pub fn is_count(&self) -> bool {
+        self.select == [SelectItem::Count]
+    }
+
+    /// Whether `ORDER BY` asks for anything but oldest first (a path, offset,
+    /// partition, key or `timestamp DESC`); the merger streams only that order,
+    /// so these rows are sorted once every message is seen.
+    pub fn sorts_at_end(&self) -> bool {
+        self.primary_order()
+            .is_some_and(|o| o.field != OrderField::Timestamp || o.dir == OrderDir::Desc)
+    }
+
+    /// Whether output waits for the end of the scan: `GROUP BY key`, a sorted
+    /// `ORDER BY` and `COUNT(*)` only emit once every message is seen.
+    pub fn buffers_until_end(&self) -> bool {
+        self.group_by.is_some() || self.sorts_at_end() || self.is_count()
+    }
+
+    /// Whether the consumer must keep message values for this query's output.
+    pub fn needs_value(&self) -> bool {
+        self.select.iter().any(SelectItem::needs_value)
+            || self.order_path().is_some_and(|p| {
+                !matches!(
+                    p.root,
+                    RootPath::Key | RootPath::Timestamp | RootPath::Topic | RootPath::Header
+                )
+            })
+    }
+}
+
+/// The parts of a message that `WHERE` and `ORDER BY` paths can see.
+#[derive(Debug, Clone, Copy)]
+pub struct MessageView<'a> {
+    pub topic: &'a str,
+    pub key: &'a str,
+    pub value: &'a Value,
+    /// The raw payload text; `None` for tombstones
+    pub value_str: Option<&'a str>,
+    pub timestamp_ms: i64,
+    pub headers: &'a [MessageHeader],
+}
+
+impl<'a> MessageView<'a> {
+    /// View `env` with its payload already parsed as `value` (Null if not JSON).
+    pub fn of(env: &'a MessageEnvelope, value: &'a Value) -> Self {
+        MessageView {
+            topic: &env.topic,
+            key: &env.key,
+            value,
+            value_str: env.value.as_deref(),
+            timestamp_ms: env.timestamp_ms,
+            headers: &env.headers,
+        }
+    }
+}
+
+impl JsonPath {
+    /// Resolve this path against a message, as `WHERE` comparisons do.
+    pub fn resolve(&self, msg: &MessageView) -> Value {
+        resolve_path(self, msg)
+    }
 }
 
 impl Expr {
-    /// Evaluate this expression against a message triple `(key, value_json, timestamp_ms)`.
-    pub fn matches(
-        &self,
-        key: &str,
-        value: &Value,
-        value_str: Option<&str>,
-        timestamp_ms: i64,
-    ) -> bool {
+    /// Evaluate this expression against a message.
+    pub fn matches(&self, msg: &MessageView) -> bool {
         match self {
-            Expr::And(lhs, rhs) => {
-                lhs.matches(key, value, value_str, timestamp_ms)
-                    && rhs.matches(key, value, value_str, timestamp_ms)
-            }
-            Expr::Or(lhs, rhs) => {
-                lhs.matches(key, value, value_str, timestamp_ms)
-                    || rhs.matches(key, value, value_str, timestamp_ms)
+            Expr::And(lhs, rhs) => lhs.matches(msg) && rhs.matches(msg),
+            Expr::Or(lhs, rhs) => lhs.matches(msg) || rhs.matches(msg),
+            Expr::Not(inner) => !inner.matches(msg),
+            Expr::Exists(path) => lookup_path(path, msg).is_some(),
+            Expr::Quantified {
+                quantifier,
+                array,
+                predicate,
+            } => {
+                // Non-arrays never match; ALL over an empty array is vacuously true.
+                let Value::Array(items) = resolve_path(array, msg) else {
+                    return false;
+                };
+                let check = |item: &Value| {
+                    predicate.matches(&MessageView {
+                        value: item,
+                        value_str: item.as_str(),
+                        ..*msg
+                    })
+                };
+                match quantifier {
+                    Quantifier::Any => items.iter().any(check),
+                    Quantifier::All => items.iter().all(check),
+                }
             }
+            Expr::In { left, list } => list
+                .iter()
+                .any(|item| cmp_eq_with_value_str(left, item, msg)),
             Expr::Cmp { left, op, right } => match op {
-                CmpOp::Eq => {
-                    cmp_eq_with_value_str(left, right, key, value, value_str, timestamp_ms)
-                }
-                CmpOp::Neq => {
-                    !cmp_eq_with_value_str(left, right, key, value, value_str, timestamp_ms)
-                }
+                CmpOp::Eq => cmp_eq_with_value_str(left, right, msg),
+                CmpOp::Neq => !cmp_eq_with_value_str(left, right, msg),
                 CmpOp::Contains => {
-                    let left_str = path_to_string(left, key, value, value_str, timestamp_ms);
+                    let left_str = path_to_string(left, msg);
                     cmp_contains(&left_str, right)
                 }
+                CmpOp::Regex | CmpOp::Like => {
+                    let Literal::Pattern(pattern) = right else {
+                        return false;
+                    };
+                    // Absent and null fields have no text to match, unlike CONTAINS's "null"
+                    if lookup_path(left, msg).is_none_or(|v| v.is_null()) {
+                        return false;
+                    }
+                    let left_str = path_to_string(left, msg);
+                    pattern.is_match(&left_str)
+                }
+                CmpOp::Lt | CmpOp::Gt | CmpOp::Le | CmpOp::Ge => {
+                    let lv = resolve_path(left, msg);
+                    match cmp_order(&lv, right) {
+                        Some(ord) => match op {
+                            CmpOp::Lt => ord.is_lt(),
+                            CmpOp::Gt => ord.is_gt(),
+                            CmpOp::Le => ord.is_le(),
+                            _ => ord.is_ge(),
+                        },
+                        None => false,
+                    }
+                }
             },
         }
     }
+
+    /// Paths of the comparisons that held for this message, i.e. what made the
+    /// expression match. Empty when the expression as a whole does not match.
+    pub fn matched_paths(&self, msg: &MessageView) -> Vec<JsonPath> {
+        let mut out = Vec::new();
+        if self.matches(msg) {
+            self.collect_matched(msg, &mut out);
+        }
+        out
+    }
+
+    // Only called on sub-expressions that evaluated true
+    fn collect_matched(&self, msg: &MessageView, out: &mut Vec<JsonPath>) {
+        let path = match self {
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                for side in [lhs, rhs] {
+                    if side.matches(msg) {
+                        side.collect_matched(msg, out);
+                    }
+                }
+                return;
+            }
+            Expr::Quantified { array, .. } | Expr::Exists(array) => array,
+            Expr::Cmp { left, .. } | Expr::In { left, .. } => left,
+            // A held negation of a comparison points at the field it tested;
+            // negated groups have no single field to point at
+            Expr::Not(inner) => match inner.as_ref() {
+                Expr::Cmp { left, .. } | Expr::In { left, .. } => left,
+                _ => return,
+            },
+        };
+        if !out.contains(path) {
+            out.push(path.clone());
+        }
+    }
+
+    /// Text the detail pane marks in a matched message: `CONTAINS` needles and
+    /// the literal parts of `LIKE` patterns. Negated comparisons are skipped,
+    /// since their text is by definition absent.
+    pub fn highlight_needles(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        self.collect_needles(&mut out);
+        out
+    }
+
+    fn collect_needles(&self, out: &mut Vec<String>) {
+        let found = match self {
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                lhs.collect_needles(out);
+                rhs.collect_needles(out);
+                return;
+            }
+            Expr::Quantified { predicate, .. } => return predicate.collect_needles(out),
+            Expr::Cmp {
+                op: CmpOp::Contains,
+                right,
+                ..
+            } => vec![literal_to_string(right)],
+            Expr::Cmp {
+                op: CmpOp::Like,
+                right: Literal::Pattern(pattern),
+                ..
+            } => pattern.like_literals(),
+            _ => return,
+        };
+        for needle in found {
+            if !needle.is_empty() && !out.contains(&needle) {
+                out.push(needle);
+            }
+        }
+    }
 }
 
-fn resolve_path(path: &JsonPath, key: &str, value: &Value, timestamp_ms: i64) -> Value {
+fn resolve_path(path: &JsonPath, msg: &MessageView) -> Value {
+    lookup_path(path, msg).unwrap_or(Value::Null)
+}
+
+/// Like `resolve_path`, but `None` when the path is absent, so a field that is
+/// present with a JSON null (`Some(Null)`) can be told apart from a missing one.
+/// A tombstone has no `value` at all; a non-JSON payload is a present `value`.
+fn lookup_path(path: &JsonPath, msg: &MessageView) -> Option<Value> {
     match path.root {
-        RootPath::Key => Value::String(key.to_string()),
-        RootPath::Timestamp => Value::Number(serde_json::Number::from(timestamp_ms)),
+        RootPath::Topic => Some(Value::String(msg.topic.to_string())),
+        RootPath::Key => Some(Value::String(msg.key.to_string())),
+        RootPath::Timestamp => Some(Value::Number(serde_json::Number::from(msg.timestamp_ms))),
+        RootPath::ValueSize => value_size(msg.value, msg.value_str).map(Value::from),
+        RootPath::ValueFields => value_fields(msg.value).map(Value::from),
+        RootPath::Header => {
+            let name = path.segments.first()?;
+            let header = msg.headers.iter().find(|h| &h.key == name)?;
+            Some(match header.value.as_deref() {
+                None => Value::Null,
+                Some(bytes) => Value::String(String::from_utf8_lossy(bytes).into_owned()),
+            })
+        }
         RootPath::Value => {
-            let mut cur = value;
-            for seg in &path.segments {
-                match cur {
-                    Value::Object(map) => {
-                        if let Some(v) = map.get(seg) {
-                            cur = v;
-                        } else {
-                            return Value::Null;
-                        }
-                    }
-                    _ => return Value::Null,
-                }
+            if path.segments.is_empty() && msg.value.is_null() && msg.value_str.is_none() {
+                return None;
             }
-            cur.clone()
+            // Walk as a JSON Pointer, so numeric segments index arrays
+            let pointer: String = path
+                .segments
+                .iter()
+                .map(|seg| format!("/{}", seg.replace('~', "~0").replace('/', "~1")))
+                .collect();
+            msg.value.pointer(&pointer).cloned()
         }
     }
 }
 
+/// `value_size`: compact serialized length of a JSON value, or the byte length
+/// of a non-JSON payload. `None` for tombstones.
+pub fn value_size(value: &Value, value_str: Option<&str>) -> Option<usize> {
+    let raw = value_str?;
+    if value.is_null() {
+        Some(raw.len())
+    } else {
+        serde_json::to_string(value).ok().map(|s| s.len())
+    }
+}
+
+/// `value_fields`: number of top-level keys of a JSON object value.
+pub fn value_fields(value: &Value) -> Option<usize> {
+    value.as_object().map(|m| m.len())
+}
+
+/// Render a derived column (`value_size`/`value_fields`) from a message's
+/// value text; empty when it does not apply.
+pub fn derived_column_text(item: SelectItem, value_str: Option<&str>) -> String {
+    let json = value_str
+        .and_then(|s| serde_json::from_str::<Value>(s).ok())
+        .unwrap_or(Value::Null);
+    let n = match item {
+        SelectItem::ValueSize => value_size(&json, value_str),
+        SelectItem::ValueFields => value_fields(&json),
+        _ => None,
+    };
+    n.map(|n| n.to_string()).unwrap_or_default()
+}
+
 fn cmp_eq(left: &Value, right: &Literal) -> bool {
     match right {
         Literal::String(s) => left.as_str().map(|x| x == s).unwrap_or(false),
@@ -163,26 +597,31 @@ fn cmp_eq(left: &Value, right: &Literal) -> bool {
             }),
         Literal::Bool(b) => left.as_bool().map(|x| x == *b).unwrap_or(false),
         Literal::Null => left.is_null(),
+        Literal::Pattern(_) => false,
     }
 }
 
-fn cmp_eq_with_value_str(
-    left: &JsonPath,
-    right: &Literal,
-    key: &str,
-    value: &Value,
-    value_str: Option<&str>,
-    timestamp_ms: i64,
-) -> bool {
+fn cmp_eq_with_value_str(left: &JsonPath, right: &Literal, msg: &MessageView) -> bool {
     if matches!(left.root, RootPath::Value) && left.segments.is_empty() {
         if let Literal::String(expected) = right {
-            return as_full_value_string(value, value_str) == *expected;
+            return as_full_value_string(msg.value, msg.value_str) == *expected;
         }
     }
-    let lv = resolve_path(left, key, value, timestamp_ms);
+    let lv = resolve_path(left, msg);
     cmp_eq(&lv, right)
 }
 
+/// Ordering of a resolved path against a literal: numbers compare numerically
+/// and strings lexicographically; anything else (null, absent, mixed types)
+/// is unordered, so range comparisons fail.
+fn cmp_order(left: &Value, right: &Literal) -> Option<std::cmp::Ordering> {
+    match (left, right) {
+        (_, Literal::Number(n)) => left.as_f64().and_then(|x| x.partial_cmp(n)),
+        (Value::String(s), Literal::String(r)) => Some(s.as_str().cmp(r.as_str())),
+        _ => None,
+    }
+}
+
 fn cmp_contains(left: &str, right: &Literal) -> bool {
     let needle = literal_to_string(right);
     left.contains(&needle)
@@ -194,20 +633,15 @@ fn literal_to_string(lit: &Literal) -> String {
         Literal::Number(n) => n.to_string(),
         Literal::Bool(b) => b.to_string(),
         Literal::Null => "null".to_string(),
+        Literal::Pattern(p) => p.as_str().to_string(),
     }
 }
 
-fn path_to_string(
-    left: &JsonPath,
-    key: &str,
-    value: &Value,
-    value_str: Option<&str>,
-    timestamp_ms: i64,
-) -> String {
+fn path_to_string(left: &JsonPath, msg: &MessageView) -> String {
     if matches!(left.root, RootPath::Value) && left.segments.is_empty() {
-        as_full_value_string(value, value_str)
+        as_full_value_string(msg.value, msg.value_str)
     } else {
-        let resolved = resolve_path(left, key, value, timestamp_ms);
+        let resolved = resolve_path(left, msg);
         value_to_string(&resolved)
     }
 }
@@ -226,6 +660,7 @@ fn value_to_string(value: &Value) -> String {
         _ => serde_json::to_string(value).unwrap_or_else(|_| "null".to_string()),
     }
 }
+use crate::models::{MessageEnvelope, MessageHeader};
 use serde_json::Value;
 
 #[cfg(test)]
@@ -239,6 +674,23 @@ mod tests {
         }
     }
 
+    fn view<'a>(
+        topic: &'a str,
+        key: &'a str,
+        value: &'a Value,
+        value_str: Option<&'a str>,
+        timestamp_ms: i64,
+    ) -> MessageView<'a> {
+        MessageView {
+            topic,
+            key,
+            value,
+            value_str,
+            timestamp_ms,
+            headers: &[],
+        }
+    }
+
     #[test]
     fn matches_equality_and_inequality() {
         let key = "user-123";
@@ -251,56 +703,56 @@ mod tests {
             op: CmpOp::Eq,
             right: Literal::String("PUT".to_string()),
         };
-        assert!(method_eq.matches(key, &value_json, Some(raw), ts));
+        assert!(method_eq.matches(&view("t", key, &value_json, Some(raw), ts)));
 
         let method_neq = Expr::Cmp {
             left: path(RootPath::Value, &["payload", "method"]),
             op: CmpOp::Neq,
             right: Literal::String("GET".to_string()),
         };
-        assert!(method_neq.matches(key, &value_json, Some(raw), ts));
+        assert!(method_neq.matches(&view("t", key, &value_json, Some(raw), ts)));
 
         let method_neq_false = Expr::Cmp {
             left: path(RootPath::Value, &["payload", "method"]),
             op: CmpOp::Neq,
             right: Literal::String("PUT".to_string()),
         };
-        assert!(!method_neq_false.matches(key, &value_json, Some(raw), ts));
+        assert!(!method_neq_false.matches(&view("t", key, &value_json, Some(raw), ts)));
 
         let code_eq = Expr::Cmp {
             left: path(RootPath::Value, &["payload", "code"]),
             op: CmpOp::Eq,
             right: Literal::Number(42.0),
         };
-        assert!(code_eq.matches(key, &value_json, Some(raw), ts));
+        assert!(code_eq.matches(&view("t", key, &value_json, Some(raw), ts)));
 
         let flag_eq = Expr::Cmp {
             left: path(RootPath::Value, &["payload", "flag"]),
             op: CmpOp::Eq,
             right: Literal::Bool(true),
         };
-        assert!(flag_eq.matches(key, &value_json, Some(raw), ts));
+        assert!(flag_eq.matches(&view("t", key, &value_json, Some(raw), ts)));
 
         let none_eq = Expr::Cmp {
             left: path(RootPath::Value, &["payload", "none"]),
             op: CmpOp::Eq,
             right: Literal::Null,
         };
-        assert!(none_eq.matches(key, &value_json, Some(raw), ts));
+        assert!(none_eq.matches(&view("t", key, &value_json, Some(raw), ts)));
 
         let full_value_eq = Expr::Cmp {
             left: path(RootPath::Value, &[]),
             op: CmpOp::Eq,
             right: Literal::String(raw.to_string()),
         };
-        assert!(full_value_eq.matches(key, &value_json, Some(raw), ts));
+        assert!(full_value_eq.matches(&view("t", key, &value_json, Some(raw), ts)));
 
         let full_value_neq = Expr::Cmp {
             left: path(RootPath::Value, &[]),
             op: CmpOp::Neq,
             right: Literal::String("other".to_string()),
         };
-        assert!(full_value_neq.matches(key, &value_json, Some(raw), ts));
+        assert!(full_value_neq.matches(&view("t", key, &value_json, Some(raw), ts)));
     }
 
     #[test]
@@ -315,35 +767,35 @@ mod tests {
             op: CmpOp::Contains,
             right: Literal::String("123".to_string()),
         };
-        assert!(key_contains.matches(key, &value_json, Some(raw), ts));
+        assert!(key_contains.matches(&view("t", key, &value_json, Some(raw), ts)));
 
         let value_contains = Expr::Cmp {
             left: path(RootPath::Value, &[]),
             op: CmpOp::Contains,
             right: Literal::String("error".to_string()),
         };
-        assert!(value_contains.matches(key, &value_json, Some(raw), ts));
+        assert!(value_contains.matches(&view("t", key, &value_json, Some(raw), ts)));
 
         let nested_contains = Expr::Cmp {
             left: path(RootPath::Value, &["payload", "msg"]),
             op: CmpOp::Contains,
             right: Literal::String("error".to_string()),
         };
-        assert!(nested_contains.matches(key, &value_json, Some(raw), ts));
+        assert!(nested_contains.matches(&view("t", key, &value_json, Some(raw), ts)));
 
         let contains_number = Expr::Cmp {
             left: path(RootPath::Value, &["payload", "code"]),
             op: CmpOp::Contains,
             right: Literal::Number(42.0),
         };
-        assert!(contains_number.matches(key, &value_json, Some(raw), ts));
+        assert!(contains_number.matches(&view("t", key, &value_json, Some(raw), ts)));
 
         let timestamp_contains = Expr::Cmp {
             left: path(RootPath::Timestamp, &[]),
             op: CmpOp::Contains,
             right: Literal::String("100".to_string()),
         };
-        assert!(timestamp_contains.matches(key, &value_json, Some(raw), ts));
+        assert!(timestamp_contains.matches(&view("t", key, &value_json, Some(raw), ts)));
 
         let bool_expr = Expr::And(
             Box::new(Expr::Or(
@@ -364,7 +816,418 @@ mod tests {
                 right: Literal::String("GET".to_string()),
             }),
         );
-        assert!(bool_expr.matches(key, &value_json, Some(raw), ts));
+        assert!(bool_expr.matches(&view("t", key, &value_json, Some(raw), ts)));
+    }
+
+    #[test]
+    fn not_inverts_the_comparison() {
+        let raw = r#"{"msg":"hello error world"}"#;
+        let value: Value = serde_json::from_str(raw).unwrap();
+        let not_contains = |needle: &str| {
+            Expr::Not(Box::new(Expr::Cmp {
+                left: path(RootPath::Value, &["msg"]),
+                op: CmpOp::Contains,
+                right: Literal::String(needle.to_string()),
+            }))
+        };
+        assert!(!not_contains("error").matches(&view("t", "k", &value, Some(raw), 0)));
+        assert!(not_contains("timeout").matches(&view("t", "k", &value, Some(raw), 0)));
+        // A held negation still points at the field it tested
+        assert_eq!(
+            not_contains("timeout").matched_paths(&view("t", "k", &value, Some(raw), 0)),
+            vec![path(RootPath::Value, &["msg"])]
+        );
+
+        // NOT NOT x is x; negating a group inverts the whole group
+        let not = |e: Expr| Expr::Not(Box::new(e));
+        assert!(not(not_contains("error")).matches(&view("t", "k", &value, Some(raw), 0)));
+        assert!(!not(not_contains("timeout")).matches(&view("t", "k", &value, Some(raw), 0)));
+        let either = Expr::Or(
+            Box::new(not_contains("error")),
+            Box::new(not_contains("hello")),
+        );
+        assert!(!either.matches(&view("t", "k", &value, Some(raw), 0)));
+        assert!(not(either).matches(&view("t", "k", &value, Some(raw), 0)));
+    }
+
+    #[test]
+    fn matches_regex_against_stringified_path() {
+        let raw = r#"{"msg":"err42","code":503,"note":"no errors here"}"#;
+        let value: Value = serde_json::from_str(raw).unwrap();
+        let regex = |field: &str, pattern: &str| Expr::Cmp {
+            left: path(RootPath::Value, &[field]),
+            op: CmpOp::Regex,
+            right: Literal::Pattern(Pattern::new(pattern).unwrap()),
+        };
+        let check = |e: Expr| e.matches(&view("t", "k", &value, Some(raw), 0));
+        assert!(check(regex("msg", "^err[0-9]+$")));
+        assert!(!check(regex("note", "^err[0-9]+$")));
+        // Unanchored, like CONTAINS; numbers are matched as text
+        assert!(check(regex("note", "err(or)?s")));
+        assert!(check(regex("code", "^5\\d\\d$")));
+        assert!(!check(regex("missing", ".")));
+        assert!(!check(regex("missing", "null")));
+    }
+
+    #[test]
+    fn like_matches_whole_text_with_wildcards() {
+        let raw = r#"{"id":"user-42","price":"9.99$","pct":"50%","empty":"","gone":null}"#;
+        let value: Value = serde_json::from_str(raw).unwrap();
+        let like = |field: &str, pattern: &str| Expr::Cmp {
+            left: path(RootPath::Value, &[field]),
+            op: CmpOp::Like,
+            right: Literal::Pattern(Pattern::like(pattern).unwrap()),
+        };
+        let check = |e: Expr| e.matches(&view("t", "k", &value, Some(raw), 0));
+        // Trailing %, and anchoring on both ends
+        assert!(check(like("id", "user-%")));
+        assert!(check(like("id", "%42")));
+        assert!(!check(like("id", "user")));
+        assert!(!check(like("id", "ser-%")));
+        // _ is exactly one character
+        assert!(check(like("id", "user-__")));
+        assert!(!check(like("id", "user-_")));
+        // Regex metacharacters are literal
+        assert!(check(like("price", "9.99$")));
+        assert!(!check(like("price", "9_99")));
+        assert!(!check(like("price", "9x99$")));
+        // Escaped wildcards
+        assert!(check(like("pct", "50\\%")));
+        assert!(!check(like("id", "user\\%")));
+        assert!(!check(like("pct", "5\\_%")));
+        // An empty pattern only matches empty text
+        assert!(check(like("empty", "")));
+        assert!(!check(like("id", "")));
+        assert!(check(like("empty", "%")));
+        // Missing and null never match, like MATCHES
+        assert!(!check(like("gone", "%")));
+        assert!(!check(like("missing", "%")));
+
+        // Literal runs come from the pattern as written, not the regex
+        let literals = |p: &str| Pattern::like(p).unwrap().like_literals();
+        assert_eq!(literals("%time_out%"), ["time", "out"]);
+        assert_eq!(literals("9.99$\\%x\\"), ["9.99$%x\\"]);
+        assert!(
+            Pattern::new("(?s)^a.b$")
+                .unwrap()
+                .like_literals()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn in_matches_any_listed_value() {
+        let raw = r#"{"code":201,"state":"ok","gone":null}"#;
+        let value: Value = serde_json::from_str(raw).unwrap();
+        let within = |root: RootPath, segs: &[&str], list: Vec<Literal>| Expr::In {
+            left: path(root, segs),
+            list,
+        };
+        let check = |e: &Expr| e.matches(&view("t", "k2", &value, Some(raw), 0));
+        let mixed = vec![
+            Literal::String("ok".to_string()),
+            Literal::Number(200.0),
+            Literal::Number(201.0),
+        ];
+        assert!(check(&within(RootPath::Value, &["code"], mixed.clone())));
+        assert!(check(&within(RootPath::Value, &["state"], mixed.clone())));
+        // No string/number coercion: "201" is not 201
+        assert!(!check(&within(
+            RootPath::Value,
+            &["code"],
+            vec![Literal::String("201".to_string())]
+        )));
+        assert!(!check(&within(RootPath::Value, &["missing"], mixed)));
+        assert!(check(&within(
+            RootPath::Value,
+            &["gone"],
+            vec![Literal::Null]
+        )));
+        let keys = within(
+            RootPath::Key,
+            &[],
+            vec![
+                Literal::String("k1".to_string()),
+                Literal::String("k2".to_string()),
+            ],
+        );
+        assert!(check(&keys));
+        assert!(!check(&Expr::Not(Box::new(keys.clone()))));
+        assert_eq!(
+            keys.matched_paths(&view("t", "k2", &value, Some(raw), 0)),
+            vec![path(RootPath::Key, &[])]
+        );
+    }
+
+    #[test]
+    fn pointer_paths_index_arrays() {
+        let raw = r#"{"payload":{"items":[{"sku":"A1"},{"sku":"B2"}],"a/b":{"c~d":1}}}"#;
+        let value: Value = serde_json::from_str(raw).unwrap();
+        let sku = |idx: &str, lit: &str| Expr::Cmp {
+            left: path(RootPath::Value, &["payload", "items", idx, "sku"]),
+            op: CmpOp::Eq,
+            right: Literal::String(lit.to_string()),
+        };
+        assert!(sku("1", "B2").matches(&view("t", "k", &value, Some(raw), 0)));
+        assert!(!sku("0", "B2").matches(&view("t", "k", &value, Some(raw), 0)));
+        assert!(!sku("5", "B2").matches(&view("t", "k", &value, Some(raw), 0)));
+        // Segments holding '/' or '~' are escaped, not split
+        let odd = path(RootPath::Value, &["payload", "a/b", "c~d"]);
+        assert_eq!(
+            odd.resolve(&view("t", "k", &value, Some(raw), 0)),
+            Value::from(1)
+        );
+    }
+
+    #[test]
+    fn arrow_paths_index_arrays() {
+        let raw = r#"{"arr":[10,20],"a":[{},{},{"b":"x"}],"obj":{"0":"key"}}"#;
+        let value: Value = serde_json::from_str(raw).unwrap();
+        let at = |segs: &[&str]| {
+            path(RootPath::Value, segs).resolve(&view("t", "k", &value, Some(raw), 0))
+        };
+        assert_eq!(at(&["arr", "0"]), Value::from(10));
+        assert_eq!(at(&["arr", "1"]), Value::from(20));
+        assert_eq!(at(&["a", "2", "b"]), Value::from("x"));
+        // Out of range, not an index, or indexing a non-array: null
+        assert_eq!(at(&["arr", "2"]), Value::Null);
+        assert_eq!(at(&["arr", "01"]), Value::Null);
+        assert_eq!(at(&["a", "0", "b"]), Value::Null);
+        assert_eq!(at(&["arr", "0", "x"]), Value::Null);
+        // On objects a digit segment is still a key
+        assert_eq!(at(&["obj", "0"]), Value::from("key"));
+        let second = Expr::Cmp {
+            left: path(RootPath::Value, &["arr", "1"]),
+            op: CmpOp::Gt,
+            right: Literal::Number(15.0),
+        };
+        assert!(second.matches(&view("t", "k", &value, Some(raw), 0)));
+    }
+
+    #[test]
+    fn exists_tells_null_from_absent() {
+        let raw = r#"{"a": null, "b": {"c": 0}}"#;
+        let value: Value = serde_json::from_str(raw).unwrap();
+        let exists = |segs: &[&str]| Expr::Exists(path(RootPath::Value, segs));
+        let check = |e: &Expr| e.matches(&view("t", "k", &value, Some(raw), 0));
+        assert!(check(&exists(&["a"])));
+        assert!(check(&exists(&["b", "c"])));
+        assert!(!check(&exists(&["missing"])));
+        assert!(!check(&exists(&["a", "deeper"])));
+        // A plain comparison can't make that distinction
+        let is_null = |field: &str| Expr::Cmp {
+            left: path(RootPath::Value, &[field]),
+            op: CmpOp::Eq,
+            right: Literal::Null,
+        };
+        assert!(check(&is_null("a")) && check(&is_null("missing")));
+        // A tombstone has no value; a non-JSON payload does
+        assert!(!exists(&[]).matches(&view("t", "k", &Value::Null, None, 0)));
+        assert!(exists(&[]).matches(&view("t", "k", &Value::Null, Some("plain"), 0)));
+    }
+
+    #[test]
+    fn matched_paths_follow_true_branches() {
+        let value: Value = serde_json::from_str(r#"{"a": 1, "b": "x", "c": true}"#).unwrap();
+        let eq = |field: &str, lit: Literal| Expr::Cmp {
+            left: path(RootPath::Value, &[field]),
+            op: CmpOp::Eq,
+            right: lit,
+        };
+        // (a = 1 AND b = 'x') OR c = false: only the AND side holds
+        let expr = Expr::Or(
+            Box::new(Expr::And(
+                Box::new(eq("a", Literal::Number(1.0))),
+                Box::new(eq("b", Literal::String("x".to_string()))),
+            )),
+            Box::new(eq("c", Literal::Bool(false))),
+        );
+        assert_eq!(
+            expr.matched_paths(&view("t", "k", &value, None, 0)),
+            vec![path(RootPath::Value, &["a"]), path(RootPath::Value, &["b"])]
+        );
+        // a = 1 OR c = true: both sides hold and both are reported
+        let either = Expr::Or(
+            Box::new(eq("a", Literal::Number(1.0))),
+            Box::new(eq("c", Literal::Bool(true))),
+        );
+        assert_eq!(
+            either.matched_paths(&view("t", "k", &value, None, 0)),
+            vec![path(RootPath::Value, &["a"]), path(RootPath::Value, &["c"])]
+        );
+        // A failed AND contributes nothing, even though one side held
+        let failed = Expr::And(
+            Box::new(eq("a", Literal::Number(1.0))),
+            Box::new(eq("c", Literal::Bool(false))),
+        );
+        assert!(
+            failed
+                .matched_paths(&view("t", "k", &value, None, 0))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn matches_topic_name() {
+        let topic_eq = Expr::Cmp {
+            left: path(RootPath::Topic, &[]),
+            op: CmpOp::Eq,
+            right: Literal::String("orders".to_string()),
+        };
+        assert!(topic_eq.matches(&view("orders", "k", &Value::Null, None, 0)));
+        assert!(!topic_eq.matches(&view("payments", "k", &Value::Null, None, 0)));
+    }
+
+    #[test]
+    fn range_operators_compare_numbers_timestamps_and_strings() {
+        let value: Value =
+            serde_json::from_str(r#"{"code": 404, "ratio": 0.25, "name": "beta", "gone": null}"#)
+                .unwrap();
+        let cmp = |root: RootPath, segs: &[&str], op: CmpOp, right: Literal| Expr::Cmp {
+            left: path(root, segs),
+            op,
+            right,
+        };
+        let holds = |e: Expr, ts: i64| e.matches(&view("t", "k", &value, None, ts));
+        // Integers
+        assert!(holds(
+            cmp(
+                RootPath::Value,
+                &["code"],
+                CmpOp::Ge,
+                Literal::Number(400.0)
+            ),
+            0
+        ));
+        assert!(holds(
+            cmp(
+                RootPath::Value,
+                &["code"],
+                CmpOp::Le,
+                Literal::Number(404.0)
+            ),
+            0
+        ));
+        assert!(!holds(
+            cmp(
+                RootPath::Value,
+                &["code"],
+                CmpOp::Lt,
+                Literal::Number(404.0)
+            ),
+            0
+        ));
+        // Floats
+        assert!(holds(
+            cmp(RootPath::Value, &["ratio"], CmpOp::Lt, Literal::Number(0.5)),
+            0
+        ));
+        assert!(!holds(
+            cmp(
+                RootPath::Value,
+                &["ratio"],
+                CmpOp::Gt,
+                Literal::Number(0.25)
+            ),
+            0
+        ));
+        // Timestamp root
+        let after = cmp(
+            RootPath::Timestamp,
+            &[],
+            CmpOp::Gt,
+            Literal::Number(1_000.0),
+        );
+        assert!(holds(after.clone(), 1_001));
+        assert!(!holds(after, 1_000));
+        // Strings fall back to lexicographic order
+        let name = |op| {
+            cmp(
+                RootPath::Value,
+                &["name"],
+                op,
+                Literal::String("alpha".to_string()),
+            )
+        };
+        assert!(holds(name(CmpOp::Gt), 0));
+        assert!(!holds(name(CmpOp::Le), 0));
+        // Null, absent and mismatched types never satisfy a range
+        for segs in [&["gone"][..], &["missing"][..]] {
+            assert!(!holds(
+                cmp(RootPath::Value, segs, CmpOp::Ge, Literal::Number(0.0)),
+                0
+            ));
+            assert!(!holds(
+                cmp(RootPath::Value, segs, CmpOp::Lt, Literal::Number(0.0)),
+                0
+            ));
+        }
+        assert!(!holds(
+            cmp(RootPath::Value, &["name"], CmpOp::Gt, Literal::Number(0.0)),
+            0
+        ));
+        assert!(!holds(
+            cmp(
+                RootPath::Value,
+                &["code"],
+                CmpOp::Gt,
+                Literal::String("1".to_string())
+            ),
+            0
+        ));
+    }
+
+    #[test]
+    fn matches_derived_field_count_and_size() {
+        let raw = r#"{"a": 1, "b": {"c": 2}, "d": [1, 2]}"#;
+        let value: Value = serde_json::from_str(raw).unwrap();
+        let cmp = |root: RootPath, op: CmpOp, n: f64| Expr::Cmp {
+            left: path(root, &[]),
+            op,
+            right: Literal::Number(n),
+        };
+        assert!(cmp(RootPath::ValueFields, CmpOp::Eq, 3.0).matches(&view(
+            "t",
+            "k",
+            &value,
+            Some(raw),
+            0
+        )));
+        assert!(!cmp(RootPath::ValueFields, CmpOp::Gt, 3.0).matches(&view(
+            "t",
+            "k",
+            &value,
+            Some(raw),
+            0
+        )));
+        // Size is the compact serialization, not the raw (spaced) text
+        let compact = r#"{"a":1,"b":{"c":2},"d":[1,2]}"#.len() as f64;
+        assert!(cmp(RootPath::ValueSize, CmpOp::Eq, compact).matches(&view(
+            "t",
+            "k",
+            &value,
+            Some(raw),
+            0
+        )));
+
+        // Non-objects have no field count; plain text sizes by its bytes
+        let text = "not json";
+        assert!(!cmp(RootPath::ValueFields, CmpOp::Ge, 0.0).matches(&view(
+            "t",
+            "k",
+            &Value::Null,
+            Some(text),
+            0
+        )));
+        assert!(cmp(RootPath::ValueSize, CmpOp::Eq, 8.0).matches(&view(
+            "t",
+            "k",
+            &Value::Null,
+            Some(text),
+            0
+        )));
+        assert_eq!(derived_column_text(SelectItem::ValueFields, Some(raw)), "3");
+        assert_eq!(derived_column_text(SelectItem::ValueSize, None), "");
     }
 
     #[test]
@@ -379,21 +1242,21 @@ mod tests {
             op: CmpOp::Contains,
             right: Literal::String("plain".to_string()),
         };
-        assert!(contains_plain.matches(key, &value_json, Some(raw_plain), ts));
+        assert!(contains_plain.matches(&view("t", key, &value_json, Some(raw_plain), ts)));
 
         let nested_contains = Expr::Cmp {
             left: path(RootPath::Value, &["foo"]),
             op: CmpOp::Contains,
             right: Literal::String("x".to_string()),
         };
-        assert!(!nested_contains.matches(key, &value_json, Some(raw_plain), ts));
+        assert!(!nested_contains.matches(&view("t", key, &value_json, Some(raw_plain), ts)));
 
         let full_value_eq = Expr::Cmp {
             left: path(RootPath::Value, &[]),
             op: CmpOp::Eq,
             right: Literal::String(raw_plain.to_string()),
         };
-        assert!(full_value_eq.matches(key, &value_json, Some(raw_plain), ts));
+        assert!(full_value_eq.matches(&view("t", key, &value_json, Some(raw_plain), ts)));
 
         let fallback_value = Expr::Cmp {
             left: path(RootPath::Value, &[]),
@@ -401,6 +1264,71 @@ mod tests {
             right: Literal::String("hello".to_string()),
         };
         let json_value = serde_json::json!({"msg":"hello"});
-        assert!(fallback_value.matches(key, &json_value, None, ts));
+        assert!(fallback_value.matches(&view("t", key, &json_value, None, ts)));
+    }
+
+    #[test]
+    fn matches_any_but_not_all_over_arrays() {
+        let value = serde_json::json!({
+            "items": [
+                {"status": "ok"},
+                {"status": "failed"}
+            ],
+            "tags": ["a", "b"]
+        });
+        let quantified = |quantifier, array: &[&str], sub: &[&str], right: &str| Expr::Quantified {
+            quantifier,
+            array: path(RootPath::Value, array),
+            predicate: Box::new(Expr::Cmp {
+                left: path(RootPath::Value, sub),
+                op: CmpOp::Eq,
+                right: Literal::String(right.to_string()),
+            }),
+        };
+
+        let any_failed = quantified(Quantifier::Any, &["items"], &["status"], "failed");
+        let all_failed = quantified(Quantifier::All, &["items"], &["status"], "failed");
+        assert!(any_failed.matches(&view("t", "k", &value, None, 0)));
+        assert!(!all_failed.matches(&view("t", "k", &value, None, 0)));
+
+        // Scalar elements compare directly
+        let any_tag = quantified(Quantifier::Any, &["tags"], &[], "b");
+        assert!(any_tag.matches(&view("t", "k", &value, None, 0)));
+
+        // Missing or non-array paths never match
+        let missing = quantified(Quantifier::All, &["nope"], &["status"], "ok");
+        assert!(!missing.matches(&view("t", "k", &value, None, 0)));
+    }
+
+    #[test]
+    fn header_paths_match_the_named_header() {
+        let headers = [
+            MessageHeader {
+                key: "trace-id".to_string(),
+                value: Some(b"abc".to_vec()),
+            },
+            MessageHeader {
+                key: "tombstone-reason".to_string(),
+                value: None,
+            },
+        ];
+        let msg = MessageView {
+            headers: &headers,
+            ..view("t", "k", &Value::Null, None, 0)
+        };
+        let header_eq = |name: &str, right: Literal| Expr::Cmp {
+            left: path(RootPath::Header, &[name]),
+            op: CmpOp::Eq,
+            right,
+        };
+
+        assert!(header_eq("trace-id", Literal::String("abc".to_string())).matches(&msg));
+        assert!(!header_eq("trace-id", Literal::String("xyz".to_string())).matches(&msg));
+        // Header names are case-sensitive, as Kafka stores them
+        assert!(!header_eq("Trace-Id", Literal::String("abc".to_string())).matches(&msg));
+        // A null-valued header is present but null; a missing one is absent
+        assert!(header_eq("tombstone-reason", Literal::Null).matches(&msg));
+        assert!(Expr::Exists(path(RootPath::Header, &["tombstone-reason"])).matches(&msg));
+        assert!(!Expr::Exists(path(RootPath::Header, &["missing"])).matches(&msg));
     }
 }
@@ -5,7 +5,7 @@ pub use ast::*;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Command {
-    Select(SelectQuery),
+    Select(Box<SelectQuery>),
     ListTopics,
 }
 
@@ -1,15 +1,22 @@
 use super::{Command, ast::*};
 
+/// `pos` is the byte offset into the parsed text where the problem starts.
 #[derive(Debug)]
 pub enum ParseError {
-    UnexpectedEof,
-    UnexpectedToken(String),
-    ExpectedKeyword(String),
-    ExpectedIdentifier,
-    ExpectedNumber,
-    ExpectedLiteral,
-    ExpectedPath,
-    InvalidOrderByField(String),
+    UnexpectedEof { pos: usize },
+    UnexpectedToken { near: String, pos: usize },
+    ExpectedKeyword { keyword: String, pos: usize },
+    ExpectedIdentifier { pos: usize },
+    ExpectedNumber { pos: usize },
+    ExpectedLiteral { pos: usize },
+    ExpectedPath { pos: usize },
+    InvalidOrderByField { near: String, pos: usize },
+    InvalidGroupByField { near: String, pos: usize },
+    InvalidRegex(String),
+    EmptyInList,
+    PathOrderNotAlone,
+    CountNotAlone,
+    CountWithLimit,
 }
 
 type PResult<T> = Result<T, ParseError>;
@@ -17,18 +24,54 @@ type PResult<T> = Result<T, ParseError>;
 pub fn parse_command(input: &str) -> Result<Command, ParseError> {
     let trimmed = strip_command_semicolon(input.trim());
     if trimmed.is_empty() {
-        return Err(ParseError::UnexpectedToken(String::new()));
+        return Err(ParseError::UnexpectedToken {
+            near: String::new(),
+            pos: 0,
+        });
     }
     if is_list_topics_command(trimmed) {
         return Ok(Command::ListTopics);
     }
-    parse_query(trimmed).map(Command::Select)
+    let lead = input.len() - input.trim_start().len();
+    parse_query(trimmed)
+        .map(|q| Command::Select(Box::new(q)))
+        .map_err(|e| e.offset_by(lead))
+}
+
+/// Split an RFC 6901 JSON Pointer into unescaped tokens (`~1` is `/`, `~0` is
+/// `~`). `None` if it doesn't start with `/` or has a bad escape; the empty
+/// pointer is the whole value.
+fn pointer_segments(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    pointer
+        .strip_prefix('/')?
+        .split('/')
+        .map(|token| {
+            let mut out = String::new();
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                if c != '~' {
+                    out.push(c);
+                    continue;
+                }
+                match chars.next()? {
+                    '0' => out.push('~'),
+                    '1' => out.push('/'),
+                    _ => return None,
+                }
+            }
+            Some(out)
+        })
+        .collect()
 }
 
 pub fn parse_query(input: &str) -> PResult<SelectQuery> {
     let mut p = Parser::new(input);
     p.consume_keyword("SELECT")?;
-    let select = p.parse_select_list()?;
+    let mut aliases = Vec::new();
+    let select = p.parse_select_list(&mut aliases)?;
     p.consume_keyword("FROM")?;
     let from = p.parse_topic()?;
     let r#where = if p.try_consume_keyword("WHERE") {
@@ -36,6 +79,12 @@ pub fn parse_query(input: &str) -> PResult<SelectQuery> {
     } else {
         None
     };
+    let group_by = if p.try_consume_keyword("GROUP") {
+        p.consume_keyword("BY")?;
+        Some(p.parse_group_by()?)
+    } else {
+        None
+    };
     let order = if p.try_consume_keyword("ORDER") {
         p.consume_keyword("BY")?;
         Some(p.parse_order_by()?)
@@ -47,30 +96,127 @@ pub fn parse_query(input: &str) -> PResult<SelectQuery> {
     } else {
         None
     };
+    // Only here, at the tail, is OFFSET a clause rather than the column
+    let skip = if p.try_consume_keyword("OFFSET") {
+        Some(p.parse_usize()?)
+    } else {
+        None
+    };
     p.skip_ws();
     if !p.is_eof() {
-        return Err(ParseError::UnexpectedToken(p.remaining().to_string()));
+        return Err(p.unexpected());
+    }
+    if select == [SelectItem::Count] && (limit.is_some() || skip.is_some()) {
+        return Err(ParseError::CountWithLimit);
     }
     Ok(SelectQuery {
         select,
+        aliases,
         from,
         r#where,
+        group_by,
         order,
         limit,
+        skip,
     })
 }
 
+impl ParseError {
+    /// Byte offset of the error in the parsed text, when it has one.
+    pub fn pos(&self) -> Option<usize> {
+        match self {
+            ParseError::UnexpectedEof { pos }
+            | ParseError::UnexpectedToken { pos, .. }
+            | ParseError::ExpectedKeyword { pos, .. }
+            | ParseError::ExpectedIdentifier { pos }
+            | ParseError::ExpectedNumber { pos }
+            | ParseError::ExpectedLiteral { pos }
+            | ParseError::ExpectedPath { pos }
+            | ParseError::InvalidOrderByField { pos, .. }
+            | ParseError::InvalidGroupByField { pos, .. } => Some(*pos),
+            ParseError::InvalidRegex(_)
+            | ParseError::EmptyInList
+            | ParseError::PathOrderNotAlone
+            | ParseError::CountNotAlone
+            | ParseError::CountWithLimit => None,
+        }
+    }
+
+    /// The message followed, when the position is known, by the offending
+    /// line of `input` with a `^` under the error.
+    pub fn annotated(&self, input: &str) -> String {
+        match self.pos() {
+            Some(pos) => format!("{}\n{}", self, caret_snippet(input, pos)),
+            None => self.to_string(),
+        }
+    }
+
+    fn offset_by(mut self, n: usize) -> Self {
+        match &mut self {
+            ParseError::UnexpectedEof { pos }
+            | ParseError::UnexpectedToken { pos, .. }
+            | ParseError::ExpectedKeyword { pos, .. }
+            | ParseError::ExpectedIdentifier { pos }
+            | ParseError::ExpectedNumber { pos }
+            | ParseError::ExpectedLiteral { pos }
+            | ParseError::ExpectedPath { pos }
+            | ParseError::InvalidOrderByField { pos, .. }
+            | ParseError::InvalidGroupByField { pos, .. } => *pos += n,
+            ParseError::InvalidRegex(_)
+            | ParseError::EmptyInList
+            | ParseError::PathOrderNotAlone
+            | ParseError::CountNotAlone
+            | ParseError::CountWithLimit => {}
+        }
+        self
+    }
+}
+
+/// The line of `input` holding byte offset `pos`, then a `^` under that
+/// column (counted in characters) on the next line.
+pub fn caret_snippet(input: &str, pos: usize) -> String {
+    let mut pos = pos.min(input.len());
+    while !input.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    let start = input[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let end = input[pos..].find('\n').map_or(input.len(), |i| pos + i);
+    let column = input[start..pos].chars().count();
+    format!("{}\n{}^", &input[start..end], " ".repeat(column))
+}
+
 impl std::fmt::Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ParseError::UnexpectedEof => write!(f, "unexpected end of input"),
-            ParseError::UnexpectedToken(s) => write!(f, "unexpected token near: {}", s),
-            ParseError::ExpectedKeyword(k) => write!(f, "expected keyword: {}", k),
-            ParseError::ExpectedIdentifier => write!(f, "expected identifier"),
-            ParseError::ExpectedNumber => write!(f, "expected number"),
-            ParseError::ExpectedLiteral => write!(f, "expected literal"),
-            ParseError::ExpectedPath => write!(f, "expected path (key|value|timestamp)"),
-            ParseError::InvalidOrderByField(s) => write!(f, "invalid ORDER BY field near: {}", s),
+            ParseError::UnexpectedEof { .. } => write!(f, "unexpected end of input"),
+            ParseError::UnexpectedToken { near, .. } => {
+                write!(f, "unexpected token near: {}", near)
+            }
+            ParseError::ExpectedKeyword { keyword, .. } => {
+                write!(f, "expected keyword: {}", keyword)
+            }
+            ParseError::ExpectedIdentifier { .. } => write!(f, "expected identifier"),
+            ParseError::ExpectedNumber { .. } => write!(f, "expected number"),
+            ParseError::ExpectedLiteral { .. } => write!(f, "expected literal"),
+            ParseError::ExpectedPath { .. } => write!(f, "expected path (key|value|timestamp)"),
+            ParseError::InvalidOrderByField { near, .. } => {
+                write!(f, "invalid ORDER BY field near: {}", near)
+            }
+            ParseError::InvalidGroupByField { near, .. } => {
+                write!(f, "invalid GROUP BY field near: {}", near)
+            }
+            ParseError::InvalidRegex(e) => write!(f, "invalid MATCHES pattern: {}", e),
+            ParseError::EmptyInList => write!(f, "IN needs at least one value"),
+            ParseError::PathOrderNotAlone => {
+                write!(f, "ORDER BY <path> can't be combined with other sort keys")
+            }
+            ParseError::CountNotAlone => write!(f, "COUNT(*) can't be combined with other columns"),
+            ParseError::CountWithLimit => {
+                write!(
+                    f,
+                    "COUNT(*) counts every match, so it can't take LIMIT or OFFSET"
+                )
+            }
         }
     }
 }
@@ -95,6 +241,19 @@ impl<'a> Parser<'a> {
         &self.s[self.pos..]
     }
 
+    /// Where an error at the current position is reported: past any blanks.
+    fn error_pos(&self) -> usize {
+        let rest = self.remaining();
+        self.pos + rest.len() - rest.trim_start().len()
+    }
+
+    fn unexpected(&self) -> ParseError {
+        ParseError::UnexpectedToken {
+            near: self.remaining().to_string(),
+            pos: self.error_pos(),
+        }
+    }
+
     fn peek_char(&self) -> Option<char> {
         self.s[self.pos..].chars().next()
     }
@@ -121,9 +280,13 @@ impl<'a> Parser<'a> {
     fn consume_keyword(&mut self, kw: &str) -> PResult<()> {
         self.skip_ws();
         let start = self.pos;
+        let expected = || ParseError::ExpectedKeyword {
+            keyword: kw.to_string(),
+            pos: start,
+        };
         let n = kw.len();
         if self.pos + n > self.s.len() {
-            return Err(ParseError::ExpectedKeyword(kw.to_string()));
+            return Err(expected());
         }
         let slice = &self.s[self.pos..self.pos + n];
         if slice.eq_ignore_ascii_case(kw) {
@@ -131,13 +294,14 @@ impl<'a> Parser<'a> {
             // next must be boundary
             if let Some(c) = self.peek_char() {
                 if c.is_alphanumeric() || c == '_' {
-                    return Err(ParseError::ExpectedKeyword(kw.to_string()));
+                    self.pos = start;
+                    return Err(expected());
                 }
             }
             Ok(())
         } else {
             self.pos = start;
-            Err(ParseError::ExpectedKeyword(kw.to_string()))
+            Err(expected())
         }
     }
 
@@ -166,12 +330,34 @@ impl<'a> Parser<'a> {
             }
         }
         if out.is_empty() {
-            return Err(ParseError::ExpectedIdentifier);
+            return Err(ParseError::ExpectedIdentifier {
+                pos: self.error_pos(),
+            });
         }
         self.pos += consumed;
         Ok(out)
     }
 
+    /// A header name: quoted, or bare with `-` and `.` allowed (`trace-id`).
+    fn parse_header_name(&mut self) -> PResult<String> {
+        self.skip_ws();
+        if self.peek_char() == Some('\'') {
+            return self.parse_string_lit();
+        }
+        let rest = self.remaining();
+        let end = rest
+            .find(|c: char| !(c.is_alphanumeric() || "_-.".contains(c)))
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return Err(ParseError::ExpectedIdentifier {
+                pos: self.error_pos(),
+            });
+        }
+        let name = rest[..end].to_string();
+        self.pos += end;
+        Ok(name)
+    }
+
     fn parse_topic(&mut self) -> PResult<String> {
         // Accept anything non-whitespace until next keyword or end
         self.skip_ws();
@@ -187,28 +373,53 @@ impl<'a> Parser<'a> {
             consumed += ch.len_utf8();
         }
         if out.is_empty() {
-            return Err(ParseError::ExpectedIdentifier);
+            return Err(ParseError::ExpectedIdentifier {
+                pos: self.error_pos(),
+            });
         }
         self.pos += consumed;
         Ok(out)
     }
 
-    fn parse_select_list(&mut self) -> PResult<Vec<SelectItem>> {
+    fn parse_select_list(
+        &mut self,
+        aliases: &mut Vec<(SelectItem, String)>,
+    ) -> PResult<Vec<SelectItem>> {
         let mut items = Vec::new();
         loop {
             self.skip_ws();
-            if self.try_consume_word_case("partition") {
-                items.push(SelectItem::Partition);
+            let item = if self.try_consume_word_case("cluster") {
+                SelectItem::Cluster
+            } else if self.try_consume_word_case("partition") {
+                SelectItem::Partition
             } else if self.try_consume_word_case("offset") {
-                items.push(SelectItem::Offset);
+                SelectItem::Offset
             } else if self.try_consume_word_case("timestamp") {
-                items.push(SelectItem::Timestamp);
+                SelectItem::Timestamp
             } else if self.try_consume_word_case("key") {
-                items.push(SelectItem::Key);
+                SelectItem::Key
+            } else if self.try_consume_word_case("value_size") {
+                SelectItem::ValueSize
+            } else if self.try_consume_word_case("value_fields") {
+                SelectItem::ValueFields
             } else if self.try_consume_word_case("value") {
-                items.push(SelectItem::Value);
+                SelectItem::Value
+            } else if self.try_consume_word_case("headers") {
+                SelectItem::Headers
+            } else if self.try_consume_word_case("count") {
+                if !(self.try_consume_char('(')
+                    && self.try_consume_char('*')
+                    && self.try_consume_char(')'))
+                {
+                    return Err(self.unexpected());
+                }
+                SelectItem::Count
             } else {
-                return Err(ParseError::UnexpectedToken(self.remaining().to_string()));
+                return Err(self.unexpected());
+            };
+            items.push(item);
+            if self.try_consume_keyword("AS") {
+                aliases.push((item, self.parse_identifier()?));
             }
 
             self.skip_ws();
@@ -217,6 +428,10 @@ impl<'a> Parser<'a> {
             }
             break;
         }
+        // The count is a single aggregate row; there is no per-message column to pair it with
+        if items.len() > 1 && items.contains(&SelectItem::Count) {
+            return Err(ParseError::CountNotAlone);
+        }
         Ok(items)
     }
 
@@ -281,12 +496,17 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
+    /// A comparison, a parenthesized expression, or `NOT` before either; `NOT`
+    /// binds tighter than AND/OR, so `NOT a AND b` is `(NOT a) AND b`.
     fn parse_primary(&mut self) -> PResult<Expr> {
         self.skip_ws();
+        if self.try_consume_keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.parse_primary()?)));
+        }
         if self.try_consume_char('(') {
             let expr = self.parse_or_expr()?;
             if !self.try_consume_char(')') {
-                return Err(ParseError::UnexpectedToken(self.remaining().to_string()));
+                return Err(self.unexpected());
             }
             Ok(expr)
         } else {
@@ -295,10 +515,162 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_comparison(&mut self) -> PResult<Expr> {
+        self.skip_ws();
+        let save = self.pos;
+        let quantifier = if self.try_consume_word_case("any") {
+            Some(Quantifier::Any)
+        } else if self.try_consume_word_case("all") {
+            Some(Quantifier::All)
+        } else {
+            None
+        };
+        if let Some(quantifier) = quantifier {
+            if self.try_consume_char('(') {
+                return self.parse_quantified(quantifier);
+            }
+            self.pos = save;
+        }
+        if self.try_consume_word_case("exists") {
+            if self.try_consume_char('(') {
+                let path = self.parse_json_path()?;
+                if !self.try_consume_char(')') {
+                    return Err(self.unexpected());
+                }
+                return Ok(Expr::Exists(path));
+            }
+            self.pos = save;
+        }
+        if self.parse_json_path().is_err() {
+            self.pos = save;
+            return self.parse_bounded_comparison();
+        }
+        self.pos = save;
         let left = self.parse_json_path()?;
+        self.parse_cmp_rest(left)
+    }
+
+    /// `<op> <literal>` after a path. `NOT CONTAINS` / `NOT MATCHES` / `NOT LIKE`
+    /// parse as the negation of the plain comparison; `NOT` before any other
+    /// operator is an error. `MATCHES` and `LIKE` take a string literal, compiled here.
+    fn parse_cmp_rest(&mut self, left: JsonPath) -> PResult<Expr> {
+        let negated = self.try_consume_keyword("NOT");
+        if self.try_consume_keyword("IN") {
+            let within = Expr::In {
+                left,
+                list: self.parse_in_list()?,
+            };
+            return Ok(if negated {
+                Expr::Not(Box::new(within))
+            } else {
+                within
+            });
+        }
+        let op_pos = self.pos;
         let op = self.parse_cmp_op()?;
-        let right = self.parse_literal()?;
-        Ok(Expr::Cmp { left, op, right })
+        if negated && !matches!(op, CmpOp::Contains | CmpOp::Regex | CmpOp::Like) {
+            self.pos = op_pos;
+            return Err(self.unexpected());
+        }
+        let literal_pos = self.error_pos();
+        let right = match self.parse_literal()? {
+            Literal::String(source) if op == CmpOp::Regex => Pattern::new(&source)
+                .map(Literal::Pattern)
+                .map_err(|e| ParseError::InvalidRegex(e.to_string()))?,
+            Literal::String(source) if op == CmpOp::Like => Pattern::like(&source)
+                .map(Literal::Pattern)
+                .map_err(|e| ParseError::InvalidRegex(e.to_string()))?,
+            _ if matches!(op, CmpOp::Regex | CmpOp::Like) => {
+                return Err(ParseError::ExpectedLiteral { pos: literal_pos });
+            }
+            lit => lit,
+        };
+        let cmp = Expr::Cmp { left, op, right };
+        Ok(if negated {
+            Expr::Not(Box::new(cmp))
+        } else {
+            cmp
+        })
+    }
+
+    /// `(<literal>, ...)` after `IN`; at least one item.
+    fn parse_in_list(&mut self) -> PResult<Vec<Literal>> {
+        if !self.try_consume_char('(') {
+            return Err(self.unexpected());
+        }
+        if self.try_consume_char(')') {
+            return Err(ParseError::EmptyInList);
+        }
+        let mut list = vec![self.parse_literal()?];
+        while self.try_consume_char(',') {
+            list.push(self.parse_literal()?);
+        }
+        if !self.try_consume_char(')') {
+            return Err(self.unexpected());
+        }
+        Ok(list)
+    }
+
+    /// `ANY(<array path>)[->sub->path] <op> <literal>`; the sub-path and
+    /// comparison become a predicate over each array element.
+    fn parse_quantified(&mut self, quantifier: Quantifier) -> PResult<Expr> {
+        let array = self.parse_json_path()?;
+        if !self.try_consume_char(')') {
+            return Err(self.unexpected());
+        }
+        let mut segments = Vec::new();
+        while self.try_consume_symbol_arrow() {
+            segments.push(self.parse_identifier()?);
+        }
+        let predicate = self.parse_cmp_rest(JsonPath {
+            root: RootPath::Value,
+            segments,
+        })?;
+        Ok(Expr::Quantified {
+            quantifier,
+            array,
+            predicate: Box::new(predicate),
+        })
+    }
+
+    /// Literal-first forms: `100 < value->code` and the chained range
+    /// `100 < value->code < 500`, which desugars to
+    /// `value->code > 100 AND value->code < 500`.
+    fn parse_bounded_comparison(&mut self) -> PResult<Expr> {
+        let lower = self.parse_literal()?;
+        let lower_op = self.parse_range_op()?;
+        let path = self.parse_json_path()?;
+        let first = Expr::Cmp {
+            left: path.clone(),
+            op: lower_op.flipped(),
+            right: lower,
+        };
+        self.skip_ws();
+        let save = self.pos;
+        let upper_op = match self.parse_range_op() {
+            Ok(op) => op,
+            Err(_) => {
+                self.pos = save;
+                return Ok(first);
+            }
+        };
+        let ascending = |op: CmpOp| matches!(op, CmpOp::Lt | CmpOp::Le);
+        if ascending(lower_op) != ascending(upper_op) {
+            return Err(self.unexpected());
+        }
+        let upper = self.parse_literal()?;
+        let second = Expr::Cmp {
+            left: path,
+            op: upper_op,
+            right: upper,
+        };
+        Ok(Expr::And(Box::new(first), Box::new(second)))
+    }
+
+    fn parse_range_op(&mut self) -> PResult<CmpOp> {
+        match self.parse_cmp_op()? {
+            op @ (CmpOp::Lt | CmpOp::Gt | CmpOp::Le | CmpOp::Ge) => Ok(op),
+            _ => Err(self.unexpected()),
+        }
     }
 
     fn parse_cmp_op(&mut self) -> PResult<CmpOp> {
@@ -306,6 +678,12 @@ impl<'a> Parser<'a> {
         if self.try_consume_keyword("CONTAINS") {
             return Ok(CmpOp::Contains);
         }
+        if self.try_consume_keyword("MATCHES") {
+            return Ok(CmpOp::Regex);
+        }
+        if self.try_consume_keyword("LIKE") {
+            return Ok(CmpOp::Like);
+        }
         let rest = self.remaining();
         if rest.starts_with("!=") {
             self.pos += 2;
@@ -315,15 +693,60 @@ impl<'a> Parser<'a> {
             self.pos += 2;
             return Ok(CmpOp::Neq);
         }
+        if rest.starts_with("<=") {
+            self.pos += 2;
+            return Ok(CmpOp::Le);
+        }
+        if rest.starts_with(">=") {
+            self.pos += 2;
+            return Ok(CmpOp::Ge);
+        }
+        if rest.starts_with('<') {
+            self.pos += 1;
+            return Ok(CmpOp::Lt);
+        }
+        if rest.starts_with('>') {
+            self.pos += 1;
+            return Ok(CmpOp::Gt);
+        }
         if rest.starts_with("=") {
             self.pos += 1;
             return Ok(CmpOp::Eq);
         }
-        Err(ParseError::UnexpectedToken(self.remaining().to_string()))
+        Err(self.unexpected())
     }
 
     fn parse_json_path(&mut self) -> PResult<JsonPath> {
         self.skip_ws();
+        // Derived roots are scalars: no `->` sub-path
+        if self.try_consume_word_case("value_size") {
+            return Ok(JsonPath {
+                root: RootPath::ValueSize,
+                segments: Vec::new(),
+            });
+        }
+        if self.try_consume_word_case("value_fields") {
+            return Ok(JsonPath {
+                root: RootPath::ValueFields,
+                segments: Vec::new(),
+            });
+        }
+        if self.try_consume_word_case("topic") {
+            return Ok(JsonPath {
+                root: RootPath::Topic,
+                segments: Vec::new(),
+            });
+        }
+        // header->name: exactly one segment naming the header
+        if self.try_consume_word_case("header") {
+            if !self.try_consume_symbol_arrow() {
+                return Err(self.unexpected());
+            }
+            return Ok(JsonPath {
+                root: RootPath::Header,
+                segments: vec![self.parse_header_name()?],
+            });
+        }
         let root = if self.try_consume_word_case("value") {
             RootPath::Value
         } else if self.try_consume_word_case("key") {
@@ -331,9 +754,34 @@ impl<'a> Parser<'a> {
         } else if self.try_consume_word_case("timestamp") {
             RootPath::Timestamp
         } else {
-            return Err(ParseError::ExpectedPath);
+            return Err(ParseError::ExpectedPath {
+                pos: self.error_pos(),
+            });
         };
 
+        // JSON Pointer form: value#/payload/items/0 (or quoted, value#'/a b')
+        if root == RootPath::Value && self.peek_char() == Some('#') {
+            let hash = self.pos;
+            self.bump();
+            let pointer = if self.peek_char() == Some('\'') {
+                self.parse_string_lit()?
+            } else {
+                let rest = self.remaining();
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || "()=!<>,;'".contains(c))
+                    .unwrap_or(rest.len());
+                let pointer = rest[..end].to_string();
+                self.pos += end;
+                pointer
+            };
+            let segments =
+                pointer_segments(&pointer).ok_or_else(|| ParseError::UnexpectedToken {
+                    near: format!("#{}", pointer),
+                    pos: hash,
+                })?;
+            return Ok(JsonPath { root, segments });
+        }
+
         let mut segments = Vec::new();
         loop {
             self.skip_ws();
@@ -381,14 +829,18 @@ impl<'a> Parser<'a> {
         if let Ok(n) = self.parse_number_opt() {
             return Ok(Literal::Number(n));
         }
-        Err(ParseError::ExpectedLiteral)
+        Err(ParseError::ExpectedLiteral {
+            pos: self.error_pos(),
+        })
     }
 
     fn parse_string_lit(&mut self) -> PResult<String> {
         // Simple single-quoted string, supports escaping of \' and \\.
         self.skip_ws();
+        // An unterminated literal is reported at its opening quote
+        let start = self.pos;
         if self.bump() != Some('\'') {
-            return Err(ParseError::ExpectedLiteral);
+            return Err(ParseError::ExpectedLiteral { pos: start });
         }
         let mut out = String::new();
         while let Some(ch) = self.bump() {
@@ -404,14 +856,14 @@ impl<'a> Parser<'a> {
                             }
                         }
                     } else {
-                        return Err(ParseError::UnexpectedEof);
+                        return Err(ParseError::UnexpectedEof { pos: start });
                     }
                 }
                 '\'' => return Ok(out),
                 c => out.push(c),
             }
         }
-        Err(ParseError::UnexpectedEof)
+        Err(ParseError::UnexpectedEof { pos: start })
     }
 
     fn parse_number_opt(&mut self) -> Result<f64, ()> {
@@ -463,6 +915,7 @@ impl<'a> Parser<'a> {
 
     fn parse_usize(&mut self) -> PResult<usize> {
         self.skip_ws();
+        let start = self.pos;
         let mut it = self.s[self.pos..].chars().peekable();
         let mut buf = String::new();
         let mut consumed = 0;
@@ -476,21 +929,70 @@ impl<'a> Parser<'a> {
             }
         }
         if buf.is_empty() {
-            return Err(ParseError::ExpectedNumber);
+            return Err(ParseError::ExpectedNumber { pos: start });
         }
         self.pos += consumed;
-        buf.parse::<usize>().map_err(|_| ParseError::ExpectedNumber)
+        buf.parse::<usize>()
+            .map_err(|_| ParseError::ExpectedNumber { pos: start })
+    }
+
+    fn parse_group_by(&mut self) -> PResult<GroupBy> {
+        self.skip_ws();
+        // Only key supported for now
+        if self.try_consume_word_case("key") {
+            Ok(GroupBy::Key)
+        } else {
+            Err(ParseError::InvalidGroupByField {
+                near: self.remaining().to_string(),
+                pos: self.pos,
+            })
+        }
+    }
+
+    /// Comma-separated sort keys; later keys break ties in earlier ones.
+    fn parse_order_by(&mut self) -> PResult<Vec<OrderSpec>> {
+        let mut keys = vec![self.parse_order_key()?];
+        while self.try_consume_char(',') {
+            keys.push(self.parse_order_key()?);
+        }
+        // Path ordering is a separate buffered stage with a single key
+        if keys.len() > 1 && keys.iter().any(|k| matches!(k.field, OrderField::Path(_))) {
+            return Err(ParseError::PathOrderNotAlone);
+        }
+        Ok(keys)
     }
 
-    fn parse_order_by(&mut self) -> PResult<OrderSpec> {
+    fn parse_order_key(&mut self) -> PResult<OrderSpec> {
         self.skip_ws();
-        // Only timestamp supported for now
-        if !self.try_consume_word_case("timestamp") {
-            // allow value->timestamp? but keep strict for now
-            let mut preview = String::new();
-            preview.push_str(self.remaining());
-            return Err(ParseError::InvalidOrderByField(preview));
+        let start = self.pos;
+        // `timestamp`, `offset`, `partition`, `key` or a JSON path such as `value->latency`
+        if self.try_consume_word_case("offset") {
+            return self.parse_order_dir(OrderField::Offset);
+        }
+        if self.try_consume_word_case("partition") {
+            return self.parse_order_dir(OrderField::Partition);
         }
+        let field = match self.parse_json_path() {
+            Ok(JsonPath {
+                root: RootPath::Timestamp,
+                segments,
+            }) if segments.is_empty() => OrderField::Timestamp,
+            Ok(JsonPath {
+                root: RootPath::Key,
+                segments,
+            }) if segments.is_empty() => OrderField::Key,
+            Ok(path) => OrderField::Path(path),
+            Err(_) => {
+                return Err(ParseError::InvalidOrderByField {
+                    near: self.remaining().to_string(),
+                    pos: start,
+                });
+            }
+        };
+        self.parse_order_dir(field)
+    }
+
+    fn parse_order_dir(&mut self, field: OrderField) -> PResult<OrderSpec> {
         let dir = if self.try_consume_keyword("ASC") {
             OrderDir::Asc
         } else if self.try_consume_keyword("DESC") {
@@ -498,10 +1000,7 @@ impl<'a> Parser<'a> {
         } else {
             OrderDir::Asc
         };
-        Ok(OrderSpec {
-            field: OrderField::Timestamp,
-            dir,
-        })
+        Ok(OrderSpec { field, dir })
     }
 }
 
@@ -554,11 +1053,11 @@ mod tests {
             _ => panic!("expected where comparison"),
         }
         assert!(matches!(
-            ast.order,
-            Some(OrderSpec {
+            ast.order.as_deref(),
+            Some([OrderSpec {
                 field: OrderField::Timestamp,
                 dir: OrderDir::Asc
-            })
+            }])
         ));
         assert_eq!(ast.limit, Some(10));
     }
@@ -578,6 +1077,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parses_column_aliases() {
+        let ast =
+            parse_query("SELECT partition AS p, offset, key as k FROM foo").expect("parse ok");
+        assert_eq!(
+            ast.select,
+            vec![SelectItem::Partition, SelectItem::Offset, SelectItem::Key]
+        );
+        assert_eq!(
+            ast.aliases,
+            vec![
+                (SelectItem::Partition, "p".to_string()),
+                (SelectItem::Key, "k".to_string()),
+            ]
+        );
+        assert_eq!(column_alias(&ast.aliases, SelectItem::Key), Some("k"));
+        assert_eq!(column_alias(&ast.aliases, SelectItem::Offset), None);
+
+        assert!(parse_query("SELECT key AS FROM foo").is_err());
+        assert!(matches!(
+            parse_query("SELECT key AS , value FROM foo"),
+            Err(ParseError::ExpectedIdentifier { pos: 14 })
+        ));
+    }
+
     fn where_expr(query: &str) -> Expr {
         parse_query(query)
             .expect("parse ok")
@@ -592,6 +1116,294 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parses_order_by_json_path() {
+        let ast = parse_query("SELECT key FROM t ORDER BY value->latency DESC LIMIT 10").unwrap();
+        assert_eq!(
+            ast.order,
+            Some(vec![OrderSpec {
+                field: OrderField::Path(path(RootPath::Value, &["latency"])),
+                dir: OrderDir::Desc,
+            }])
+        );
+        assert!(ast.needs_value());
+        let ts = parse_query("SELECT key FROM t ORDER BY timestamp").unwrap();
+        assert_eq!(ts.primary_order().unwrap().field, OrderField::Timestamp);
+        assert!(matches!(
+            parse_query("SELECT key FROM t ORDER BY 42"),
+            Err(ParseError::InvalidOrderByField { .. })
+        ));
+    }
+
+    #[test]
+    fn parses_offset_clause() {
+        let q = parse_query("SELECT key FROM t LIMIT 10 OFFSET 5").unwrap();
+        assert_eq!((q.limit, q.skip), (Some(10), Some(5)));
+        let q = parse_query("SELECT offset, key FROM t OFFSET 100").unwrap();
+        assert_eq!((q.limit, q.skip), (None, Some(100)));
+        assert_eq!(q.select, vec![SelectItem::Offset, SelectItem::Key]);
+        // The column and the clause side by side
+        let q = parse_query("SELECT key FROM t ORDER BY offset DESC OFFSET 3").unwrap();
+        assert_eq!(q.primary_order().unwrap().field, OrderField::Offset);
+        assert_eq!(q.skip, Some(3));
+        assert_eq!(parse_query("SELECT key FROM t").unwrap().skip, None);
+
+        assert!(parse_query("SELECT key FROM t OFFSET").is_err());
+        assert!(parse_query("SELECT key FROM t OFFSET 5 LIMIT 10").is_err());
+    }
+
+    #[test]
+    fn parse_errors_report_their_offset() {
+        let input = "SELECT key, value FORM orders";
+        let err = parse_query(input).unwrap_err();
+        assert!(
+            matches!(&err, ParseError::ExpectedKeyword { keyword, pos: 18 } if keyword == "FROM"),
+            "{err:?}"
+        );
+        assert_eq!(
+            err.annotated(input),
+            "expected keyword: FROM\nSELECT key, value FORM orders\n                  ^"
+        );
+
+        // An unterminated string points at its opening quote, here on line 2
+        let input = "SELECT key FROM t\nWHERE value->name = 'ab";
+        let err = parse_query(input).unwrap_err();
+        assert_eq!(err.pos(), Some(38), "{err:?}");
+        assert!(matches!(err, ParseError::UnexpectedEof { .. }));
+        assert_eq!(
+            caret_snippet(input, 38),
+            "WHERE value->name = 'ab\n                    ^"
+        );
+
+        // parse_command trims, but offsets stay relative to what was typed
+        let err = parse_command("  SELECT key FORM t").unwrap_err();
+        assert_eq!(err.pos(), Some(13));
+    }
+
+    #[test]
+    fn parses_count_star() {
+        let ast = parse_query("select count( * ) from t where value->ok = true").unwrap();
+        assert_eq!(ast.select, vec![SelectItem::Count]);
+        assert!(ast.is_count());
+        assert!(!ast.needs_value());
+        let ast = parse_query("SELECT COUNT(*) AS total FROM t").unwrap();
+        assert_eq!(ast.aliases, vec![(SelectItem::Count, "total".to_string())]);
+        // A count is the whole total; capping or paging it would be a different number
+        assert!(matches!(
+            parse_query("SELECT COUNT(*) FROM t ORDER BY timestamp DESC LIMIT 5"),
+            Err(ParseError::CountWithLimit)
+        ));
+        assert!(matches!(
+            parse_query("SELECT COUNT(*) FROM t OFFSET 5"),
+            Err(ParseError::CountWithLimit)
+        ));
+
+        assert!(matches!(
+            parse_query("SELECT key, COUNT(*) FROM t"),
+            Err(ParseError::CountNotAlone)
+        ));
+        assert!(matches!(
+            parse_query("SELECT COUNT(key) FROM t"),
+            Err(ParseError::UnexpectedToken { .. })
+        ));
+    }
+
+    #[test]
+    fn parses_multiple_order_by_keys() {
+        let spec = |field, dir| OrderSpec { field, dir };
+        let ast =
+            parse_query("SELECT key FROM t ORDER BY partition ASC, offset DESC LIMIT 10").unwrap();
+        assert_eq!(
+            ast.order,
+            Some(vec![
+                spec(OrderField::Partition, OrderDir::Asc),
+                spec(OrderField::Offset, OrderDir::Desc),
+            ])
+        );
+        assert_eq!(ast.limit, Some(10));
+        let ast =
+            parse_query("SELECT key FROM t ORDER BY key DESC,partition, timestamp DESC").unwrap();
+        assert_eq!(
+            ast.order,
+            Some(vec![
+                spec(OrderField::Key, OrderDir::Desc),
+                spec(OrderField::Partition, OrderDir::Asc),
+                spec(OrderField::Timestamp, OrderDir::Desc),
+            ])
+        );
+        // Only the single-key form gets the latest-N plan
+        let tail = parse_query("SELECT key FROM t ORDER BY timestamp DESC, key LIMIT 5").unwrap();
+        assert_eq!(tail.latest_n(), None);
+
+        assert!(matches!(
+            parse_query("SELECT key FROM t ORDER BY value->latency DESC, offset"),
+            Err(ParseError::PathOrderNotAlone)
+        ));
+        assert!(matches!(
+            parse_query("SELECT key FROM t ORDER BY offset,"),
+            Err(ParseError::InvalidOrderByField { .. })
+        ));
+    }
+
+    #[test]
+    fn parses_order_by_offset_partition_and_key() {
+        let order = |q: &str| parse_query(q).unwrap().primary_order().unwrap().clone();
+        assert_eq!(
+            order("SELECT key FROM t ORDER BY offset DESC"),
+            OrderSpec {
+                field: OrderField::Offset,
+                dir: OrderDir::Desc,
+            }
+        );
+        assert_eq!(
+            order("SELECT key FROM t ORDER BY Partition"),
+            OrderSpec {
+                field: OrderField::Partition,
+                dir: OrderDir::Asc,
+            }
+        );
+        assert_eq!(
+            order("SELECT key FROM t ORDER BY key ASC LIMIT 5"),
+            OrderSpec {
+                field: OrderField::Key,
+                dir: OrderDir::Asc,
+            }
+        );
+        // A path under the key is still a numeric path ordering
+        assert_eq!(
+            order("SELECT key FROM t ORDER BY key->id").field,
+            OrderField::Path(path(RootPath::Key, &["id"]))
+        );
+    }
+
+    #[test]
+    fn parses_range_operators() {
+        let cmp = |op, n| Expr::Cmp {
+            left: path(RootPath::Value, &["code"]),
+            op,
+            right: Literal::Number(n),
+        };
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE value->code >= 200"),
+            cmp(CmpOp::Ge, 200.0)
+        );
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE value->code<=299"),
+            cmp(CmpOp::Le, 299.0)
+        );
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE value->code < 300"),
+            cmp(CmpOp::Lt, 300.0)
+        );
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE value->code > 1.5"),
+            cmp(CmpOp::Gt, 1.5)
+        );
+        // `<>` stays inequality rather than `<` followed by `>`
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE value->code <> 200"),
+            cmp(CmpOp::Neq, 200.0)
+        );
+    }
+
+    #[test]
+    fn parses_derived_value_columns() {
+        let ast = parse_query(
+            "SELECT key, value_fields, VALUE_SIZE FROM t WHERE value_fields > 50 AND value_size >= 1024",
+        )
+        .expect("parse ok");
+        assert_eq!(
+            ast.select,
+            vec![
+                SelectItem::Key,
+                SelectItem::ValueFields,
+                SelectItem::ValueSize
+            ]
+        );
+        let expected = Expr::And(
+            Box::new(Expr::Cmp {
+                left: path(RootPath::ValueFields, &[]),
+                op: CmpOp::Gt,
+                right: Literal::Number(50.0),
+            }),
+            Box::new(Expr::Cmp {
+                left: path(RootPath::ValueSize, &[]),
+                op: CmpOp::Ge,
+                right: Literal::Number(1024.0),
+            }),
+        );
+        assert_eq!(ast.r#where, Some(expected));
+        // Derived roots are scalars
+        assert!(parse_query("SELECT key FROM t WHERE value_size->x = 1").is_err());
+    }
+
+    #[test]
+    fn parses_header_column_and_paths() {
+        let ast = parse_query(
+            "SELECT key, headers FROM t WHERE header->trace-id = 'abc' OR header->'x b' = 'y'",
+        )
+        .expect("parse ok");
+        assert_eq!(ast.select, vec![SelectItem::Key, SelectItem::Headers]);
+        let expected = Expr::Or(
+            Box::new(Expr::Cmp {
+                left: path(RootPath::Header, &["trace-id"]),
+                op: CmpOp::Eq,
+                right: Literal::String("abc".to_string()),
+            }),
+            Box::new(Expr::Cmp {
+                left: path(RootPath::Header, &["x b"]),
+                op: CmpOp::Eq,
+                right: Literal::String("y".to_string()),
+            }),
+        );
+        assert_eq!(ast.r#where, Some(expected));
+        // A header path names exactly one header
+        assert!(parse_query("SELECT key FROM t WHERE header = 'x'").is_err());
+        assert!(parse_query("SELECT key FROM t WHERE header->a->b = 'x'").is_err());
+    }
+
+    #[test]
+    fn parses_json_pointer_paths() {
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE value#/payload/items/0/sku = 'A1'"),
+            Expr::Cmp {
+                left: path(RootPath::Value, &["payload", "items", "0", "sku"]),
+                op: CmpOp::Eq,
+                right: Literal::String("A1".to_string()),
+            }
+        );
+        // Escapes and the quoted form for keys with spaces or operators
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE EXISTS(value#'/a~1b/c~0d e')"),
+            Expr::Exists(path(RootPath::Value, &["a/b", "c~d e"]))
+        );
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE value#/a!=1"),
+            Expr::Cmp {
+                left: path(RootPath::Value, &["a"]),
+                op: CmpOp::Neq,
+                right: Literal::Number(1.0),
+            }
+        );
+        assert!(parse_query("SELECT key FROM t WHERE value#a = 1").is_err());
+        assert!(parse_query("SELECT key FROM t WHERE value#/a~2 = 1").is_err());
+        assert!(parse_query("SELECT key FROM t WHERE key#/a = 1").is_err());
+    }
+
+    #[test]
+    fn parses_topic_predicate() {
+        let ast = parse_query("SELECT key FROM t WHERE topic = 'orders'").expect("parse ok");
+        assert_eq!(
+            ast.r#where,
+            Some(Expr::Cmp {
+                left: path(RootPath::Topic, &[]),
+                op: CmpOp::Eq,
+                right: Literal::String("orders".to_string()),
+            })
+        );
+        assert!(parse_query("SELECT key FROM t WHERE topic->x = 'a'").is_err());
+    }
+
     #[test]
     fn parses_or_and_precedence() {
         let expr =
@@ -654,6 +1466,80 @@ mod tests {
         assert!(matches!(expr_alt, Expr::Cmp { op: CmpOp::Neq, .. }));
     }
 
+    #[test]
+    fn desugars_double_bounded_comparison() {
+        let expr = where_expr("SELECT key FROM t WHERE 100 < value->code < 500");
+        let expected = Expr::And(
+            Box::new(Expr::Cmp {
+                left: path(RootPath::Value, &["code"]),
+                op: CmpOp::Gt,
+                right: Literal::Number(100.0),
+            }),
+            Box::new(Expr::Cmp {
+                left: path(RootPath::Value, &["code"]),
+                op: CmpOp::Lt,
+                right: Literal::Number(500.0),
+            }),
+        );
+        assert_eq!(expr, expected);
+
+        let inclusive =
+            where_expr("SELECT key FROM t WHERE 500 >= value->code >= 100 AND key = 'a'");
+        assert!(matches!(inclusive, Expr::And(ref lhs, _) if matches!(**lhs, Expr::And(..))));
+
+        assert!(parse_query("SELECT key FROM t WHERE 100 < value->code > 500").is_err());
+    }
+
+    #[test]
+    fn parses_any_and_all_quantifiers() {
+        let expr = where_expr("SELECT key FROM t WHERE ANY(value->items)->status = 'failed'");
+        assert_eq!(
+            expr,
+            Expr::Quantified {
+                quantifier: Quantifier::Any,
+                array: path(RootPath::Value, &["items"]),
+                predicate: Box::new(Expr::Cmp {
+                    left: path(RootPath::Value, &["status"]),
+                    op: CmpOp::Eq,
+                    right: Literal::String("failed".to_string()),
+                }),
+            }
+        );
+
+        let expr = where_expr("SELECT key FROM t WHERE all( value->tags ) != 'x' AND key = 'k'");
+        match expr {
+            Expr::And(lhs, _) => match *lhs {
+                Expr::Quantified {
+                    quantifier,
+                    array,
+                    predicate,
+                } => {
+                    assert_eq!(quantifier, Quantifier::All);
+                    assert_eq!(array, path(RootPath::Value, &["tags"]));
+                    assert!(
+                        matches!(*predicate, Expr::Cmp { op: CmpOp::Neq, ref left, .. } if left.segments.is_empty())
+                    );
+                }
+                other => panic!("expected quantifier, got {:?}", other),
+            },
+            other => panic!("expected AND, got {:?}", other),
+        }
+
+        assert!(parse_query("SELECT key FROM t WHERE ANY(value->items = 'x'").is_err());
+    }
+
+    #[test]
+    fn parses_group_by_key() {
+        let q =
+            parse_query("SELECT key, value FROM t GROUP BY key ORDER BY timestamp DESC LIMIT 5")
+                .expect("parse ok");
+        assert_eq!(q.group_by, Some(GroupBy::Key));
+        assert_eq!(q.primary_order().map(|o| o.dir), Some(OrderDir::Desc));
+        assert_eq!(q.limit, Some(5));
+
+        assert!(parse_query("SELECT key FROM t GROUP BY value").is_err());
+    }
+
     #[test]
     fn parses_contains_variants() {
         let expr_key = where_expr("SELECT key FROM t WHERE key CONTAINS '123'");
@@ -684,6 +1570,224 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parses_exists() {
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE EXISTS(value->error->code)"),
+            Expr::Exists(path(RootPath::Value, &["error", "code"]))
+        );
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE exists ( value->a ) AND key = 'k'"),
+            Expr::And(
+                Box::new(Expr::Exists(path(RootPath::Value, &["a"]))),
+                Box::new(Expr::Cmp {
+                    left: path(RootPath::Key, &[]),
+                    op: CmpOp::Eq,
+                    right: Literal::String("k".to_string()),
+                }),
+            )
+        );
+        assert!(parse_query("SELECT key FROM t WHERE EXISTS(value->a").is_err());
+    }
+
+    #[test]
+    fn parses_matches_regex() {
+        let expr = where_expr("SELECT key FROM t WHERE value->msg MATCHES '^err[0-9]+$'");
+        assert_eq!(
+            expr,
+            Expr::Cmp {
+                left: path(RootPath::Value, &["msg"]),
+                op: CmpOp::Regex,
+                right: Literal::Pattern(Pattern::new("^err[0-9]+$").unwrap()),
+            }
+        );
+        assert!(matches!(
+            where_expr("SELECT key FROM t WHERE key not matches 'tmp-.*'"),
+            Expr::Not(_)
+        ));
+
+        let err =
+            parse_query("SELECT key FROM t WHERE value->msg MATCHES '(unclosed'").unwrap_err();
+        assert!(matches!(err, ParseError::InvalidRegex(_)), "{err:?}");
+        assert!(parse_query("SELECT key FROM t WHERE value->msg MATCHES 42").is_err());
+    }
+
+    #[test]
+    fn parses_like_and_not_like() {
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE key LIKE 'user-%'"),
+            Expr::Cmp {
+                left: path(RootPath::Key, &[]),
+                op: CmpOp::Like,
+                right: Literal::Pattern(Pattern::like("user-%").unwrap()),
+            }
+        );
+        let Expr::Not(inner) = where_expr("SELECT key FROM t WHERE value->sku not like 'A_'")
+        else {
+            panic!("expected NOT LIKE to negate");
+        };
+        assert!(matches!(
+            *inner,
+            Expr::Cmp {
+                op: CmpOp::Like,
+                ..
+            }
+        ));
+        assert!(parse_query("SELECT key FROM t WHERE key LIKE 5").is_err());
+    }
+
+    #[test]
+    fn parses_in_lists() {
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE key IN ('a', 'b', 'c')"),
+            Expr::In {
+                left: path(RootPath::Key, &[]),
+                list: ["a", "b", "c"]
+                    .map(|s| Literal::String(s.to_string()))
+                    .to_vec(),
+            }
+        );
+        let codes = Expr::In {
+            left: path(RootPath::Value, &["code"]),
+            list: vec![
+                Literal::Number(200.0),
+                Literal::String("ok".to_string()),
+                Literal::Null,
+            ],
+        };
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE value->code in (200,'ok', null)"),
+            codes
+        );
+        assert_eq!(
+            where_expr(
+                "SELECT key FROM t WHERE value->code NOT IN (200, 'ok', null) AND key = 'k'"
+            ),
+            Expr::And(
+                Box::new(Expr::Not(Box::new(codes))),
+                Box::new(Expr::Cmp {
+                    left: path(RootPath::Key, &[]),
+                    op: CmpOp::Eq,
+                    right: Literal::String("k".to_string()),
+                })
+            )
+        );
+        assert!(matches!(
+            parse_query("SELECT key FROM t WHERE key IN ( )"),
+            Err(ParseError::EmptyInList)
+        ));
+        assert!(parse_query("SELECT key FROM t WHERE key IN ('a',)").is_err());
+        assert!(parse_query("SELECT key FROM t WHERE key IN 'a'").is_err());
+    }
+
+    #[test]
+    fn parses_numeric_arrow_segments() {
+        let ast =
+            parse_query("SELECT key FROM t WHERE value->a->2->b = 'x' ORDER BY value->arr->0")
+                .expect("parse ok");
+        assert_eq!(
+            ast.r#where,
+            Some(Expr::Cmp {
+                left: path(RootPath::Value, &["a", "2", "b"]),
+                op: CmpOp::Eq,
+                right: Literal::String("x".to_string()),
+            })
+        );
+        assert_eq!(
+            ast.order_path(),
+            Some(&path(RootPath::Value, &["arr", "0"]))
+        );
+    }
+
+    #[test]
+    fn parses_prefix_not() {
+        let flag = Expr::Cmp {
+            left: path(RootPath::Value, &["flag"]),
+            op: CmpOp::Eq,
+            right: Literal::Bool(true),
+        };
+        let key_a = Expr::Cmp {
+            left: path(RootPath::Key, &[]),
+            op: CmpOp::Eq,
+            right: Literal::String("a".to_string()),
+        };
+        let not = |e: Expr| Expr::Not(Box::new(e));
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE NOT value->flag = true"),
+            not(flag.clone())
+        );
+        // Tighter than AND, and the parenthesized form negates the whole group
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE NOT value->flag = true AND key = 'a'"),
+            Expr::And(Box::new(not(flag.clone())), Box::new(key_a.clone()))
+        );
+        assert_eq!(
+            where_expr(
+                "SELECT key FROM t WHERE key = 'a' OR not (value->flag = true AND key = 'a')"
+            ),
+            Expr::Or(
+                Box::new(key_a.clone()),
+                Box::new(not(Expr::And(
+                    Box::new(flag.clone()),
+                    Box::new(key_a.clone())
+                )))
+            )
+        );
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE NOT NOT value->flag = true"),
+            not(not(flag))
+        );
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE NOT EXISTS(value->a)"),
+            not(Expr::Exists(path(RootPath::Value, &["a"])))
+        );
+        assert!(parse_query("SELECT key FROM t WHERE NOT").is_err());
+    }
+
+    #[test]
+    fn parses_not_contains() {
+        let contains = |left: JsonPath, s: &str| Expr::Cmp {
+            left,
+            op: CmpOp::Contains,
+            right: Literal::String(s.to_string()),
+        };
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE value not contains 'x'"),
+            Expr::Not(Box::new(contains(path(RootPath::Value, &[]), "x")))
+        );
+
+        // NOT binds to the single comparison, below AND
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE key NOT CONTAINS 'tmp' AND value->a = 1"),
+            Expr::And(
+                Box::new(Expr::Not(Box::new(contains(
+                    path(RootPath::Key, &[]),
+                    "tmp"
+                )))),
+                Box::new(Expr::Cmp {
+                    left: path(RootPath::Value, &["a"]),
+                    op: CmpOp::Eq,
+                    right: Literal::Number(1.0),
+                }),
+            )
+        );
+
+        assert_eq!(
+            where_expr("SELECT key FROM t WHERE ANY(value->tags) NOT CONTAINS 'x'"),
+            Expr::Quantified {
+                quantifier: Quantifier::Any,
+                array: path(RootPath::Value, &["tags"]),
+                predicate: Box::new(Expr::Not(Box::new(contains(
+                    path(RootPath::Value, &[]),
+                    "x"
+                )))),
+            }
+        );
+
+        // Only CONTAINS has a negated infix form
+        assert!(parse_query("SELECT key FROM t WHERE key NOT = 'a'").is_err());
+    }
+
     #[test]
     fn parses_list_topics_command() {
         let cmd = parse_command("LIST topics;").expect("parse LIST");
@@ -0,0 +1,218 @@
+//! `--from-file`: read messages from an NDJSON dump instead of a broker, for
+//! demos and bug reproduction without Kafka.
+use crate::args::RunArgs;
+use crate::consumer::{ReaderContext, filter_message};
+use crate::merger::{ReaderEvent, ReaderTx};
+use crate::models::{MessageEnvelope, MessageHeader, PartitionSummary, StopSignal};
+use crate::query::SelectQuery;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinSet;
+
+/// One line of a replay file. Every field is optional; a string `value` is the
+/// payload text itself, any other JSON is stored compact, and a missing or
+/// null `value` is a tombstone.
+#[derive(Debug, Deserialize)]
+struct ReplayRecord {
+    #[serde(default)]
+    topic: Option<String>,
+    #[serde(default)]
+    partition: i32,
+    #[serde(default)]
+    offset: i64,
+    #[serde(default)]
+    timestamp_ms: i64,
+    #[serde(default)]
+    key: Option<String>,
+    #[serde(default)]
+    value: Value,
+    #[serde(default)]
+    headers: BTreeMap<String, Option<String>>,
+}
+
+/// Parse a replay file into raw envelopes for `topic`, grouped by partition
+/// in file order. Lines naming another topic are skipped; blank lines are ignored.
+pub fn load_replay(path: &Path, topic: &str) -> Result<BTreeMap<i32, Vec<MessageEnvelope>>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read replay file {}", path.display()))?;
+    let mut partitions: BTreeMap<i32, Vec<MessageEnvelope>> = BTreeMap::new();
+    for (idx, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let rec: ReplayRecord = serde_json::from_str(line)
+            .with_context(|| format!("{}:{}: invalid replay record", path.display(), idx + 1))?;
+        if rec.topic.as_deref().is_some_and(|t| t != topic) {
+            continue;
+        }
+        let value = match rec.value {
+            Value::Null => None,
+            Value::String(s) => Some(s),
+            other => Some(other.to_string()),
+        };
+        partitions
+            .entry(rec.partition)
+            .or_default()
+            .push(MessageEnvelope {
+                topic: topic.to_string(),
+                partition: rec.partition,
+                offset: rec.offset,
+                timestamp_ms: rec.timestamp_ms,
+                key: rec.key.unwrap_or_else(|| "null".to_string()),
+                value,
+                headers: rec
+                    .headers
+                    .into_iter()
+                    .map(|(key, value)| MessageHeader {
+                        key,
+                        value: value.map(String::into_bytes),
+                    })
+                    .collect(),
+                cluster: None,
+                count: None,
+            });
+    }
+    Ok(partitions)
+}
+
+/// Load `path` and spawn one reader per partition (or just `--partition`),
+/// each filtering like a Kafka consumer and feeding `tx`. Returns the
+/// partitions being replayed.
+pub fn spawn_replay(
+    args: &RunArgs,
+    path: &Path,
+    topic: &str,
+    query: Option<Arc<SelectQuery>>,
+    tx: Sender<ReaderEvent>,
+    joinset: &mut JoinSet<Result<PartitionSummary>>,
+    ctx: ReaderContext,
+) -> Result<Vec<i32>> {
+    let mut partitions = load_replay(path, topic)?;
+    if let Some(p) = args.partition {
+        partitions.retain(|&id, _| id == p);
+    }
+    let ids = partitions.keys().copied().collect();
+    for (partition, rows) in partitions {
+        let args = args.clone();
+        let query = query.clone();
+        let every = Duration::from_millis(args.flush_interval_ms);
+        let mut lane = ReaderTx::new(tx.clone(), None, partition, every);
+        let progress = ctx.progress.clone();
+        let stop = ctx.stop.clone();
+        joinset.spawn(async move {
+            let mut matched = 0u64;
+            for env in rows {
+                if stop.as_ref().is_some_and(StopSignal::is_raised) {
+                    break;
+                }
+                if let Some(ref p) = progress {
+                    p.record_scanned();
+                }
+                let timestamp_ms = env.timestamp_ms;
+                let Some(env) = filter_message(&args, query.as_deref(), env) else {
+                    if lane.skipped(timestamp_ms).await.is_err() {
+                        break;
+                    }
+                    continue;
+                };
+                if lane.row(env).await.is_err() {
+                    break;
+                }
+                if let Some(ref p) = progress {
+                    p.record_matched();
+                }
+                matched += 1;
+                if args
+                    .limit_per_partition
+                    .is_some_and(|max| matched as usize >= max)
+                {
+                    break;
+                }
+            }
+            let _ = lane.idle().await;
+            Ok(PartitionSummary { partition, matched })
+        });
+    }
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_lines_become_envelopes_by_partition() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dump.ndjson");
+        std::fs::write(
+            &path,
+            concat!(
+                r#"{"partition":1,"offset":7,"timestamp_ms":20,"key":"a","value":{"n":1}}"#,
+                "\n\n",
+                r#"{"partition":0,"offset":3,"key":"b","value":"plain text","headers":{"h":"v","n":null}}"#,
+                "\n",
+                r#"{"topic":"other","partition":0,"offset":4,"value":"skipped"}"#,
+                "\n",
+                r#"{"partition":0,"offset":5}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+
+        let parts = load_replay(&path, "orders").unwrap();
+        assert_eq!(parts.keys().copied().collect::<Vec<_>>(), vec![0, 1]);
+        let p0 = &parts[&0];
+        assert_eq!(p0.len(), 2);
+        assert_eq!(p0[0].value.as_deref(), Some("plain text"));
+        assert_eq!(p0[0].headers[0].value.as_deref(), Some(&b"v"[..]));
+        assert_eq!(p0[0].headers[1].value, None);
+        // Missing key and value: a null-keyed tombstone
+        assert_eq!(p0[1].key, "null");
+        assert_eq!(p0[1].value, None);
+        assert_eq!(parts[&1][0].value.as_deref(), Some(r#"{"n":1}"#));
+        assert_eq!(parts[&1][0].topic, "orders");
+
+        std::fs::write(&path, "{\"offset\":1}\nnot json\n").unwrap();
+        let err = format!("{:#}", load_replay(&path, "orders").unwrap_err());
+        assert!(err.contains("dump.ndjson:2"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn readers_stop_once_the_limit_is_signalled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dump.ndjson");
+        let lines: Vec<String> = (0..50)
+            .map(|o| format!(r#"{{"partition":0,"offset":{o},"key":"k{o}"}}"#))
+            .collect();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let (stop_tx, stop) = StopSignal::channel();
+        let args = RunArgs::default();
+        let ctx = ReaderContext {
+            stop: Some(stop),
+            ..ReaderContext::default()
+        };
+        // Capacity 1 keeps the reader in step with what is received here
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let mut joinset = JoinSet::new();
+        spawn_replay(&args, &path, "orders", None, tx, &mut joinset, ctx).unwrap();
+        for _ in 0..2 {
+            rx.recv().await.unwrap();
+        }
+        stop_tx.send_replace(true);
+        // The receiver stays open: the reader stops on the signal, not on a closed channel
+        let mut after = 0;
+        while rx.recv().await.is_some() {
+            after += 1;
+        }
+        let summary = joinset.join_next().await.unwrap().unwrap().unwrap();
+        assert!(after <= 2, "{after} rows after the signal");
+        assert!(summary.matched < 50, "{}", summary.matched);
+    }
+}
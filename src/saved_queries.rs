@@ -0,0 +1,181 @@
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SavedQuery {
+    pub name: String,
+    pub query: String,
+}
+
+/// Named queries persisted as a single JSON file under ~/.rkl.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SavedQueryStore {
+    pub queries: Vec<SavedQuery>,
+}
+
+impl SavedQueryStore {
+    pub fn load() -> Self {
+        Self::load_from(&store_path())
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<SavedQueryStore>(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&store_path())
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context("create saved queries dir")?;
+        }
+        let s = serde_json::to_string_pretty(self).context("serialize saved queries")?;
+        fs::write(path, s).context("write saved queries file")?;
+        Ok(())
+    }
+
+    /// Case-insensitive lookup by name.
+    pub fn find(&self, name: &str) -> Option<&SavedQuery> {
+        self.queries
+            .iter()
+            .find(|q| q.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Store `query` under `name`, replacing any query with the same name
+    /// (case-insensitively). Returns true when an existing entry was replaced.
+    pub fn upsert(&mut self, name: &str, query: &str) -> bool {
+        let name = name.trim();
+        let entry = SavedQuery {
+            name: name.to_string(),
+            query: query.to_string(),
+        };
+        match self
+            .queries
+            .iter_mut()
+            .find(|q| q.name.eq_ignore_ascii_case(name))
+        {
+            Some(existing) => {
+                *existing = entry;
+                true
+            }
+            None => {
+                self.queries.push(entry);
+                false
+            }
+        }
+    }
+
+    /// Like `find`, but errors with the list of available names.
+    pub fn lookup(&self, name: &str) -> Result<&str> {
+        if let Some(q) = self.find(name) {
+            return Ok(&q.query);
+        }
+        let mut names: Vec<&str> = self.queries.iter().map(|q| q.name.as_str()).collect();
+        names.sort_by_key(|n| n.to_lowercase());
+        if names.is_empty() {
+            Err(anyhow!(
+                "Saved query '{}' not found (no saved queries)",
+                name
+            ))
+        } else {
+            Err(anyhow!(
+                "Saved query '{}' not found. Available: {}",
+                name,
+                names.join(", ")
+            ))
+        }
+    }
+}
+
+pub fn store_path() -> PathBuf {
+    std::env::var("HOME")
+        .map(|h| PathBuf::from(h).join(".rkl").join("saved_queries.json"))
+        .unwrap_or_else(|_| PathBuf::from(".rkl").join("saved_queries.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_saved_query_by_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("saved_queries.json");
+        let store = SavedQueryStore {
+            queries: vec![
+                SavedQuery {
+                    name: "errors".to_string(),
+                    query: "SELECT key FROM t WHERE value CONTAINS 'error'".to_string(),
+                },
+                SavedQuery {
+                    name: "Recent".to_string(),
+                    query: "SELECT key FROM t ORDER BY timestamp DESC LIMIT 5".to_string(),
+                },
+            ],
+        };
+        store.save_to(&path).unwrap();
+
+        let loaded = SavedQueryStore::load_from(&path);
+        assert_eq!(
+            loaded.lookup("recent").unwrap(),
+            "SELECT key FROM t ORDER BY timestamp DESC LIMIT 5"
+        );
+    }
+
+    #[test]
+    fn upsert_adds_then_overwrites_by_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("saved_queries.json");
+        let mut store = SavedQueryStore::load_from(&path);
+        assert!(!store.upsert(" errors ", "SELECT key FROM t"));
+        assert!(!store.upsert("recent", "SELECT key FROM r"));
+        // Same name in another case replaces the entry in place
+        assert!(store.upsert("Errors", "SELECT key FROM t WHERE value CONTAINS 'error'"));
+        store.save_to(&path).unwrap();
+
+        let loaded = SavedQueryStore::load_from(&path);
+        assert_eq!(loaded.queries.len(), 2);
+        assert_eq!(loaded.queries[0].name, "Errors");
+        assert_eq!(
+            loaded.lookup("errors").unwrap(),
+            "SELECT key FROM t WHERE value CONTAINS 'error'"
+        );
+    }
+
+    #[test]
+    fn missing_name_lists_available_queries() {
+        let store = SavedQueryStore {
+            queries: vec![
+                SavedQuery {
+                    name: "b-query".to_string(),
+                    query: "SELECT key FROM b".to_string(),
+                },
+                SavedQuery {
+                    name: "a-query".to_string(),
+                    query: "SELECT key FROM a".to_string(),
+                },
+            ],
+        };
+        let err = store.lookup("nope").unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Saved query 'nope' not found. Available: a-query, b-query"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let empty = SavedQueryStore::load_from(&dir.path().join("saved_queries.json"));
+        assert!(
+            empty
+                .lookup("nope")
+                .unwrap_err()
+                .to_string()
+                .contains("no saved queries")
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Avro values framed for Confluent Schema Registry: a magic `0x00` byte, the
+//! writer schema's id as a big-endian `u32`, then the Avro datum.
+
+use anyhow::{Context, Result, bail};
+use apache_avro::Schema;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+pub const MAGIC_BYTE: u8 = 0x00;
+
+/// Split a registry-framed payload into its schema id and Avro datum, or
+/// `None` if it lacks the magic byte or the full 5-byte header.
+pub fn wire_header(payload: &[u8]) -> Option<(u32, &[u8])> {
+    match payload {
+        [MAGIC_BYTE, a, b, c, d, datum @ ..] => Some((u32::from_be_bytes([*a, *b, *c, *d]), datum)),
+        _ => None,
+    }
+}
+
+/// Schemas fetched from the registry, by id. Clones share one cache, so every
+/// partition reader of a run fetches each schema once.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaCache(Arc<Mutex<HashMap<u32, Arc<Schema>>>>);
+
+impl SchemaCache {
+    /// Decode a registry-framed payload as compact JSON text. `None` when the
+    /// payload is not framed; an error when its schema cannot be fetched.
+    pub async fn decode(
+        &self,
+        registry_url: &str,
+        payload: &[u8],
+        timeout: Duration,
+    ) -> Option<Result<String>> {
+        let (id, datum) = wire_header(payload)?;
+        Some(match self.schema(registry_url, id, timeout).await {
+            Ok(schema) => decode_datum(&schema, datum),
+            Err(e) => Err(e),
+        })
+    }
+
+    async fn schema(&self, registry_url: &str, id: u32, timeout: Duration) -> Result<Arc<Schema>> {
+        // Held across the fetch so readers racing on a new id fetch it once
+        let mut cache = self.0.lock().await;
+        if let Some(schema) = cache.get(&id) {
+            return Ok(schema.clone());
+        }
+        let url = format!("{}/schemas/ids/{}", registry_url.trim_end_matches('/'), id);
+        let body = tokio::task::spawn_blocking(move || {
+            ureq::get(&url)
+                .timeout(timeout)
+                .call()
+                .with_context(|| format!("GET {}", url))?
+                .into_string()
+                .context("read schema registry response")
+        })
+        .await??;
+        let schema = Arc::new(
+            parse_registry_schema(&body)
+                .with_context(|| format!("schema id {} from the registry", id))?,
+        );
+        cache.insert(id, schema.clone());
+        Ok(schema)
+    }
+}
+
+/// Body of `GET /schemas/ids/{id}`; `schemaType` is absent for Avro.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegistrySchema {
+    schema: String,
+    schema_type: Option<String>,
+}
+
+fn parse_registry_schema(body: &str) -> Result<Schema> {
+    let resp: RegistrySchema = serde_json::from_str(body).context("parse registry response")?;
+    if let Some(kind) = resp.schema_type.filter(|k| k != "AVRO") {
+        bail!("{} schemas are not supported, only Avro", kind);
+    }
+    Schema::parse_str(&resp.schema).context("parse Avro schema")
+}
+
+/// Decode one Avro datum written with `schema` into compact JSON text.
+pub fn decode_datum(schema: &Schema, mut datum: &[u8]) -> Result<String> {
+    let value = apache_avro::from_avro_datum(schema, &mut datum, None).context("decode Avro")?;
+    let json = serde_json::Value::try_from(value).context("convert Avro to JSON")?;
+    Ok(json.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use apache_avro::types::{Record, Value as AvroValue};
+
+    #[test]
+    fn wire_header_extracts_big_endian_schema_id() {
+        let payload = [0x00, 0x00, 0x00, 0x01, 0x02, 0xaa, 0xbb];
+        assert_eq!(wire_header(&payload), Some((258, &[0xaa, 0xbb][..])));
+        // The header alone is a valid frame with an empty datum
+        assert_eq!(wire_header(&[0, 0, 0, 0, 7]), Some((7, &[][..])));
+        // Wrong magic byte, or too short to hold an id
+        assert_eq!(wire_header(b"{\"a\":1}"), None);
+        assert_eq!(wire_header(&[0x00, 0x00, 0x01]), None);
+    }
+
+    #[test]
+    fn decodes_registry_schema_and_datum_to_json() {
+        let body = r#"{"schema": "{\"type\":\"record\",\"name\":\"Order\",\"fields\":[{\"name\":\"id\",\"type\":\"string\"},{\"name\":\"qty\",\"type\":\"int\"}]}"}"#;
+        let schema = parse_registry_schema(body).unwrap();
+        let mut record = Record::new(&schema).unwrap();
+        record.put("id", "o-1");
+        record.put("qty", 3);
+        let datum = apache_avro::to_avro_datum(&schema, AvroValue::from(record)).unwrap();
+        assert_eq!(
+            decode_datum(&schema, &datum).unwrap(),
+            r#"{"id":"o-1","qty":3}"#
+        );
+
+        let protobuf = r#"{"schemaType": "PROTOBUF", "schema": "syntax = \"proto3\";"}"#;
+        assert!(parse_registry_schema(protobuf).is_err());
+    }
+}
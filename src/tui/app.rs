@@ -1,9 +1,155 @@
-use super::env_store::{EnvStore, Environment};
-use crate::models::{MessageEnvelope, SslConfig};
-use crate::query::SelectItem;
-use std::time::Instant;
+use super::history::{self, QueryHistory};
+use super::settings::{PaneLayout, RowEviction, UiSettings, errors_only_query};
+use super::theme::Theme;
+use crate::args::ConfigDefaults;
+use crate::env_store::{EnvStore, Environment};
+use crate::models::{MessageEnvelope, ReadProgress, SaslConfig, SslConfig, headers_compact};
+use crate::query::{Expr, SelectItem, derived_column_text};
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
 use tui_textarea::TextArea;
 
+/// Progress ticks kept for the status-panel rate sparkline.
+pub const RATE_SAMPLES: usize = 30;
+
+/// How long a newly arrived row stays highlighted in the results table.
+pub const NEW_ROW_HIGHLIGHT: Duration = Duration::from_millis(1500);
+
+/// Input poll timeout while keys or pipeline events keep arriving.
+const ACTIVE_POLL: Duration = Duration::from_millis(20);
+/// Poll timeout once nothing has happened for `IDLE_AFTER`.
+const IDLE_POLL: Duration = Duration::from_millis(250);
+const IDLE_AFTER: Duration = Duration::from_secs(2);
+
+/// Decides when the main loop redraws and how long it blocks on input. Every
+/// state change marks it dirty; a clean frame is not redrawn.
+#[derive(Debug, Clone)]
+pub struct RedrawTracker {
+    dirty: bool,
+    last_activity: Instant,
+}
+
+impl RedrawTracker {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            dirty: true,
+            last_activity: now,
+        }
+    }
+
+    /// Something changed the state at `now`.
+    pub fn mark(&mut self, now: Instant) {
+        self.dirty = true;
+        self.last_activity = now;
+    }
+
+    /// Whether a redraw is due; clears the flag.
+    pub fn take(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Short while streaming or typing, longer when idle, and never past
+    /// `deadline` (a timed state change such as the copy button release).
+    pub fn poll_timeout(&self, now: Instant, deadline: Option<Instant>) -> Duration {
+        let timeout = if now.duration_since(self.last_activity) < IDLE_AFTER {
+            ACTIVE_POLL
+        } else {
+            IDLE_POLL
+        };
+        deadline.map_or(timeout, |d| timeout.min(d.saturating_duration_since(now)))
+    }
+}
+
+impl Default for RedrawTracker {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+/// One result set: its rows, run, columns and the view over them. The active
+/// tab is `AppState::tab`; the others wait in `AppState::tabs` and
+/// `switch_tab` swaps them in whole.
+#[derive(Default)]
+pub struct Tab {
+    pub rows: Vec<MessageEnvelope>,
+    /// When each of `rows` arrived, for the new-row highlight; same length.
+    pub row_arrivals: Vec<Instant>,
+    /// Rows offered to `push_rows` since the last `clear_rows`.
+    pub rows_seen: u64,
+    /// xorshift state for `RowEviction::ReservoirSample`; 0 means unseeded.
+    pub sample_rng: u64,
+    pub topics_with_partitions: Vec<(String, usize)>,
+    pub results_mode: ResultsMode,
+    pub selected_columns: Vec<SelectItem>,
+    /// `AS` labels from the last SELECT, used for the column headers.
+    pub column_aliases: Vec<(SelectItem, String)>,
+    /// Widths set with `+`/`-` for this session, overriding the defaults.
+    pub column_widths: Vec<(SelectItem, u16)>,
+    pub current_run: Option<u64>,
+    /// Topic of the current run, for the tab bar and the terminal title.
+    pub run_topic: Option<String>,
+    pub selected_row: usize,
+    pub selected_col: usize,
+    /// Columns, visibility and selection from before `i` isolated one column;
+    /// the next `i` restores them.
+    pub isolated_from: Option<(Vec<SelectItem>, Vec<bool>, usize)>,
+    /// Per-column visibility from the `c` overlay, parallel to
+    /// `selected_columns`; a missing entry counts as shown.
+    pub visible_columns: Vec<bool>,
+    /// Cursor in the `c` column overlay; `Some` while it is open.
+    pub column_picker: Option<usize>,
+    pub table_hscroll: usize,
+    pub pin_columns: bool,
+    pub json_vscroll: u16,
+    pub detail_flat: bool,
+    /// Ctrl-F: runs start at the end of each partition and stream until the
+    /// next run, keeping the newest row selected (see `push_batch`).
+    pub follow: bool,
+    /// `WHERE` of the last query run, if any.
+    pub last_where: Option<Expr>,
+    pub diff_base: Option<MessageEnvelope>,
+    /// Line under the cursor in the detail pane while it has focus.
+    pub detail_cursor: usize,
+    /// Detail-pane objects/arrays folded with Enter/Space, by path (array
+    /// items by index); kept across rows so similar payloads fold alike.
+    pub collapsed_paths: HashSet<Vec<String>>,
+    pub show_headers: bool,
+    /// Path being edited in the Ctrl-E export prompt; `Some` while it is open.
+    pub export_prompt: Option<String>,
+    /// Digits typed after `g` in the results view; `Some` while the prompt is open.
+    pub partition_prompt: Option<String>,
+    /// Digits typed after `o` in the results view; `Some` while the prompt is open.
+    pub offset_prompt: Option<String>,
+    /// Text typed after `/` in the results view: only rows with a cell
+    /// containing it (ignoring case) are shown. Empty when not filtering.
+    pub row_filter: String,
+    /// The `/` prompt is open and keys edit `row_filter`.
+    pub filter_prompt: bool,
+    /// Indices into `rows` that pass `row_filter`, when one is set;
+    /// `selected_row` then indexes this list.
+    pub filtered_rows: Option<Vec<usize>>,
+    /// Messages scanned per progress tick for the current run, oldest first
+    /// (at most `RATE_SAMPLES`); drawn as a sparkline in the status title.
+    pub rate_samples: VecDeque<u64>,
+    pub last_scanned: u64,
+    /// Offsets read against the partitions' high watermarks for the current
+    /// run; shown in the status title.
+    pub read_progress: Option<ReadProgress>,
+    /// Stats of the last completed run, shown until Esc or the next run.
+    pub scan_summary: Option<ScanSummary>,
+}
+
+impl Tab {
+    /// An empty tab showing the standard columns.
+    pub fn new() -> Self {
+        Self {
+            selected_columns: SelectItem::standard(true),
+            rate_samples: VecDeque::with_capacity(RATE_SAMPLES),
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct AppState {
     pub input: String,
@@ -12,22 +158,42 @@ pub struct AppState {
     pub status: String,
     pub status_buffer: String,
     pub status_vscroll: u16,
-    pub rows: Vec<MessageEnvelope>,
-    pub topics_with_partitions: Vec<(String, usize)>,
-    pub results_mode: ResultsMode,
-    pub selected_columns: Vec<SelectItem>,
-    pub current_run: Option<u64>,
+    /// The active result tab.
+    pub tab: Tab,
+    /// Result tabs opened with Ctrl-T; `tabs[active_tab]` is a placeholder
+    /// while its state is in `tab`. Empty until a second tab opens.
+    pub tabs: Vec<Tab>,
+    pub active_tab: usize,
     pub max_rows_in_memory: usize,
+    pub row_eviction: RowEviction,
     pub host: String,
     pub focus: Focus,
-    pub selected_row: usize,
-    pub selected_col: usize,
     pub env_store: EnvStore,
+    /// Environment bar text for the selected env, brokers already parsed;
+    /// see `refresh_env_bar`.
+    pub env_bar: String,
     pub show_env_modal: bool,
     pub env_editor: Option<EnvEditor>,
-    // Results/table view state
-    pub table_hscroll: usize,
-    pub json_vscroll: u16,
+    /// Indented or compact JSON in the detail pane; saved, so shared by all tabs.
+    pub detail_pretty: bool,
+    /// Editor height and table/detail split; adjusted with Ctrl-arrows.
+    pub panes: PaneLayout,
+    /// Colors from ~/.rkl/theme.toml, read at startup.
+    pub theme: Theme,
+    /// Errors-only preset template run by `e` (see `settings::errors_only_query`).
+    pub errors_query: String,
+    /// Up/Down wrap around the ends of the results list (see `step_row`).
+    pub wrap_rows: bool,
+    /// Vim-style keys move the results selection; `v` in the help toggles it.
+    pub vim_mode: bool,
+    /// Emphasize the value fields that made the last run's `WHERE` match.
+    pub detail_emphasis: bool,
+    /// Name being typed in the "save query as" prompt; `Some` while it is open.
+    pub save_prompt: Option<String>,
+    /// Queries run from the editor, browsed with Up/Down (see `step_history`).
+    pub history: QueryHistory,
+    /// History entry shown in the editor; `None` when not browsing.
+    pub history_index: Option<usize>,
     pub copy_btn_pressed: bool,
     pub copy_btn_deadline: Option<Instant>,
     pub last_run_query_range: Option<(usize, usize)>,
@@ -42,10 +208,12 @@ pub struct AppState {
     pub help_vscroll: u32,
     // Info screen
     pub topics: Vec<String>,
+    pub info_selected: usize,
     pub autocomplete: Option<AutoCompleteState>,
     pub topics_last_fetched_at: Option<Instant>,
     pub autocomplete_frozen_token: Option<(usize, usize, String)>,
     pub autocomplete_dirty: bool,
+    pub redraw: RedrawTracker,
 }
 
 impl AppState {
@@ -56,34 +224,50 @@ impl AppState {
                 name: "Default".to_string(),
                 host: host.clone(),
                 private_key_pem: None,
+                key_password: None,
                 public_key_pem: None,
                 ssl_ca_pem: None,
+                sasl: None,
             });
             env_store.selected = Some(0);
             let _ = env_store.save();
         }
-        Self {
+        let settings = UiSettings::load();
+        let status = env_store
+            .load_error_summary()
+            .or_else(|| {
+                let error = ConfigDefaults::load().err()?;
+                Some(format!("Ignored the config file: {:#}", error))
+            })
+            .unwrap_or_else(|| String::from("Enter a query and press Ctrl-Enter to run"));
+        let mut app = Self {
             input: initial_input.clone(),
             input_cursor: initial_input.len(),
             input_vscroll: 0,
-            status: String::from("Enter a query and press Ctrl-Enter to run"),
+            status,
             status_buffer: String::new(),
             status_vscroll: 0,
-            rows: Vec::new(),
-            topics_with_partitions: Vec::new(),
-            results_mode: ResultsMode::Messages,
-            selected_columns: SelectItem::standard(true),
-            current_run: None,
+            tab: Tab::new(),
+            tabs: Vec::new(),
+            active_tab: 0,
             max_rows_in_memory: 2000,
+            row_eviction: settings.row_eviction,
             host,
             focus: Focus::Host,
-            selected_row: 0,
-            selected_col: 0,
             env_store,
+            env_bar: String::new(),
             show_env_modal: false,
             env_editor: None,
-            table_hscroll: 0,
-            json_vscroll: 0,
+            detail_pretty: settings.detail_pretty,
+            panes: settings.panes.clamped(),
+            theme: Theme::load(),
+            errors_query: settings.errors_query,
+            wrap_rows: settings.wrap_rows,
+            vim_mode: settings.vim_mode,
+            detail_emphasis: true,
+            save_prompt: None,
+            history: QueryHistory::load(),
+            history_index: None,
             copy_btn_pressed: false,
             copy_btn_deadline: None,
             last_run_query_range: None,
@@ -95,27 +279,311 @@ impl AppState {
             show_help: false,
             help_vscroll: 0,
             topics: Vec::new(),
+            info_selected: 0,
             autocomplete: None,
             topics_last_fetched_at: None,
             autocomplete_frozen_token: None,
             autocomplete_dirty: false,
+            redraw: RedrawTracker::default(),
+        };
+        app.refresh_env_bar();
+        app
+    }
+
+    pub fn reset_rate(&mut self) {
+        self.tab.rate_samples.clear();
+        self.tab.last_scanned = 0;
+        self.tab.read_progress = None;
+    }
+
+    /// Record a progress heartbeat as the number scanned since the previous one.
+    pub fn record_progress(&mut self, scanned: u64) {
+        if self.tab.rate_samples.len() == RATE_SAMPLES {
+            self.tab.rate_samples.pop_front();
         }
+        self.tab
+            .rate_samples
+            .push_back(scanned.saturating_sub(self.tab.last_scanned));
+        self.tab.last_scanned = scanned;
     }
 
     pub fn clear_rows(&mut self) {
-        self.rows.clear();
+        self.tab.rows.clear();
+        self.tab.row_arrivals.clear();
+        self.tab.rows_seen = 0;
+        self.set_row_filter(String::new());
+        self.tab.filter_prompt = false;
+    }
+
+    /// Filter the loaded rows to those with a cell containing `filter`.
+    pub fn set_row_filter(&mut self, filter: String) {
+        self.tab.row_filter = filter;
+        self.refresh_row_filter();
+        self.clamp_selection();
+    }
+
+    fn refresh_row_filter(&mut self) {
+        self.tab.filtered_rows = (!self.tab.row_filter.is_empty()).then(|| {
+            let needle = self.tab.row_filter.to_lowercase();
+            let columns = self.filter_columns();
+            (0..self.tab.rows.len())
+                .filter(|&i| row_matches_filter(&self.tab.rows[i], &columns, &needle))
+                .collect()
+        });
+    }
+
+    /// The columns the `/` filter searches: those on screen.
+    fn filter_columns(&self) -> Vec<SelectItem> {
+        self.shown_columns()
+            .into_iter()
+            .map(|c| self.tab.selected_columns[c])
+            .collect()
     }
 
-    pub fn push_rows(&mut self, mut batch: Vec<MessageEnvelope>) {
-        // Keep memory bounded
-        if self.rows.len() + batch.len() > self.max_rows_in_memory {
-            let overflow = self.rows.len() + batch.len() - self.max_rows_in_memory;
-            let drop_n = overflow.min(self.rows.len());
-            if drop_n > 0 {
-                self.rows.drain(0..drop_n);
+    /// Bring `filtered_rows` up to date after a batch without re-checking the
+    /// rows it already covers: `dropped` rows left the front, rows from
+    /// `appended` on are new and the `replaced` slots hold other messages.
+    fn filter_batch(&mut self, dropped: usize, appended: usize, replaced: &[usize]) {
+        if self.tab.filtered_rows.is_none() {
+            return;
+        }
+        let needle = self.tab.row_filter.to_lowercase();
+        let columns = self.filter_columns();
+        let rows = &self.tab.rows;
+        let Some(shown) = self.tab.filtered_rows.as_mut() else {
+            return;
+        };
+        if dropped > 0 {
+            shown.retain(|&i| i >= dropped);
+            shown.iter_mut().for_each(|i| *i -= dropped);
+        }
+        for &slot in replaced.iter().filter(|&&slot| slot < appended) {
+            let matches = row_matches_filter(&rows[slot], &columns, &needle);
+            match shown.binary_search(&slot) {
+                Err(at) if matches => shown.insert(at, slot),
+                Ok(at) if !matches => {
+                    shown.remove(at);
+                }
+                _ => {}
             }
         }
-        self.rows.append(&mut batch);
+        shown.extend(
+            (appended..rows.len()).filter(|&i| row_matches_filter(&rows[i], &columns, &needle)),
+        );
+    }
+
+    /// Rows the results table shows: all of them, or those passing `row_filter`.
+    pub fn shown_len(&self) -> usize {
+        self.tab
+            .filtered_rows
+            .as_ref()
+            .map_or(self.tab.rows.len(), Vec::len)
+    }
+
+    /// The `i`th shown row and its index in `rows`.
+    pub fn shown_row(&self, i: usize) -> Option<(usize, &MessageEnvelope)> {
+        let idx = match &self.tab.filtered_rows {
+            Some(shown) => *shown.get(i)?,
+            None => i,
+        };
+        Some((idx, self.tab.rows.get(idx)?))
+    }
+
+    /// The row under the results cursor, a message or a `COUNT(*)` total.
+    pub fn selected_envelope(&self) -> Option<&MessageEnvelope> {
+        let last = self.shown_len().checked_sub(1)?;
+        self.shown_row(self.tab.selected_row.min(last))
+            .map(|(_, env)| env)
+    }
+
+    /// The message under the results cursor; `None` on a `COUNT(*)` total,
+    /// which has no partition, offset or headers.
+    pub fn selected_message(&self) -> Option<&MessageEnvelope> {
+        self.selected_envelope().filter(|env| env.count.is_none())
+    }
+
+    /// Add a run's batch. When following and the newest row was selected,
+    /// the selection moves to the new newest row; a user who scrolled up stays put.
+    pub fn push_batch(&mut self, batch: Vec<MessageEnvelope>) {
+        let at_newest = self.tab.selected_row + 1 >= self.shown_len();
+        self.push_rows(batch);
+        self.clamp_selection();
+        if self.tab.follow && at_newest {
+            self.tab.selected_row = self.shown_len().saturating_sub(1);
+        }
+    }
+
+    /// Append a batch, keeping at most `max_rows_in_memory` rows as chosen by
+    /// `row_eviction`.
+    pub fn push_rows(&mut self, batch: Vec<MessageEnvelope>) {
+        self.push_rows_at(batch, Instant::now());
+    }
+
+    /// `push_rows` with the batch's arrival time.
+    pub fn push_rows_at(&mut self, mut batch: Vec<MessageEnvelope>, now: Instant) {
+        let cap = self.max_rows_in_memory;
+        let offered = batch.len() as u64;
+        let mut dropped = 0;
+        let mut replaced = Vec::new();
+        let appended;
+        match self.row_eviction {
+            RowEviction::DropOldest => {
+                if self.tab.rows.len() + batch.len() > cap {
+                    let overflow = self.tab.rows.len() + batch.len() - cap;
+                    dropped = overflow.min(self.tab.rows.len());
+                    if dropped > 0 {
+                        self.tab.rows.drain(0..dropped);
+                        self.tab.row_arrivals.drain(0..dropped);
+                    }
+                }
+                self.tab
+                    .row_arrivals
+                    .extend(std::iter::repeat_n(now, batch.len()));
+                appended = self.tab.rows.len();
+                self.tab.rows.append(&mut batch);
+            }
+            RowEviction::StopAtCap => {
+                let room = cap.saturating_sub(self.tab.rows.len());
+                batch.truncate(room);
+                self.tab
+                    .row_arrivals
+                    .extend(std::iter::repeat_n(now, batch.len()));
+                appended = self.tab.rows.len();
+                self.tab.rows.append(&mut batch);
+            }
+            RowEviction::ReservoirSample => {
+                appended = self.tab.rows.len();
+                // Algorithm R: row n (0-based) replaces a random slot with probability cap/(n+1)
+                for (i, env) in batch.into_iter().enumerate() {
+                    if self.tab.rows.len() < cap {
+                        self.tab.rows.push(env);
+                        self.tab.row_arrivals.push(now);
+                    } else {
+                        let n = self.tab.rows_seen + i as u64;
+                        let slot = self.next_random() % (n + 1);
+                        if let Some(row) = self.tab.rows.get_mut(slot as usize) {
+                            *row = env;
+                            self.tab.row_arrivals[slot as usize] = now;
+                            replaced.push(slot as usize);
+                        }
+                    }
+                }
+            }
+        }
+        self.tab.rows_seen += offered;
+        self.filter_batch(dropped, appended, &replaced);
+    }
+
+    /// How long ago row `idx` arrived, if it is still within the highlight window.
+    pub fn new_row_age(&self, idx: usize, now: Instant) -> Option<Duration> {
+        let age = now.saturating_duration_since(*self.tab.row_arrivals.get(idx)?);
+        (age < NEW_ROW_HIGHLIGHT).then_some(age)
+    }
+
+    /// Some row is still fading, so the table must keep redrawing.
+    pub fn highlight_active(&self, now: Instant) -> bool {
+        self.tab
+            .row_arrivals
+            .iter()
+            .max()
+            .is_some_and(|&t| now.saturating_duration_since(t) < NEW_ROW_HIGHLIGHT)
+    }
+
+    fn next_random(&mut self) -> u64 {
+        if self.tab.sample_rng == 0 {
+            self.tab.sample_rng = uuid::Uuid::new_v4().as_u64_pair().0 | 1;
+        }
+        let mut x = self.tab.sample_rng;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.tab.sample_rng = x;
+        x
+    }
+}
+
+/// A selection move in the results pane (see `AppState::move_results`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultsMotion {
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+}
+
+/// Rows PageUp/PageDown (and Ctrl-U/Ctrl-D in vim mode) move by.
+pub const RESULTS_PAGE: usize = 10;
+
+/// Row selected after moving one step up or down from `current` in a list of
+/// `total` rows. Without `wrap` the selection stops at either end.
+pub fn step_row(current: usize, total: usize, down: bool, wrap: bool) -> usize {
+    if total == 0 {
+        return 0;
+    }
+    match (down, current + 1 >= total, current == 0) {
+        (true, true, _) if wrap => 0,
+        (true, true, _) => current,
+        (true, false, _) => current + 1,
+        (false, _, true) if wrap => total - 1,
+        (false, _, true) => 0,
+        (false, _, false) => current - 1,
+    }
+}
+
+/// Index of the first of `rows` with an offset of at least `offset`, only
+/// counting rows from `partition` when one is given.
+pub fn first_row_at_offset<'a>(
+    rows: impl IntoIterator<Item = &'a MessageEnvelope>,
+    partition: Option<i32>,
+    offset: i64,
+) -> Option<usize> {
+    rows.into_iter()
+        .position(|env| env.offset >= offset && partition.is_none_or(|p| env.partition == p))
+}
+
+/// `--terminal-title` text for a run that has delivered `count` messages.
+pub fn terminal_title(count: u64, topic: Option<&str>) -> String {
+    let noun = if count == 1 { "message" } else { "messages" };
+    match topic {
+        Some(topic) => format!("rkl — {} {} · {}", count, noun, topic),
+        None => format!("rkl — {} {}", count, noun),
+    }
+}
+
+/// Completion stats for a run, shown in the scan summary overlay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanSummary {
+    pub scanned: u64,
+    pub matched: u64,
+    /// Rows that reached the results table (after LIMIT / GROUP BY).
+    pub returned: u64,
+    pub partitions: usize,
+    pub elapsed: Duration,
+    pub limit: Option<usize>,
+    /// The merger stopped at `limit` rather than running out of input.
+    pub limit_hit: bool,
+}
+
+impl ScanSummary {
+    /// `(label, value)` rows for the overlay.
+    pub fn rows(&self) -> Vec<(&'static str, String)> {
+        let limit = match (self.limit, self.limit_hit) {
+            (Some(n), true) => format!("{} (reached; more rows may match)", n),
+            (Some(n), false) => format!("{} (not reached)", n),
+            (None, _) => "none".to_string(),
+        };
+        vec![
+            ("Scanned", self.scanned.to_string()),
+            ("Matched", self.matched.to_string()),
+            ("Returned", self.returned.to_string()),
+            ("Partitions", self.partitions.to_string()),
+            ("Elapsed", format!("{:.2}s", self.elapsed.as_secs_f64())),
+            ("Limit", limit),
+        ]
     }
 }
 
@@ -125,8 +593,26 @@ pub enum TuiEvent {
         run_id: u64,
         rows: Vec<MessageEnvelope>,
     },
+    Progress {
+        run_id: u64,
+        scanned: u64,
+        matched: u64,
+    },
+    /// Leader broker of each partition being read, as a display line
+    Leaders {
+        run_id: u64,
+        summary: String,
+    },
+    /// How far the readers are through their partitions; sent with a
+    /// progress heartbeat when it moved
+    Read {
+        run_id: u64,
+        progress: ReadProgress,
+    },
     Done {
         run_id: u64,
+        summary: ScanSummary,
+        skew_warning: Option<String>,
     },
     Error {
         run_id: u64,
@@ -147,6 +633,8 @@ pub enum Focus {
     Host,
     Query,
     Results,
+    /// The detail pane beside the results table (Messages mode only).
+    Detail,
 }
 
 impl AppState {
@@ -154,9 +642,33 @@ impl AppState {
         self.focus = match self.focus {
             Focus::Host => Focus::Query,
             Focus::Query => Focus::Results,
-            Focus::Results => Focus::Host,
+            Focus::Results if matches!(self.tab.results_mode, ResultsMode::Messages) => {
+                // Start the detail cursor on the top visible line
+                self.tab.detail_cursor = self.tab.json_vscroll as usize;
+                Focus::Detail
+            }
+            Focus::Results | Focus::Detail => Focus::Host,
         };
     }
+
+    /// Fold the detail-pane object or array at `path`, or unfold it.
+    pub fn toggle_fold(&mut self, path: Vec<String>) {
+        if !self.tab.collapsed_paths.remove(&path) {
+            self.tab.collapsed_paths.insert(path);
+        }
+    }
+
+    /// Scroll the detail pane just enough to show `detail_cursor` in a view
+    /// `height` lines tall.
+    pub fn scroll_to_detail_cursor(&mut self, height: usize) {
+        let top = self.tab.json_vscroll as usize;
+        let height = height.max(1);
+        if self.tab.detail_cursor < top {
+            self.tab.json_vscroll = self.tab.detail_cursor as u16;
+        } else if self.tab.detail_cursor >= top + height {
+            self.tab.json_vscroll = (self.tab.detail_cursor + 1 - height) as u16;
+        }
+    }
 }
 
 impl Default for Focus {
@@ -187,41 +699,472 @@ pub struct AutoCompleteState {
     pub token_abs_end: usize,
 }
 
+/// Bounds for a column width set with `+`/`-`.
+pub const COLUMN_WIDTH_RANGE: std::ops::RangeInclusive<u16> = 4..=200;
+
+/// A cell's full text: what the results table shows (before previews), what
+/// the `/` filter searches and what the detail pane and copying use.
+pub fn column_text(env: &MessageEnvelope, col: SelectItem) -> String {
+    match col {
+        SelectItem::Cluster => env.cluster.clone().unwrap_or_default(),
+        SelectItem::Partition => env.partition.to_string(),
+        SelectItem::Offset => env.offset.to_string(),
+        SelectItem::Timestamp => fmt_ts(env.timestamp_ms),
+        SelectItem::Key => env.key.clone(),
+        SelectItem::Value => env.value.as_deref().unwrap_or("null").to_string(),
+        SelectItem::ValueSize | SelectItem::ValueFields => {
+            derived_column_text(col, env.value.as_deref())
+        }
+        SelectItem::Headers => headers_compact(&env.headers),
+        SelectItem::Count => env.count.map(|n| n.to_string()).unwrap_or_default(),
+    }
+}
+
+/// Whether any of `env`'s cells in `columns` contains `needle`, which the
+/// caller has lowercased: the `/` results filter.
+pub fn row_matches_filter(env: &MessageEnvelope, columns: &[SelectItem], needle: &str) -> bool {
+    columns
+        .iter()
+        .any(|&col| column_text(env, col).to_lowercase().contains(needle))
+}
+
+fn fmt_ts(ms: i64) -> String {
+    if ms <= 0 {
+        return "0".to_string();
+    }
+    // Keep short human readable format
+    let secs = ms / 1000;
+    let tm = time::OffsetDateTime::from_unix_timestamp(secs)
+        .unwrap_or_else(|_| time::OffsetDateTime::UNIX_EPOCH);
+    tm.format(&time::format_description::well_known::Rfc3339)
+        .unwrap_or_else(|_| ms.to_string())
+}
+
+/// The table width of `col`: its `+`/`-` width from `widths` if one was set,
+/// else the default. Drawing, scrolling and mouse clicks all size columns here.
+pub fn column_width(widths: &[(SelectItem, u16)], col: SelectItem) -> u16 {
+    match widths.iter().find(|(c, _)| *c == col) {
+        Some(&(_, width)) => width,
+        None => match col {
+            SelectItem::Cluster => 14,
+            SelectItem::Partition => 10,
+            SelectItem::Offset => 12,
+            SelectItem::Timestamp => 26,
+            SelectItem::Key => 30,
+            SelectItem::Value => 30,
+            SelectItem::ValueSize | SelectItem::ValueFields => 12,
+            SelectItem::Headers => 30,
+            SelectItem::Count => 12,
+        },
+    }
+}
+
 impl AppState {
     pub fn clamp_selection(&mut self) {
-        let total_rows = match self.results_mode {
-            ResultsMode::Messages => self.rows.len(),
-            ResultsMode::TopicList => self.topics_with_partitions.len(),
+        let total_rows = match self.tab.results_mode {
+            ResultsMode::Messages => self.shown_len(),
+            ResultsMode::TopicList => self.tab.topics_with_partitions.len(),
         };
         if total_rows == 0 {
-            self.selected_row = 0;
-        } else if self.selected_row >= total_rows {
-            self.selected_row = total_rows.saturating_sub(1);
+            self.tab.selected_row = 0;
+        } else if self.tab.selected_row >= total_rows {
+            self.tab.selected_row = total_rows.saturating_sub(1);
         }
-        let cols = match self.results_mode {
-            ResultsMode::Messages => self.selected_columns.len().max(1),
+        let cols = match self.tab.results_mode {
+            ResultsMode::Messages => self.tab.selected_columns.len().max(1),
             ResultsMode::TopicList => 1,
         };
-        if self.selected_col >= cols {
-            self.selected_col = cols.saturating_sub(1);
+        if self.tab.selected_col >= cols {
+            self.tab.selected_col = cols.saturating_sub(1);
+        }
+        if matches!(self.tab.results_mode, ResultsMode::Messages)
+            && !self.column_visible(self.tab.selected_col)
+        {
+            self.tab.selected_col = self
+                .next_shown_column(self.tab.selected_col, -1)
+                .or_else(|| self.next_shown_column(self.tab.selected_col, 1))
+                .unwrap_or(0);
+        }
+    }
+
+    pub fn column_visible(&self, col: usize) -> bool {
+        self.tab.visible_columns.get(col).copied().unwrap_or(true)
+    }
+
+    /// Indices into `selected_columns` of the columns the table draws.
+    pub fn shown_columns(&self) -> Vec<usize> {
+        (0..self.tab.selected_columns.len())
+            .filter(|&c| self.column_visible(c))
+            .collect()
+    }
+
+    /// The nearest shown column left (`delta < 0`) or right of `col`.
+    pub fn next_shown_column(&self, col: usize, delta: isize) -> Option<usize> {
+        if delta < 0 {
+            (0..col.min(self.tab.selected_columns.len()))
+                .rev()
+                .find(|&c| self.column_visible(c))
+        } else {
+            (col + 1..self.tab.selected_columns.len()).find(|&c| self.column_visible(c))
+        }
+    }
+
+    /// Move the selection to the next shown column, skipping hidden ones.
+    pub fn step_column(&mut self, delta: isize) {
+        if let Some(col) = self.next_shown_column(self.tab.selected_col, delta) {
+            self.tab.selected_col = col;
+        }
+        self.tab.json_vscroll = 0;
+    }
+
+    /// Hide or show column `col` from the `c` overlay. The last shown column
+    /// stays, so the table never goes empty.
+    pub fn toggle_column_visible(&mut self, col: usize) {
+        let len = self.tab.selected_columns.len();
+        if col >= len {
+            return;
+        }
+        if self.column_visible(col) && self.shown_columns().len() == 1 {
+            self.status = "At least one column stays shown".to_string();
+            return;
+        }
+        self.tab.visible_columns.resize(len, true);
+        self.tab.visible_columns[col] = !self.tab.visible_columns[col];
+        // The filter matches the cells on screen
+        self.refresh_row_filter();
+        self.clamp_selection();
+    }
+
+    /// Flip pretty/compact JSON in the detail pane; returns the settings to persist.
+    pub fn toggle_detail_pretty(&mut self) -> UiSettings {
+        self.detail_pretty = !self.detail_pretty;
+        self.tab.json_vscroll = 0;
+        self.ui_settings()
+    }
+
+    pub fn ui_settings(&self) -> UiSettings {
+        UiSettings {
+            detail_pretty: self.detail_pretty,
+            panes: self.panes,
+            row_eviction: self.row_eviction,
+            errors_query: self.errors_query.clone(),
+            wrap_rows: self.wrap_rows,
+            vim_mode: self.vim_mode,
+        }
+    }
+
+    /// Move the results selection; arrows, paging keys and vim keys all land
+    /// here. Rows honour `wrap_rows` for single steps only.
+    pub fn move_results(&mut self, motion: ResultsMotion) {
+        let messages = matches!(self.tab.results_mode, ResultsMode::Messages);
+        let total = if messages {
+            self.shown_len()
+        } else {
+            self.tab.topics_with_partitions.len()
+        };
+        let last = total.saturating_sub(1);
+        let row = match motion {
+            ResultsMotion::Up => step_row(self.tab.selected_row, total, false, self.wrap_rows),
+            ResultsMotion::Down => step_row(self.tab.selected_row, total, true, self.wrap_rows),
+            ResultsMotion::PageUp => self.tab.selected_row.saturating_sub(RESULTS_PAGE),
+            ResultsMotion::PageDown => self.tab.selected_row.saturating_add(RESULTS_PAGE).min(last),
+            ResultsMotion::Top => 0,
+            ResultsMotion::Bottom => last,
+            ResultsMotion::Left | ResultsMotion::Right => {
+                if messages {
+                    self.step_column(if motion == ResultsMotion::Left { -1 } else { 1 });
+                }
+                return;
+            }
+        };
+        if row != self.tab.selected_row {
+            self.tab.selected_row = row;
+            if messages {
+                self.tab.json_vscroll = 0;
+            }
         }
     }
 
+    /// Index among the shown rows of the first one from `partition`.
+    pub fn first_row_of_partition(&self, partition: i32) -> Option<usize> {
+        (0..self.shown_len()).find(|&i| {
+            self.shown_row(i)
+                .is_some_and(|(_, env)| env.partition == partition)
+        })
+    }
+
+    /// Select the first loaded row of `partition`, or say why not in the status.
+    pub fn jump_to_partition(&mut self, partition: i32) {
+        match self.first_row_of_partition(partition) {
+            Some(idx) => {
+                self.tab.selected_row = idx;
+                self.tab.json_vscroll = 0;
+                self.status = format!("Partition {}: row {}", partition, idx + 1);
+            }
+            None => self.status = format!("No rows from partition {} are loaded", partition),
+        }
+    }
+
+    /// Open an empty tab after the others and make it active.
+    pub fn open_tab(&mut self) {
+        if self.tabs.is_empty() {
+            self.tabs.push(Tab::default());
+        }
+        self.tabs[self.active_tab] = std::mem::replace(&mut self.tab, Tab::new());
+        self.active_tab = self.tabs.len();
+        self.tabs.push(Tab::default());
+        self.focus = Focus::Query;
+    }
+
+    /// Activate the tab `delta` places after the active one, wrapping around.
+    pub fn switch_tab(&mut self, delta: isize) {
+        let count = self.tabs.len();
+        if count < 2 {
+            return;
+        }
+        let target = (self.active_tab as isize + delta).rem_euclid(count as isize) as usize;
+        let parked = std::mem::take(&mut self.tabs[target]);
+        self.tabs[self.active_tab] = std::mem::replace(&mut self.tab, parked);
+        self.active_tab = target;
+        if self.focus == Focus::Detail && self.tab.results_mode != ResultsMode::Messages {
+            self.focus = Focus::Results;
+        }
+    }
+
+    /// Index of the tab that owns `run_id`, active or not.
+    pub fn tab_of_run(&self, run_id: u64) -> Option<usize> {
+        if self.tab.current_run == Some(run_id) {
+            return Some(self.active_tab);
+        }
+        self.tabs
+            .iter()
+            .position(|tab| tab.current_run == Some(run_id))
+    }
+
+    /// Run `f` on the tab that owns `run_id`, swapping a background tab in for
+    /// the call. `None` when no tab owns the run (it was replaced).
+    pub fn with_run_tab<R>(
+        &mut self,
+        run_id: u64,
+        f: impl FnOnce(&mut AppState) -> R,
+    ) -> Option<R> {
+        let idx = self.tab_of_run(run_id)?;
+        if idx == self.active_tab {
+            return Some(f(self));
+        }
+        std::mem::swap(&mut self.tab, &mut self.tabs[idx]);
+        let out = f(self);
+        std::mem::swap(&mut self.tab, &mut self.tabs[idx]);
+        Some(out)
+    }
+
+    /// Append a batch to the tab whose run produced it; false if none does.
+    pub fn route_batch(&mut self, run_id: u64, batch: Vec<MessageEnvelope>) -> bool {
+        self.with_run_tab(run_id, |app| app.push_batch(batch))
+            .is_some()
+    }
+
+    /// Select the first shown row at or past `offset` in the selected row's
+    /// partition, or say why not in the status.
+    pub fn jump_to_offset(&mut self, offset: i64) {
+        let Some(partition) = self.selected_message().map(|env| env.partition) else {
+            self.status = match self.selected_envelope() {
+                Some(_) => "A COUNT(*) result has no offsets".to_string(),
+                None => "No rows are loaded".to_string(),
+            };
+            return;
+        };
+        let shown = (0..self.shown_len()).map_while(|i| self.shown_row(i).map(|(_, env)| env));
+        match first_row_at_offset(shown, Some(partition), offset) {
+            Some(idx) => {
+                self.tab.selected_row = idx;
+                self.tab.json_vscroll = 0;
+                self.status = format!("Partition {} offset {}: row {}", partition, offset, idx + 1);
+            }
+            None => {
+                self.status = format!(
+                    "No loaded rows from partition {} at offset {} or later",
+                    partition, offset
+                )
+            }
+        }
+    }
+
+    /// Move the selected results column one place left (`delta < 0`) or right,
+    /// keeping it selected. Display-only: the query text is untouched.
+    pub fn move_selected_column(&mut self, delta: isize) {
+        let len = self.tab.selected_columns.len();
+        if len < 2 || self.tab.selected_col >= len {
+            return;
+        }
+        // Hop over hidden columns so the move is visible
+        let Some(target) = self.next_shown_column(self.tab.selected_col, delta) else {
+            return;
+        };
+        self.tab
+            .selected_columns
+            .swap(self.tab.selected_col, target);
+        self.tab.visible_columns.resize(len, true);
+        self.tab.visible_columns.swap(self.tab.selected_col, target);
+        self.tab.selected_col = target;
+    }
+
+    /// Widen (`delta > 0`) or narrow the selected column, within
+    /// `COLUMN_WIDTH_RANGE`. Session only; nothing is saved.
+    pub fn adjust_column_width(&mut self, delta: i16) {
+        let Some(&col) = self.tab.selected_columns.get(self.tab.selected_col) else {
+            return;
+        };
+        let width = column_width(&self.tab.column_widths, col)
+            .saturating_add_signed(delta)
+            .clamp(*COLUMN_WIDTH_RANGE.start(), *COLUMN_WIDTH_RANGE.end());
+        match self.tab.column_widths.iter_mut().find(|(c, _)| *c == col) {
+            Some(entry) => entry.1 = width,
+            None => self.tab.column_widths.push((col, width)),
+        }
+        self.status = format!("Column width {}", width);
+    }
+
+    /// Show only the selected column (after Key), or restore the full column
+    /// set when one is already isolated. Display-only like the reorder.
+    pub fn toggle_isolate_column(&mut self) {
+        if let Some((columns, visible, col)) = self.tab.isolated_from.take() {
+            self.tab.selected_columns = columns;
+            self.tab.visible_columns = visible;
+            self.tab.selected_col = col;
+            self.status = "Showing all columns".to_string();
+            self.refresh_row_filter();
+            self.clamp_selection();
+            return;
+        }
+        let Some(&col) = self.tab.selected_columns.get(self.tab.selected_col) else {
+            return;
+        };
+        let isolated = if col == SelectItem::Key {
+            vec![SelectItem::Key]
+        } else {
+            vec![SelectItem::Key, col]
+        };
+        let previous = std::mem::replace(&mut self.tab.selected_columns, isolated);
+        let visible = std::mem::take(&mut self.tab.visible_columns);
+        self.tab.isolated_from = Some((previous, visible, self.tab.selected_col));
+        self.tab.selected_col = self.tab.selected_columns.len() - 1;
+        self.status = "Showing one column; press i again for all".to_string();
+        // The filter matches the cells on screen
+        self.refresh_row_filter();
+        self.clamp_selection();
+    }
+
+    /// Append a starter `SELECT` for `topic` to the editor and jump Home with
+    /// the cursor inside it, ready for Ctrl-Enter. Existing queries are kept.
+    pub fn open_topic_query(&mut self, topic: &str) {
+        self.append_query(&format!("SELECT key, value FROM {} LIMIT 100", topic));
+        self.status = format!("Query for '{}' ready; press Ctrl-Enter to run", topic);
+    }
+
+    /// Append the errors-only preset for `topic` like `open_topic_query`; the
+    /// caller runs it straight away.
+    pub fn open_errors_query(&mut self, topic: &str) {
+        let query = errors_only_query(&self.errors_query, topic);
+        self.append_query(query.trim_end_matches(';'));
+    }
+
+    fn append_query(&mut self, query: &str) {
+        let query = format!("{};", query);
+        let kept = self.input.trim_end();
+        let mut input = String::with_capacity(kept.len() + query.len() + 2);
+        if !kept.is_empty() {
+            input.push_str(kept);
+            if !kept.ends_with(';') {
+                input.push(';');
+            }
+            input.push('\n');
+        }
+        input.push_str(&query);
+        self.input_cursor = input.len() - 1; // before the ';'
+        self.input = input;
+        self.screen = Screen::Home;
+        self.focus = Focus::Query;
+        self.autocomplete = None;
+    }
+
+    /// Remember a query that parsed and ran, and stop browsing history.
+    pub fn record_history(&mut self, query: &str) {
+        self.history.push(query);
+        self.history_index = None;
+        let _ = self.history.save();
+    }
+
+    /// Up/Down browse history while the editor is empty or still shows the
+    /// entry they last put there; otherwise they move the cursor.
+    pub fn browsing_history(&self) -> bool {
+        self.input.is_empty()
+            || self
+                .history_index
+                .and_then(|i| self.history.get(i))
+                .is_some_and(|q| q == self.input)
+    }
+
+    /// Replace the editor with the next older (Up) or newer (Down) entry;
+    /// stepping past the newest leaves an empty editor.
+    pub fn step_history(&mut self, older: bool) {
+        let from = self.history_index.filter(|_| !self.input.is_empty());
+        let len = self.history.len();
+        self.history_index = if older {
+            history::older(from, len)
+        } else {
+            history::newer(from, len)
+        };
+        self.input = self
+            .history_index
+            .and_then(|i| self.history.get(i))
+            .unwrap_or_default()
+            .to_string();
+        self.input_cursor = self.input.len();
+        self.autocomplete = None;
+    }
+
     pub fn selected_env(&self) -> Option<&Environment> {
         self.env_store
             .selected
             .and_then(|i| self.env_store.envs.get(i))
     }
     pub fn current_ssl_config(&self) -> Option<SslConfig> {
-        self.selected_env().map(|e| {
-            // Ensure we pass actual newlines to librdkafka
-            let decode = |s: &Option<String>| s.as_ref().map(|v| v.replace("\\n", "\n"));
-            SslConfig {
-                ca_pem: decode(&e.ssl_ca_pem),
-                cert_pem: decode(&e.public_key_pem),
-                key_pem: decode(&e.private_key_pem),
+        self.selected_env().map(Environment::ssl_config)
+    }
+
+    pub fn current_sasl_config(&self) -> Option<SaslConfig> {
+        self.selected_env().and_then(|env| env.sasl.clone())
+    }
+
+    /// `env_name @ host` for the selected environment (just the host when it
+    /// has no name or none is selected), as copied by F5 on the Host bar.
+    pub fn connection_string(&self) -> String {
+        match self.selected_env() {
+            Some(env) if !env.name.trim().is_empty() => format!("{} @ {}", env.name, env.host),
+            Some(env) => env.host.clone(),
+            None => self.host.clone(),
+        }
+    }
+
+    /// Rebuild `env_bar` once an environment is selected, saved or removed,
+    /// so drawing does not parse its hosts every frame.
+    pub fn refresh_env_bar(&mut self) {
+        let (name, host) = match self.selected_env() {
+            Some(env) => (env.name.as_str(), env.host.as_str()),
+            None => ("(none)", ""),
+        };
+        self.env_bar = match crate::models::parse_brokers(host) {
+            Ok(brokers) if brokers.len() > 1 => {
+                format!(
+                    "{name}  —  {} brokers: {}",
+                    brokers.len(),
+                    brokers.join(", ")
+                )
             }
-        })
+            Ok(_) => format!("{name}  —  host: {host}"),
+            Err(e) => format!("{name}  —  host: {host}  ({e})"),
+        };
     }
 }
 
@@ -233,6 +1176,8 @@ pub struct EnvEditor {
     pub host: String,
     pub host_cursor: usize,
     pub ta_private: TextArea<'static>,
+    pub key_password: String,
+    pub key_password_cursor: usize,
     pub ta_public: TextArea<'static>,
     pub ta_ca: TextArea<'static>,
     #[allow(dead_code)]
@@ -245,6 +1190,7 @@ pub enum EnvFieldFocus {
     Name,
     Host,
     PrivateKey,
+    KeyPassword,
     PublicKey,
     Ca,
     Conn,
@@ -270,3 +1216,618 @@ impl Default for Screen {
         Screen::Home
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn following_keeps_the_newest_row_selected_unless_scrolled_up() {
+        let env = |offset: i64| MessageEnvelope::at(offset).with_timestamp(offset);
+        let mut app = AppState {
+            tab: Tab {
+                follow: true,
+                ..Default::default()
+            },
+            max_rows_in_memory: 100,
+            ..Default::default()
+        };
+        app.push_batch(vec![env(0), env(1)]);
+        assert_eq!(app.tab.selected_row, 1);
+        app.push_batch(vec![env(2)]);
+        assert_eq!(app.tab.selected_row, 2);
+        // Scrolled up: the selection stays where the user left it
+        app.tab.selected_row = 0;
+        app.push_batch(vec![env(3)]);
+        assert_eq!(app.tab.selected_row, 0);
+
+        app.tab.follow = false;
+        app.tab.selected_row = 3;
+        app.push_batch(vec![env(4)]);
+        assert_eq!(app.tab.selected_row, 3);
+    }
+
+    #[test]
+    fn up_down_browse_history_from_an_empty_editor() {
+        let mut app = AppState::default();
+        app.history.push("SELECT key FROM a");
+        app.history.push("SELECT key FROM b");
+        assert!(app.browsing_history());
+        app.step_history(true);
+        assert_eq!(app.input, "SELECT key FROM b");
+        assert_eq!(app.input_cursor, app.input.len());
+        app.step_history(true);
+        app.step_history(true);
+        assert_eq!(app.input, "SELECT key FROM a");
+        app.step_history(false);
+        assert_eq!(app.input, "SELECT key FROM b");
+        // Past the newest entry the editor is empty again
+        app.step_history(false);
+        assert_eq!((app.input.as_str(), app.history_index), ("", None));
+
+        // Once the shown entry is edited, arrows move the cursor instead
+        app.step_history(true);
+        app.input.push_str(" LIMIT 5");
+        assert!(!app.browsing_history());
+    }
+
+    #[test]
+    fn connection_string_names_the_selected_env() {
+        let mut app = AppState {
+            host: "fallback:9092".to_string(),
+            ..Default::default()
+        };
+        let named = |name: &str| Environment {
+            name: name.to_string(),
+            host: "b1:9092,b2:9092".to_string(),
+            ..Environment::default()
+        };
+        app.env_store.envs = vec![named("prod"), named("  ")];
+        app.env_store.selected = Some(0);
+        assert_eq!(app.connection_string(), "prod @ b1:9092,b2:9092");
+        app.env_store.selected = Some(1);
+        assert_eq!(app.connection_string(), "b1:9092,b2:9092");
+        app.env_store.selected = None;
+        assert_eq!(app.connection_string(), "fallback:9092");
+
+        app.env_store.selected = Some(0);
+        app.refresh_env_bar();
+        assert_eq!(app.env_bar, "prod  —  2 brokers: b1:9092, b2:9092");
+        app.env_store.envs[0].host = "bad host".to_string();
+        app.refresh_env_bar();
+        assert!(app.env_bar.starts_with("prod  —  host: bad host  ("));
+        app.env_store.selected = None;
+        app.refresh_env_bar();
+        assert!(
+            app.env_bar
+                .starts_with("(none)  —  host:   (no broker given")
+        );
+    }
+
+    #[test]
+    fn redraw_only_when_dirty_and_poll_backs_off_when_idle() {
+        let start = Instant::now();
+        let mut redraw = RedrawTracker::new(start);
+        // The first frame is always drawn, then nothing until a change
+        assert!(redraw.take());
+        assert!(!redraw.take());
+        redraw.mark(start);
+        assert!(redraw.take());
+        assert!(!redraw.take());
+
+        assert_eq!(redraw.poll_timeout(start, None), ACTIVE_POLL);
+        let idle = start + IDLE_AFTER;
+        assert_eq!(redraw.poll_timeout(idle, None), IDLE_POLL);
+        // A pending deadline caps the wait
+        let soon = idle + Duration::from_millis(5);
+        assert_eq!(
+            redraw.poll_timeout(idle, Some(soon)),
+            Duration::from_millis(5)
+        );
+        assert_eq!(redraw.poll_timeout(idle, Some(start)), Duration::ZERO);
+        redraw.mark(idle);
+        assert_eq!(redraw.poll_timeout(idle, None), ACTIVE_POLL);
+    }
+
+    #[test]
+    fn terminal_title_shows_count_and_topic() {
+        assert_eq!(
+            terminal_title(0, Some("orders")),
+            "rkl — 0 messages · orders"
+        );
+        assert_eq!(
+            terminal_title(1, Some("orders")),
+            "rkl — 1 message · orders"
+        );
+        assert_eq!(terminal_title(1234, None), "rkl — 1234 messages");
+    }
+
+    #[test]
+    fn scan_summary_rows_report_completion_stats() {
+        let mut summary = ScanSummary {
+            scanned: 5000,
+            matched: 120,
+            returned: 100,
+            partitions: 6,
+            elapsed: Duration::from_millis(1234),
+            limit: Some(100),
+            limit_hit: true,
+        };
+        let rows = summary.rows();
+        let labels: Vec<&str> = rows.iter().map(|(l, _)| *l).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "Scanned",
+                "Matched",
+                "Returned",
+                "Partitions",
+                "Elapsed",
+                "Limit"
+            ]
+        );
+        assert_eq!(rows[0].1, "5000");
+        assert_eq!(rows[3].1, "6");
+        assert_eq!(rows[4].1, "1.23s");
+        assert_eq!(rows[5].1, "100 (reached; more rows may match)");
+
+        summary.limit_hit = false;
+        assert_eq!(summary.rows()[5].1, "100 (not reached)");
+        summary.limit = None;
+        assert_eq!(summary.rows()[5].1, "none");
+    }
+
+    #[test]
+    fn detail_pretty_toggle_is_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        // Missing file: pretty by default
+        assert!(UiSettings::load_from(&path).detail_pretty);
+
+        let mut app = AppState {
+            tab: Tab {
+                json_vscroll: 7,
+                ..Default::default()
+            },
+            detail_pretty: true,
+            ..Default::default()
+        };
+        let settings = app.toggle_detail_pretty();
+        assert!(!app.detail_pretty);
+        assert_eq!(app.tab.json_vscroll, 0);
+        settings.save_to(&path).unwrap();
+
+        assert!(!UiSettings::load_from(&path).detail_pretty);
+        app.toggle_detail_pretty().save_to(&path).unwrap();
+        assert!(UiSettings::load_from(&path).detail_pretty);
+    }
+
+    fn env(partition: i32, offset: i64) -> MessageEnvelope {
+        MessageEnvelope::at(offset)
+            .on_partition(partition)
+            .with_key("k")
+    }
+
+    fn offsets(app: &AppState) -> Vec<i64> {
+        app.tab.rows.iter().map(|env| env.offset).collect()
+    }
+
+    #[test]
+    fn eviction_policies_bound_the_buffer() {
+        let batch = |range: std::ops::Range<i64>| range.map(|o| env(0, o)).collect::<Vec<_>>();
+        let app_with = |row_eviction: RowEviction| AppState {
+            max_rows_in_memory: 5,
+            row_eviction,
+            ..Default::default()
+        };
+
+        let mut oldest = app_with(RowEviction::DropOldest);
+        oldest.push_rows(batch(0..3));
+        oldest.push_rows(batch(3..8));
+        assert_eq!(offsets(&oldest), vec![3, 4, 5, 6, 7]);
+
+        let mut capped = app_with(RowEviction::StopAtCap);
+        capped.push_rows(batch(0..3));
+        capped.push_rows(batch(3..8));
+        capped.push_rows(batch(8..10));
+        assert_eq!(offsets(&capped), vec![0, 1, 2, 3, 4]);
+        assert_eq!(capped.tab.rows_seen, 10);
+
+        let mut sample = AppState {
+            tab: Tab {
+                sample_rng: 42,
+                ..Default::default()
+            },
+            ..app_with(RowEviction::ReservoirSample)
+        };
+        sample.push_rows(batch(0..3));
+        sample.push_rows(batch(3..1000));
+        assert_eq!(sample.tab.rows.len(), 5);
+        assert_eq!(sample.tab.rows_seen, 1000);
+        let mut kept = offsets(&sample);
+        kept.sort_unstable();
+        kept.dedup();
+        assert_eq!(kept.len(), 5);
+        // Later rows get in, not just the first five
+        assert!(kept.iter().any(|&o| o >= 5));
+
+        sample.clear_rows();
+        assert_eq!(sample.tab.rows_seen, 0);
+    }
+
+    #[test]
+    fn arrival_times_follow_evicted_rows() {
+        let start = Instant::now();
+        let later = start + Duration::from_millis(600);
+        let mut app = AppState {
+            max_rows_in_memory: 3,
+            ..Default::default()
+        };
+        app.push_rows_at(vec![env(0, 0), env(0, 1)], start);
+        app.push_rows_at(vec![env(0, 2), env(0, 3)], later);
+        assert_eq!(offsets(&app), vec![1, 2, 3]);
+        assert_eq!(app.tab.row_arrivals, vec![start, later, later]);
+        assert_eq!(app.new_row_age(0, later), Some(Duration::from_millis(600)));
+        assert_eq!(app.new_row_age(2, later), Some(Duration::ZERO));
+        assert!(app.highlight_active(start + NEW_ROW_HIGHLIGHT));
+        let faded = later + NEW_ROW_HIGHLIGHT;
+        assert_eq!(app.new_row_age(2, faded), None);
+        assert!(!app.highlight_active(faded));
+        app.clear_rows();
+        assert!(app.tab.row_arrivals.is_empty());
+    }
+
+    #[test]
+    fn partition_jump_finds_first_loaded_row() {
+        let mut app = AppState {
+            tab: Tab {
+                rows: vec![env(0, 5), env(2, 7), env(0, 6), env(2, 8), env(1, 1)],
+                json_vscroll: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(app.first_row_of_partition(0), Some(0));
+        assert_eq!(app.first_row_of_partition(2), Some(1));
+        assert_eq!(app.first_row_of_partition(1), Some(4));
+        assert_eq!(app.first_row_of_partition(9), None);
+
+        app.jump_to_partition(1);
+        assert_eq!(app.tab.selected_row, 4);
+        assert_eq!(app.tab.json_vscroll, 0);
+        app.jump_to_partition(9);
+        assert_eq!(app.tab.selected_row, 4);
+        assert!(app.status.contains("partition 9"));
+    }
+
+    #[test]
+    fn offset_jump_finds_first_row_at_or_past_offset() {
+        let rows = vec![env(0, 5), env(2, 7), env(0, 9), env(2, 12), env(0, 12)];
+        assert_eq!(first_row_at_offset(&rows, Some(0), 6), Some(2));
+        assert_eq!(first_row_at_offset(&rows, Some(0), 5), Some(0));
+        assert_eq!(first_row_at_offset(&rows, Some(2), 8), Some(3));
+        assert_eq!(first_row_at_offset(&rows, None, 8), Some(2));
+        assert_eq!(first_row_at_offset(&rows, Some(2), 13), None);
+        assert_eq!(first_row_at_offset(&rows, Some(1), 0), None);
+
+        let mut app = AppState {
+            tab: Tab {
+                rows,
+                selected_row: 1,
+                json_vscroll: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        app.jump_to_offset(10);
+        assert_eq!(app.tab.selected_row, 3);
+        assert_eq!(app.tab.json_vscroll, 0);
+        app.jump_to_offset(99);
+        assert_eq!(app.tab.selected_row, 3);
+        assert!(app.status.contains("partition 2 at offset 99"));
+    }
+
+    #[test]
+    fn row_filter_matches_any_rendered_cell_ignoring_case() {
+        let row = |key: &str, value| {
+            MessageEnvelope::at(42)
+                .on_partition(3)
+                .with_key(key)
+                .with_value(value)
+        };
+        let rows = [
+            row("order-1", Some(r#"{"status":"FAILED"}"#)),
+            row("order-2", Some(r#"{"status":"ok"}"#)),
+            row("Refund-7", None),
+        ];
+        let columns = [SelectItem::Partition, SelectItem::Key, SelectItem::Value];
+        let shown = |needle: &str| -> Vec<usize> {
+            (0..rows.len())
+                .filter(|&i| row_matches_filter(&rows[i], &columns, needle))
+                .collect()
+        };
+        assert_eq!(shown("failed"), [0]);
+        assert_eq!(shown("refund"), [2]);
+        assert_eq!(shown("order-"), [0, 1]);
+        // Tombstones render as null; numbers as their text
+        assert_eq!(shown("null"), [2]);
+        assert_eq!(shown("3"), [0, 1, 2]);
+        // Only selected columns count: offset 42 isn't on screen
+        assert!(shown("42").is_empty());
+
+        // The app keeps every row and selects within the filtered ones
+        let mut app = AppState {
+            tab: Tab {
+                rows: rows.to_vec(),
+                selected_columns: columns.to_vec(),
+                selected_row: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        app.set_row_filter("ORDER".to_string());
+        assert_eq!(app.shown_len(), 2);
+        assert_eq!(app.tab.selected_row, 1);
+        assert_eq!(app.selected_message().unwrap().key, "order-2");
+        app.set_row_filter(String::new());
+        assert_eq!((app.shown_len(), app.tab.rows.len()), (3, 3));
+    }
+
+    #[test]
+    fn the_row_filter_checks_only_new_rows_in_step_with_eviction() {
+        for row_eviction in [
+            RowEviction::DropOldest,
+            RowEviction::StopAtCap,
+            RowEviction::ReservoirSample,
+        ] {
+            let mut app = AppState {
+                tab: Tab {
+                    selected_columns: vec![SelectItem::Key],
+                    ..Default::default()
+                },
+                max_rows_in_memory: 5,
+                row_eviction,
+                ..Default::default()
+            };
+            app.set_row_filter("hit".to_string());
+            for batch in 0..4 {
+                let rows = (0..3).map(|i| {
+                    let offset = batch * 3 + i;
+                    let key = if offset % 2 == 0 { "hit" } else { "miss" };
+                    MessageEnvelope::at(offset).with_key(key)
+                });
+                app.push_rows(rows.collect());
+                let kept = app.tab.filtered_rows.clone();
+                app.refresh_row_filter();
+                assert_eq!(
+                    kept, app.tab.filtered_rows,
+                    "{row_eviction:?} batch {batch}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_count_total_is_not_a_message() {
+        let mut app = AppState {
+            tab: Tab {
+                rows: vec![MessageEnvelope {
+                    count: Some(42),
+                    ..MessageEnvelope::at(0)
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(app.selected_envelope().and_then(|env| env.count), Some(42));
+        assert!(app.selected_message().is_none());
+        app.jump_to_offset(0);
+        assert_eq!(app.status, "A COUNT(*) result has no offsets");
+    }
+
+    #[test]
+    fn batches_are_routed_to_the_tab_running_them() {
+        let mut app = AppState {
+            tab: Tab {
+                current_run: Some(1),
+                rows: vec![env(0, 1)],
+                ..Default::default()
+            },
+            max_rows_in_memory: 10,
+            ..Default::default()
+        };
+        app.open_tab();
+        app.tab.current_run = Some(2);
+        app.open_tab();
+        app.tab.current_run = Some(3);
+        assert_eq!(app.active_tab, 2);
+        assert!(app.tab.rows.is_empty());
+
+        assert!(app.route_batch(1, vec![env(0, 2), env(0, 3)]));
+        assert!(app.route_batch(3, vec![env(1, 9)]));
+        assert!(!app.route_batch(7, vec![env(0, 4)]));
+        assert_eq!(app.tab_of_run(2), Some(1));
+        assert_eq!(app.tab_of_run(7), None);
+
+        let offsets = |app: &AppState| app.tab.rows.iter().map(|e| e.offset).collect::<Vec<_>>();
+        assert_eq!(offsets(&app), [9]);
+        app.switch_tab(1);
+        assert_eq!(app.active_tab, 0);
+        assert_eq!(app.tab.current_run, Some(1));
+        assert_eq!(offsets(&app), [1, 2, 3]);
+        app.switch_tab(-1);
+        assert_eq!((app.active_tab, app.tab.current_run), (2, Some(3)));
+        app.switch_tab(-1);
+        assert_eq!((app.active_tab, app.tab.current_run), (1, Some(2)));
+        assert!(app.tab.rows.is_empty());
+    }
+
+    #[test]
+    fn column_widths_and_pins_stay_with_their_tab() {
+        let mut app = AppState {
+            tab: Tab::new(),
+            ..Default::default()
+        };
+        app.tab.selected_col = 3;
+        app.adjust_column_width(10);
+        app.tab.pin_columns = true;
+        app.tab.detail_flat = true;
+        app.tab.collapsed_paths.insert(vec!["a".to_string()]);
+        let widened = column_width(&app.tab.column_widths, SelectItem::Key);
+
+        app.open_tab();
+        assert!(app.tab.column_widths.is_empty());
+        assert!(!app.tab.pin_columns && !app.tab.detail_flat);
+        assert!(app.tab.collapsed_paths.is_empty());
+        app.tab.partition_prompt = Some("1".to_string());
+
+        app.switch_tab(-1);
+        assert_eq!(
+            column_width(&app.tab.column_widths, SelectItem::Key),
+            widened
+        );
+        assert!(app.tab.pin_columns && app.tab.detail_flat);
+        assert_eq!(app.tab.collapsed_paths.len(), 1);
+        assert_eq!(app.tab.partition_prompt, None);
+        app.switch_tab(1);
+        assert_eq!(app.tab.partition_prompt.as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn move_selected_column_reorders_and_follows_selection() {
+        let mut app = AppState {
+            tab: Tab {
+                selected_columns: SelectItem::standard(true),
+                selected_col: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        app.move_selected_column(1);
+        assert_eq!(
+            app.tab.selected_columns,
+            vec![
+                SelectItem::Partition,
+                SelectItem::Timestamp,
+                SelectItem::Offset,
+                SelectItem::Key,
+                SelectItem::Value,
+            ]
+        );
+        assert_eq!(app.tab.selected_col, 2);
+
+        // Edges are clamped and the column set never changes
+        app.tab.selected_col = 0;
+        app.move_selected_column(-1);
+        app.tab.selected_col = 4;
+        app.move_selected_column(1);
+        let mut sorted = app.tab.selected_columns.clone();
+        sorted.sort_by_key(|c| *c as u8);
+        assert_eq!(sorted, SelectItem::standard(true));
+        assert_eq!(app.tab.selected_columns[0], SelectItem::Partition);
+    }
+
+    #[test]
+    fn isolate_column_shows_it_beside_key_and_restores() {
+        let mut app = AppState {
+            tab: Tab {
+                selected_columns: SelectItem::standard(true),
+                selected_col: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        app.toggle_isolate_column();
+        assert_eq!(
+            app.tab.selected_columns,
+            vec![SelectItem::Key, SelectItem::Value]
+        );
+        assert_eq!(app.tab.selected_col, 1);
+
+        app.toggle_isolate_column();
+        assert_eq!(app.tab.selected_columns, SelectItem::standard(true));
+        assert_eq!(app.tab.selected_col, 4);
+        assert!(app.tab.isolated_from.is_none());
+
+        // Isolating Key itself leaves just Key
+        app.tab.selected_col = 3;
+        app.toggle_isolate_column();
+        assert_eq!(app.tab.selected_columns, vec![SelectItem::Key]);
+        assert_eq!(app.tab.selected_col, 0);
+    }
+
+    #[test]
+    fn hidden_columns_are_skipped_by_the_column_mapping() {
+        let mut app = AppState {
+            tab: Tab {
+                selected_columns: SelectItem::standard(true),
+                selected_col: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        // Hiding the selected Timestamp moves the selection to a shown column
+        app.toggle_column_visible(2);
+        app.toggle_column_visible(1);
+        assert_eq!(app.shown_columns(), vec![0, 3, 4]);
+        assert_eq!(app.tab.selected_col, 0);
+
+        app.step_column(1);
+        assert_eq!(app.tab.selected_col, 3);
+        app.step_column(-1);
+        assert_eq!(app.tab.selected_col, 0);
+        app.step_column(-1);
+        assert_eq!(app.tab.selected_col, 0);
+
+        // Moving a column hops over hidden ones and carries the mask along
+        app.tab.selected_col = 3;
+        app.move_selected_column(-1);
+        assert_eq!(app.tab.selected_columns[0], SelectItem::Key);
+        assert_eq!(app.shown_columns(), vec![0, 3, 4]);
+        assert_eq!(app.tab.selected_col, 0);
+
+        // The last shown column stays
+        app.toggle_column_visible(3);
+        app.toggle_column_visible(4);
+        app.toggle_column_visible(0);
+        assert_eq!(app.shown_columns(), vec![0]);
+    }
+
+    #[test]
+    fn step_row_wraps_only_when_enabled() {
+        assert_eq!(step_row(4, 5, true, false), 4);
+        assert_eq!(step_row(4, 5, true, true), 0);
+        assert_eq!(step_row(0, 5, false, false), 0);
+        assert_eq!(step_row(0, 5, false, true), 4);
+        assert_eq!(step_row(2, 5, true, true), 3);
+        assert_eq!(step_row(2, 5, false, false), 1);
+        assert_eq!(step_row(0, 0, true, true), 0);
+    }
+
+    #[test]
+    fn open_topic_query_composes_select_and_goes_home() {
+        let mut app = AppState {
+            screen: Screen::Info,
+            topics: vec!["orders".to_string(), "payments".to_string()],
+            info_selected: 1,
+            ..AppState::default()
+        };
+        let topic = app.topics[app.info_selected].clone();
+        app.open_topic_query(&topic);
+        assert_eq!(app.input, "SELECT key, value FROM payments LIMIT 100;");
+        assert_eq!(app.screen, Screen::Home);
+        assert_eq!(app.focus, Focus::Query);
+        assert_eq!(
+            &app.input[..app.input_cursor],
+            "SELECT key, value FROM payments LIMIT 100"
+        );
+
+        // Existing editor content is kept as a separate statement
+        app.input = "SELECT key FROM a".to_string();
+        app.open_topic_query("orders");
+        assert_eq!(
+            app.input,
+            "SELECT key FROM a;\nSELECT key, value FROM orders LIMIT 100;"
+        );
+    }
+}
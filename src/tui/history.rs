@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Entries kept in the history file; `push` drops the oldest beyond this.
+pub const MAX_ENTRIES: usize = 500;
+
+/// Queries run from the editor, oldest first, persisted under ~/.rkl.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct QueryHistory {
+    pub queries: Vec<String>,
+}
+
+impl QueryHistory {
+    pub fn load() -> Self {
+        Self::load_from(&history_path())
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<QueryHistory>(&s).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&history_path())
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context("create history dir")?;
+        }
+        let s = serde_json::to_string_pretty(self).context("serialize query history")?;
+        fs::write(path, s).context("write query history file")?;
+        Ok(())
+    }
+
+    /// Append `query` unless it repeats the newest entry, keeping at most
+    /// `MAX_ENTRIES`.
+    pub fn push(&mut self, query: &str) {
+        if self.queries.last().is_some_and(|last| last == query) {
+            return;
+        }
+        self.queries.push(query.to_string());
+        let excess = self.queries.len().saturating_sub(MAX_ENTRIES);
+        self.queries.drain(..excess);
+    }
+
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.queries.get(index).map(String::as_str)
+    }
+}
+
+/// The entry Up shows after `index` (`None` when not browsing): one older,
+/// starting from the newest and stopping at the oldest.
+pub fn older(index: Option<usize>, len: usize) -> Option<usize> {
+    match index {
+        _ if len == 0 => None,
+        None => Some(len - 1),
+        Some(i) => Some(i.min(len - 1).saturating_sub(1)),
+    }
+}
+
+/// The entry Down shows after `index`: one newer, or `None` (back to an
+/// empty editor) past the newest.
+pub fn newer(index: Option<usize>, len: usize) -> Option<usize> {
+    index.map(|i| i + 1).filter(|&i| i < len)
+}
+
+pub fn history_path() -> PathBuf {
+    std::env::var("HOME")
+        .map(|h| PathBuf::from(h).join(".rkl").join("history.json"))
+        .unwrap_or_else(|_| PathBuf::from(".rkl").join("history.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_skips_repeats_and_caps_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+        let mut history = QueryHistory::load_from(&path);
+        history.push("SELECT key FROM a");
+        history.push("SELECT key FROM a");
+        history.push("SELECT key FROM b");
+        history.push("SELECT key FROM a");
+        history.save_to(&path).unwrap();
+        let loaded = QueryHistory::load_from(&path);
+        assert_eq!(
+            loaded.queries,
+            [
+                "SELECT key FROM a",
+                "SELECT key FROM b",
+                "SELECT key FROM a"
+            ]
+        );
+
+        let mut long = QueryHistory::default();
+        for i in 0..MAX_ENTRIES + 3 {
+            long.push(&format!("SELECT key FROM t{}", i));
+        }
+        assert_eq!(long.len(), MAX_ENTRIES);
+        assert_eq!(long.get(0), Some("SELECT key FROM t3"));
+    }
+
+    #[test]
+    fn up_and_down_step_through_entries() {
+        // Up starts at the newest and stops at the oldest
+        assert_eq!(older(None, 3), Some(2));
+        assert_eq!(older(Some(2), 3), Some(1));
+        assert_eq!(older(Some(0), 3), Some(0));
+        assert_eq!(older(None, 0), None);
+        // Down walks back and leaves history past the newest
+        assert_eq!(newer(Some(1), 3), Some(2));
+        assert_eq!(newer(Some(2), 3), None);
+        assert_eq!(newer(None, 3), None);
+        // An index beyond a shrunken history clamps
+        assert_eq!(older(Some(9), 3), Some(1));
+    }
+}
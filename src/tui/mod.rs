@@ -1,7 +1,9 @@
 mod app;
-mod env_store;
+mod history;
 mod query_bounds;
 mod runner;
+mod settings;
+mod theme;
 mod ui;
 
 pub use runner::run;
@@ -1,3 +1,4 @@
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result, anyhow};
@@ -15,11 +16,17 @@ use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use tokio::sync::mpsc;
 
 use crate::args::RunArgs;
-use crate::consumer::spawn_partition_consumer;
-use crate::merger::run_merger;
-use crate::models::{MessageEnvelope, OffsetSpec};
-use crate::output::OutputSink;
+use crate::consumer::{ReaderContext, spawn_partition_consumer};
+use crate::merger::{MergeBuffer, ReaderEvent, run_merger};
+use crate::models::{
+    ClusterSpec, MessageEnvelope, ReadProgress, SaslConfig, ScanProgress, apply_security,
+    leader_summary, partition_leaders, partition_skew_warning,
+};
+use crate::output::{
+    CsvOutput, JsonOutput, OutputFormat, OutputSink, TimeFormat, apply_query_stages,
+};
 use crate::query::{Command, OrderDir, SelectItem, parse_command, parse_query};
+use crate::saved_queries::SavedQueryStore;
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
 use rdkafka::client::ClientContext;
@@ -29,12 +36,14 @@ use rdkafka::consumer::ConsumerContext;
 use rdkafka::consumer::{Consumer, StreamConsumer};
 
 use super::app::{
-    AppState, AutoCompleteState, EnvEditor, EnvFieldFocus, ResultsMode, Screen, TuiEvent,
+    AppState, AutoCompleteState, EnvEditor, EnvFieldFocus, RESULTS_PAGE, ResultsMode,
+    ResultsMotion, ScanSummary, Screen, TuiEvent, column_text, column_width, terminal_title,
 };
-use super::env_store::Environment;
-use super::env_store::config_dir;
 use super::query_bounds::{find_query_range, strip_trailing_semicolon};
 use super::ui::{draw, help_content_line_count};
+use crate::env_store::Environment;
+use crate::env_store::config_dir;
+use crate::env_store::pem_sanity_issue;
 
 const ENV_COPY_LABEL: &str = "[Copy]";
 const ENV_PASTE_LABEL: &str = "[Paste]";
@@ -84,12 +93,17 @@ pub async fn run(args: RunArgs) -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
 
     let (tx_evt, mut rx_evt) = mpsc::unbounded_channel::<TuiEvent>();
-    let mut app = AppState::new(args.query.clone().unwrap_or_default(), args.broker.clone());
+    let mut app = AppState::new(
+        args.query.clone().unwrap_or_default(),
+        args.broker.join(","),
+    );
 
     let mut run_counter: u64 = 0;
-
-    // Initial draw
-    terminal.draw(|f| draw(f, &app))?;
+    // Runs by id; one is aborted once no tab shows it, since a followed run
+    // never ends
+    let mut pipelines: Vec<(u64, tokio::task::JoinHandle<()>)> = Vec::new();
+    // --terminal-title: the title last written
+    let mut shown_title = String::new();
 
     // Main loop
     let res = loop {
@@ -99,37 +113,86 @@ pub async fn run(args: RunArgs) -> Result<()> {
                 if Instant::now() >= deadline {
                     app.copy_btn_pressed = false;
                     app.copy_btn_deadline = None;
+                    app.redraw.mark(Instant::now());
                 }
             } else {
                 app.copy_btn_pressed = false;
+                app.redraw.mark(Instant::now());
             }
         }
 
-        // Draw UI
-        terminal.draw(|f| draw(f, &app))?;
+        // New rows fade out over a few frames
+        if app.highlight_active(Instant::now()) {
+            app.redraw.mark(Instant::now());
+        }
+
+        // Draw UI only when something changed since the last frame
+        if app.redraw.take() {
+            terminal.draw(|f| draw(f, &app))?;
+        }
 
         // Drain any events from pipeline
         while let Ok(ev) = rx_evt.try_recv() {
+            app.redraw.mark(Instant::now());
             match ev {
                 TuiEvent::Batch { run_id, mut rows } => {
-                    if Some(run_id) == app.current_run {
-                        app.push_rows(std::mem::take(&mut rows));
-                        app.clamp_selection();
+                    app.route_batch(run_id, std::mem::take(&mut rows));
+                }
+                TuiEvent::Progress {
+                    run_id,
+                    scanned,
+                    matched,
+                } => {
+                    let shown = Some(run_id) == app.tab.current_run;
+                    app.with_run_tab(run_id, |app| app.record_progress(scanned));
+                    if shown {
+                        app.status =
+                            format!("Running (run {run_id}): scanned {scanned}, matched {matched}");
                     }
                 }
-                TuiEvent::Done { run_id } => {
-                    if Some(run_id) == app.current_run {
-                        app.status = format!("Run {run_id} complete");
+                TuiEvent::Done {
+                    run_id,
+                    summary,
+                    skew_warning,
+                } => {
+                    let shown = Some(run_id) == app.tab.current_run;
+                    if app
+                        .with_run_tab(run_id, |app| app.tab.scan_summary = Some(summary))
+                        .is_some()
+                    {
+                        if shown {
+                            app.status = format!("Run {run_id} complete");
+                        }
                         if !app.status_buffer.is_empty() {
                             app.status_buffer.push('\n');
                         }
                         app.status_buffer
                             .push_str(&format!("✔ Completed run {}", run_id));
+                        if let Some(warning) = skew_warning {
+                            if shown {
+                                app.status = format!("Run {run_id} complete (partition skew)");
+                            }
+                            app.status_buffer.push_str(&format!("\n⚠ {}", warning));
+                        }
+                    }
+                }
+                TuiEvent::Read { run_id, progress } => {
+                    app.with_run_tab(run_id, |app| app.tab.read_progress = Some(progress));
+                }
+                TuiEvent::Leaders { run_id, summary } => {
+                    if app.tab_of_run(run_id).is_some() {
+                        if !app.status_buffer.is_empty() {
+                            app.status_buffer.push('\n');
+                        }
+                        app.status_buffer
+                            .push_str(&format!("Partition leaders: {}", summary));
                     }
                 }
                 TuiEvent::Error { run_id, message } => {
-                    if Some(run_id) == app.current_run {
-                        app.status = format!("Error: {message}");
+                    if app.tab_of_run(run_id).is_some() {
+                        if Some(run_id) == app.tab.current_run {
+                            app.status = format!("Error: {message}");
+                        }
                         if !app.status_buffer.is_empty() {
                             app.status_buffer.push('\n');
                         }
@@ -157,30 +220,44 @@ pub async fn run(args: RunArgs) -> Result<()> {
                 }
                 TuiEvent::Topics(list) => {
                     app.topics = list;
+                    app.info_selected = app.info_selected.min(app.topics.len().saturating_sub(1));
                     if app.autocomplete.is_some() {
                         maybe_update_autocomplete(&mut app, &tx_evt, true);
                     }
                 }
                 TuiEvent::TopicsWithPartitions(list) => {
-                    app.topics_with_partitions = list;
-                    app.selected_row = 0;
-                    if app.topics_with_partitions.len() == 1
-                        && app.topics_with_partitions[0].0.starts_with("Error:")
+                    app.tab.topics_with_partitions = list;
+                    app.tab.selected_row = 0;
+                    if app.tab.topics_with_partitions.len() == 1
+                        && app.tab.topics_with_partitions[0].0.starts_with("Error:")
                     {
-                        app.status = app.topics_with_partitions[0].0.clone();
-                    } else if app.topics_with_partitions.is_empty() {
+                        app.status = app.tab.topics_with_partitions[0].0.clone();
+                    } else if app.tab.topics_with_partitions.is_empty() {
                         app.status = "No topics found".to_string();
                     } else {
-                        app.status = format!("Found {} topics", app.topics_with_partitions.len());
+                        app.status =
+                            format!("Found {} topics", app.tab.topics_with_partitions.len());
                     }
                     app.clamp_selection();
                 }
             }
         }
 
-        // Handle key input (non-blocking poll)
-        if crossterm::event::poll(Duration::from_millis(50))? {
-            match crossterm::event::read()? {
+        if args.terminal_title && app.tab.current_run.is_some() {
+            let title = terminal_title(app.tab.rows_seen, app.tab.run_topic.as_deref());
+            if title != shown_title {
+                execute!(std::io::stdout(), crossterm::terminal::SetTitle(&title)).ok();
+                shown_title = title;
+            }
+        }
+
+        // Handle key input; the wait shortens while events are flowing
+        let now = Instant::now();
+        let timeout = app.redraw.poll_timeout(now, app.copy_btn_deadline);
+        if crossterm::event::poll(timeout)? {
+            let event = crossterm::event::read()?;
+            app.redraw.mark(Instant::now());
+            match event {
                 Event::Key(key) => {
                     // Honor both Press and Repeat so held keys accelerate movement/editing.
                     if !(key.kind == KeyEventKind::Press || key.kind == KeyEventKind::Repeat) {
@@ -189,6 +266,121 @@ pub async fn run(args: RunArgs) -> Result<()> {
                     let KeyEvent {
                         code, modifiers, ..
                     } = key;
+                    if app.tab.scan_summary.is_some() && code == KeyCode::Esc {
+                        app.tab.scan_summary = None;
+                        continue;
+                    }
+                    if app.tab.show_headers {
+                        if matches!(code, KeyCode::Esc | KeyCode::Char('h')) {
+                            app.tab.show_headers = false;
+                        }
+                        continue;
+                    }
+                    if let Some(cursor) = app.tab.column_picker {
+                        let len = app.tab.selected_columns.len();
+                        match code {
+                            KeyCode::Esc | KeyCode::Char('c') => app.tab.column_picker = None,
+                            KeyCode::Up => app.tab.column_picker = Some(cursor.saturating_sub(1)),
+                            KeyCode::Down => {
+                                app.tab.column_picker =
+                                    Some((cursor + 1).min(len.saturating_sub(1)))
+                            }
+                            KeyCode::Char(' ') | KeyCode::Enter => {
+                                app.toggle_column_visible(cursor)
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if let Some(digits) = app.tab.partition_prompt.as_mut() {
+                        match code {
+                            KeyCode::Esc => app.tab.partition_prompt = None,
+                            KeyCode::Enter => {
+                                let parsed = digits.parse::<i32>();
+                                app.tab.partition_prompt = None;
+                                match parsed {
+                                    Ok(p) => app.jump_to_partition(p),
+                                    Err(_) => app.status = "Enter a partition number".to_string(),
+                                }
+                            }
+                            KeyCode::Backspace => {
+                                digits.pop();
+                            }
+                            KeyCode::Char(ch) if ch.is_ascii_digit() => digits.push(ch),
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if let Some(digits) = app.tab.offset_prompt.as_mut() {
+                        match code {
+                            KeyCode::Esc => app.tab.offset_prompt = None,
+                            KeyCode::Enter => {
+                                let parsed = digits.parse::<i64>();
+                                app.tab.offset_prompt = None;
+                                match parsed {
+                                    Ok(offset) => app.jump_to_offset(offset),
+                                    Err(_) => app.status = "Enter an offset".to_string(),
+                                }
+                            }
+                            KeyCode::Backspace => {
+                                digits.pop();
+                            }
+                            KeyCode::Char(ch) if ch.is_ascii_digit() => digits.push(ch),
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if app.tab.filter_prompt {
+                        // Incremental: the table narrows as the filter is typed
+                        let mut filter = app.tab.row_filter.clone();
+                        match code {
+                            KeyCode::Esc => {
+                                app.tab.filter_prompt = false;
+                                filter.clear();
+                            }
+                            KeyCode::Enter => app.tab.filter_prompt = false,
+                            KeyCode::Backspace => {
+                                filter.pop();
+                            }
+                            KeyCode::Char(ch) if !modifiers.contains(KeyModifiers::CONTROL) => {
+                                filter.push(ch);
+                            }
+                            _ => {}
+                        }
+                        if filter != app.tab.row_filter {
+                            app.set_row_filter(filter);
+                            app.tab.json_vscroll = 0;
+                        }
+                        continue;
+                    }
+                    if let Some(path) = app.tab.export_prompt.as_mut() {
+                        match code {
+                            KeyCode::Esc => app.tab.export_prompt = None,
+                            KeyCode::Enter => export_current_rows(&mut app),
+                            KeyCode::Backspace => {
+                                path.pop();
+                            }
+                            KeyCode::Char(ch) if !modifiers.contains(KeyModifiers::CONTROL) => {
+                                path.push(ch);
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if let Some(name) = app.save_prompt.as_mut() {
+                        match code {
+                            KeyCode::Esc => app.save_prompt = None,
+                            KeyCode::Enter => save_current_query(&mut app),
+                            KeyCode::Backspace => {
+                                name.pop();
+                            }
+                            KeyCode::Char(ch) if !modifiers.contains(KeyModifiers::CONTROL) => {
+                                name.push(ch);
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
                     if app.show_help {
                         match code {
                             KeyCode::Esc | KeyCode::F(10) => {
@@ -200,13 +392,104 @@ pub async fn run(args: RunArgs) -> Result<()> {
                             KeyCode::PageDown => scroll_help(&mut app, 10),
                             KeyCode::Home => app.help_vscroll = 0,
                             KeyCode::End => jump_help_to_end(&mut app),
+                            KeyCode::Char('v') => {
+                                app.vim_mode = !app.vim_mode;
+                                if let Err(e) = app.ui_settings().save() {
+                                    app.status = format!("Could not save settings: {e}");
+                                }
+                            }
                             _ => {}
                         }
                         continue;
                     }
+                    if matches!(app.focus, super::app::Focus::Detail)
+                        && matches!(app.screen, Screen::Home)
+                        && !app.show_env_modal
+                        && !has_ctrl_or_alt(modifiers)
+                        && detail_key(&mut app, code)
+                    {
+                        continue;
+                    }
+                    if app.vim_mode
+                        && matches!(app.screen, Screen::Home)
+                        && !app.show_env_modal
+                        && matches!(app.focus, super::app::Focus::Results)
+                        && let Some(motion) = vim_motion(code, modifiers)
+                    {
+                        app.move_results(motion);
+                        continue;
+                    }
                     match (code, modifiers) {
                         (KeyCode::Char('c'), KeyModifiers::CONTROL) => break Ok(()),
                         (KeyCode::Char('q'), KeyModifiers::CONTROL) => break Ok(()),
+                        (KeyCode::Char('s'), m)
+                            if m.contains(KeyModifiers::CONTROL)
+                                && matches!(app.screen, Screen::Home)
+                                && !app.show_env_modal =>
+                        {
+                            app.save_prompt = Some(String::new());
+                        }
+                        // Another result set; earlier tabs keep their rows and runs
+                        (KeyCode::Char('t'), m)
+                            if m.contains(KeyModifiers::CONTROL)
+                                && matches!(app.screen, Screen::Home)
+                                && !app.show_env_modal =>
+                        {
+                            app.open_tab();
+                            app.status = format!(
+                                "Opened tab {}; Ctrl-PageUp/PageDown switch tabs",
+                                app.active_tab + 1
+                            );
+                        }
+                        (KeyCode::PageUp | KeyCode::PageDown, m)
+                            if m.contains(KeyModifiers::CONTROL)
+                                && matches!(app.screen, Screen::Home)
+                                && !app.show_env_modal =>
+                        {
+                            app.switch_tab(if code == KeyCode::PageUp { -1 } else { 1 });
+                        }
+                        // Export the loaded rows, starting from a path under ~/.rkl/exports
+                        (KeyCode::Char('e'), m)
+                            if m.contains(KeyModifiers::CONTROL)
+                                && matches!(app.screen, Screen::Home)
+                                && !app.show_env_modal =>
+                        {
+                            if matches!(app.tab.results_mode, ResultsMode::Messages)
+                                && app.shown_len() > 0
+                            {
+                                app.tab.export_prompt =
+                                    Some(default_export_path(&app).display().to_string());
+                            } else {
+                                app.status = "No rows to export; run a query first".to_string();
+                            }
+                        }
+                        // Follow mode applies from the next run
+                        (KeyCode::Char('f'), m)
+                            if m.contains(KeyModifiers::CONTROL)
+                                && matches!(app.screen, Screen::Home)
+                                && !app.show_env_modal =>
+                        {
+                            app.tab.follow = !app.tab.follow;
+                            app.status = if app.tab.follow {
+                                "Follow on: the next run starts at the end and streams new messages"
+                            } else {
+                                "Follow off: runs scan from --offset and stop at their limit"
+                            }
+                            .to_string();
+                        }
+                        // Ctrl-O in a PEM field: replace the typed file path with the file
+                        (KeyCode::Char('o'), m)
+                            if m.contains(KeyModifiers::CONTROL)
+                                && (matches!(app.screen, Screen::Envs) || app.show_env_modal) =>
+                        {
+                            if let Some(ed) = app.env_editor.as_mut() {
+                                let message = match load_pem_from_path(ed) {
+                                    Ok(message) | Err(message) => message,
+                                };
+                                app.env_test_message = Some(message.clone());
+                                app.status = message;
+                            }
+                        }
                         (KeyCode::F(10), _) => {
                             if app.show_help {
                                 app.show_help = false;
@@ -260,148 +543,20 @@ pub async fn run(args: RunArgs) -> Result<()> {
                         }
                         // Some macOS terminals send Ctrl-Enter as Ctrl-J (LF) or Ctrl-M (CR)
                         // Ctrl-Enter (and common terminal fallbacks) → run
-                        (KeyCode::Char('j'), m) | (KeyCode::Char('m'), m)
+                        (KeyCode::Enter, m) | (KeyCode::Char('j'), m) | (KeyCode::Char('m'), m)
                             if m.contains(KeyModifiers::CONTROL) =>
                         {
                             if matches!(app.screen, Screen::Home)
                                 && !app.show_env_modal
                                 && matches!(app.focus, super::app::Focus::Query)
                             {
-                                let (qs, qe) = find_query_range(&app.input, app.input_cursor);
-                                let raw = &app.input[qs..qe];
-                                let query = strip_trailing_semicolon(raw).trim().to_string();
-                                if query.is_empty() {
-                                    app.status = "Please enter a query".to_string();
-                                    continue;
-                                }
-                                match parse_command(&query) {
-                                    Ok(Command::Select(ast)) => {
-                                        let columns = ast.select.clone();
-                                        app.results_mode = ResultsMode::Messages;
-                                        app.autocomplete = None;
-                                        app.autocomplete_frozen_token = None;
-                                        app.selected_columns = columns;
-                                        app.table_hscroll = 0;
-                                        app.clear_rows();
-                                        app.topics_with_partitions.clear();
-                                        run_counter += 1;
-                                        app.current_run = Some(run_counter);
-                                        app.last_run_query_range = Some((qs, qe));
-                                        let env_host = app
-                                            .selected_env()
-                                            .map(|e| e.host.clone())
-                                            .unwrap_or(app.host.clone());
-                                        app.status = format!(
-                                            "Running (run {}): topic '{}' on {}. Press q to quit.",
-                                            run_counter, ast.from, env_host
-                                        );
-                                        let mut run_args = args.clone();
-                                        run_args.broker = env_host;
-                                        app.clamp_selection();
-                                        let ssl = app.current_ssl_config();
-                                        spawn_pipeline_with_ssl(
-                                            run_args,
-                                            query,
-                                            run_counter,
-                                            tx_evt.clone(),
-                                            ssl,
-                                        )
-                                        .await;
-                                    }
-                                    Ok(Command::ListTopics) => {
-                                        app.results_mode = ResultsMode::TopicList;
-                                        app.autocomplete = None;
-                                        app.autocomplete_frozen_token = None;
-                                        app.table_hscroll = 0;
-                                        app.clear_rows();
-                                        app.topics_with_partitions.clear();
-                                        app.current_run = None;
-                                        app.last_run_query_range = Some((qs, qe));
-                                        app.selected_row = 0;
-                                        app.json_vscroll = 0;
-                                        let env_host = app
-                                            .selected_env()
-                                            .map(|e| e.host.clone())
-                                            .unwrap_or(app.host.clone());
-                                        app.status = format!("Listing topics from {}...", env_host);
-                                        fetch_topics_with_partitions_async(&app, tx_evt.clone());
-                                        app.clamp_selection();
-                                    }
-                                    Err(e) => {
-                                        app.status = format!("Parse error: {}", e);
-                                    }
-                                }
-                            }
-                        }
-                        (KeyCode::Enter, m) if m.contains(KeyModifiers::CONTROL) => {
-                            if matches!(app.screen, Screen::Home)
-                                && !app.show_env_modal
-                                && matches!(app.focus, super::app::Focus::Query)
-                            {
-                                let (qs, qe) = find_query_range(&app.input, app.input_cursor);
-                                let raw = &app.input[qs..qe];
-                                let query = strip_trailing_semicolon(raw).trim().to_string();
-                                if query.is_empty() {
-                                    app.status = "Please enter a query".to_string();
-                                    continue;
-                                }
-                                match parse_command(&query) {
-                                    Ok(Command::Select(ast)) => {
-                                        let columns = ast.select.clone();
-                                        app.results_mode = ResultsMode::Messages;
-                                        app.autocomplete = None;
-                                        app.autocomplete_frozen_token = None;
-                                        app.selected_columns = columns;
-                                        app.table_hscroll = 0;
-                                        app.clear_rows();
-                                        app.topics_with_partitions.clear();
-                                        run_counter += 1;
-                                        app.current_run = Some(run_counter);
-                                        app.last_run_query_range = Some((qs, qe));
-                                        let env_host = app
-                                            .selected_env()
-                                            .map(|e| e.host.clone())
-                                            .unwrap_or(app.host.clone());
-                                        app.status = format!(
-                                            "Running (run {}): topic '{}' on {}. Press q to quit.",
-                                            run_counter, ast.from, env_host
-                                        );
-                                        let mut run_args = args.clone();
-                                        run_args.broker = env_host;
-                                        app.clamp_selection();
-                                        let ssl = app.current_ssl_config();
-                                        spawn_pipeline_with_ssl(
-                                            run_args,
-                                            query,
-                                            run_counter,
-                                            tx_evt.clone(),
-                                            ssl,
-                                        )
-                                        .await;
-                                    }
-                                    Ok(Command::ListTopics) => {
-                                        app.results_mode = ResultsMode::TopicList;
-                                        app.autocomplete = None;
-                                        app.autocomplete_frozen_token = None;
-                                        app.table_hscroll = 0;
-                                        app.clear_rows();
-                                        app.topics_with_partitions.clear();
-                                        app.current_run = None;
-                                        app.last_run_query_range = Some((qs, qe));
-                                        app.selected_row = 0;
-                                        app.json_vscroll = 0;
-                                        let env_host = app
-                                            .selected_env()
-                                            .map(|e| e.host.clone())
-                                            .unwrap_or(app.host.clone());
-                                        app.status = format!("Listing topics from {}...", env_host);
-                                        fetch_topics_with_partitions_async(&app, tx_evt.clone());
-                                        app.clamp_selection();
-                                    }
-                                    Err(e) => {
-                                        app.status = format!("Parse error: {}", e);
-                                    }
-                                }
+                                run_query_at_cursor(
+                                    &mut app,
+                                    &args,
+                                    &mut run_counter,
+                                    &mut pipelines,
+                                    &tx_evt,
+                                );
                             }
                         }
                         // Enter: editor newline; open env screen from host bar
@@ -423,6 +578,23 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                         _ => {}
                                     }
                                 }
+                            } else if matches!(app.screen, Screen::Info) {
+                                if let Some(topic) = app.topics.get(app.info_selected).cloned() {
+                                    app.open_topic_query(&topic);
+                                    ensure_input_cursor_visible(&mut app);
+                                }
+                            } else if matches!(app.focus, super::app::Focus::Results)
+                                && matches!(app.tab.results_mode, ResultsMode::TopicList)
+                            {
+                                if let Some((topic, _)) = app
+                                    .tab
+                                    .topics_with_partitions
+                                    .get(app.tab.selected_row)
+                                    .cloned()
+                                {
+                                    app.open_topic_query(&topic);
+                                    ensure_input_cursor_visible(&mut app);
+                                }
                             } else if matches!(app.focus, super::app::Focus::Host) {
                                 // Open env screen
                                 let (idx, env) = if let Some(env) = app.selected_env() {
@@ -434,8 +606,10 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                             name: String::new(),
                                             host: app.host.clone(),
                                             private_key_pem: None,
+                                            key_password: None,
                                             public_key_pem: None,
                                             ssl_ca_pem: None,
+                                            sasl: None,
                                         },
                                     )
                                 };
@@ -492,6 +666,12 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                                 meta_changed = true;
                                             }
                                         }
+                                        EnvFieldFocus::KeyPassword => {
+                                            if ed.key_password_cursor > 0 {
+                                                ed.key_password.remove(ed.key_password_cursor - 1);
+                                                ed.key_password_cursor -= 1;
+                                            }
+                                        }
                                         EnvFieldFocus::PrivateKey => {
                                             ed.ta_private.input(ta_input_from_key(key));
                                         }
@@ -531,7 +711,7 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                         maybe_update_autocomplete(&mut app, &tx_evt, false);
                                     }
                                 }
-                                super::app::Focus::Results => {}
+                                super::app::Focus::Results | super::app::Focus::Detail => {}
                             }
                         }
                         (KeyCode::Delete, m) => {
@@ -551,6 +731,11 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                                 meta_changed = true;
                                             }
                                         }
+                                        EnvFieldFocus::KeyPassword => {
+                                            if ed.key_password_cursor < ed.key_password.len() {
+                                                ed.key_password.remove(ed.key_password_cursor);
+                                            }
+                                        }
                                         EnvFieldFocus::PrivateKey => {
                                             ed.ta_private.input(ta_input_from_key(key));
                                         }
@@ -592,7 +777,8 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                     ed.field_focus = match ed.field_focus {
                                         EnvFieldFocus::Name => EnvFieldFocus::Host,
                                         EnvFieldFocus::Host => EnvFieldFocus::PrivateKey,
-                                        EnvFieldFocus::PrivateKey => EnvFieldFocus::PublicKey,
+                                        EnvFieldFocus::PrivateKey => EnvFieldFocus::KeyPassword,
+                                        EnvFieldFocus::KeyPassword => EnvFieldFocus::PublicKey,
                                         EnvFieldFocus::PublicKey => EnvFieldFocus::Ca,
                                         EnvFieldFocus::Ca => EnvFieldFocus::Conn,
                                         EnvFieldFocus::Conn => EnvFieldFocus::Buttons,
@@ -613,7 +799,8 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                         EnvFieldFocus::Name => EnvFieldFocus::Buttons,
                                         EnvFieldFocus::Host => EnvFieldFocus::Name,
                                         EnvFieldFocus::PrivateKey => EnvFieldFocus::Host,
-                                        EnvFieldFocus::PublicKey => EnvFieldFocus::PrivateKey,
+                                        EnvFieldFocus::KeyPassword => EnvFieldFocus::PrivateKey,
+                                        EnvFieldFocus::PublicKey => EnvFieldFocus::KeyPassword,
                                         EnvFieldFocus::Ca => EnvFieldFocus::PublicKey,
                                         EnvFieldFocus::Conn => EnvFieldFocus::Ca,
                                         EnvFieldFocus::Buttons => EnvFieldFocus::Conn,
@@ -641,6 +828,19 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                         app.status = "Environment name already exists. Choose a unique name.".to_string();
                                         continue;
                                     }
+                                    if let Err(e) = crate::models::parse_brokers(&ed.host) {
+                                        app.status = format!("Host: {}", e);
+                                        continue;
+                                    }
+                                    let pem_warnings: Vec<String> =
+                                        [("Private key", &pk), ("Certificate", &cert), ("CA", &ca)]
+                                            .into_iter()
+                                            .filter(|(_, pem)| !pem.trim().is_empty())
+                                            .filter_map(|(label, pem)| {
+                                                pem_sanity_issue(pem)
+                                                    .map(|issue| format!("{}: {}", label, issue))
+                                            })
+                                            .collect();
                                     let new_env = Environment {
                                         name: ed.name.clone(),
                                         host: ed.host.clone(),
@@ -649,6 +849,11 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                         } else {
                                             Some(pk)
                                         },
+                                        key_password: if ed.key_password.is_empty() {
+                                            None
+                                        } else {
+                                            Some(ed.key_password.clone())
+                                        },
                                         public_key_pem: if cert.trim().is_empty() {
                                             None
                                         } else {
@@ -659,6 +864,11 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                         } else {
                                             Some(ca)
                                         },
+                                        // Not edited here; keep what the file had
+                                        sasl: ed
+                                            .idx
+                                            .and_then(|i| app.env_store.envs.get(i))
+                                            .and_then(|env| env.sasl.clone()),
                                     };
                                     if let Some(i) = ed.idx {
                                         if i < app.env_store.envs.len() {
@@ -674,13 +884,24 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                         app.env_store.selected = Some(app.env_store.envs.len() - 1);
                                     }
                                     let _ = app.env_store.save();
+                                    app.refresh_env_bar();
                                     if let Some(sel) = app.env_store.selected {
                                         if let Some(e) = app.env_store.envs.get(sel) {
                                             app.host = e.host.clone();
                                         }
                                     }
-                                    if app.show_env_modal {
-                                        app.show_env_modal = false;
+                                    if pem_warnings.is_empty() {
+                                        if app.show_env_modal {
+                                            app.show_env_modal = false;
+                                        }
+                                    } else {
+                                        // Saved anyway; keep the editor open so the warning is visible.
+                                        app.env_test_message = Some(format!(
+                                            "Saved, but some fields don't look like PEM:\n{}",
+                                            pem_warnings.join("\n")
+                                        ));
+                                        app.status =
+                                            "Environment saved with PEM warnings".to_string();
                                     }
                                 }
                             }
@@ -693,11 +914,14 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                     name: name.clone(),
                                     host: String::new(),
                                     private_key_pem: None,
+                                    key_password: None,
                                     public_key_pem: None,
                                     ssl_ca_pem: None,
+                                    sasl: None,
                                 });
                                 let idx = app.env_store.envs.len().saturating_sub(1);
                                 app.env_store.selected = Some(idx);
+                                app.refresh_env_bar();
                                 if let Some(env) = app.env_store.envs.get(idx) {
                                     let mut editor = build_env_editor_from_env(env, Some(idx));
                                     editor.name_cursor = editor.name.len();
@@ -718,6 +942,7 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                             Some((i).min(app.env_store.envs.len() - 1))
                                         };
                                         let _ = app.env_store.save();
+                                        app.refresh_env_bar();
                                         sync_env_editor_to_selection(&mut app);
                                     }
                                 }
@@ -728,6 +953,10 @@ pub async fn run(args: RunArgs) -> Result<()> {
                             if matches!(app.screen, Screen::Envs) || app.show_env_modal {
                                 if let Some(ed) = app.env_editor.as_ref() {
                                     let host = ed.host.clone();
+                                    if let Err(e) = crate::models::parse_brokers(&host) {
+                                        app.env_test_message = Some(format!("Host: {}", e));
+                                        continue;
+                                    }
                                     let pk = ed.ta_private.lines().join("\n");
                                     let cert = ed.ta_public.lines().join("\n");
                                     let ca = ed.ta_ca.lines().join("\n");
@@ -739,7 +968,16 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                             Some(cert)
                                         },
                                         key_pem: if pk.trim().is_empty() { None } else { Some(pk) },
+                                        key_password: if ed.key_password.is_empty() {
+                                            None
+                                        } else {
+                                            Some(ed.key_password.clone())
+                                        },
                                     };
+                                    let sasl = ed
+                                        .idx
+                                        .and_then(|i| app.env_store.envs.get(i))
+                                        .and_then(|env| env.sasl.clone());
                                     // Prefer CA PEM; do not auto-create ssl.ca.location if PEM is provided
                                     // Start debug log
                                     let _ = start_test_log(&host, &ssl);
@@ -770,29 +1008,21 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                             .set("enable.auto.commit", "false")
                                             .set("auto.offset.reset", "earliest")
                                             .set("enable.partition.eof", "true");
-                                        if ssl.ca_pem.is_some()
-                                            || ssl.cert_pem.is_some()
-                                            || ssl.key_pem.is_some()
-                                        {
-                                            cfg.set("security.protocol", "ssl");
-                                            if let Some(ref s) = ssl.ca_pem {
-                                                cfg.set("ssl.ca.pem", s);
-                                            }
-                                            if let Some(ref s) = ssl.cert_pem {
-                                                cfg.set("ssl.certificate.pem", s);
-                                            }
-                                            if let Some(ref s) = ssl.key_pem {
-                                                cfg.set("ssl.key.pem", s);
-                                            }
+                                        if ssl.is_enabled() {
+                                            ssl.apply_to(&mut cfg);
                                             // Use supported debug contexts; omit "ssl" token (not recognized in some builds)
                                             cfg.set("debug", "security,broker,protocol");
                                         }
+                                        if let Some(sasl) = &sasl {
+                                            sasl.apply_to(&mut cfg);
+                                        }
                                         // Record effective TLS params (redacted)
                                         append_test_log_line(&format!(
-                                            "[params] security.protocol=ssl, using_ca=pem, ca.pem_len={}, cert.pem_len={}, key.pem_len={}",
+                                            "[params] security.protocol=ssl, using_ca=pem, ca.pem_len={}, cert.pem_len={}, key.pem_len={}, key.password={}",
                                             ssl.ca_pem.as_ref().map(|s| s.len()).unwrap_or(0),
                                             ssl.cert_pem.as_ref().map(|s| s.len()).unwrap_or(0),
-                                            ssl.key_pem.as_ref().map(|s| s.len()).unwrap_or(0)
+                                            ssl.key_pem.as_ref().map(|s| s.len()).unwrap_or(0),
+                                            redacted(&ssl.key_password)
                                         ));
                                         if let Some(ref s) = ssl.ca_pem {
                                             append_test_log_line(&format!(
@@ -871,13 +1101,22 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                         }
                                     });
                                 }
-                            } else if matches!(app.focus, super::app::Focus::Results) {
+                            } else if matches!(
+                                app.focus,
+                                super::app::Focus::Results | super::app::Focus::Detail
+                            ) {
                                 if let Some(s) = selected_cell_text(&app) {
                                     match copy_to_clipboard(&s) {
                                         Ok(()) => app.status = "Copied to clipboard".to_string(),
                                         Err(e) => app.status = format!("Clipboard error: {}", e),
                                     }
                                 }
+                            } else if matches!(app.focus, super::app::Focus::Host) {
+                                let conn = app.connection_string();
+                                match copy_to_clipboard(&conn) {
+                                    Ok(()) => app.status = format!("Copied '{}'", conn),
+                                    Err(e) => app.status = format!("Clipboard error: {}", e),
+                                }
                             }
                         }
                         // (F8 removed)
@@ -916,6 +1155,10 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                             ed.host_cursor += 1;
                                             meta_changed = true;
                                         }
+                                        EnvFieldFocus::KeyPassword => {
+                                            ed.key_password.insert(ed.key_password_cursor, ch);
+                                            ed.key_password_cursor += 1;
+                                        }
                                         EnvFieldFocus::PrivateKey => {
                                             ed.ta_private.input(TAInput {
                                                 key: TAKey::Char(ch),
@@ -950,8 +1193,120 @@ pub async fn run(args: RunArgs) -> Result<()> {
                             }
                             match app.focus {
                                 super::app::Focus::Results => {
-                                    // ignore normal chars in results
+                                    if ch == 'f'
+                                        && matches!(app.tab.results_mode, ResultsMode::Messages)
+                                    {
+                                        // Toggle flat "path = value" rendering in the detail pane
+                                        app.tab.detail_flat = !app.tab.detail_flat;
+                                        app.tab.json_vscroll = 0;
+                                    } else if ch == 'm'
+                                        && matches!(app.tab.results_mode, ResultsMode::Messages)
+                                    {
+                                        // Pretty/compact JSON; remembered across restarts
+                                        if let Err(e) = app.toggle_detail_pretty().save() {
+                                            app.status = format!("Could not save settings: {e}");
+                                        }
+                                    } else if ch == 'w'
+                                        && matches!(app.tab.results_mode, ResultsMode::Messages)
+                                    {
+                                        // Emphasize the fields the WHERE matched on
+                                        app.detail_emphasis = !app.detail_emphasis;
+                                    } else if ch == '/'
+                                        && matches!(app.tab.results_mode, ResultsMode::Messages)
+                                    {
+                                        // Narrow the loaded rows without re-querying
+                                        app.tab.filter_prompt = true;
+                                    } else if ch == 'g'
+                                        && matches!(app.tab.results_mode, ResultsMode::Messages)
+                                    {
+                                        // Jump to the first loaded row of a partition
+                                        app.tab.partition_prompt = Some(String::new());
+                                    } else if ch == 'o'
+                                        && matches!(app.tab.results_mode, ResultsMode::Messages)
+                                    {
+                                        // Jump to an offset in the selected row's partition
+                                        app.tab.offset_prompt = Some(String::new());
+                                    } else if matches!(ch, '+' | '=' | '-')
+                                        && matches!(app.tab.results_mode, ResultsMode::Messages)
+                                    {
+                                        // Widen/narrow the selected column for this session
+                                        app.adjust_column_width(if ch == '-' { -2 } else { 2 });
+                                    } else if ch == 'c'
+                                        && matches!(app.tab.results_mode, ResultsMode::Messages)
+                                    {
+                                        // Hide/show columns without editing the query
+                                        app.tab.column_picker = Some(app.tab.selected_col);
+                                    } else if ch == 'h'
+                                        && matches!(app.tab.results_mode, ResultsMode::Messages)
+                                        && app.selected_message().is_some()
+                                    {
+                                        // Inspect the selected message's headers
+                                        app.tab.show_headers = true;
+                                    } else if ch == 'p'
+                                        && matches!(app.tab.results_mode, ResultsMode::Messages)
+                                    {
+                                        // Pin leading columns; only the value region scrolls
+                                        app.tab.pin_columns = !app.tab.pin_columns;
+                                    } else if (ch == '<' || ch == '>')
+                                        && matches!(app.tab.results_mode, ResultsMode::Messages)
+                                    {
+                                        // Session-only column reorder; the query is untouched
+                                        app.move_selected_column(if ch == '<' { -1 } else { 1 });
+                                    } else if ch == 'i'
+                                        && matches!(app.tab.results_mode, ResultsMode::Messages)
+                                    {
+                                        // Session-only: just the selected column (and Key)
+                                        app.toggle_isolate_column();
+                                    } else if ch == 'e'
+                                        && matches!(app.tab.results_mode, ResultsMode::Messages)
+                                    {
+                                        // Errors-only preset on the current topic, run at once
+                                        match app.tab.run_topic.clone() {
+                                            Some(topic) => {
+                                                app.open_errors_query(&topic);
+                                                run_query_at_cursor(
+                                                    &mut app,
+                                                    &args,
+                                                    &mut run_counter,
+                                                    &mut pipelines,
+                                                    &tx_evt,
+                                                );
+                                            }
+                                            None => {
+                                                app.status =
+                                                    "Run a query first; e then shows its errors"
+                                                        .to_string();
+                                            }
+                                        }
+                                    } else if ch == 'b'
+                                        && matches!(app.tab.results_mode, ResultsMode::Messages)
+                                    {
+                                        // Mark/unmark the selected message as the diff base
+                                        let selected = app.selected_message().cloned();
+                                        app.tab.diff_base = match (
+                                            app.tab.diff_base.take(),
+                                            selected,
+                                        ) {
+                                            (Some(base), Some(env))
+                                                if (base.partition, base.offset)
+                                                    == (env.partition, env.offset) =>
+                                            {
+                                                app.status = "Diff base cleared".to_string();
+                                                None
+                                            }
+                                            (_, Some(env)) => {
+                                                app.status = format!(
+                                                    "Diff base: p{}@{}; select another message's value to compare",
+                                                    env.partition, env.offset
+                                                );
+                                                Some(env)
+                                            }
+                                            (base, None) => base,
+                                        };
+                                        app.tab.json_vscroll = 0;
+                                    }
                                 }
+                                super::app::Focus::Detail => {}
                                 super::app::Focus::Host => {
                                     if app.show_env_modal {
                                         // NOP (handled below in modal)
@@ -988,6 +1343,27 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                 app.autocomplete = None;
                                 app.autocomplete_dirty = false;
                                 app.autocomplete_frozen_token = None;
+                            } else if matches!(app.focus, super::app::Focus::Results)
+                                && !app.tab.row_filter.is_empty()
+                            {
+                                app.set_row_filter(String::new());
+                            }
+                        }
+                        // Ctrl-arrows resize the Home panes; the editor keeps them for word moves
+                        (KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right, m)
+                            if m.contains(KeyModifiers::CONTROL)
+                                && matches!(app.screen, Screen::Home)
+                                && !app.show_env_modal
+                                && !matches!(app.focus, super::app::Focus::Query) =>
+                        {
+                            match code {
+                                KeyCode::Up => app.panes.resize_editor(-1),
+                                KeyCode::Down => app.panes.resize_editor(1),
+                                KeyCode::Left => app.panes.resize_table(-1),
+                                _ => app.panes.resize_table(1),
+                            }
+                            if let Err(e) = app.ui_settings().save() {
+                                app.status = format!("Could not save settings: {e}");
                             }
                         }
                         // Navigation: results or env list / textareas
@@ -1014,15 +1390,17 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                 if !handled {
                                     move_env_selection(&mut app, -1);
                                 }
+                            } else if matches!(app.screen, Screen::Info) {
+                                app.info_selected = app.info_selected.saturating_sub(1);
                             } else if matches!(app.focus, super::app::Focus::Results) {
-                                if app.selected_row > 0 {
-                                    app.selected_row -= 1;
-                                    if matches!(app.results_mode, ResultsMode::Messages) {
-                                        app.json_vscroll = 0;
-                                    }
-                                }
+                                app.move_results(ResultsMotion::Up);
                             } else if matches!(app.focus, super::app::Focus::Query) {
-                                move_cursor_up(&mut app);
+                                if app.browsing_history() {
+                                    app.step_history(true);
+                                    ensure_input_cursor_visible(&mut app);
+                                } else {
+                                    move_cursor_up(&mut app);
+                                }
                             }
                         }
                         (KeyCode::Down, _) => {
@@ -1048,26 +1426,29 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                 if !handled {
                                     move_env_selection(&mut app, 1);
                                 }
-                            } else if matches!(app.focus, super::app::Focus::Results) {
-                                let total = total_results_rows(&app);
-                                if total > 0 && app.selected_row + 1 < total {
-                                    app.selected_row += 1;
-                                    if matches!(app.results_mode, ResultsMode::Messages) {
-                                        app.json_vscroll = 0;
-                                    }
+                            } else if matches!(app.screen, Screen::Info) {
+                                if app.info_selected + 1 < app.topics.len() {
+                                    app.info_selected += 1;
                                 }
+                            } else if matches!(app.focus, super::app::Focus::Results) {
+                                app.move_results(ResultsMotion::Down);
                             } else if matches!(app.focus, super::app::Focus::Query) {
-                                move_cursor_down(&mut app);
+                                if app.browsing_history() {
+                                    app.step_history(false);
+                                    ensure_input_cursor_visible(&mut app);
+                                } else {
+                                    move_cursor_down(&mut app);
+                                }
                             }
                         }
                         (KeyCode::Left, KeyModifiers::SHIFT) => {
                             if matches!(app.focus, super::app::Focus::Results) {
-                                app.table_hscroll = app.table_hscroll.saturating_sub(2);
+                                app.tab.table_hscroll = app.tab.table_hscroll.saturating_sub(2);
                             }
                         }
                         (KeyCode::Right, KeyModifiers::SHIFT) => {
                             if matches!(app.focus, super::app::Focus::Results) {
-                                app.table_hscroll = app.table_hscroll.saturating_add(2);
+                                app.tab.table_hscroll = app.tab.table_hscroll.saturating_add(2);
                             }
                         }
                         (KeyCode::Left, m) => {
@@ -1084,6 +1465,11 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                                 ed.host_cursor -= 1;
                                             }
                                         }
+                                        EnvFieldFocus::KeyPassword => {
+                                            if ed.key_password_cursor > 0 {
+                                                ed.key_password_cursor -= 1;
+                                            }
+                                        }
                                         EnvFieldFocus::PrivateKey => {
                                             ed.ta_private.input(ta_input_from_key(key));
                                         }
@@ -1098,14 +1484,7 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                     }
                                 }
                             } else if matches!(app.focus, super::app::Focus::Results) {
-                                if matches!(app.results_mode, ResultsMode::Messages) {
-                                    if app.selected_col > 0 {
-                                        app.selected_col -= 1;
-                                    } else {
-                                        app.selected_col = 0;
-                                    }
-                                    app.json_vscroll = 0;
-                                }
+                                app.move_results(ResultsMotion::Left);
                             } else if matches!(app.focus, super::app::Focus::Query) {
                                 if has_ctrl_or_alt(m) {
                                     move_prev_word(&mut app);
@@ -1129,6 +1508,11 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                                 ed.host_cursor += 1;
                                             }
                                         }
+                                        EnvFieldFocus::KeyPassword => {
+                                            if ed.key_password_cursor < ed.key_password.len() {
+                                                ed.key_password_cursor += 1;
+                                            }
+                                        }
                                         EnvFieldFocus::PrivateKey => {
                                             ed.ta_private.input(ta_input_from_key(key));
                                         }
@@ -1143,13 +1527,7 @@ pub async fn run(args: RunArgs) -> Result<()> {
                                     }
                                 }
                             } else if matches!(app.focus, super::app::Focus::Results) {
-                                if matches!(app.results_mode, ResultsMode::Messages) {
-                                    let cols = app.selected_columns.len();
-                                    if cols > 0 && app.selected_col + 1 < cols {
-                                        app.selected_col += 1;
-                                    }
-                                    app.json_vscroll = 0;
-                                }
+                                app.move_results(ResultsMotion::Right);
                             } else if matches!(app.focus, super::app::Focus::Query) {
                                 if m.is_empty() && try_accept_autocomplete(&mut app) {
                                     continue;
@@ -1164,37 +1542,21 @@ pub async fn run(args: RunArgs) -> Result<()> {
                         }
                         (KeyCode::PageUp, _) => {
                             if matches!(app.focus, super::app::Focus::Results) {
-                                let step = 10;
-                                app.selected_row = app.selected_row.saturating_sub(step);
-                                if matches!(app.results_mode, ResultsMode::Messages) {
-                                    app.json_vscroll = 0;
-                                }
+                                app.move_results(ResultsMotion::PageUp);
                             } else if matches!(app.focus, super::app::Focus::Query) {
                                 scroll_input(&mut app, true);
                             }
                         }
                         (KeyCode::PageDown, _) => {
                             if matches!(app.focus, super::app::Focus::Results) {
-                                let step = 10;
-                                let total = total_results_rows(&app);
-                                if total > 0 {
-                                    let max_idx = total - 1;
-                                    let target = app.selected_row.saturating_add(step);
-                                    app.selected_row = target.min(max_idx);
-                                    if matches!(app.results_mode, ResultsMode::Messages) {
-                                        app.json_vscroll = 0;
-                                    }
-                                }
+                                app.move_results(ResultsMotion::PageDown);
                             } else if matches!(app.focus, super::app::Focus::Query) {
                                 scroll_input(&mut app, false);
                             }
                         }
                         (KeyCode::Home, m) => {
                             if matches!(app.focus, super::app::Focus::Results) {
-                                app.selected_row = 0;
-                                if matches!(app.results_mode, ResultsMode::Messages) {
-                                    app.json_vscroll = 0;
-                                }
+                                app.move_results(ResultsMotion::Top);
                             } else if matches!(app.focus, super::app::Focus::Query) {
                                 if m.contains(KeyModifiers::CONTROL) {
                                     goto_start_of_doc(&mut app);
@@ -1205,13 +1567,7 @@ pub async fn run(args: RunArgs) -> Result<()> {
                         }
                         (KeyCode::End, m) => {
                             if matches!(app.focus, super::app::Focus::Results) {
-                                let total = total_results_rows(&app);
-                                if total > 0 {
-                                    app.selected_row = total - 1;
-                                    if matches!(app.results_mode, ResultsMode::Messages) {
-                                        app.json_vscroll = 0;
-                                    }
-                                }
+                                app.move_results(ResultsMotion::Bottom);
                             } else if matches!(app.focus, super::app::Focus::Query) {
                                 if m.contains(KeyModifiers::CONTROL) {
                                     goto_end_of_doc(&mut app);
@@ -1267,6 +1623,9 @@ pub async fn run(args: RunArgs) -> Result<()> {
     };
 
     // Restore terminal
+    if args.terminal_title {
+        execute!(std::io::stdout(), crossterm::terminal::SetTitle("")).ok();
+    }
     disable_raw_mode().ok();
     // Use crossterm global execute to restore screen
     execute!(
@@ -1285,14 +1644,27 @@ struct TuiOutput {
     run_id: u64,
     tx: mpsc::UnboundedSender<TuiEvent>,
     buffer: Vec<MessageEnvelope>,
+    /// Rows sent to the table so far, read back for the scan summary.
+    delivered: std::sync::Arc<AtomicU64>,
+    /// Reader positions, sent along with heartbeats when they moved.
+    scan: std::sync::Arc<ScanProgress>,
+    last_read: ReadProgress,
 }
 
 impl TuiOutput {
-    fn new(run_id: u64, tx: mpsc::UnboundedSender<TuiEvent>) -> Self {
+    fn new(
+        run_id: u64,
+        tx: mpsc::UnboundedSender<TuiEvent>,
+        delivered: std::sync::Arc<AtomicU64>,
+        scan: std::sync::Arc<ScanProgress>,
+    ) -> Self {
         Self {
             run_id,
             tx,
             buffer: Vec::with_capacity(256),
+            delivered,
+            scan,
+            last_read: ReadProgress::default(),
         }
     }
 }
@@ -1307,29 +1679,142 @@ impl OutputSink for TuiOutput {
         }
         let mut out = Vec::new();
         std::mem::swap(&mut out, &mut self.buffer);
+        self.delivered
+            .fetch_add(out.len() as u64, AtomicOrdering::Relaxed);
         let _ = self.tx.send(TuiEvent::Batch {
             run_id: self.run_id,
             rows: out,
         });
     }
+    fn heartbeat(&mut self, scanned: u64, matched: u64) {
+        let _ = self.tx.send(TuiEvent::Progress {
+            run_id: self.run_id,
+            scanned,
+            matched,
+        });
+        // Heartbeats already come at most once per flush tick
+        let read = self.scan.read_progress();
+        if read != self.last_read {
+            self.last_read = read;
+            let _ = self.tx.send(TuiEvent::Read {
+                run_id: self.run_id,
+                progress: read,
+            });
+        }
+    }
+}
+
+/// Run the statement under the editor cursor: a SELECT starts a pipeline for
+/// the active tab, stopping runs no tab shows any more; `LIST TOPICS` fetches
+/// the topic list. Ctrl-Enter and the `e` errors preset both land here.
+fn run_query_at_cursor(
+    app: &mut AppState,
+    args: &RunArgs,
+    run_counter: &mut u64,
+    pipelines: &mut Vec<(u64, tokio::task::JoinHandle<()>)>,
+    tx_evt: &mpsc::UnboundedSender<TuiEvent>,
+) {
+    let (qs, qe) = find_query_range(&app.input, app.input_cursor);
+    let raw = &app.input[qs..qe];
+    let query = strip_trailing_semicolon(raw).trim().to_string();
+    if query.is_empty() {
+        app.status = "Please enter a query".to_string();
+        return;
+    }
+    let parsed = parse_command(&query);
+    if parsed.is_ok() {
+        app.record_history(&query);
+    }
+    match parsed {
+        Ok(Command::Select(ast)) => {
+            let columns = ast.select.clone();
+            app.tab.results_mode = ResultsMode::Messages;
+            app.autocomplete = None;
+            app.autocomplete_frozen_token = None;
+            app.tab.selected_columns = columns;
+            app.tab.isolated_from = None;
+            app.tab.visible_columns.clear();
+            app.tab.column_aliases = ast.aliases.clone();
+            app.tab.last_where = ast.r#where.clone();
+            app.tab.scan_summary = None;
+            app.tab.table_hscroll = 0;
+            app.clear_rows();
+            app.reset_rate();
+            app.tab.topics_with_partitions.clear();
+            *run_counter += 1;
+            app.tab.current_run = Some(*run_counter);
+            app.last_run_query_range = Some((qs, qe));
+            app.tab.run_topic = Some(ast.from.clone());
+            let env_host = app
+                .selected_env()
+                .map(|e| e.host.clone())
+                .unwrap_or(app.host.clone());
+            app.status = format!(
+                "Running (run {}): topic '{}' on {}. Press q to quit.",
+                *run_counter, ast.from, env_host
+            );
+            let mut run_args = args.clone();
+            run_args.broker = vec![env_host];
+            run_args.follow = app.tab.follow;
+            app.clamp_selection();
+            let ssl = app.current_ssl_config();
+            let sasl = app.current_sasl_config();
+            // Stop runs no tab shows, like this tab's last one
+            pipelines.retain(|(run, handle)| {
+                let keep = app.tab_of_run(*run).is_some() && !handle.is_finished();
+                if !keep {
+                    handle.abort();
+                }
+                keep
+            });
+            pipelines.push((
+                *run_counter,
+                spawn_pipeline_with_ssl(run_args, query, *run_counter, tx_evt.clone(), ssl, sasl),
+            ));
+        }
+        Ok(Command::ListTopics) => {
+            app.tab.results_mode = ResultsMode::TopicList;
+            app.autocomplete = None;
+            app.autocomplete_frozen_token = None;
+            app.tab.table_hscroll = 0;
+            app.clear_rows();
+            app.tab.topics_with_partitions.clear();
+            app.tab.current_run = None;
+            app.last_run_query_range = Some((qs, qe));
+            app.tab.selected_row = 0;
+            app.tab.json_vscroll = 0;
+            let env_host = app
+                .selected_env()
+                .map(|e| e.host.clone())
+                .unwrap_or(app.host.clone());
+            app.status = format!("Listing topics from {}...", env_host);
+            fetch_topics_with_partitions_async(app, tx_evt.clone());
+            app.clamp_selection();
+        }
+        Err(e) => {
+            app.status = format!("Parse error: {}", e.annotated(&query));
+        }
+    }
 }
 
 // Spawn pipeline but with ssl provided
-async fn spawn_pipeline_with_ssl(
+fn spawn_pipeline_with_ssl(
     args: RunArgs,
     query_text: String,
     run_id: u64,
     tx: mpsc::UnboundedSender<TuiEvent>,
     ssl: Option<crate::models::SslConfig>,
-) {
+    sasl: Option<SaslConfig>,
+) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
-        if let Err(e) = run_pipeline_with_ssl(args, query_text, run_id, tx.clone(), ssl).await {
+        if let Err(e) = run_pipeline_with_ssl(args, query_text, run_id, tx.clone(), ssl, sasl).await
+        {
             let _ = tx.send(TuiEvent::Error {
                 run_id,
                 message: e.to_string(),
             });
         }
-    });
+    })
 }
 
 async fn run_pipeline_with_ssl(
@@ -1338,129 +1823,178 @@ async fn run_pipeline_with_ssl(
     run_id: u64,
     tx: mpsc::UnboundedSender<TuiEvent>,
     ssl: Option<crate::models::SslConfig>,
+    sasl: Option<SaslConfig>,
 ) -> Result<()> {
+    let started = Instant::now();
     let ast = parse_query(&query_text).context("Failed to parse query")?;
     let topic = ast.from.clone();
-    let keys_only = !ast.select.iter().any(|i| matches!(i, SelectItem::Value));
-    let max_messages_global = ast.limit.or(args.max_messages).or(Some(100));
+    let keys_only = !ast.needs_value();
+    if args.follow && ast.buffers_until_end() {
+        return Err(anyhow!(
+            "Follow mode streams rows as they arrive; GROUP BY key, ORDER BY (other than timestamp ASC) and COUNT(*) need the scan to end (Ctrl-F turns it off)"
+        ));
+    }
+    // The TUI caps unbounded runs at 100 rows, except when following or counting
+    let max_messages_global = match args.row_limit(Some(&ast)) {
+        None if !args.follow && !ast.is_count() => Some(100),
+        limit => limit,
+    };
     let order_desc = ast
-        .order
-        .as_ref()
+        .primary_order()
         .map(|o| matches!(o.dir, OrderDir::Desc))
         .unwrap_or(false);
 
-    let mut cfg = ClientConfig::new();
-    cfg.set("bootstrap.servers", &args.broker)
-        .set("group.id", format!("rkl-probe-{}", uuid::Uuid::new_v4()))
-        .set("enable.auto.commit", "false")
-        .set("auto.offset.reset", "earliest")
-        .set("enable.partition.eof", "true");
-    if let Some(ssl) = &ssl {
-        if ssl.ca_pem.is_some() || ssl.cert_pem.is_some() || ssl.key_pem.is_some() {
-            cfg.set("security.protocol", "ssl");
-            if let Some(ref s) = ssl.ca_pem {
-                cfg.set("ssl.ca.pem", s);
-            }
-            if let Some(ref s) = ssl.cert_pem {
-                cfg.set("ssl.certificate.pem", s);
-            }
-            if let Some(ref s) = ssl.key_pem {
-                cfg.set("ssl.key.pem", s);
-            }
-        }
-    }
-    struct QuietContext;
-    impl ClientContext for QuietContext {
-        fn log(&self, _level: RDKafkaLogLevel, _fac: &str, _log_message: &str) {}
-    }
-    impl ConsumerContext for QuietContext {}
-
-    let probe_consumer: StreamConsumer<QuietContext> = cfg
-        .create_with_context(QuietContext)
-        .context("Failed to create probe consumer")?;
-
-    let metadata = probe_consumer
-        .fetch_metadata(Some(&topic), Duration::from_secs(10))
-        .context("Failed to fetch metadata")?;
-    let topic_md = metadata
-        .topics()
-        .iter()
-        .find(|t| t.name() == topic)
-        .ok_or_else(|| anyhow!("Topic not found: {}", topic))?;
-    let partitions: Vec<i32> = topic_md.partitions().iter().map(|p| p.id()).collect();
-
-    let (tx_msg, rx_msg) = mpsc::channel::<MessageEnvelope>(args.channel_capacity);
-    let offset_spec = OffsetSpec::from_str(&args.offset).unwrap_or_else(|_| OffsetSpec::Beginning);
+    let (tx_msg, rx_msg) = mpsc::channel::<ReaderEvent>(args.channel_capacity);
+    let (stop_readers, stop) = crate::models::StopSignal::channel();
     let query_arc = std::sync::Arc::new(ast.clone());
-
+    let progress = std::sync::Arc::new(ScanProgress::default());
+    let ctx = ReaderContext {
+        stop: Some(stop),
+        progress: Some(progress.clone()),
+        ..ReaderContext::default()
+    };
     let mut joinset = tokio::task::JoinSet::new();
-    for &p in &partitions {
-        let txp = tx_msg.clone();
-        let mut a = args.clone();
-        a.topic = Some(topic.clone());
-        a.keys_only = keys_only;
-        a.max_messages = None;
-        let q = Some(query_arc.clone());
-        let ssl_clone = ssl.clone();
-        joinset.spawn(async move {
-            spawn_partition_consumer(a, p, offset_spec, txp, q, ssl_clone).await
+
+    let partitions = if let Some(path) = &args.from_file {
+        // Offline: replay a dump through the same filtering and merger
+        crate::replay::spawn_replay(
+            &args.for_consumer(&topic, keys_only),
+            path,
+            &topic,
+            Some(query_arc),
+            tx_msg.clone(),
+            &mut joinset,
+            ctx,
+        )?
+    } else {
+        // The TUI reads a single cluster: the selected environment
+        let brokers = crate::models::parse_brokers(&args.broker.join(","))
+            .map_err(|e| anyhow!("Invalid host: {}", e))?;
+        let cluster = ClusterSpec {
+            label: None,
+            bootstrap: brokers.join(","),
+            ssl,
+            sasl,
+        };
+        let mut cfg = ClientConfig::new();
+        cfg.set("bootstrap.servers", &cluster.bootstrap)
+            .set("group.id", format!("rkl-probe-{}", uuid::Uuid::new_v4()))
+            .set("enable.auto.commit", "false")
+            .set("auto.offset.reset", "earliest")
+            .set("enable.partition.eof", "true");
+        cluster.apply_security(&mut cfg);
+        args.apply_client_options(&mut cfg);
+        struct QuietContext;
+        impl ClientContext for QuietContext {
+            fn log(&self, _level: RDKafkaLogLevel, _fac: &str, _log_message: &str) {}
+        }
+        impl ConsumerContext for QuietContext {}
+
+        let probe_consumer: StreamConsumer<QuietContext> = cfg
+            .create_with_context(QuietContext)
+            .context("Failed to create probe consumer")?;
+
+        let metadata = probe_consumer
+            .fetch_metadata(Some(&topic), args.metadata_timeout())
+            .context("Failed to fetch metadata")?;
+        let topic_md = metadata
+            .topics()
+            .iter()
+            .find(|t| t.name() == topic)
+            .ok_or_else(|| anyhow!("Topic not found: {}", topic))?;
+        let partitions: Vec<i32> = topic_md.partitions().iter().map(|p| p.id()).collect();
+        let leaders = partition_leaders(
+            topic_md.partitions().iter().map(|p| (p.id(), p.leader())),
+            &partitions,
+        );
+        let _ = tx.send(TuiEvent::Leaders {
+            run_id,
+            summary: leader_summary(&leaders),
         });
-    }
+
+        let offset_spec = args.offset_spec();
+        let tail = crate::consumer::tail_plan(&args, Some(&ast));
+        for &p in &partitions {
+            let mut offset_spec = offset_spec;
+            let mut a = args.for_consumer(&topic, keys_only);
+            let mut reader_ctx = ctx.clone();
+            // Latest-N: read only each partition's newest n, then stop
+            if let Some(n) = tail {
+                offset_spec = crate::consumer::tail_reader(&mut a, &mut reader_ctx, n);
+            }
+            let txp = tx_msg.clone();
+            let q = Some(query_arc.clone());
+            let c = cluster.clone();
+            joinset.spawn(async move {
+                spawn_partition_consumer(a, p, offset_spec, txp, q, c, reader_ctx).await
+            });
+        }
+        partitions
+    };
     drop(tx_msg);
 
-    let mut sink = TuiOutput::new(run_id, tx.clone());
-    run_merger(
+    let delivered = std::sync::Arc::new(AtomicU64::new(0));
+    let sink = TuiOutput::new(run_id, tx.clone(), delivered.clone(), progress.clone());
+    // GROUP BY key / ORDER BY <path> buffer rows; OFFSET/LIMIT then apply to their result.
+    let (mut sink, window) = match crate::consumer::tail_plan(&args, Some(&ast)) {
+        Some(n) => (
+            crate::output::newest_first(Box::new(sink), n),
+            crate::merger::RowWindow::default(),
+        ),
+        None => apply_query_stages(Box::new(sink), &ast, order_desc, max_messages_global),
+    };
+    let buffer = MergeBuffer {
+        readers: joinset.len(),
+        watermark: args.watermark,
+        flush_interval_ms: args.flush_interval_ms,
+    };
+    let emitted = run_merger(
         rx_msg,
-        &mut sink,
-        args.watermark,
-        args.flush_interval_ms,
-        max_messages_global,
-        order_desc,
+        sink.as_mut(),
+        buffer,
+        window,
+        crate::merger::merge_order(Some(&ast)),
+        Some(progress.clone()),
     )
     .await?;
 
+    // Readers tailing past a met LIMIT would otherwise hold the run open
+    stop_readers.send_replace(true);
+    let mut summaries = Vec::with_capacity(partitions.len());
     while let Some(res) = joinset.join_next().await {
-        let _ = res;
+        if let Ok(Ok(summary)) = res {
+            summaries.push(summary);
+        }
     }
 
-    let _ = tx.send(TuiEvent::Done { run_id });
+    let (scanned, matched) = progress.snapshot();
+    let _ = tx.send(TuiEvent::Done {
+        run_id,
+        summary: ScanSummary {
+            scanned,
+            matched,
+            returned: delivered.load(AtomicOrdering::Relaxed),
+            partitions: partitions.len(),
+            elapsed: started.elapsed(),
+            limit: max_messages_global,
+            limit_hit: window.limit.is_some_and(|max| emitted >= max),
+        },
+        skew_warning: partition_skew_warning(&summaries),
+    });
     Ok(())
 }
 
 fn selected_cell_text(app: &AppState) -> Option<String> {
-    if app.rows.is_empty() {
-        return None;
-    }
-    if app.selected_columns.is_empty() {
+    let env = app.selected_envelope()?;
+    if app.tab.selected_columns.is_empty() {
         return None;
     }
-    let idx = app.selected_row.min(app.rows.len() - 1);
-    let env = &app.rows[idx];
     let col_idx = app
+        .tab
         .selected_col
-        .min(app.selected_columns.len().saturating_sub(1));
-    let col = app.selected_columns[col_idx];
-    Some(runner_column_text(env, col))
-}
-
-fn runner_column_text(env: &MessageEnvelope, col: SelectItem) -> String {
-    match col {
-        SelectItem::Partition => env.partition.to_string(),
-        SelectItem::Offset => env.offset.to_string(),
-        SelectItem::Timestamp => fmt_ts(env.timestamp_ms),
-        SelectItem::Key => env.key.clone(),
-        SelectItem::Value => env.value.as_deref().unwrap_or("null").to_string(),
-    }
-}
-
-fn runner_column_width_hint(col: SelectItem) -> usize {
-    match col {
-        SelectItem::Partition => 10,
-        SelectItem::Offset => 12,
-        SelectItem::Timestamp => 26,
-        SelectItem::Key => 30,
-        SelectItem::Value => usize::MAX,
-    }
+        .min(app.tab.selected_columns.len().saturating_sub(1));
+    let col = app.tab.selected_columns[col_idx];
+    Some(column_text(env, col))
 }
 
 #[allow(dead_code)]
@@ -1520,33 +2054,32 @@ fn start_test_log(host: &str, ssl: &crate::models::SslConfig) -> Result<()> {
         .unwrap_or_else(|_| "".into());
     let _ = writeln!(
         f,
-        "{} [start] test connection host={} ca_pem_len={} cert_pem_len={} key_pem_len={}",
+        "{} [start] test connection host={} ca_pem_len={} cert_pem_len={} key_pem_len={} key_password={}",
         ts,
         host,
         ssl.ca_pem.as_ref().map(|s| s.len()).unwrap_or(0),
         ssl.cert_pem.as_ref().map(|s| s.len()).unwrap_or(0),
         ssl.key_pem.as_ref().map(|s| s.len()).unwrap_or(0),
+        redacted(&ssl.key_password),
     );
     Ok(())
 }
 
+/// Never log secrets; only whether one is set.
+fn redacted(secret: &Option<String>) -> &'static str {
+    if secret.is_some() {
+        "<redacted>"
+    } else {
+        "<unset>"
+    }
+}
+
 fn copy_to_clipboard(s: &str) -> Result<()> {
     let mut cb = arboard::Clipboard::new().context("open clipboard")?;
     cb.set_text(s.to_string()).context("set clipboard text")?;
     Ok(())
 }
 
-fn fmt_ts(ms: i64) -> String {
-    if ms <= 0 {
-        return "0".to_string();
-    }
-    let secs = ms / 1000;
-    let tm = time::OffsetDateTime::from_unix_timestamp(secs as i64)
-        .unwrap_or_else(|_| time::OffsetDateTime::UNIX_EPOCH);
-    tm.format(&time::format_description::well_known::Rfc3339)
-        .unwrap_or_else(|_| ms.to_string())
-}
-
 fn handle_env_editor_paste(app: &mut AppState, raw: &str) -> bool {
     if matches!(
         app.env_editor.as_ref().map(|e| e.field_focus),
@@ -1580,6 +2113,11 @@ fn handle_env_editor_paste(app: &mut AppState, raw: &str) -> bool {
                 ed.ta_private.insert_str(normalize_pem_input(raw));
                 handled = true;
             }
+            EnvFieldFocus::KeyPassword => {
+                let text = normalize_plain_input(raw).replace('\n', "");
+                handled = true;
+                insert_text_at_cursor(&mut ed.key_password, &mut ed.key_password_cursor, &text);
+            }
             EnvFieldFocus::PublicKey => {
                 ed.ta_public.insert_str(normalize_pem_input(raw));
                 handled = true;
@@ -1597,6 +2135,42 @@ fn handle_env_editor_paste(app: &mut AppState, raw: &str) -> bool {
     handled
 }
 
+/// Treat the focused PEM field's text as a file path (`~/` expands to the home
+/// directory) and replace it with the file's contents. `Err` leaves the field
+/// untouched; both carry a message for the editor.
+fn load_pem_from_path(ed: &mut EnvEditor) -> Result<String, String> {
+    let (label, ta) = match ed.field_focus {
+        EnvFieldFocus::PrivateKey => ("Private key", &mut ed.ta_private),
+        EnvFieldFocus::PublicKey => ("Certificate", &mut ed.ta_public),
+        EnvFieldFocus::Ca => ("CA", &mut ed.ta_ca),
+        _ => return Err("Ctrl-O loads a file into a PEM field; focus one first".to_string()),
+    };
+    let typed = ta.lines().join("\n");
+    let typed = typed.trim();
+    if typed.is_empty() {
+        return Err(format!("{}: type a file path, then press Ctrl-O", label));
+    }
+    let path = match (typed.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => std::path::PathBuf::from(home).join(rest),
+        _ => std::path::PathBuf::from(typed),
+    };
+    if !path.is_file() {
+        return Err(format!("{}: no such file: {}", label, path.display()));
+    }
+    let pem = fs::read_to_string(&path)
+        .map_err(|e| format!("{}: cannot read {}: {}", label, path.display(), e))?;
+    *ta = text_area_from_string(pem.trim_end().to_string());
+    Ok(match pem_sanity_issue(&pem) {
+        Some(issue) => format!(
+            "{}: loaded {}, but it doesn't look like PEM: {}",
+            label,
+            path.display(),
+            issue
+        ),
+        None => format!("{}: loaded {}", label, path.display()),
+    })
+}
+
 fn move_env_selection(app: &mut AppState, delta: isize) {
     if app.env_store.envs.is_empty() {
         return;
@@ -1618,6 +2192,7 @@ fn move_env_selection(app: &mut AppState, delta: isize) {
         return;
     }
     app.env_store.selected = Some(next as usize);
+    app.refresh_env_bar();
     sync_env_editor_to_selection(app);
 }
 
@@ -1636,6 +2211,8 @@ fn load_env_into_editor(ed: &mut EnvEditor, env: &Environment, idx: usize) {
     ed.name_cursor = ed.name_cursor.min(ed.name.len());
     ed.host_cursor = ed.host_cursor.min(ed.host.len());
     ed.ta_private = text_area_from_string(env.private_key_pem.clone().unwrap_or_default());
+    ed.key_password = env.key_password.clone().unwrap_or_default();
+    ed.key_password_cursor = ed.key_password.len();
     ed.ta_public = text_area_from_string(env.public_key_pem.clone().unwrap_or_default());
     ed.ta_ca = text_area_from_string(env.ssl_ca_pem.clone().unwrap_or_default());
 }
@@ -1655,6 +2232,8 @@ fn build_env_editor_from_env(env: &Environment, idx: Option<usize>) -> EnvEditor
         host: env.host.clone(),
         host_cursor: 0,
         ta_private: text_area_from_string(env.private_key_pem.clone().unwrap_or_default()),
+        key_password: env.key_password.clone().unwrap_or_default(),
+        key_password_cursor: 0,
         ta_public: text_area_from_string(env.public_key_pem.clone().unwrap_or_default()),
         ta_ca: text_area_from_string(env.ssl_ca_pem.clone().unwrap_or_default()),
         ssl_ca_cursor: 0,
@@ -1662,6 +2241,123 @@ fn build_env_editor_from_env(env: &Environment, idx: Option<usize>) -> EnvEditor
     }
 }
 
+/// Store the statement under the editor cursor as a saved query named by the
+/// open prompt, overwriting a query of the same name.
+fn save_current_query(app: &mut AppState) {
+    let name = app.save_prompt.as_deref().unwrap_or("").trim().to_string();
+    if name.is_empty() {
+        app.status = "Enter a name for the saved query".to_string();
+        return;
+    }
+    app.save_prompt = None;
+    let (qs, qe) = find_query_range(&app.input, app.input_cursor);
+    let query = strip_trailing_semicolon(&app.input[qs..qe])
+        .trim()
+        .to_string();
+    if query.is_empty() {
+        app.status = "Nothing to save: the editor has no statement at the cursor".to_string();
+        return;
+    }
+    let mut store = SavedQueryStore::load();
+    let replaced = store.upsert(&name, &query);
+    app.status = match store.save() {
+        Ok(()) if replaced => format!("Replaced saved query '{}'", name),
+        Ok(()) => format!("Saved query '{}'", name),
+        Err(e) => format!("Could not save query '{}': {}", name, e),
+    };
+}
+
+/// `~/.rkl/exports/<topic>-<UTC time>.ndjson` for the loaded rows.
+fn default_export_path(app: &AppState) -> std::path::PathBuf {
+    let topic = app
+        .tab
+        .rows
+        .first()
+        .map(|env| env.topic.as_str())
+        .unwrap_or("rows");
+    let stamp = time::format_description::parse("[year][month][day]-[hour][minute][second]")
+        .ok()
+        .and_then(|f| time::OffsetDateTime::now_utc().format(&f).ok())
+        .unwrap_or_default();
+    std::env::var("HOME")
+        .map(|h| std::path::PathBuf::from(h).join(".rkl"))
+        .unwrap_or_else(|_| std::path::PathBuf::from(".rkl"))
+        .join("exports")
+        .join(format!("{}-{}.ndjson", topic, stamp))
+}
+
+/// Export format from the path's extension: `.csv`, `.json` (one array),
+/// otherwise NDJSON.
+fn export_format(path: &std::path::Path) -> OutputFormat {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("csv") => OutputFormat::Csv,
+        Some(ext) if ext.eq_ignore_ascii_case("json") => OutputFormat::Json,
+        _ => OutputFormat::Ndjson,
+    }
+}
+
+/// Write the shown rows with the table's columns and aliases through the
+/// `--format` sinks, so values are exported whole rather than as previews,
+/// and as written rather than reformatted where the format allows.
+/// Returns how many rows were written.
+fn write_export(app: &AppState, out: impl std::io::Write, format: OutputFormat) -> usize {
+    // Hidden columns are left out, like in the table
+    let columns: Vec<SelectItem> = app
+        .shown_columns()
+        .into_iter()
+        .map(|c| app.tab.selected_columns[c])
+        .collect();
+    let aliases = app.tab.column_aliases.clone();
+    let mut sink: Box<dyn OutputSink + '_> = match format {
+        OutputFormat::Csv => Box::new(
+            CsvOutput::new(out, columns, TimeFormat::Iso, crate::output::now_ms())
+                .with_aliases(aliases)
+                .keeping_value_text(),
+        ),
+        _ => Box::new(
+            JsonOutput::new(out, columns, format == OutputFormat::Json)
+                .with_aliases(aliases)
+                .keeping_value_text(),
+        ),
+    };
+    let mut written = 0;
+    while let Some((_, env)) = app.shown_row(written) {
+        sink.push(env);
+        written += 1;
+    }
+    sink.finish();
+    written
+}
+
+fn export_current_rows(app: &mut AppState) {
+    let typed = app.tab.export_prompt.take().unwrap_or_default();
+    let typed = typed.trim();
+    if typed.is_empty() {
+        app.status = "Enter a path to export to".to_string();
+        return;
+    }
+    let path = match (typed.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => std::path::PathBuf::from(home).join(rest),
+        _ => std::path::PathBuf::from(typed),
+    };
+    let file = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::File::create(&path));
+    app.status = match file {
+        Ok(file) => {
+            let mut out = std::io::BufWriter::new(file);
+            let n = write_export(app, &mut out, export_format(&path));
+            match std::io::Write::flush(&mut out) {
+                Ok(()) => format!("Exported {} row(s) to {}", n, path.display()),
+                Err(e) => format!("Could not write {}: {}", path.display(), e),
+            }
+        }
+        Err(e) => format!("Could not create {}: {}", path.display(), e),
+    };
+}
+
 fn sync_env_metadata_from_editor(app: &mut AppState) {
     let (idx, name, host) = if let Some(ed) = app.env_editor.as_ref() {
         (ed.idx, ed.name.clone(), ed.host.clone())
@@ -1693,21 +2389,10 @@ fn handle_mouse(app: &mut AppState, me: MouseEvent) {
         width: w,
         height: h,
     };
-    let rows = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Length(10),
-            Constraint::Fill(1),
-            Constraint::Length(3),
-        ])
-        .split(root);
+    let rows = super::ui::home_rows(root, app);
     let query_area = rows[1];
     // Split row into editor and status
-    let cols = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(68), Constraint::Percentage(32)])
-        .split(query_area);
+    let cols = super::ui::home_cols(query_area, app);
     let status_rect = cols[1];
     let status_inner = Rect {
         x: status_rect.x.saturating_add(1),
@@ -1728,12 +2413,9 @@ fn handle_mouse(app: &mut AppState, me: MouseEvent) {
         .split(q_inner);
     let _q_gutter = q_cols[0];
     let q_content = q_cols[1];
-    let results_area = rows[2];
-    let (table_rect, json_rect_opt) = if matches!(app.results_mode, ResultsMode::Messages) {
-        let cols = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(68), Constraint::Percentage(32)])
-            .split(results_area);
+    let results_area = rows[3];
+    let (table_rect, json_rect_opt) = if matches!(app.tab.results_mode, ResultsMode::Messages) {
+        let cols = super::ui::home_cols(results_area, app);
         (cols[0], Some(cols[1]))
     } else {
         (results_area, None)
@@ -1802,7 +2484,7 @@ fn handle_mouse(app: &mut AppState, me: MouseEvent) {
                 return;
             }
             if point_in(mx, my, table_rect) {
-                match app.results_mode {
+                match app.tab.results_mode {
                     ResultsMode::Messages => {
                         let data_start_y = table_rect.y.saturating_add(2);
                         if my >= data_start_y
@@ -1811,16 +2493,16 @@ fn handle_mouse(app: &mut AppState, me: MouseEvent) {
                                     .y
                                     .saturating_add(table_rect.height.saturating_sub(1))
                         {
-                            if !app.rows.is_empty() {
+                            if app.shown_len() > 0 {
                                 let y_rel = (my - data_start_y) as usize;
                                 let visible_rows = table_rect.height.saturating_sub(3) as usize;
                                 let approx_first =
-                                    app.selected_row.saturating_sub(visible_rows / 2);
+                                    app.tab.selected_row.saturating_sub(visible_rows / 2);
                                 let new_row =
-                                    (approx_first + y_rel).min(app.rows.len().saturating_sub(1));
-                                if new_row != app.selected_row {
-                                    app.selected_row = new_row;
-                                    app.json_vscroll = 0;
+                                    (approx_first + y_rel).min(app.shown_len().saturating_sub(1));
+                                if new_row != app.tab.selected_row {
+                                    app.tab.selected_row = new_row;
+                                    app.tab.json_vscroll = 0;
                                 }
                             }
                         }
@@ -1829,13 +2511,16 @@ fn handle_mouse(app: &mut AppState, me: MouseEvent) {
                         if mx >= inner_x {
                             let mut x_rel = (mx - inner_x) as usize;
                             let mut col = 0usize;
-                            let widths: Vec<usize> = app
-                                .selected_columns
+                            let shown = app.shown_columns();
+                            let widths: Vec<usize> = shown
                                 .iter()
                                 .enumerate()
-                                .map(|(i, c)| {
-                                    let mut w = runner_column_width_hint(*c);
-                                    if i + 1 < app.selected_columns.len() {
+                                .map(|(i, &c)| {
+                                    let mut w = usize::from(column_width(
+                                        &app.tab.column_widths,
+                                        app.tab.selected_columns[c],
+                                    ));
+                                    if i + 1 < shown.len() {
                                         w = w.saturating_add(1);
                                     }
                                     w
@@ -1843,10 +2528,6 @@ fn handle_mouse(app: &mut AppState, me: MouseEvent) {
                                 .collect();
                             if !widths.is_empty() {
                                 for (i, w) in widths.iter().enumerate() {
-                                    if *w == usize::MAX {
-                                        col = i;
-                                        break;
-                                    }
                                     if x_rel < *w {
                                         col = i;
                                         break;
@@ -1857,9 +2538,10 @@ fn handle_mouse(app: &mut AppState, me: MouseEvent) {
                                 if col >= widths.len() {
                                     col = widths.len() - 1;
                                 }
-                                if app.selected_col != col {
-                                    app.selected_col = col;
-                                    app.json_vscroll = 0;
+                                let col = shown[col];
+                                if app.tab.selected_col != col {
+                                    app.tab.selected_col = col;
+                                    app.tab.json_vscroll = 0;
                                 }
                             }
                         }
@@ -1872,14 +2554,14 @@ fn handle_mouse(app: &mut AppState, me: MouseEvent) {
                                     .y
                                     .saturating_add(table_rect.height.saturating_sub(1))
                         {
-                            if !app.topics_with_partitions.is_empty() {
+                            if !app.tab.topics_with_partitions.is_empty() {
                                 let y_rel = (my - data_start_y) as usize;
                                 let visible_rows = table_rect.height.saturating_sub(3) as usize;
                                 let approx_first =
-                                    app.selected_row.saturating_sub(visible_rows / 2);
+                                    app.tab.selected_row.saturating_sub(visible_rows / 2);
                                 let new_row = (approx_first + y_rel)
-                                    .min(app.topics_with_partitions.len().saturating_sub(1));
-                                app.selected_row = new_row;
+                                    .min(app.tab.topics_with_partitions.len().saturating_sub(1));
+                                app.tab.selected_row = new_row;
                             }
                         }
                     }
@@ -1949,6 +2631,7 @@ fn handle_mouse(app: &mut AppState, me: MouseEvent) {
                         Constraint::Length(3),
                         Constraint::Length(3),
                         Constraint::Min(5),
+                        Constraint::Length(3),
                         Constraint::Min(5),
                         Constraint::Min(5),
                         Constraint::Length(3),
@@ -1962,7 +2645,7 @@ fn handle_mouse(app: &mut AppState, me: MouseEvent) {
                     ed.ta_public.input(inp.clone());
                     ed.ta_ca.input(inp);
                 }
-                if point_in(mx, my, fields[6]) {
+                if point_in(mx, my, fields[7]) {
                     app.env_conn_vscroll = app.env_conn_vscroll.saturating_sub(1);
                     return;
                 }
@@ -1970,13 +2653,13 @@ fn handle_mouse(app: &mut AppState, me: MouseEvent) {
             if point_in(mx, my, q_content) {
                 app.input_vscroll = app.input_vscroll.saturating_sub(1);
             } else if point_in(mx, my, table_rect) {
-                if app.selected_row > 0 {
-                    app.selected_row -= 1;
+                if app.tab.selected_row > 0 {
+                    app.tab.selected_row -= 1;
                 }
-            } else if matches!(app.results_mode, ResultsMode::Messages) {
+            } else if matches!(app.tab.results_mode, ResultsMode::Messages) {
                 if let Some(json_rect) = json_rect_opt {
                     if point_in(mx, my, json_rect) {
-                        app.json_vscroll = app.json_vscroll.saturating_sub(1);
+                        app.tab.json_vscroll = app.tab.json_vscroll.saturating_sub(1);
                     }
                 }
             }
@@ -2012,6 +2695,7 @@ fn handle_mouse(app: &mut AppState, me: MouseEvent) {
                         Constraint::Length(3),
                         Constraint::Length(3),
                         Constraint::Min(5),
+                        Constraint::Length(3),
                         Constraint::Min(5),
                         Constraint::Min(5),
                         Constraint::Length(3),
@@ -2024,7 +2708,7 @@ fn handle_mouse(app: &mut AppState, me: MouseEvent) {
                     ed.ta_public.input(inp.clone());
                     ed.ta_ca.input(inp);
                 }
-                if point_in(mx, my, fields[6]) {
+                if point_in(mx, my, fields[7]) {
                     app.env_conn_vscroll = app.env_conn_vscroll.saturating_add(1);
                     return;
                 }
@@ -2033,25 +2717,25 @@ fn handle_mouse(app: &mut AppState, me: MouseEvent) {
                 app.input_vscroll = app.input_vscroll.saturating_add(1);
             } else if point_in(mx, my, table_rect) {
                 let total = total_results_rows(app);
-                if total > 0 && app.selected_row + 1 < total {
-                    app.selected_row += 1;
+                if total > 0 && app.tab.selected_row + 1 < total {
+                    app.tab.selected_row += 1;
                 }
-            } else if matches!(app.results_mode, ResultsMode::Messages) {
+            } else if matches!(app.tab.results_mode, ResultsMode::Messages) {
                 if let Some(json_rect) = json_rect_opt {
                     if point_in(mx, my, json_rect) {
-                        app.json_vscroll = app.json_vscroll.saturating_add(1);
+                        app.tab.json_vscroll = app.tab.json_vscroll.saturating_add(1);
                     }
                 }
             }
         }
         MouseEventKind::ScrollLeft => {
             if point_in(mx, my, table_rect) {
-                app.table_hscroll = app.table_hscroll.saturating_sub(4);
+                app.tab.table_hscroll = app.tab.table_hscroll.saturating_sub(4);
             }
         }
         MouseEventKind::ScrollRight => {
             if point_in(mx, my, table_rect) {
-                app.table_hscroll = app.table_hscroll.saturating_add(4);
+                app.tab.table_hscroll = app.tab.table_hscroll.saturating_add(4);
             }
         }
         _ => {}
@@ -2085,6 +2769,7 @@ fn fetch_topics_async(app: &AppState, tx: mpsc::UnboundedSender<TuiEvent>) {
         .map(|e| e.host.clone())
         .unwrap_or_else(|| app.host.clone());
     let ssl = app.current_ssl_config();
+    let sasl = app.current_sasl_config();
     tokio::spawn(async move {
         let mut cfg = ClientConfig::new();
         cfg.set("bootstrap.servers", &host)
@@ -2092,20 +2777,7 @@ fn fetch_topics_async(app: &AppState, tx: mpsc::UnboundedSender<TuiEvent>) {
             .set("enable.auto.commit", "false")
             .set("auto.offset.reset", "earliest")
             .set("enable.partition.eof", "true");
-        if let Some(ssl) = &ssl {
-            if ssl.ca_pem.is_some() || ssl.cert_pem.is_some() || ssl.key_pem.is_some() {
-                cfg.set("security.protocol", "ssl");
-                if let Some(ref s) = ssl.ca_pem {
-                    cfg.set("ssl.ca.pem", s);
-                }
-                if let Some(ref s) = ssl.cert_pem {
-                    cfg.set("ssl.certificate.pem", s);
-                }
-                if let Some(ref s) = ssl.key_pem {
-                    cfg.set("ssl.key.pem", s);
-                }
-            }
-        }
+        apply_security(&mut cfg, ssl.as_ref(), sasl.as_ref());
         let list = async {
             struct QuietContext;
             impl ClientContext for QuietContext {
@@ -2140,6 +2812,7 @@ fn fetch_topics_with_partitions_async(app: &AppState, tx: mpsc::UnboundedSender<
         .map(|e| e.host.clone())
         .unwrap_or_else(|| app.host.clone());
     let ssl = app.current_ssl_config();
+    let sasl = app.current_sasl_config();
     tokio::spawn(async move {
         let mut cfg = ClientConfig::new();
         cfg.set("bootstrap.servers", &host)
@@ -2147,20 +2820,7 @@ fn fetch_topics_with_partitions_async(app: &AppState, tx: mpsc::UnboundedSender<
             .set("enable.auto.commit", "false")
             .set("auto.offset.reset", "earliest")
             .set("enable.partition.eof", "true");
-        if let Some(ssl) = &ssl {
-            if ssl.ca_pem.is_some() || ssl.cert_pem.is_some() || ssl.key_pem.is_some() {
-                cfg.set("security.protocol", "ssl");
-                if let Some(ref s) = ssl.ca_pem {
-                    cfg.set("ssl.ca.pem", s);
-                }
-                if let Some(ref s) = ssl.cert_pem {
-                    cfg.set("ssl.certificate.pem", s);
-                }
-                if let Some(ref s) = ssl.key_pem {
-                    cfg.set("ssl.key.pem", s);
-                }
-            }
-        }
+        apply_security(&mut cfg, ssl.as_ref(), sasl.as_ref());
         let list = async {
             struct QuietContext;
             impl ClientContext for QuietContext {
@@ -2241,6 +2901,7 @@ fn env_editor_fields(app: &AppState, root: Rect) -> Option<Vec<Rect>> {
             Constraint::Length(3),
             Constraint::Length(3),
             Constraint::Min(5),
+            Constraint::Length(3),
             Constraint::Min(5),
             Constraint::Min(5),
             Constraint::Length(3),
@@ -2251,7 +2912,7 @@ fn env_editor_fields(app: &AppState, root: Rect) -> Option<Vec<Rect>> {
 }
 
 fn handle_env_copy_paste_click(app: &mut AppState, fields: &[Rect], mx: u16, my: u16) -> bool {
-    if fields.len() < 7 || app.env_editor.is_none() {
+    if fields.len() < 8 || app.env_editor.is_none() {
         return false;
     }
     if let Some(button) = detect_title_button(
@@ -2358,7 +3019,7 @@ fn handle_env_copy_paste_click(app: &mut AppState, fields: &[Rect], mx: u16, my:
         return true;
     }
     if let Some(button) = detect_title_button(
-        fields[3],
+        fields[4],
         mx,
         my,
         &[
@@ -2393,7 +3054,7 @@ fn handle_env_copy_paste_click(app: &mut AppState, fields: &[Rect], mx: u16, my:
         return true;
     }
     if let Some(button) = detect_title_button(
-        fields[4],
+        fields[5],
         mx,
         my,
         &[
@@ -2428,7 +3089,7 @@ fn handle_env_copy_paste_click(app: &mut AppState, fields: &[Rect], mx: u16, my:
         return true;
     }
     if let Some(button) = detect_title_button(
-        fields[6],
+        fields[7],
         mx,
         my,
         &[
@@ -3057,11 +3718,7 @@ fn build_topic_suggestions(topics: &[String], filter: &str) -> Vec<String> {
             scored.push((distance, score, name));
         }
     }
-    scored.sort_by(|a, b| {
-        a.0.cmp(&b.0)
-            .then(b.1.cmp(&a.1))
-            .then_with(|| a.2.cmp(b.2))
-    });
+    scored.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then_with(|| a.2.cmp(b.2)));
     scored
         .into_iter()
         .map(|(_, _, name)| name.clone())
@@ -3087,9 +3744,7 @@ fn levenshtein_chars(a: &[char], b: &[char]) -> usize {
         curr[0] = i + 1;
         for (j, ac) in a.iter().enumerate() {
             let cost = if ac == bc { 0 } else { 1 };
-            curr[j + 1] = (curr[j] + 1)
-                .min(prev[j + 1] + 1)
-                .min(prev[j] + cost);
+            curr[j + 1] = (curr[j] + 1).min(prev[j + 1] + 1).min(prev[j] + cost);
         }
         prev.copy_from_slice(&curr);
     }
@@ -3136,10 +3791,76 @@ fn move_autocomplete_selection(app: &mut AppState, down: bool) {
     }
 }
 
+/// Keys for the focused detail pane: Up/Down, PageUp/PageDown and Home/End
+/// move the line cursor and Enter/Space fold the object or array under it.
+/// Returns false for keys it leaves to the main handler.
+fn detail_key(app: &mut AppState, code: KeyCode) -> bool {
+    let tree = super::ui::detail_content(app);
+    let last = tree.lines.len().saturating_sub(1);
+    let cursor = app.tab.detail_cursor.min(last);
+    app.tab.detail_cursor = match code {
+        KeyCode::Up => cursor.saturating_sub(1),
+        KeyCode::Down => (cursor + 1).min(last),
+        KeyCode::PageUp => cursor.saturating_sub(RESULTS_PAGE),
+        KeyCode::PageDown => (cursor + RESULTS_PAGE).min(last),
+        KeyCode::Home => 0,
+        KeyCode::End => last,
+        KeyCode::Enter | KeyCode::Char(' ') => match tree.nodes.get(cursor).cloned().flatten() {
+            Some(path) => {
+                app.toggle_fold(path.clone());
+                // Stay on the node, which may now start on its key line
+                super::ui::detail_content(app)
+                    .nodes
+                    .iter()
+                    .position(|n| n.as_ref() == Some(&path))
+                    .unwrap_or(cursor)
+            }
+            None => {
+                app.status = "No object or array to fold on this line".to_string();
+                cursor
+            }
+        },
+        _ => return false,
+    };
+    app.scroll_to_detail_cursor(detail_view_height(app));
+    true
+}
+
+/// Text rows inside the detail pane at the current terminal size.
+fn detail_view_height(app: &AppState) -> usize {
+    let (width, height) = crossterm::terminal::size().unwrap_or((0, 0));
+    let root = Rect {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    };
+    let rows = super::ui::home_rows(root, app);
+    let detail = super::ui::home_cols(rows[3], app)[1];
+    detail.height.saturating_sub(2) as usize
+}
+
+/// The results motion a vim-style key stands for when `vim_mode` is on.
+/// `h` and `g` are not mapped: they open the headers popup and the
+/// partition prompt, and Left and Home already do their moves.
+fn vim_motion(code: KeyCode, modifiers: KeyModifiers) -> Option<ResultsMotion> {
+    let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+    Some(match code {
+        KeyCode::Char('d') if ctrl => ResultsMotion::PageDown,
+        KeyCode::Char('u') if ctrl => ResultsMotion::PageUp,
+        _ if ctrl || modifiers.contains(KeyModifiers::ALT) => return None,
+        KeyCode::Char('j') => ResultsMotion::Down,
+        KeyCode::Char('k') => ResultsMotion::Up,
+        KeyCode::Char('l') => ResultsMotion::Right,
+        KeyCode::Char('G') => ResultsMotion::Bottom,
+        _ => return None,
+    })
+}
+
 fn total_results_rows(app: &AppState) -> usize {
-    match app.results_mode {
-        ResultsMode::Messages => app.rows.len(),
-        ResultsMode::TopicList => app.topics_with_partitions.len(),
+    match app.tab.results_mode {
+        ResultsMode::Messages => app.shown_len(),
+        ResultsMode::TopicList => app.tab.topics_with_partitions.len(),
     }
 }
 
@@ -3151,3 +3872,198 @@ fn freeze_autocomplete_at_cursor(app: &mut AppState) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::app::Tab;
+
+    #[test]
+    fn vim_keys_move_the_results_selection() {
+        let row = |offset| MessageEnvelope::at(offset).on_topic("orders");
+        let mut app = AppState {
+            tab: Tab {
+                rows: (0..25).map(row).collect(),
+                selected_columns: vec![SelectItem::Offset, SelectItem::Key, SelectItem::Value],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let none = KeyModifiers::NONE;
+        let ctrl = KeyModifiers::CONTROL;
+        let mut press = |ch, modifiers| {
+            let motion = vim_motion(KeyCode::Char(ch), modifiers).expect("vim key");
+            app.move_results(motion);
+            (app.tab.selected_row, app.tab.selected_col)
+        };
+        assert_eq!(press('j', none), (1, 0));
+        assert_eq!(press('l', none), (1, 1));
+        assert_eq!(press('d', ctrl), (11, 1));
+        assert_eq!(press('G', KeyModifiers::SHIFT), (24, 1));
+        assert_eq!(press('k', none), (23, 1));
+        assert_eq!(press('u', ctrl), (13, 1));
+        assert_eq!(press('l', none), (13, 2));
+        assert_eq!(press('l', none), (13, 2));
+
+        // Other keys, and plain d/u, are left to the usual handlers
+        assert_eq!(vim_motion(KeyCode::Char('d'), none), None);
+        // h and g keep opening the headers popup and the partition prompt
+        assert_eq!(vim_motion(KeyCode::Char('h'), none), None);
+        assert_eq!(vim_motion(KeyCode::Char('g'), none), None);
+        assert_eq!(vim_motion(KeyCode::Char('j'), ctrl), None);
+        assert_eq!(vim_motion(KeyCode::Down, none), None);
+    }
+
+    #[test]
+    fn export_writes_whole_values_for_the_shown_rows() {
+        let row = |key: &str, value: &str| {
+            MessageEnvelope::at(7)
+                .on_topic("orders")
+                .with_key(key)
+                .with_value(value)
+        };
+        let long = "x".repeat(500);
+        let pretty = format!("{{\n  \"note\": \"{}\",\n  \"n\": 1\n}}", long);
+        let mut app = AppState {
+            tab: Tab {
+                rows: vec![row("a", &pretty), row("b", "plain, text")],
+                selected_columns: vec![SelectItem::Key, SelectItem::Value],
+                column_aliases: vec![(SelectItem::Key, "id".to_string())],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let export = |app: &AppState, format| {
+            let mut out = Vec::new();
+            let n = write_export(app, &mut out, format);
+            (n, String::from_utf8(out).unwrap())
+        };
+
+        let (n, ndjson) = export(&app, OutputFormat::Ndjson);
+        assert_eq!(n, 2);
+        assert_eq!(
+            ndjson,
+            format!(
+                "{{\"id\":\"a\",\"value\":{{\"note\":\"{}\",\"n\":1}}}}\n{{\"id\":\"b\",\"value\":\"plain, text\"}}\n",
+                long
+            )
+        );
+        // CSV and a JSON array keep the value's own text
+        let (_, csv) = export(&app, OutputFormat::Csv);
+        assert!(csv.starts_with("id,value\n"));
+        assert!(csv.contains(&format!("a,\"{}\"\n", pretty.replace('"', "\"\""))));
+        assert!(csv.ends_with("b,\"plain, text\"\n"));
+        let (_, json) = export(&app, OutputFormat::Json);
+        assert!(json.contains(&format!("{{\"id\":\"a\",\"value\":{}}}", pretty)));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap()[0]["value"]["n"],
+            1
+        );
+
+        // Only the rows the / filter shows are exported
+        app.set_row_filter("PLAIN".to_string());
+        let (n, json) = export(&app, OutputFormat::Json);
+        assert_eq!(n, 1);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::json!([{"id": "b", "value": "plain, text"}])
+        );
+
+        let path = |p: &str| export_format(std::path::Path::new(p));
+        assert_eq!(path("/tmp/out.CSV"), OutputFormat::Csv);
+        assert_eq!(path("out.json"), OutputFormat::Json);
+        assert_eq!(path("out.ndjson"), OutputFormat::Ndjson);
+        assert_eq!(path("out"), OutputFormat::Ndjson);
+    }
+
+    #[test]
+    fn ctrl_o_loads_a_pem_file_into_the_focused_field() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ca.pem");
+        let pem = "-----BEGIN CERTIFICATE-----\nQUJD\n-----END CERTIFICATE-----\n";
+        std::fs::write(&path, pem).unwrap();
+        let env = Environment {
+            name: "prod".to_string(),
+            host: "kafka:9093".to_string(),
+            private_key_pem: None,
+            key_password: None,
+            public_key_pem: None,
+            ssl_ca_pem: Some(format!("  {}  ", path.display())),
+            sasl: None,
+        };
+        let mut ed = build_env_editor_from_env(&env, Some(0));
+
+        // Only PEM fields take a path
+        assert!(load_pem_from_path(&mut ed).is_err());
+        ed.field_focus = EnvFieldFocus::Ca;
+        let message = load_pem_from_path(&mut ed).unwrap();
+        assert!(message.starts_with("CA: loaded"), "{message}");
+        assert_eq!(
+            ed.ta_ca.lines(),
+            [
+                "-----BEGIN CERTIFICATE-----",
+                "QUJD",
+                "-----END CERTIFICATE-----"
+            ]
+        );
+
+        // A missing file is reported and the typed path is kept
+        ed.field_focus = EnvFieldFocus::PublicKey;
+        ed.ta_public = text_area_from_string(dir.path().join("missing.pem").display().to_string());
+        let err = load_pem_from_path(&mut ed).unwrap_err();
+        assert!(err.contains("no such file"), "{err}");
+        assert_eq!(ed.ta_public.lines().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn pipeline_replays_a_file_through_the_tui_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("orders.ndjson");
+        let lines = [
+            r#"{"partition":0,"offset":0,"timestamp_ms":10,"key":"a","value":{"n":1}}"#,
+            r#"{"partition":1,"offset":0,"timestamp_ms":20,"key":"b","value":{"n":5}}"#,
+            r#"{"partition":0,"offset":1,"timestamp_ms":30,"key":"c","value":{"n":9}}"#,
+            r#"{"topic":"other","partition":0,"offset":2,"timestamp_ms":40,"key":"d","value":{"n":9}}"#,
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let args = RunArgs {
+            from_file: Some(path),
+            ..RunArgs::default()
+        };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        run_pipeline_with_ssl(
+            args,
+            "SELECT key FROM orders WHERE value->n > 1".to_string(),
+            7,
+            tx,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let mut keys = Vec::new();
+        let mut done = false;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                TuiEvent::Batch { run_id, rows } => {
+                    assert_eq!(run_id, 7);
+                    keys.extend(rows.into_iter().map(|env| env.key));
+                }
+                TuiEvent::Done { summary, .. } => {
+                    assert_eq!(
+                        (summary.scanned, summary.matched, summary.returned),
+                        (3, 2, 2)
+                    );
+                    assert_eq!(summary.partitions, 2);
+                    assert!(!summary.limit_hit);
+                    done = true;
+                }
+                _ => {}
+            }
+        }
+        assert!(done);
+        assert_eq!(keys, vec!["b", "c"]);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::args::config_path;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// TUI preferences that survive restarts, stored in the `[tui]` table of
+/// ~/.rkl/config.toml.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct UiSettings {
+    /// Detail pane shows indented JSON (true) or a single compact line.
+    pub detail_pretty: bool,
+    pub panes: PaneLayout,
+    pub row_eviction: RowEviction,
+    /// The `e` errors-only preset; `{topic}` becomes the current topic.
+    pub errors_query: String,
+    /// Up/Down in the results list wrap from the last row to the first and back.
+    pub wrap_rows: bool,
+    /// h/j/k/l, g/G and Ctrl-D/Ctrl-U move the results selection.
+    pub vim_mode: bool,
+}
+
+/// Default errors-only preset: error text, error/fatal levels, or 5xx statuses.
+pub const DEFAULT_ERRORS_QUERY: &str = "SELECT key, value FROM {topic} WHERE value CONTAINS 'error' OR value->level IN ('ERROR', 'FATAL') OR value->status >= 500 LIMIT 100";
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        Self {
+            detail_pretty: true,
+            panes: PaneLayout::default(),
+            row_eviction: RowEviction::default(),
+            errors_query: DEFAULT_ERRORS_QUERY.to_string(),
+            wrap_rows: false,
+            vim_mode: false,
+        }
+    }
+}
+
+/// The errors-only statement for `topic` from the configured template.
+pub fn errors_only_query(template: &str, topic: &str) -> String {
+    template.trim().replace("{topic}", topic)
+}
+
+/// What the results buffer does once it holds `max_rows_in_memory` rows.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RowEviction {
+    /// Drop the oldest rows to make room: shows the latest matches (tail-like).
+    #[default]
+    DropOldest,
+    /// Keep the first rows and ignore the rest: preserves the start of a scan.
+    StopAtCap,
+    /// Keep a uniform random sample of every row seen; rows lose arrival order.
+    ReservoirSample,
+}
+
+pub const EDITOR_HEIGHT_RANGE: (u16, u16) = (5, 30);
+pub const TABLE_PERCENT_RANGE: (u16, u16) = (30, 85);
+const TABLE_PERCENT_STEP: i16 = 4;
+
+/// Home screen split: rows given to the editor/status band and the share of
+/// the results area taken by the table (the detail pane gets the rest).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PaneLayout {
+    pub editor_height: u16,
+    pub table_percent: u16,
+}
+
+impl Default for PaneLayout {
+    fn default() -> Self {
+        Self {
+            editor_height: 10,
+            table_percent: 68,
+        }
+    }
+}
+
+impl PaneLayout {
+    /// Grow (`delta > 0`) or shrink the editor band by `delta` rows.
+    pub fn resize_editor(&mut self, delta: i16) {
+        self.editor_height = step_clamped(self.editor_height, delta, EDITOR_HEIGHT_RANGE);
+    }
+
+    /// Widen (`delta > 0`) or narrow the table by `delta` steps.
+    pub fn resize_table(&mut self, delta: i16) {
+        self.table_percent = step_clamped(
+            self.table_percent,
+            delta * TABLE_PERCENT_STEP,
+            TABLE_PERCENT_RANGE,
+        );
+    }
+
+    /// The same layout with hand-edited config values pulled back into range.
+    pub fn clamped(self) -> Self {
+        Self {
+            editor_height: step_clamped(self.editor_height, 0, EDITOR_HEIGHT_RANGE),
+            table_percent: step_clamped(self.table_percent, 0, TABLE_PERCENT_RANGE),
+        }
+    }
+}
+
+fn step_clamped(value: u16, delta: i16, (min, max): (u16, u16)) -> u16 {
+    (value as i32 + delta as i32).clamp(min as i32, max as i32) as u16
+}
+
+impl UiSettings {
+    pub fn load() -> Self {
+        Self::load_from(&config_path())
+    }
+
+    /// The `[tui]` table of `path`, falling back to the config.json next to it
+    /// that older versions wrote, until the TUI next saves.
+    pub fn load_from(path: &Path) -> Self {
+        Self::read_toml(path)
+            .or_else(|| Self::read_legacy_json(&path.with_file_name("config.json")))
+            .unwrap_or_default()
+    }
+
+    /// The `[tui]` table of `path`; `None` when the file or table is missing
+    /// or does not parse.
+    fn read_toml(path: &Path) -> Option<Self> {
+        let mut file: toml::Table = fs::read_to_string(path).ok()?.parse().ok()?;
+        file.remove("tui")?.try_into().ok()
+    }
+
+    fn read_legacy_json(path: &Path) -> Option<Self> {
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&config_path())
+    }
+
+    /// Rewrite only the `[tui]` table of `path`; the rest of the file,
+    /// comments included, is kept as written.
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let mut doc = match fs::read_to_string(path) {
+            Ok(text) => text
+                .parse::<toml_edit::DocumentMut>()
+                .with_context(|| format!("{} is not valid TOML", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Default::default(),
+            Err(e) => return Err(e).with_context(|| format!("read {}", path.display())),
+        };
+        let tui = toml_edit::ser::to_document(self).context("serialize settings")?;
+        doc["tui"] = toml_edit::Item::Table(tui.as_table().clone());
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context("create config dir")?;
+        }
+        fs::write(path, doc.to_string()).context("write config file")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pane_resizes_stay_within_bounds() {
+        let mut panes = PaneLayout::default();
+        for _ in 0..100 {
+            panes.resize_editor(1);
+            panes.resize_table(1);
+        }
+        assert_eq!(panes.editor_height, EDITOR_HEIGHT_RANGE.1);
+        assert_eq!(panes.table_percent, TABLE_PERCENT_RANGE.1);
+        for _ in 0..100 {
+            panes.resize_editor(-1);
+            panes.resize_table(-1);
+        }
+        assert_eq!(panes.editor_height, EDITOR_HEIGHT_RANGE.0);
+        assert_eq!(panes.table_percent, TABLE_PERCENT_RANGE.0);
+
+        panes.resize_table(2);
+        assert_eq!(panes.table_percent, TABLE_PERCENT_RANGE.0 + 8);
+
+        // Out-of-range values from a hand-edited config are clamped
+        let wild = PaneLayout {
+            editor_height: 0,
+            table_percent: 250,
+        };
+        assert_eq!(
+            wild.clamped(),
+            PaneLayout {
+                editor_height: EDITOR_HEIGHT_RANGE.0,
+                table_percent: TABLE_PERCENT_RANGE.1,
+            }
+        );
+    }
+
+    #[test]
+    fn older_config_without_panes_loads_defaults() {
+        let s: UiSettings = toml::from_str("detail_pretty = false").unwrap();
+        assert!(!s.detail_pretty);
+        assert_eq!(s.panes, PaneLayout::default());
+        assert_eq!(s.errors_query, DEFAULT_ERRORS_QUERY);
+        // Settings saved by versions that used config.json still load
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("config.json"),
+            r#"{"detail_pretty": false, "wrap_rows": true}"#,
+        )
+        .unwrap();
+        let s = UiSettings::load_from(&dir.path().join("config.toml"));
+        assert!(!s.detail_pretty && s.wrap_rows);
+    }
+
+    #[test]
+    fn saving_keeps_the_rest_of_config_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let written =
+            "# shared defaults\nbroker = \"kafka-1:9092\" # prod\n\n[tui]\nwrap_rows = true\n";
+        fs::write(&path, written).unwrap();
+        let mut settings = UiSettings::load_from(&path);
+        assert!(settings.wrap_rows);
+        settings.panes.editor_height = 12;
+        settings.save_to(&path).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# shared defaults\nbroker = \"kafka-1:9092\" # prod\n"));
+        assert_eq!(UiSettings::load_from(&path), settings);
+        // The CLI defaults in the same file still load
+        let config = crate::args::ConfigDefaults::load_from(&path).unwrap();
+        assert_eq!(config.broker.unwrap(), ["kafka-1:9092"]);
+
+        // A file that is not TOML is left alone rather than overwritten
+        fs::write(&path, "broker = [").unwrap();
+        assert!(settings.save_to(&path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "broker = [");
+    }
+
+    #[test]
+    fn errors_preset_expands_the_configured_template() {
+        let s: UiSettings = toml::from_str(
+            r#"errors_query = " SELECT key FROM {topic} WHERE value->severity = 'high' LIMIT 20 ""#,
+        )
+        .unwrap();
+        assert_eq!(
+            errors_only_query(&s.errors_query, "payments"),
+            "SELECT key FROM payments WHERE value->severity = 'high' LIMIT 20"
+        );
+        let default = errors_only_query(DEFAULT_ERRORS_QUERY, "orders");
+        assert_eq!(
+            default,
+            "SELECT key, value FROM orders WHERE value CONTAINS 'error' OR value->level IN ('ERROR', 'FATAL') OR value->status >= 500 LIMIT 100"
+        );
+        assert!(crate::query::parse_query(&default).is_ok());
+    }
+}
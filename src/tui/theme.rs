@@ -0,0 +1,148 @@
+use ratatui::style::{Color, Modifier, Style};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// TUI colors, read once from ~/.rkl/theme.toml. Each key takes a color name
+/// (`light_cyan`), a 256-color index (`208` or `"208"`) or `#rrggbb`; keys
+/// that are missing or do not parse keep their default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// JSON object keys.
+    pub key: Color,
+    /// Strings in JSON and in the query editor.
+    pub string: Color,
+    /// Numbers in JSON and in the query editor.
+    pub number: Color,
+    /// SQL keywords in the query editor.
+    pub keyword: Color,
+    /// JSON `true`/`false`.
+    pub boolean: Color,
+    pub null: Color,
+    /// JSON brackets, commas and colons, and query punctuation.
+    pub punctuation: Color,
+    /// Background of the selected results cell; unset draws it reversed.
+    pub selection: Option<Color>,
+    pub border: Color,
+    /// Border of the pane with focus.
+    pub border_focused: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            key: Color::Green,
+            string: Color::Yellow,
+            number: Color::Cyan,
+            keyword: Color::LightCyan,
+            boolean: Color::Magenta,
+            null: Color::DarkGray,
+            punctuation: Color::Gray,
+            selection: None,
+            border: Color::DarkGray,
+            border_focused: Color::LightCyan,
+        }
+    }
+}
+
+impl Theme {
+    pub fn load() -> Self {
+        Self::load_from(&theme_path())
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .map(|s| Self::from_toml(&s))
+            .unwrap_or_default()
+    }
+
+    /// The keys set in `text` over the defaults. Each key is read on its own,
+    /// so one bad value keeps only that key's default, and malformed TOML
+    /// gives the defaults rather than an error: a bad theme never blocks startup.
+    pub fn from_toml(text: &str) -> Self {
+        let file: toml::Table = toml::from_str(text).unwrap_or_default();
+        let color = |name: &str| file.get(name).and_then(parse_color);
+        let d = Self::default();
+        Self {
+            key: color("key").unwrap_or(d.key),
+            string: color("string").unwrap_or(d.string),
+            number: color("number").unwrap_or(d.number),
+            keyword: color("keyword").unwrap_or(d.keyword),
+            boolean: color("boolean").unwrap_or(d.boolean),
+            null: color("null").unwrap_or(d.null),
+            punctuation: color("punctuation").unwrap_or(d.punctuation),
+            selection: color("selection"),
+            border: color("border").unwrap_or(d.border),
+            border_focused: color("border_focused").unwrap_or(d.border_focused),
+        }
+    }
+
+    pub fn border_style(&self, focused: bool) -> Style {
+        let color = if focused {
+            self.border_focused
+        } else {
+            self.border
+        };
+        Style::default().fg(color)
+    }
+
+    pub fn selected_cell_style(&self) -> Style {
+        match self.selection {
+            Some(bg) => Style::default().bg(bg).add_modifier(Modifier::BOLD),
+            None => Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+        }
+    }
+}
+
+/// A color written as a string, or as a bare 256-color index.
+fn parse_color(value: &toml::Value) -> Option<Color> {
+    match value {
+        toml::Value::String(s) => Color::from_str(s.trim()).ok(),
+        toml::Value::Integer(n) => u8::try_from(*n).ok().map(Color::Indexed),
+        _ => None,
+    }
+}
+
+pub fn theme_path() -> PathBuf {
+    std::env::var("HOME")
+        .map(|h| PathBuf::from(h).join(".rkl").join("theme.toml"))
+        .unwrap_or_else(|_| PathBuf::from(".rkl").join("theme.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_theme_merges_over_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("theme.toml");
+        fs::write(
+            &path,
+            "key = \"light_blue\"\nnumber = \"#ff8800\"\nselection = \"208\"\n\
+             string = \"not-a-color\"\nfont = \"mono\"\nborder = 99\n\
+             null = 300\nboolean = true\n\n[extra]\nsize = 3\n",
+        )
+        .unwrap();
+        let theme = Theme::load_from(&path);
+
+        assert_eq!(theme.key, Color::LightBlue);
+        assert_eq!(theme.number, Color::Rgb(0xff, 0x88, 0x00));
+        assert_eq!(theme.selection, Some(Color::Indexed(208)));
+        assert_eq!(theme.border, Color::Indexed(99));
+        // Unparseable and unset keys keep their defaults
+        let d = Theme::default();
+        assert_eq!(theme.string, d.string);
+        assert_eq!(theme.null, d.null);
+        assert_eq!(theme.boolean, d.boolean);
+        assert_eq!(theme.border_focused, d.border_focused);
+
+        assert_eq!(Theme::from_toml("key = [unclosed"), d);
+        assert_eq!(
+            Theme::from_toml("selection = 208\nkey = 1.5").selection,
+            Some(Color::Indexed(208))
+        );
+        assert_eq!(Theme::load_from(&dir.path().join("missing.toml")), d);
+    }
+}
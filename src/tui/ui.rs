@@ -1,5 +1,5 @@
-use crate::models::MessageEnvelope;
-use crate::query::SelectItem;
+use crate::models::{MessageEnvelope, MessageHeader, ReadProgress};
+use crate::query::{MessageView, RootPath, SelectItem, column_alias};
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::prelude::*;
 use ratatui::style::{Color, Modifier, Style};
@@ -9,34 +9,57 @@ use ratatui::widgets::{
     ScrollbarOrientation, ScrollbarState, Table, TableState, Wrap,
 };
 
-use super::app::{AppState, EnvFieldFocus, Focus, ResultsMode, Screen};
+use super::app::{
+    AppState, EnvFieldFocus, Focus, NEW_ROW_HIGHLIGHT, ResultsMode, ScanSummary, Screen,
+    column_text, column_width,
+};
 use super::query_bounds::find_query_range;
+use super::theme::Theme;
+use std::collections::HashSet;
 
 pub(super) const COPY_BTN_LABEL: &str = "[ Copy ]";
 
+/// Home screen rows: env bar, editor + status, results, footer. Shared with
+/// the mouse hit-testing in the runner.
+pub(super) fn home_rows(area: Rect, app: &AppState) -> std::rc::Rc<[Rect]> {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(app.panes.editor_height),
+            // Tab bar, only once a second tab is open
+            Constraint::Length(u16::from(app.tabs.len() > 1)),
+            Constraint::Fill(1),
+            Constraint::Length(3),
+        ])
+        .split(area)
+}
+
+/// Split of the editor band and of the results area: `[left, right]`.
+pub(super) fn home_cols(area: Rect, app: &AppState) -> std::rc::Rc<[Rect]> {
+    let left = app.panes.table_percent;
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(left),
+            Constraint::Percentage(100 - left),
+        ])
+        .split(area)
+}
+
 pub fn draw(frame: &mut Frame, app: &AppState) {
     let size = frame.area();
     match app.screen {
         Screen::Home => {
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([
-                    Constraint::Length(3),  // env bar
-                    Constraint::Length(10), // editor + status
-                    Constraint::Fill(1),    // results
-                    Constraint::Length(3),  // footer
-                ])
-                .split(size);
+            let chunks = home_rows(size, app);
 
             draw_env_bar(frame, chunks[0], app);
-            let cols = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(68), Constraint::Percentage(32)])
-                .split(chunks[1]);
+            let cols = home_cols(chunks[1], app);
             draw_input(frame, cols[0], app);
             draw_status_panel(frame, cols[1], app);
-            draw_results(frame, chunks[2], app);
-            draw_footer(frame, chunks[3], app);
+            draw_tab_bar(frame, chunks[2], app);
+            draw_results(frame, chunks[3], app);
+            draw_footer(frame, chunks[4], app);
         }
         Screen::Envs => {
             // Full-screen environments UI
@@ -63,6 +86,50 @@ pub fn draw(frame: &mut Frame, app: &AppState) {
         }
     }
 
+    if let Some(summary) = &app.tab.scan_summary {
+        draw_scan_summary(frame, size, summary);
+    }
+    if app.tab.show_headers {
+        draw_headers_overlay(frame, size, app);
+    }
+    if let Some(cursor) = app.tab.column_picker {
+        draw_column_picker(frame, size, app, cursor);
+    }
+    if let Some(name) = app.save_prompt.as_deref() {
+        draw_prompt(frame, size, "Save query as  Enter save, Esc cancel", name);
+    }
+    if let Some(path) = app.tab.export_prompt.as_deref() {
+        draw_prompt(
+            frame,
+            size,
+            "Export rows to (.csv, .json or .ndjson)  Enter write, Esc cancel",
+            path,
+        );
+    }
+    if let Some(digits) = app.tab.partition_prompt.as_deref() {
+        draw_prompt(
+            frame,
+            size,
+            "Jump to partition  Enter go, Esc cancel",
+            digits,
+        );
+    }
+    if let Some(digits) = app.tab.offset_prompt.as_deref() {
+        draw_prompt(
+            frame,
+            size,
+            "Jump to offset in this partition  Enter go, Esc cancel",
+            digits,
+        );
+    }
+    if app.tab.filter_prompt {
+        draw_prompt(
+            frame,
+            size,
+            "Filter rows  Enter keep, Esc clear",
+            &app.tab.row_filter,
+        );
+    }
     if app.show_help {
         draw_help_overlay(frame, size, app);
     }
@@ -71,11 +138,7 @@ pub fn draw(frame: &mut Frame, app: &AppState) {
 fn draw_input(frame: &mut Frame, area: Rect, app: &AppState) {
     let focused = app.focus == Focus::Query;
     let title = "Query (Ctrl-Enter runs current SELECT; ';' ends)";
-    let border_style = if focused {
-        Style::default().fg(Color::LightCyan)
-    } else {
-        Style::default().fg(Color::DarkGray)
-    };
+    let border_style = app.theme.border_style(focused);
     let block = Block::default()
         .borders(Borders::ALL)
         .title(title)
@@ -119,7 +182,7 @@ fn draw_input(frame: &mut Frame, area: Rect, app: &AppState) {
     let mut out_lines: Vec<Line> = Vec::with_capacity(lines.len());
     for (i, &lstart) in line_starts.iter().enumerate() {
         let lend = lstart + lines[i].len();
-        let mut line = Line::from(highlight_sql_line(lines[i]));
+        let mut line = Line::from(highlight_sql_line(lines[i], &app.theme));
         if intersects(lstart, lend, cur_q_start, cur_q_end) {
             // Current query highlight
             line = line.style(Style::default().bg(Color::Rgb(35, 60, 100)));
@@ -276,30 +339,42 @@ fn draw_input(frame: &mut Frame, area: Rect, app: &AppState) {
 
 fn draw_env_bar(frame: &mut Frame, area: Rect, app: &AppState) {
     let title = "Environment (F2 to manage)";
-    let border_style = if app.focus == Focus::Host {
-        Style::default().fg(Color::LightCyan)
-    } else {
-        Style::default().fg(Color::DarkGray)
-    };
+    let border_style = app.theme.border_style(app.focus == Focus::Host);
     let block = Block::default()
         .borders(Borders::ALL)
         .title(title)
         .border_style(border_style);
-    let name = app
-        .selected_env()
-        .map(|e| e.name.clone())
-        .unwrap_or_else(|| "(none)".to_string());
-    let host = app
-        .selected_env()
-        .map(|e| e.host.clone())
-        .unwrap_or_default();
-    let content = format!("{name}  —  host: {host}");
-    let para = Paragraph::new(content).block(block);
+    let para = Paragraph::new(app.env_bar.as_str()).block(block);
     frame.render_widget(para, area);
 }
 
+/// Status title text for how far a run has read, e.g. `read 42% · lag 1200`.
+fn read_progress_label(progress: &ReadProgress) -> Option<String> {
+    let percent = progress.percent()?;
+    Some(format!(
+        "read {:.0}% · lag {}",
+        percent.floor(),
+        progress.lag()
+    ))
+}
+
 fn draw_status_panel(frame: &mut Frame, area: Rect, app: &AppState) {
-    let block = Block::default().borders(Borders::ALL).title("Status");
+    let mut title = vec![Span::raw("Status")];
+    if !app.tab.rate_samples.is_empty() {
+        let style = if std::env::var_os("NO_COLOR").is_some() {
+            Style::default()
+        } else {
+            Style::default().fg(Color::Cyan)
+        };
+        title.push(Span::raw(" "));
+        title.push(Span::styled(sparkline(&app.tab.rate_samples), style));
+    }
+    if let Some(label) = app.tab.read_progress.as_ref().and_then(read_progress_label) {
+        title.push(Span::raw(format!(" {}", label)));
+    }
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Line::from(title));
     let inner = block.inner(area);
     frame.render_widget(block, area);
     let text = if app.status_buffer.is_empty() {
@@ -343,6 +418,17 @@ fn draw_status_panel(frame: &mut Frame, area: Rect, app: &AppState) {
     }
 }
 
+/// One block character per sample, scaled to the largest sample.
+fn sparkline<'a>(counts: impl IntoIterator<Item = &'a u64>) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let counts: Vec<u64> = counts.into_iter().copied().collect();
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    counts
+        .iter()
+        .map(|&c| BARS[(c * 7).div_ceil(max) as usize])
+        .collect()
+}
+
 fn draw_footer(frame: &mut Frame, area: Rect, app: &AppState) {
     let legend = footer_legend(app);
     let block = Block::default().borders(Borders::ALL).title("Help");
@@ -353,9 +439,10 @@ fn draw_footer(frame: &mut Frame, area: Rect, app: &AppState) {
 fn footer_legend(app: &AppState) -> String {
     match app.screen {
         Screen::Home => match app.focus {
-            Focus::Query => "Tab focus | Query: Enter newline, Ctrl-Enter run, Right accept autocomplete, Ctrl-N/P navigate autocomplete | F10 Help | Ctrl-Q/C quit".to_string(),
-            Focus::Results => "Tab focus | Results: arrows select, Shift-←/→ h-scroll, F5 copy value, F7 copy status | F10 Help | Ctrl-Q/C quit".to_string(),
-            Focus::Host => "Tab focus | Host: Enter open envs, F2 Envs | F10 Help | Ctrl-Q/C quit".to_string(),
+            Focus::Query => "Tab focus | Query: Enter newline, Ctrl-Enter run, Ctrl-F follow, Up/Down history (empty editor), Right accept autocomplete, Ctrl-N/P navigate autocomplete, Ctrl-S save as, Ctrl-E export, Ctrl-T new tab | F10 Help | Ctrl-Q/C quit".to_string(),
+            Focus::Results => "Tab focus | Results: arrows select, Shift-←/→ h-scroll, f flat/tree details, m pretty/compact, w WHERE emphasis, / filter rows, g jump to partition, o jump to offset, h headers, p pin cols, </> move col, c columns, +/- col width, b diff base, Ctrl-arrows resize, F5 copy value, F7 copy status | F10 Help | Ctrl-Q/C quit".to_string(),
+            Focus::Detail => "Tab focus | Details: Up/Down move, Enter/Space fold/unfold object or array, PageUp/PageDown/Home/End jump, F5 copy value | F10 Help | Ctrl-Q/C quit".to_string(),
+            Focus::Host => "Tab focus | Host: Enter open envs, F2 Envs, F5 copy connection, Ctrl-arrows resize | F10 Help | Ctrl-Q/C quit".to_string(),
         },
         Screen::Envs => "F4 Save, F5 Test, Tab move, Up/Down select, Esc Close | F10 Help".to_string(),
         Screen::Info => "F6 Refresh, F8 Home | F10 Help | Ctrl-Q/C quit".to_string(),
@@ -398,6 +485,7 @@ fn draw_env_modal(frame: &mut Frame, area: Rect, app: &AppState) {
             Constraint::Length(3),
             Constraint::Length(3),
             Constraint::Min(5),
+            Constraint::Length(3),
             Constraint::Min(5),
             Constraint::Min(5),
             Constraint::Length(3),
@@ -407,6 +495,10 @@ fn draw_env_modal(frame: &mut Frame, area: Rect, app: &AppState) {
 
     let name_val = ed.map(|e| e.name.clone()).unwrap_or_default();
     let host_val = ed.map(|e| e.host.clone()).unwrap_or_default();
+    // Mask the key passphrase; one '*' per byte keeps the caret math in sync
+    let key_password_masked = ed
+        .map(|e| "*".repeat(e.key_password.len()))
+        .unwrap_or_default();
     // Values are drawn via TextAreas; no pre-rendered strings needed here.
 
     let title_name_base = if matches!(ed.map(|e| e.field_focus), Some(EnvFieldFocus::Name)) {
@@ -427,6 +519,12 @@ fn draw_env_modal(frame: &mut Frame, area: Rect, app: &AppState) {
         "Private Key (PEM)"
     };
     let title_pk = format!("{}  [Copy] [Paste] [Clear]", title_pk_base);
+    let title_key_password =
+        if matches!(ed.map(|e| e.field_focus), Some(EnvFieldFocus::KeyPassword)) {
+            "Key Passphrase (optional) [FOCUSED]"
+        } else {
+            "Key Passphrase (optional)"
+        };
     let title_cert_base = if matches!(ed.map(|e| e.field_focus), Some(EnvFieldFocus::PublicKey)) {
         "Public/Certificate (PEM) [FOCUSED]"
     } else {
@@ -450,6 +548,14 @@ fn draw_env_modal(frame: &mut Frame, area: Rect, app: &AppState) {
             .block(Block::default().borders(Borders::ALL).title(title_host)),
         fields[1],
     );
+    frame.render_widget(
+        Paragraph::new(key_password_masked.clone()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title_key_password),
+        ),
+        fields[3],
+    );
     // Render multi-line fields using tui-textarea
     if let Some(edm) = app.env_editor.as_ref() {
         // Draw outer blocks for titles and copy affordance
@@ -463,11 +569,11 @@ fn draw_env_modal(frame: &mut Frame, area: Rect, app: &AppState) {
             .borders(Borders::ALL)
             .title(title_ca.clone());
         let inner_pk = block_pk.inner(fields[2]);
-        let inner_pub = block_pub.inner(fields[3]);
-        let inner_ca = block_ca.inner(fields[4]);
+        let inner_pub = block_pub.inner(fields[4]);
+        let inner_ca = block_ca.inner(fields[5]);
         frame.render_widget(block_pk, fields[2]);
-        frame.render_widget(block_pub, fields[3]);
-        frame.render_widget(block_ca, fields[4]);
+        frame.render_widget(block_pub, fields[4]);
+        frame.render_widget(block_ca, fields[5]);
         frame.render_widget(&edm.ta_private, inner_pk);
         frame.render_widget(&edm.ta_public, inner_pub);
         frame.render_widget(&edm.ta_ca, inner_ca);
@@ -477,6 +583,9 @@ fn draw_env_modal(frame: &mut Frame, area: Rect, app: &AppState) {
             super::app::EnvFieldFocus::Name => caret_pos_in(fields[0], &name_val, ed.name_cursor),
             super::app::EnvFieldFocus::Host => caret_pos_in(fields[1], &host_val, ed.host_cursor),
             // TextArea draws its own cursor; we skip frame.set_cursor for these
+            super::app::EnvFieldFocus::KeyPassword => {
+                caret_pos_in(fields[3], &key_password_masked, ed.key_password_cursor)
+            }
             super::app::EnvFieldFocus::PrivateKey => (0, 0),
             super::app::EnvFieldFocus::PublicKey => (0, 0),
             super::app::EnvFieldFocus::Ca => (0, 0),
@@ -487,10 +596,10 @@ fn draw_env_modal(frame: &mut Frame, area: Rect, app: &AppState) {
             frame.set_cursor_position(Position::new(x, y));
         }
     }
-    let help = "F1 New | F2 Edit | F3 Delete | F4 Save | F5 Test | F6 Next | F7 Prev | F9 Mouse select on/off | Tab/Shift-Tab Move | Up/Down Select | Shift-←/→ H-scroll | Esc Close";
+    let help = "F1 New | F2 Edit | F3 Delete | F4 Save | F5 Test | Ctrl-O Load PEM file | F6 Next | F7 Prev | F9 Mouse select on/off | Tab/Shift-Tab Move | Up/Down Select | Shift-←/→ H-scroll | Esc Close";
     frame.render_widget(
         Paragraph::new(help).block(Block::default().borders(Borders::ALL).title("Actions")),
-        fields[5],
+        fields[6],
     );
 
     // Connection status/progress area (scrollable)
@@ -530,7 +639,7 @@ fn draw_env_modal(frame: &mut Frame, area: Rect, app: &AppState) {
     let conn_para = Paragraph::new(status_text)
         .block(conn_block)
         .scroll((app.env_conn_vscroll, 0));
-    frame.render_widget(conn_para, fields[6]);
+    frame.render_widget(conn_para, fields[7]);
 }
 
 fn caret_pos_in(area: Rect, text: &str, cursor: usize) -> (u16, u16) {
@@ -639,7 +748,7 @@ fn byte_index_to_line(line_starts: &[usize], byte_idx: usize) -> usize {
     lo
 }
 
-fn highlight_sql_line(s: &str) -> Vec<Span<'static>> {
+fn highlight_sql_line(s: &str, theme: &Theme) -> Vec<Span<'static>> {
     // Very small SQL-ish highlighter
     let mut spans: Vec<Span> = Vec::new();
     let mut word = String::new();
@@ -648,13 +757,13 @@ fn highlight_sql_line(s: &str) -> Vec<Span<'static>> {
         match ch {
             '\'' | '"' => {
                 if !word.is_empty() {
-                    push_word(&mut spans, &word);
+                    push_word(&mut spans, &word, theme);
                     word.clear();
                 }
                 in_string = !in_string;
                 spans.push(Span::styled(
                     ch.to_string(),
-                    Style::default().fg(Color::Yellow),
+                    Style::default().fg(theme.string),
                 ));
             }
             c if c.is_alphanumeric() || c == '_' => {
@@ -662,25 +771,25 @@ fn highlight_sql_line(s: &str) -> Vec<Span<'static>> {
             }
             _ => {
                 if !word.is_empty() {
-                    push_word(&mut spans, &word);
+                    push_word(&mut spans, &word, theme);
                     word.clear();
                 }
                 let color = if in_string {
-                    Color::Yellow
+                    theme.string
                 } else {
-                    Color::Gray
+                    theme.punctuation
                 };
                 spans.push(Span::styled(ch.to_string(), Style::default().fg(color)));
             }
         }
     }
     if !word.is_empty() {
-        push_word(&mut spans, &word);
+        push_word(&mut spans, &word, theme);
     }
     spans
 }
 
-fn push_word(spans: &mut Vec<Span<'static>>, w: &str) {
+fn push_word(spans: &mut Vec<Span<'static>>, w: &str, theme: &Theme) {
     let kw = [
         "select",
         "list",
@@ -703,26 +812,67 @@ fn push_word(spans: &mut Vec<Span<'static>>, w: &str) {
         spans.push(Span::styled(
             w.to_uppercase(),
             Style::default()
-                .fg(Color::LightCyan)
+                .fg(theme.keyword)
                 .add_modifier(Modifier::BOLD),
         ));
     } else if w.chars().all(|c| c.is_ascii_digit()) {
         spans.push(Span::styled(
             w.to_string(),
-            Style::default().fg(Color::Cyan),
+            Style::default().fg(theme.number),
         ));
     } else {
         spans.push(Span::raw(w.to_string()));
     }
 }
 
+/// Label of each result tab: its number and the topic it shows.
+fn tab_labels(app: &AppState) -> Vec<String> {
+    (0..app.tabs.len().max(1))
+        .map(|i| {
+            let (mode, topic) = if i == app.active_tab {
+                (app.tab.results_mode, app.tab.run_topic.as_deref())
+            } else {
+                (app.tabs[i].results_mode, app.tabs[i].run_topic.as_deref())
+            };
+            let name = match mode {
+                ResultsMode::TopicList => "topics",
+                ResultsMode::Messages => topic.unwrap_or("new"),
+            };
+            format!(" {} {} ", i + 1, name)
+        })
+        .collect()
+}
+
+fn draw_tab_bar(frame: &mut Frame, area: Rect, app: &AppState) {
+    if area.height == 0 {
+        return;
+    }
+    let active = Style::default()
+        .fg(app.theme.border_focused)
+        .add_modifier(Modifier::REVERSED | Modifier::BOLD);
+    let mut spans = Vec::new();
+    for (i, label) in tab_labels(app).into_iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::styled("│", Style::default().fg(app.theme.border)));
+        }
+        let style = if i == app.active_tab {
+            active
+        } else {
+            Style::default()
+        };
+        spans.push(Span::styled(label, style));
+    }
+    spans.push(Span::styled(
+        "  Ctrl-PageUp/PageDown switch, Ctrl-T new",
+        Style::default().fg(app.theme.border),
+    ));
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
 fn draw_results(frame: &mut Frame, area: Rect, app: &AppState) {
-    match app.results_mode {
+    match app.tab.results_mode {
         ResultsMode::Messages => {
-            let cols = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(68), Constraint::Percentage(32)])
-                .split(area);
+            let cols = home_cols(area, app);
             draw_table(frame, cols[0], app);
             draw_json_detail(frame, cols[1], app);
         }
@@ -733,14 +883,36 @@ fn draw_results(frame: &mut Frame, area: Rect, app: &AppState) {
 }
 
 fn draw_topics_results_table(frame: &mut Frame, area: Rect, app: &AppState) {
+    let mut state = TableState::default();
+    if !app.tab.topics_with_partitions.is_empty() {
+        state.select(Some(
+            app.tab
+                .selected_row
+                .min(app.tab.topics_with_partitions.len().saturating_sub(1)),
+        ));
+    }
+    frame.render_stateful_widget(topics_results_table(app), area, &mut state);
+
+    let total = app.tab.topics_with_partitions.len();
+    if total > 0 {
+        let mut vs =
+            ScrollbarState::new(total).position(app.tab.selected_row.min(total.saturating_sub(1)));
+        let vbar = Scrollbar::new(ScrollbarOrientation::VerticalRight);
+        frame.render_stateful_widget(vbar, area, &mut vs);
+    }
+}
+
+/// `LIST topics` results: one row per topic with its partition count.
+fn topics_results_table(app: &AppState) -> Table<'static> {
     let headers = vec![
         Cell::from(header_span("Topic")),
         Cell::from(header_span("Partitions")),
     ];
-    let rows: Vec<Row> = if app.topics_with_partitions.is_empty() {
+    let rows: Vec<Row> = if app.tab.topics_with_partitions.is_empty() {
         vec![Row::new(vec![Cell::from("No topics"), Cell::from("")])]
     } else {
-        app.topics_with_partitions
+        app.tab
+            .topics_with_partitions
             .iter()
             .map(|(topic, parts)| {
                 Row::new(vec![
@@ -750,12 +922,8 @@ fn draw_topics_results_table(frame: &mut Frame, area: Rect, app: &AppState) {
             })
             .collect()
     };
-    let border_style = if app.focus == Focus::Results {
-        Style::default().fg(Color::LightCyan)
-    } else {
-        Style::default().fg(Color::DarkGray)
-    };
-    let table = Table::new(
+    let border_style = app.theme.border_style(app.focus == Focus::Results);
+    Table::new(
         rows,
         [Constraint::Percentage(70), Constraint::Percentage(30)],
     )
@@ -767,23 +935,7 @@ fn draw_topics_results_table(frame: &mut Frame, area: Rect, app: &AppState) {
             .border_style(border_style),
     )
     .row_highlight_style(Style::default())
-    .column_spacing(2);
-    let mut state = TableState::default();
-    if !app.topics_with_partitions.is_empty() {
-        state.select(Some(
-            app.selected_row
-                .min(app.topics_with_partitions.len().saturating_sub(1)),
-        ));
-    }
-    frame.render_stateful_widget(table, area, &mut state);
-
-    let total = app.topics_with_partitions.len();
-    if total > 0 {
-        let mut vs =
-            ScrollbarState::new(total).position(app.selected_row.min(total.saturating_sub(1)));
-        let vbar = Scrollbar::new(ScrollbarOrientation::VerticalRight);
-        frame.render_stateful_widget(vbar, area, &mut vs);
-    }
+    .column_spacing(2)
 }
 
 fn draw_topics(frame: &mut Frame, area: Rect, app: &AppState) {
@@ -795,8 +947,18 @@ fn draw_topics(frame: &mut Frame, area: Rect, app: &AppState) {
             .map(|t| ListItem::new(t.clone()))
             .collect()
     };
-    let list = List::new(items).block(Block::default().borders(Borders::ALL).title("Topics"));
-    frame.render_widget(list, area);
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Topics (Enter: query topic)"),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default();
+    if !app.topics.is_empty() {
+        state.select(Some(app.info_selected.min(app.topics.len() - 1)));
+    }
+    frame.render_stateful_widget(list, area, &mut state);
 }
 
 fn draw_help_overlay(frame: &mut Frame, area: Rect, app: &AppState) {
@@ -804,7 +966,10 @@ fn draw_help_overlay(frame: &mut Frame, area: Rect, app: &AppState) {
     frame.render_widget(Clear, popup);
     let block = Block::default()
         .borders(Borders::ALL)
-        .title("Help")
+        .title(format!(
+            "Help  v vim keys: {}",
+            if app.vim_mode { "on" } else { "off" }
+        ))
         .border_style(Style::default().fg(Color::Yellow));
     let inner = block.inner(popup);
     frame.render_widget(block, popup);
@@ -829,6 +994,129 @@ fn draw_help_overlay(frame: &mut Frame, area: Rect, app: &AppState) {
     }
 }
 
+fn draw_headers_overlay(frame: &mut Frame, area: Rect, app: &AppState) {
+    let popup = centered_rect(60, 50, area);
+    frame.render_widget(Clear, popup);
+    let Some(env) = app.selected_message() else {
+        return;
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(
+            "Headers (p{} @ {})  Esc close",
+            env.partition, env.offset
+        ))
+        .border_style(Style::default().fg(Color::Yellow));
+    let rows = header_rows(&env.headers);
+    if rows.is_empty() {
+        let para = Paragraph::new("(no headers)").block(block);
+        frame.render_widget(para, popup);
+        return;
+    }
+    let table = Table::new(
+        rows.into_iter()
+            .map(|(name, value)| Row::new(vec![Cell::from(name), Cell::from(value)])),
+        [Constraint::Percentage(30), Constraint::Percentage(70)],
+    )
+    .header(Row::new(vec![header_span("Name"), header_span("Value")]))
+    .block(block);
+    frame.render_widget(table, popup);
+}
+
+/// The `c` overlay: one checkbox per selected column, in table order.
+fn draw_column_picker(frame: &mut Frame, area: Rect, app: &AppState, cursor: usize) {
+    let popup = centered_rect(40, 50, area);
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Columns  Space toggle, Esc close")
+        .border_style(Style::default().fg(Color::Yellow));
+    let rows = app
+        .tab
+        .selected_columns
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let mark = if app.column_visible(i) { "[x]" } else { "[ ]" };
+            let style = if i == cursor {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            Row::new(vec![
+                Cell::from(mark),
+                Cell::from(column_label(*item, &app.tab.column_aliases).to_string()),
+            ])
+            .style(style)
+        });
+    let table = Table::new(rows, [Constraint::Length(4), Constraint::Min(1)]).block(block);
+    frame.render_widget(table, popup);
+}
+
+/// Completion stats for the last run, anchored to the bottom-right corner so
+/// the results stay readable underneath.
+fn draw_scan_summary(frame: &mut Frame, area: Rect, summary: &ScanSummary) {
+    let rows = summary.rows();
+    let width = area.width.min(48);
+    let height = area.height.min(rows.len() as u16 + 2);
+    let popup = Rect {
+        x: area.x + area.width - width,
+        y: area.y + area.height.saturating_sub(height + 1),
+        width,
+        height,
+    };
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Scan summary  Esc close")
+        .border_style(Style::default().fg(Color::Green));
+    let table = Table::new(
+        rows.into_iter().map(|(label, value)| {
+            Row::new(vec![
+                Cell::from(Span::styled(
+                    label,
+                    Style::default().add_modifier(Modifier::BOLD),
+                )),
+                Cell::from(value),
+            ])
+        }),
+        [Constraint::Length(11), Constraint::Min(10)],
+    )
+    .block(block);
+    frame.render_widget(table, popup);
+}
+
+/// One-line input box centered over the screen.
+fn draw_prompt(frame: &mut Frame, area: Rect, title: &str, text: &str) {
+    let width = area.width.min(60);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + area.height.saturating_sub(3) / 2,
+        width,
+        height: area.height.min(3),
+    };
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title.to_string())
+        .border_style(Style::default().fg(Color::Yellow));
+    let para = Paragraph::new(Line::from(vec![
+        Span::raw(text.to_string()),
+        Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),
+    ]))
+    .block(block);
+    frame.render_widget(para, popup);
+}
+
+/// Display rows for the headers popup. UTF-8 text values are shown as-is;
+/// binary values as `0x`-prefixed hex.
+fn header_rows(headers: &[MessageHeader]) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|h| (h.key.clone(), h.display_value()))
+        .collect()
+}
+
 pub fn help_content_line_count() -> usize {
     build_help_lines().len()
 }
@@ -841,11 +1129,28 @@ fn build_help_lines() -> Vec<Line<'static>> {
     lines.push(Line::from(""));
 
     lines.push(heading_line("Home - Host bar"));
-    lines.push(Line::from("- Tab focus; Enter open envs; F2 Envs for full screen"));
+    lines.push(Line::from(
+        "- Tab focus; Enter open envs; F2 Envs for full screen",
+    ));
     lines.push(Line::from(""));
 
     lines.push(heading_line("Home - Query"));
     lines.push(Line::from("- Ctrl-Enter run current SELECT; Enter newline"));
+    lines.push(Line::from(
+        "- Ctrl-S save the statement under the cursor as a named query (same name overwrites)",
+    ));
+    lines.push(Line::from(
+        "- Ctrl-E export the shown rows to a .csv, .json or .ndjson file (~/.rkl/exports)",
+    ));
+    lines.push(Line::from(
+        "- Ctrl-T open a result tab; Ctrl-PageUp/PageDown switch tabs (other tabs keep running)",
+    ));
+    lines.push(Line::from(
+        "- Up/Down in an empty editor browse past queries (~/.rkl/history.json)",
+    ));
+    lines.push(Line::from(
+        "- Ctrl-F toggle follow: next runs start at the end and stream new messages",
+    ));
     lines.push(Line::from(
         "- Right accept autocomplete; Ctrl-N/P navigate autocomplete",
     ));
@@ -862,7 +1167,61 @@ fn build_help_lines() -> Vec<Line<'static>> {
         "- Arrows move selection; PageUp/PageDown step; Home/End jump",
     ));
     lines.push(Line::from(
-        "- Shift-Left/Right horizontal scroll; F5 copy value; F7 copy status",
+        "- Shift-Left/Right horizontal scroll; F5 copy value (Host bar: connection); F7 copy status",
+    ));
+    lines.push(Line::from(
+        "- f toggle flat path = value / tree details view",
+    ));
+    lines.push(Line::from(
+        "- m toggle pretty/compact JSON in the details pane (remembered)",
+    ));
+    lines.push(Line::from(
+        "- w toggle emphasis of the value fields the WHERE matched on",
+    ));
+    lines.push(Line::from(
+        "- Tab to the details pane, then Enter/Space fold/unfold the object or array at the cursor",
+    ));
+    lines.push(Line::from(
+        "- / filter the loaded rows by text in any cell (Enter keeps, Esc clears)",
+    ));
+    lines.push(Line::from(
+        "- g then a partition number and Enter: jump to that partition's first loaded row",
+    ));
+    lines.push(Line::from(
+        "- o then an offset and Enter: jump to the first row at or past it in the selected row's partition",
+    ));
+    lines.push(Line::from(
+        "- h inspect the selected message's headers (Esc closes)",
+    ));
+    lines.push(Line::from(
+        "- Esc dismisses the scan summary shown when a run completes",
+    ));
+    lines.push(Line::from(
+        "- p pin partition/offset/key columns so only the value scrolls",
+    ));
+    lines.push(Line::from(
+        "- < / > move the selected column left/right (display only)",
+    ));
+    lines.push(Line::from(
+        "- i show only the selected column (with Key); i again restores all",
+    ));
+    lines.push(Line::from(
+        "- c pick columns to hide/show (Space toggles); Left/Right skip hidden ones",
+    ));
+    lines.push(Line::from(
+        "- + / - widen or narrow the selected column (this session only)",
+    ));
+    lines.push(Line::from(
+        "- Vim keys (v in this help): j/k/l move, G last row, Ctrl-D/Ctrl-U page",
+    ));
+    lines.push(Line::from(
+        "- e run the errors-only preset on the current topic (errors_query under [tui] in ~/.rkl/config.toml)",
+    ));
+    lines.push(Line::from(
+        "- b mark the selected message as diff base; other values then show a diff (b again clears)",
+    ));
+    lines.push(Line::from(
+        "- Ctrl-Up/Down shrink/grow the editor; Ctrl-Left/Right resize table vs details (remembered)",
     ));
     lines.push(Line::from("- Mouse wheel scroll supported"));
     lines.push(Line::from(""));
@@ -870,7 +1229,12 @@ fn build_help_lines() -> Vec<Line<'static>> {
     lines.push(heading_line("Environments"));
     lines.push(Line::from("- F1 New, F2 Edit, F3 Delete"));
     lines.push(Line::from("- F4 Save, F5 Test, Tab/Shift-Tab move fields"));
-    lines.push(Line::from("- Up/Down select; F9 toggle mouse select; Esc close"));
+    lines.push(Line::from(
+        "- Ctrl-O in a PEM field loads the file whose path is typed there",
+    ));
+    lines.push(Line::from(
+        "- Up/Down select; F9 toggle mouse select; Esc close",
+    ));
     lines.push(Line::from("- Text areas accept typing and paste"));
     lines.push(Line::from(""));
 
@@ -880,10 +1244,29 @@ fn build_help_lines() -> Vec<Line<'static>> {
 
     lines.push(heading_line("Query syntax"));
     lines.push(Line::from(
-        "- SELECT columns FROM topic [WHERE expr] [ORDER BY timestamp ASC|DESC] [LIMIT n]",
+        "- SELECT columns FROM topic [WHERE expr] [GROUP BY key] [ORDER BY timestamp|offset|partition|key|value->path ASC|DESC, ...] [LIMIT n] [OFFSET n]",
+    ));
+    lines.push(Line::from(
+        "- JSON path via value->field->subfield, or a JSON Pointer: value#/items/0/sku",
+    ));
+    lines.push(Line::from(
+        "- Operators: =, !=, <>, CONTAINS, NOT CONTAINS, MATCHES '<regex>', LIKE 'user-%', IN ('a', 'b'), NOT <expr>, <, >, <=, >= (ranges: 100 < value->code < 500)",
+    ));
+    lines.push(Line::from(
+        "- Arrays: ANY(value->items)->status = 'failed', ALL(value->tags) != 'x'",
+    ));
+    lines.push(Line::from(
+        "- Presence: EXISTS(value->error) holds even when the field is null",
+    ));
+    lines.push(Line::from(
+        "- Derived: value_size (serialized length), value_fields (top-level keys) in SELECT and WHERE",
+    ));
+    lines.push(Line::from(
+        "- WHERE topic = 'name' matches the source topic",
+    ));
+    lines.push(Line::from(
+        "- Headers: SELECT headers; WHERE header->trace-id = 'abc'",
     ));
-    lines.push(Line::from("- JSON path via value->field->subfield"));
-    lines.push(Line::from("- Operators: =, !=, <>, CONTAINS"));
     lines.push(Line::from(""));
 
     lines.push(heading_line("Examples"));
@@ -905,7 +1288,9 @@ fn build_help_lines() -> Vec<Line<'static>> {
         "- Triggered after typing FROM and a space in a SELECT",
     ));
     lines.push(Line::from("- Fuzzy-matched suggestions for topics"));
-    lines.push(Line::from("- Right accepts; Ctrl-N/Ctrl-P move; Esc dismiss"));
+    lines.push(Line::from(
+        "- Right accepts; Ctrl-N/Ctrl-P move; Esc dismiss",
+    ));
     lines.push(Line::from(""));
 
     lines.push(heading_line("Help navigation"));
@@ -926,54 +1311,79 @@ fn heading_line(text: &'static str) -> Line<'static> {
 }
 
 fn draw_table(frame: &mut Frame, area: Rect, app: &AppState) {
-    let headers: Vec<Cell> = app
-        .selected_columns
+    let border_style = app.theme.border_style(app.focus == Focus::Results);
+    let mut title = if app.tab.pin_columns {
+        "Results (pinned)".to_string()
+    } else {
+        "Results".to_string()
+    };
+    if app.tab.follow {
+        title.push_str(" [follow]");
+    }
+    let shown_cols = app.shown_columns();
+    let hidden = app.tab.selected_columns.len() - shown_cols.len();
+    if hidden > 0 {
+        title.push_str(&format!(" ({} hidden)", hidden));
+    }
+    if !app.tab.row_filter.is_empty() {
+        title.push_str(&format!(
+            " /{}: {} of {}",
+            app.tab.row_filter,
+            app.shown_len(),
+            app.tab.rows.len()
+        ));
+    }
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .border_style(border_style);
+
+    let all_cols = shown_cols;
+    let value_cols: Vec<usize> = all_cols
         .iter()
-        .map(|col| Cell::from(header_span(column_label(col))))
+        .copied()
+        .filter(|&i| matches!(app.tab.selected_columns[i], SelectItem::Value))
         .collect();
-
-    // Create single-line rows with truncated previews; full JSON moves to right pane
-    let rows: Vec<Row> = app
-        .rows
+    let leading_cols: Vec<usize> = all_cols
         .iter()
-        .enumerate()
-        .map(|(i, env)| make_row(i, env, app))
+        .copied()
+        .filter(|i| !value_cols.contains(i))
         .collect();
 
-    let mut constraints: Vec<Constraint> =
-        app.selected_columns.iter().map(column_constraint).collect();
-    if let Some(last) = constraints.last_mut() {
-        *last = Constraint::Percentage(100);
-    } else {
-        constraints.push(Constraint::Percentage(100));
+    let mut state = TableState::default();
+    let total_rows = app.shown_len();
+    if total_rows > 0 {
+        state.select(Some(app.tab.selected_row.min(total_rows - 1)));
     }
 
-    let table = Table::new(rows, constraints)
-        .header(Row::new(headers).style(Style::default().add_modifier(Modifier::BOLD)))
-        .block({
-            let border_style = if app.focus == Focus::Results {
-                Style::default().fg(Color::LightCyan)
-            } else {
-                Style::default().fg(Color::DarkGray)
-            };
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Results")
-                .border_style(border_style)
-        })
-        .row_highlight_style(Style::default())
-        .column_spacing(1);
-
-    let mut state = TableState::default();
-    if !app.rows.is_empty() {
-        state.select(Some(app.selected_row.min(app.rows.len() - 1)));
+    if app.tab.pin_columns && !value_cols.is_empty() && !leading_cols.is_empty() {
+        // Pinned: leading columns in a fixed region, value column scrolls on its own.
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let natural = leading_cols
+            .iter()
+            .map(|&i| column_width(&app.tab.column_widths, app.tab.selected_columns[i]) + 1)
+            .sum::<u16>();
+        let (pinned_w, scroll_w) = pinned_split(inner.width, natural);
+        let regions = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(pinned_w), Constraint::Length(scroll_w)])
+            .split(inner);
+        let mut pinned_state = state.clone();
+        frame.render_stateful_widget(
+            results_table(app, &leading_cols),
+            regions[0],
+            &mut pinned_state,
+        );
+        frame.render_stateful_widget(results_table(app, &value_cols), regions[1], &mut state);
+    } else {
+        frame.render_stateful_widget(results_table(app, &all_cols).block(block), area, &mut state);
     }
-    frame.render_stateful_widget(table, area, &mut state);
 
     // Vertical scrollbar for table (binds to selected_row)
-    let total_rows = app.rows.len();
     if total_rows > 0 {
-        let mut vs = ScrollbarState::new(total_rows).position(app.selected_row.min(total_rows - 1));
+        let mut vs =
+            ScrollbarState::new(total_rows).position(app.tab.selected_row.min(total_rows - 1));
         let vbar = Scrollbar::new(ScrollbarOrientation::VerticalRight);
         frame.render_stateful_widget(vbar, area, &mut vs);
     }
@@ -987,74 +1397,128 @@ fn draw_table(frame: &mut Frame, area: Rect, app: &AppState) {
             .saturating_add(1);
         if h_content > 1 {
             let mut hs =
-                ScrollbarState::new(h_content).position(app.table_hscroll.min(h_content - 1));
+                ScrollbarState::new(h_content).position(app.tab.table_hscroll.min(h_content - 1));
             let hbar = Scrollbar::new(ScrollbarOrientation::HorizontalBottom);
             frame.render_stateful_widget(hbar, area, &mut hs);
         }
     }
 }
 
+/// Table over a subset of `selected_columns` (by index); the last column fills.
+fn results_table(app: &AppState, cols: &[usize]) -> Table<'static> {
+    let headers: Vec<Cell> = cols
+        .iter()
+        .map(|&i| {
+            Cell::from(
+                column_label(app.tab.selected_columns[i], &app.tab.column_aliases).to_string(),
+            )
+        })
+        .collect();
+
+    // Create single-line rows with truncated previews; full JSON moves to right pane
+    let now = std::time::Instant::now();
+    let rows: Vec<Row> = (0..app.shown_len())
+        .filter_map(|i| app.shown_row(i).map(|(idx, env)| (i, idx, env)))
+        .map(|(i, idx, env)| {
+            let row = make_row(i, env, app, cols);
+            match app.new_row_age(idx, now).and_then(new_row_style) {
+                Some(style) => row.style(style),
+                None => row,
+            }
+        })
+        .collect();
+
+    let mut constraints: Vec<Constraint> = cols
+        .iter()
+        .map(|&i| column_constraint(&app.tab.selected_columns[i], &app.tab.column_widths))
+        .collect();
+    if let Some(last) = constraints.last_mut() {
+        *last = Constraint::Percentage(100);
+    } else {
+        constraints.push(Constraint::Percentage(100));
+    }
+
+    Table::new(rows, constraints)
+        .header(Row::new(headers).style(Style::default().add_modifier(Modifier::BOLD)))
+        .row_highlight_style(Style::default())
+        .column_spacing(1)
+}
+
+/// Split `total` width between the pinned leading columns (wanting `natural`)
+/// and the scrolling value region, which always keeps at least a third.
+fn pinned_split(total: u16, natural: u16) -> (u16, u16) {
+    let pinned = natural.min(total - total / 3);
+    (pinned, total - pinned)
+}
+
 fn header_span(text: &str) -> Span<'_> {
     Span::styled(text, Style::default().add_modifier(Modifier::BOLD))
 }
 
-fn column_label(col: &SelectItem) -> &'static str {
+fn column_label(col: SelectItem, aliases: &[(SelectItem, String)]) -> &str {
+    if let Some(alias) = column_alias(aliases, col) {
+        return alias;
+    }
     match col {
+        SelectItem::Cluster => "Cluster",
         SelectItem::Partition => "Partition",
         SelectItem::Offset => "Offset",
         SelectItem::Timestamp => "Timestamp",
         SelectItem::Key => "Key",
         SelectItem::Value => "Value",
+        SelectItem::ValueSize => "Value size",
+        SelectItem::ValueFields => "Value fields",
+        SelectItem::Headers => "Headers",
+        SelectItem::Count => "Count",
     }
 }
 
-fn column_constraint(col: &SelectItem) -> Constraint {
-    match col {
-        SelectItem::Partition => Constraint::Length(10),
-        SelectItem::Offset => Constraint::Length(12),
-        SelectItem::Timestamp => Constraint::Length(26),
-        SelectItem::Key => Constraint::Length(30),
-        SelectItem::Value => Constraint::Length(30),
-    }
+fn column_constraint(col: &SelectItem, widths: &[(SelectItem, u16)]) -> Constraint {
+    Constraint::Length(column_width(widths, *col))
 }
 
-fn make_row(idx: usize, env: &MessageEnvelope, app: &AppState) -> Row<'static> {
-    let selected_row = idx == app.selected_row;
+fn make_row(idx: usize, env: &MessageEnvelope, app: &AppState, cols: &[usize]) -> Row<'static> {
+    let selected_row = idx == app.tab.selected_row;
     let mut cells = Vec::new();
-    for (col_idx, col) in app.selected_columns.iter().enumerate() {
+    for &col_idx in cols {
+        let col = &app.tab.selected_columns[col_idx];
         let text = match col {
             SelectItem::Value => {
                 let raw_value = env.value.as_deref().unwrap_or("null");
                 let preview = json_preview_minified(raw_value);
-                apply_hscroll(&preview, app.table_hscroll)
+                apply_hscroll(&preview, app.tab.table_hscroll)
             }
-            _ => column_raw_text(env, *col),
+            // Structured keys stay on one line here even with --pretty-key
+            SelectItem::Key if env.key.starts_with(['{', '[']) => json_preview_minified(&env.key),
+            _ => column_text(env, *col),
         };
         cells.push(style_cell(
             Cell::from(text),
-            selected_row && app.selected_col == col_idx,
+            selected_row && app.tab.selected_col == col_idx,
+            &app.theme,
         ));
     }
     Row::new(cells).height(1)
 }
 
-fn style_cell(mut cell: Cell<'static>, selected: bool) -> Cell<'static> {
-    if selected {
-        cell = cell.style(Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD));
-    }
-    cell
+/// Background for a row that arrived `age` ago: fades through darker shades
+/// over `NEW_ROW_HIGHLIGHT`, then none.
+fn new_row_style(age: std::time::Duration) -> Option<Style> {
+    let step = NEW_ROW_HIGHLIGHT.as_millis() / 3;
+    let bg = match age.as_millis() / step.max(1) {
+        0 => Color::Rgb(30, 90, 45),
+        1 => Color::Rgb(22, 60, 32),
+        2 => Color::Rgb(15, 35, 20),
+        _ => return None,
+    };
+    Some(Style::default().bg(bg))
 }
 
-fn fmt_ts(ms: i64) -> String {
-    if ms <= 0 {
-        return "0".to_string();
+fn style_cell(mut cell: Cell<'static>, selected: bool, theme: &Theme) -> Cell<'static> {
+    if selected {
+        cell = cell.style(theme.selected_cell_style());
     }
-    // Keep short human readable format
-    let secs = ms / 1000;
-    let tm = time::OffsetDateTime::from_unix_timestamp(secs as i64)
-        .unwrap_or_else(|_| time::OffsetDateTime::UNIX_EPOCH);
-    tm.format(&time::format_description::well_known::Rfc3339)
-        .unwrap_or_else(|_| ms.to_string())
+    cell
 }
 
 #[allow(dead_code)]
@@ -1063,7 +1527,7 @@ fn make_json_cell_and_height(s: &str) -> (Text<'static>, u16) {
     // If it isn't JSON, return plain text with height 1.
     match serde_json::from_str::<serde_json::Value>(s) {
         Ok(v) => {
-            let lines = json_to_highlighted_lines(&v);
+            let lines = json_to_highlighted_lines(&v, &[], &Theme::default());
             let h = lines.len().max(1) as u16;
             (Text::from(lines), h)
         }
@@ -1071,133 +1535,468 @@ fn make_json_cell_and_height(s: &str) -> (Text<'static>, u16) {
     }
 }
 
-fn json_to_highlighted_lines(v: &serde_json::Value) -> Vec<Line<'static>> {
-    // Pretty-print JSON into multiple lines with Postman-like colors:
-    // - keys: green, strings: yellow, numbers: cyan, booleans: magenta, null: dark gray, punctuation: gray
-    fn indent(depth: usize) -> Span<'static> {
-        Span::raw(" ".repeat(depth * 2))
-    }
-    fn punct(s: &str) -> Span<'static> {
-        Span::styled(s.to_string(), Style::default().fg(Color::Gray))
-    }
-    fn string_span(s: &str) -> Span<'static> {
-        Span::styled(format!("\"{}\"", s), Style::default().fg(Color::Yellow))
-    }
-    fn number_span(n: &serde_json::Number) -> Span<'static> {
-        Span::styled(n.to_string(), Style::default().fg(Color::Cyan))
-    }
-    fn bool_span(b: bool) -> Span<'static> {
-        Span::styled(b.to_string(), Style::default().fg(Color::Magenta))
+/// Pretty JSON lines, with `nodes[i]` the path of the object or array line
+/// `i` opens (or its key line, or its placeholder when folded) and `None` for
+/// other lines; the detail pane folds the node under its cursor.
+pub(super) struct JsonTree {
+    pub lines: Vec<Line<'static>>,
+    pub nodes: Vec<Option<Vec<String>>>,
+}
+
+impl JsonTree {
+    fn plain(lines: Vec<Line<'static>>) -> Self {
+        let nodes = vec![None; lines.len()];
+        Self { lines, nodes }
     }
-    fn null_span() -> Span<'static> {
-        Span::styled("null".to_string(), Style::default().fg(Color::DarkGray))
+}
+
+fn json_to_highlighted_lines(
+    v: &serde_json::Value,
+    emphasis: &[Vec<String>],
+    theme: &Theme,
+) -> Vec<Line<'static>> {
+    json_tree(v, emphasis, &HashSet::new(), theme).lines
+}
+
+/// `emphasis` lists paths (`value->a->b` as `["a", "b"]`, array items by
+/// index) whose entries are drawn bold and underlined. Non-empty objects and
+/// arrays at a `collapsed` path show as a single `{...}`/`[...]` line.
+fn json_tree(
+    v: &serde_json::Value,
+    emphasis: &[Vec<String>],
+    collapsed: &HashSet<Vec<String>>,
+    theme: &Theme,
+) -> JsonTree {
+    // Pretty-print JSON into multiple lines with Postman-like colors from the
+    // theme (by default green keys, yellow strings and cyan numbers)
+    struct Render<'a> {
+        emphasis: &'a [Vec<String>],
+        collapsed: &'a HashSet<Vec<String>>,
+        t: &'a Theme,
+        tree: JsonTree,
     }
 
-    fn render_scalar(val: &serde_json::Value) -> Vec<Span<'static>> {
-        match val {
-            serde_json::Value::String(s) => vec![string_span(s)],
-            serde_json::Value::Number(n) => vec![number_span(n)],
-            serde_json::Value::Bool(b) => vec![bool_span(*b)],
-            serde_json::Value::Null => vec![null_span()],
-            _ => vec![Span::raw(String::new())],
+    impl Render<'_> {
+        fn push(&mut self, spans: Vec<Span<'static>>, node: Option<&[String]>) {
+            self.tree.lines.push(Line::from(spans));
+            self.tree.nodes.push(node.map(<[String]>::to_vec));
         }
-    }
 
-    fn render_value(v: &serde_json::Value, depth: usize, out: &mut Vec<Line<'static>>) {
-        match v {
-            serde_json::Value::Null
-            | serde_json::Value::Bool(_)
-            | serde_json::Value::Number(_)
-            | serde_json::Value::String(_) => {
-                let mut spans = Vec::new();
-                spans.push(indent(depth));
-                spans.extend(render_scalar(v));
-                out.push(Line::from(spans));
+        fn punct(&self, s: &str) -> Span<'static> {
+            Span::styled(s.to_string(), Style::default().fg(self.t.punctuation))
+        }
+
+        /// Append `,` to the last line when `more` entries follow.
+        fn comma_if(&mut self, more: bool) {
+            if more {
+                let comma = self.punct(",");
+                if let Some(last) = self.tree.lines.last_mut() {
+                    last.spans.push(comma);
+                }
             }
-            serde_json::Value::Array(arr) => {
-                if arr.is_empty() {
-                    out.push(Line::from(vec![indent(depth), punct("[]")]));
-                } else {
-                    out.push(Line::from(vec![indent(depth), punct("[")]));
+        }
+
+        fn folded(&self, v: &serde_json::Value, path: &[String]) -> Option<&'static str> {
+            match v {
+                serde_json::Value::Array(a) if !a.is_empty() => Some("[...]"),
+                serde_json::Value::Object(m) if !m.is_empty() => Some("{...}"),
+                _ => None,
+            }
+            .filter(|_| self.collapsed.contains(path))
+        }
+
+        fn value(&mut self, v: &serde_json::Value, depth: usize, path: &mut Vec<String>) {
+            if let Some(placeholder) = self.folded(v, path) {
+                let spans = vec![indent(depth), self.punct(placeholder)];
+                self.push(spans, Some(path));
+                return;
+            }
+            match v {
+                serde_json::Value::Array(arr) if !arr.is_empty() => {
+                    let spans = vec![indent(depth), self.punct("[")];
+                    self.push(spans, Some(path));
                     for (i, item) in arr.iter().enumerate() {
-                        let before_len = out.len();
-                        render_value(item, depth + 1, out);
-                        // append comma to the last rendered line for this item if not last
-                        if i + 1 != arr.len() {
-                            let idx = out.len().saturating_sub(1);
-                            if let Some(last) = out.get_mut(idx) {
-                                last.spans.push(punct(","));
-                            }
-                        }
-                        // ensure at least one line was added
-                        if out.len() == before_len {
-                            out.push(Line::from(vec![indent(depth + 1), punct("")]));
-                        }
+                        path.push(i.to_string());
+                        self.value(item, depth + 1, path);
+                        path.pop();
+                        self.comma_if(i + 1 != arr.len());
                     }
-                    out.push(Line::from(vec![indent(depth), punct("]")]));
+                    let spans = vec![indent(depth), self.punct("]")];
+                    self.push(spans, None);
                 }
-            }
-            serde_json::Value::Object(map) => {
-                if map.is_empty() {
-                    out.push(Line::from(vec![indent(depth), punct("{}")]));
-                } else {
-                    out.push(Line::from(vec![indent(depth), punct("{")]));
-                    let len = map.len();
+                serde_json::Value::Object(map) if !map.is_empty() => {
+                    let spans = vec![indent(depth), self.punct("{")];
+                    self.push(spans, Some(path));
                     for (i, (k, val)) in map.iter().enumerate() {
-                        match val {
-                            serde_json::Value::Null
-                            | serde_json::Value::Bool(_)
-                            | serde_json::Value::Number(_)
-                            | serde_json::Value::String(_) => {
-                                let mut spans = Vec::new();
-                                spans.push(indent(depth + 1));
-                                spans.push(Span::styled(
-                                    format!("\"{}\"", k),
-                                    Style::default().fg(Color::Green),
-                                ));
-                                spans.push(punct(": "));
-                                spans.extend(render_scalar(val));
-                                if i + 1 != len {
-                                    spans.push(punct(","));
-                                }
-                                out.push(Line::from(spans));
-                            }
-                            _ => {
-                                // complex value: print key on its own line, then nested structure
-                                let mut key_line = Vec::new();
-                                key_line.push(indent(depth + 1));
-                                key_line.push(Span::styled(
-                                    format!("\"{}\"", k),
-                                    Style::default().fg(Color::Green),
-                                ));
-                                key_line.push(punct(":"));
-                                out.push(Line::from(key_line));
-
-                                let before_len = out.len();
-                                render_value(val, depth + 1, out);
-                                if i + 1 != len {
-                                    let idx = out.len().saturating_sub(1);
-                                    if let Some(last) = out.get_mut(idx) {
-                                        last.spans.push(punct(","));
-                                    }
-                                }
-                                if out.len() == before_len {
-                                    out.push(Line::from(vec![indent(depth + 1), punct("")]));
-                                }
-                            }
-                        }
+                        path.push(k.clone());
+                        self.entry(k, val, depth + 1, path);
+                        path.pop();
+                        self.comma_if(i + 1 != map.len());
                     }
-                    out.push(Line::from(vec![indent(depth), punct("}")]));
+                    let spans = vec![indent(depth), self.punct("}")];
+                    self.push(spans, None);
                 }
+                serde_json::Value::Array(_) => {
+                    let spans = vec![indent(depth), self.punct("[]")];
+                    self.push(spans, None);
+                }
+                serde_json::Value::Object(_) => {
+                    let spans = vec![indent(depth), self.punct("{}")];
+                    self.push(spans, None);
+                }
+                _ => {
+                    let spans = vec![indent(depth), json_scalar_span(v, self.t)];
+                    self.push(spans, None);
+                }
+            }
+        }
+
+        /// One `"key": value` member of an object; `path` ends with `key`.
+        fn entry(
+            &mut self,
+            k: &str,
+            val: &serde_json::Value,
+            depth: usize,
+            path: &mut Vec<String>,
+        ) {
+            let hit = self.emphasis.contains(path);
+            let mut spans = vec![
+                indent(depth),
+                Span::styled(format!("\"{}\"", k), Style::default().fg(self.t.key)),
+            ];
+            let nested = match val {
+                serde_json::Value::Array(_) | serde_json::Value::Object(_) => true,
+                _ => {
+                    spans.push(self.punct(": "));
+                    spans.push(json_scalar_span(val, self.t));
+                    false
+                }
+            };
+            let folded = self.folded(val, path);
+            if let Some(placeholder) = folded {
+                spans.push(self.punct(": "));
+                spans.push(self.punct(placeholder));
+            } else if nested {
+                // Complex value: key on its own line, then the nested structure
+                spans.push(self.punct(":"));
+            }
+            if hit {
+                emphasize(&mut spans[1..]);
+            }
+            let foldable = match val {
+                serde_json::Value::Array(a) => !a.is_empty(),
+                serde_json::Value::Object(m) => !m.is_empty(),
+                _ => false,
+            };
+            self.push(spans, foldable.then_some(path.as_slice()));
+            if nested && folded.is_none() {
+                self.value(val, depth, path);
             }
         }
     }
 
-    let mut lines: Vec<Line<'static>> = Vec::new();
-    render_value(v, 0, &mut lines);
+    fn indent(depth: usize) -> Span<'static> {
+        Span::raw(" ".repeat(depth * 2))
+    }
+
+    fn emphasize(spans: &mut [Span<'static>]) {
+        for span in spans {
+            span.style = span
+                .style
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+        }
+    }
+
+    let mut render = Render {
+        emphasis,
+        collapsed,
+        t: theme,
+        tree: JsonTree::plain(Vec::new()),
+    };
+    render.value(v, 0, &mut Vec::new());
+    render.tree
+}
+
+/// Flatten into `(a.b[0].c, leaf)` pairs sorted by path. Empty objects/arrays
+/// are kept as leaves so they don't silently disappear.
+fn flatten_json(v: &serde_json::Value) -> Vec<(String, &serde_json::Value)> {
+    fn collect<'a>(
+        v: &'a serde_json::Value,
+        path: String,
+        out: &mut Vec<(String, &'a serde_json::Value)>,
+    ) {
+        match v {
+            serde_json::Value::Object(map) if !map.is_empty() => {
+                for (k, val) in map {
+                    let next = if path.is_empty() {
+                        k.clone()
+                    } else {
+                        format!("{}.{}", path, k)
+                    };
+                    collect(val, next, out);
+                }
+            }
+            serde_json::Value::Array(arr) if !arr.is_empty() => {
+                for (i, item) in arr.iter().enumerate() {
+                    collect(item, format!("{}[{}]", path, i), out);
+                }
+            }
+            _ => out.push((path, v)),
+        }
+    }
+
+    let mut leaves = Vec::new();
+    collect(v, String::new(), &mut leaves);
+    leaves.sort_by(|a, b| a.0.cmp(&b.0));
+    leaves
+}
+
+/// A JSON leaf in its theme color; containers show as `[]`/`{}`.
+fn json_scalar_span(v: &serde_json::Value, theme: &Theme) -> Span<'static> {
+    let (text, color) = match v {
+        serde_json::Value::String(s) => (format!("\"{}\"", s), theme.string),
+        serde_json::Value::Number(n) => (n.to_string(), theme.number),
+        serde_json::Value::Bool(b) => (b.to_string(), theme.boolean),
+        serde_json::Value::Null => ("null".to_string(), theme.null),
+        serde_json::Value::Array(_) => ("[]".to_string(), theme.punctuation),
+        serde_json::Value::Object(_) => ("{}".to_string(), theme.punctuation),
+    };
+    Span::styled(text, Style::default().fg(color))
+}
+
+fn json_to_flat_lines(v: &serde_json::Value, theme: &Theme) -> Vec<Line<'static>> {
+    // One `a.b[0].c = value` line per leaf, sorted by path.
+    let leaves = flatten_json(v);
+    leaves
+        .into_iter()
+        .map(|(path, val)| {
+            let value_span = json_scalar_span(val, theme);
+            if path.is_empty() {
+                Line::from(vec![value_span])
+            } else {
+                Line::from(vec![
+                    Span::styled(path, Style::default().fg(theme.key)),
+                    Span::styled(" = ".to_string(), Style::default().fg(theme.punctuation)),
+                    value_span,
+                ])
+            }
+        })
+        .collect()
+}
+
+/// Structural diff of `base` -> `other` as `+ added`, `- removed` and
+/// `~ changed` lines over leaf paths (see `flatten_json`). Unchanged paths
+/// are omitted.
+fn json_diff_lines(base: &serde_json::Value, other: &serde_json::Value) -> Vec<Line<'static>> {
+    let before: std::collections::BTreeMap<String, &serde_json::Value> =
+        flatten_json(base).into_iter().collect();
+    let after: std::collections::BTreeMap<String, &serde_json::Value> =
+        flatten_json(other).into_iter().collect();
+    let compact = |v: &serde_json::Value| serde_json::to_string(v).unwrap_or_default();
+    let label = |path: &str| {
+        if path.is_empty() {
+            "(root)".to_string()
+        } else {
+            path.to_string()
+        }
+    };
+
+    let mut paths: Vec<&String> = before.keys().chain(after.keys()).collect();
+    paths.sort();
+    paths.dedup();
+    let mut lines = Vec::new();
+    for path in paths {
+        let line = match (before.get(path), after.get(path)) {
+            (Some(old), Some(new)) if old == new => continue,
+            (Some(old), Some(new)) => Line::styled(
+                format!("~ {}: {} -> {}", label(path), compact(old), compact(new)),
+                Style::default().fg(Color::Yellow),
+            ),
+            (Some(old), None) => Line::styled(
+                format!("- {} = {}", label(path), compact(old)),
+                Style::default().fg(Color::Red),
+            ),
+            (None, Some(new)) => Line::styled(
+                format!("+ {} = {}", label(path), compact(new)),
+                Style::default().fg(Color::Green),
+            ),
+            (None, None) => continue,
+        };
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        lines.push(Line::styled(
+            "(no differences)",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
     lines
 }
 
+/// When a diff base is marked and the Value cell of a different message is
+/// selected, the detail pane shows the diff instead of the JSON.
+fn detail_diff(app: &AppState) -> Option<(String, Vec<Line<'static>>)> {
+    let base = app.tab.diff_base.as_ref()?;
+    let env = app.selected_message()?;
+    let col = app.tab.selected_columns.get(app.tab.selected_col)?;
+    if !matches!(col, SelectItem::Value)
+        || (env.partition, env.offset) == (base.partition, base.offset)
+    {
+        return None;
+    }
+    let parse = |v: &Option<String>| {
+        v.as_deref()
+            .and_then(|s| serde_json::from_str::<serde_json::Value>(s).ok())
+    };
+    let lines = json_diff_lines(&parse(&base.value)?, &parse(&env.value)?);
+    Some((
+        format!("Details (diff vs p{}@{})", base.partition, base.offset),
+        lines,
+    ))
+}
+
+/// Detail pane body for the selected cell: any cell that parses as JSON (the
+/// value, or a composite key such as `{"tenant":1,"id":"a"}`) gets the JSON
+/// views; other text is shown verbatim.
+fn detail_cell_tree(raw: &str, app: &AppState) -> JsonTree {
+    match serde_json::from_str::<serde_json::Value>(raw) {
+        Ok(v) => detail_json_tree(&v, app),
+        Err(_) => JsonTree::plain(raw.lines().map(|l| Line::from(l.to_string())).collect()),
+    }
+}
+
+/// Only the indented view folds; flat and compact lines have no nodes.
+fn detail_json_tree(v: &serde_json::Value, app: &AppState) -> JsonTree {
+    if app.tab.detail_flat {
+        JsonTree::plain(json_to_flat_lines(v, &app.theme))
+    } else if !app.detail_pretty {
+        JsonTree::plain(vec![Line::from(
+            serde_json::to_string(v).unwrap_or_default(),
+        )])
+    } else {
+        json_tree(
+            v,
+            &where_emphasis(v, app),
+            &app.tab.collapsed_paths,
+            &app.theme,
+        )
+    }
+}
+
+/// The detail pane's lines for the current selection: the diff against a
+/// marked base, or the selected cell with its `WHERE` text marked.
+pub(super) fn detail_content(app: &AppState) -> JsonTree {
+    if let Some((_, lines)) = detail_diff(app) {
+        return JsonTree::plain(lines);
+    }
+    let Some(raw) = selected_cell_for_detail(app).1 else {
+        return JsonTree::plain(Vec::new());
+    };
+    let needles = detail_needles(app);
+    let mut tree = detail_cell_tree(&raw, app);
+    tree.lines = tree
+        .lines
+        .into_iter()
+        .map(|line| mark_needles(line, &needles))
+        .collect();
+    tree
+}
+
+/// Value paths of the `WHERE` comparisons that held for the selected message,
+/// so the detail pane can show why it matched.
+fn where_emphasis(v: &serde_json::Value, app: &AppState) -> Vec<Vec<String>> {
+    let (Some(expr), Some(env)) = (app.tab.last_where.as_ref(), app.selected_message()) else {
+        return Vec::new();
+    };
+    if !app.detail_emphasis
+        || !matches!(
+            app.tab.selected_columns.get(app.tab.selected_col),
+            Some(SelectItem::Value)
+        )
+    {
+        return Vec::new();
+    }
+    expr.matched_paths(&MessageView::of(env, v))
+        .into_iter()
+        .filter(|p| p.root == RootPath::Value && !p.segments.is_empty())
+        .map(|p| p.segments)
+        .collect()
+}
+
+/// `CONTAINS`/`LIKE` text of the last run's `WHERE` to mark in the detail
+/// pane; off along with the rest of the `w` emphasis.
+fn detail_needles(app: &AppState) -> Vec<String> {
+    match &app.tab.last_where {
+        Some(expr) if app.detail_emphasis => expr.highlight_needles(),
+        _ => Vec::new(),
+    }
+}
+
+/// Byte ranges of `text` inside any of `needles`, sorted with overlaps merged.
+/// Case-sensitive, like `CONTAINS` itself.
+fn needle_ranges(text: &str, needles: &[String]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = needles
+        .iter()
+        .filter(|n| !n.is_empty())
+        .flat_map(|n| text.match_indices(n.as_str()))
+        .map(|(i, m)| (i, i + m.len()))
+        .collect();
+    ranges.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// `line` with its needle matches drawn reversed. Matches are found on the
+/// whole line, so one may cross span boundaries; spans are split at the match
+/// edges and otherwise keep their styles.
+fn mark_needles(line: Line<'static>, needles: &[String]) -> Line<'static> {
+    let text: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+    let ranges = needle_ranges(&text, needles);
+    if ranges.is_empty() {
+        return line;
+    }
+    let Line {
+        style,
+        alignment,
+        spans: old,
+    } = line;
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    for span in old {
+        let end = offset + span.content.len();
+        let mut cut = offset;
+        for &(start, stop) in &ranges {
+            if stop <= cut || start >= end {
+                continue;
+            }
+            let (start, stop) = (start.max(cut), stop.min(end));
+            if start > cut {
+                spans.push(Span::styled(text[cut..start].to_string(), span.style));
+            }
+            spans.push(Span::styled(
+                text[start..stop].to_string(),
+                span.style.add_modifier(Modifier::REVERSED),
+            ));
+            cut = stop;
+        }
+        if cut < end {
+            spans.push(Span::styled(text[cut..end].to_string(), span.style));
+        }
+        offset = end;
+    }
+    Line {
+        style,
+        alignment,
+        spans,
+    }
+}
+
 fn json_preview_minified(s: &str) -> String {
     match serde_json::from_str::<serde_json::Value>(s) {
         Ok(v) => serde_json::to_string(&v).unwrap_or_else(|_| s.to_string()),
@@ -1215,49 +2014,29 @@ fn apply_hscroll(s: &str, offset: usize) -> String {
     s.chars().skip(offset).collect()
 }
 
-fn column_raw_text(env: &MessageEnvelope, col: SelectItem) -> String {
-    match col {
-        SelectItem::Partition => env.partition.to_string(),
-        SelectItem::Offset => env.offset.to_string(),
-        SelectItem::Timestamp => fmt_ts(env.timestamp_ms),
-        SelectItem::Key => env.key.clone(),
-        SelectItem::Value => env.value.as_deref().unwrap_or("null").to_string(),
-    }
-}
-
-fn column_width_hint(col: SelectItem) -> usize {
-    match col {
-        SelectItem::Partition => 10,
-        SelectItem::Offset => 12,
-        SelectItem::Timestamp => 26,
-        SelectItem::Key => 30,
-        SelectItem::Value => 40,
-    }
-}
-
 fn has_value_column(app: &AppState) -> bool {
-    app.selected_columns
-        .iter()
-        .any(|c| matches!(c, SelectItem::Value))
+    app.shown_columns()
+        .into_iter()
+        .any(|c| app.tab.selected_columns[c] == SelectItem::Value)
 }
 
 fn estimate_table_content_width(app: &AppState) -> usize {
     // Approximate widths of fixed columns + spacing + average key/value preview length
     let mut fixed = 0usize;
-    for (idx, col) in app.selected_columns.iter().enumerate() {
+    for (idx, c) in app.shown_columns().into_iter().enumerate() {
         if idx > 0 {
             fixed = fixed.saturating_add(1);
         }
-        match col {
+        match app.tab.selected_columns[c] {
             SelectItem::Value => {}
-            _ => fixed = fixed.saturating_add(column_width_hint(*col)),
+            col => fixed = fixed.saturating_add(column_width(&app.tab.column_widths, col).into()),
         }
     }
     if !has_value_column(app) {
         return fixed;
     }
     let mut max_preview = 0usize;
-    for env in &app.rows {
+    for env in &app.tab.rows {
         let raw = env.value.as_deref().unwrap_or("null");
         let p = json_preview_minified(raw);
         max_preview = max_preview.max(p.chars().count());
@@ -1268,23 +2047,40 @@ fn estimate_table_content_width(app: &AppState) -> usize {
 fn draw_json_detail(frame: &mut Frame, area: Rect, app: &AppState) {
     // Show the currently selected cell content with wrapping and vertical scroll
     let (title_suffix, raw) = selected_cell_for_detail(app);
-    let title = format!("Details ({})", title_suffix);
-    let block = Block::default().borders(Borders::ALL).title(title);
+    let diff = detail_diff(app);
+    let title = if let Some((ref diff_title, _)) = diff {
+        diff_title.clone()
+    } else if app.tab.detail_flat {
+        format!("Details ({}, flat)", title_suffix)
+    } else if !app.detail_pretty {
+        format!("Details ({}, compact)", title_suffix)
+    } else {
+        format!("Details ({})", title_suffix)
+    };
+    let focused = app.focus == Focus::Detail;
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .border_style(app.theme.border_style(focused));
     let inner_area = block.inner(area);
     frame.render_widget(block, area);
 
-    // Build Text using existing highlighter
-    let text: Text = match raw.as_deref() {
-        Some(s) => match serde_json::from_str::<serde_json::Value>(s) {
-            Ok(v) => Text::from(json_to_highlighted_lines(&v)),
-            Err(_) => Text::from(s.to_string()),
-        },
-        None => Text::from(""),
+    let mut lines = match diff {
+        Some((_, lines)) => lines,
+        None if raw.is_some() => detail_content(app).lines,
+        None => Vec::new(),
     };
+    if focused && !lines.is_empty() {
+        let cursor = app.tab.detail_cursor.min(lines.len() - 1);
+        let bg = app.theme.selection.unwrap_or(Color::DarkGray);
+        lines[cursor].style = lines[cursor].style.bg(bg);
+    }
+    let content_len = lines.len();
+    let text = Text::from(lines);
 
     let para = Paragraph::new(text)
         .wrap(Wrap { trim: false })
-        .scroll((app.json_vscroll, 0));
+        .scroll((app.tab.json_vscroll, 0));
     frame.render_widget(para, inner_area);
 
     // Draw Copy button at top-right of inner area
@@ -1311,34 +2107,395 @@ fn draw_json_detail(frame: &mut Frame, area: Rect, app: &AppState) {
     }
 
     // Vertical scrollbar for JSON
-    // Estimate content length by lines (simple; Paragraph wrap may change it, but this is sufficient)
-    let content_len = match raw.as_deref() {
-        Some(s) => match serde_json::from_str::<serde_json::Value>(s) {
-            Ok(v) => json_to_highlighted_lines(&v).len(),
-            Err(_) => s.lines().count(),
-        },
-        None => 0,
-    };
+    // Content length by lines (Paragraph wrap may change it, but this is sufficient)
     if content_len > 0 {
-        let mut vs = ScrollbarState::new(content_len)
-            .position(app.json_vscroll.min((content_len.saturating_sub(1)) as u16) as usize);
+        let mut vs = ScrollbarState::new(content_len).position(
+            app.tab
+                .json_vscroll
+                .min((content_len.saturating_sub(1)) as u16) as usize,
+        );
         let vbar = Scrollbar::new(ScrollbarOrientation::VerticalRight);
         frame.render_stateful_widget(vbar, area, &mut vs);
     }
 }
 
 fn selected_cell_for_detail(app: &AppState) -> (String, Option<String>) {
-    if app.rows.is_empty() || app.selected_columns.is_empty() {
+    let (Some(env), false) = (app.selected_envelope(), app.tab.selected_columns.is_empty()) else {
         return ("none".to_string(), None);
-    }
-    let idx = app.selected_row.min(app.rows.len() - 1);
-    let env = &app.rows[idx];
+    };
     let col_idx = app
+        .tab
         .selected_col
-        .min(app.selected_columns.len().saturating_sub(1));
-    let col = app.selected_columns[col_idx];
+        .min(app.tab.selected_columns.len().saturating_sub(1));
+    let col = app.tab.selected_columns[col_idx];
     (
-        column_label(&col).to_string(),
-        Some(column_raw_text(env, col)),
+        column_label(col, &app.tab.column_aliases).to_string(),
+        Some(column_text(env, col)),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::app::Tab;
+
+    #[test]
+    fn json_keys_render_highlighted_in_the_detail_pane() {
+        let row = |key: &str| MessageEnvelope::at(0).with_key(key).with_value("{}");
+        let mut app = AppState {
+            tab: Tab {
+                rows: vec![row(r#"{"tenant":7,"id":"a-1"}"#), row("plain-key")],
+                selected_columns: vec![SelectItem::Key, SelectItem::Value],
+                ..Default::default()
+            },
+            detail_pretty: true,
+            ..Default::default()
+        };
+        let (label, raw) = selected_cell_for_detail(&app);
+        assert_eq!(label, "Key");
+        let lines = detail_cell_tree(&raw.unwrap(), &app).lines;
+        let text: Vec<String> = lines.iter().map(line_text).collect();
+        assert_eq!(text, ["{", "  \"tenant\": 7,", "  \"id\": \"a-1\"", "}"]);
+        // Highlighted: the number is coloured like any JSON value
+        assert!(
+            lines[1]
+                .spans
+                .iter()
+                .any(|s| s.content == "7" && s.style.fg == Some(Color::Cyan))
+        );
+
+        app.tab.selected_row = 1;
+        let (_, raw) = selected_cell_for_detail(&app);
+        let lines = detail_cell_tree(&raw.unwrap(), &app).lines;
+        assert_eq!(
+            lines.iter().map(line_text).collect::<Vec<_>>(),
+            ["plain-key"]
+        );
+        assert!(lines[0].spans.iter().all(|s| s.style == Style::default()));
+    }
+
+    #[test]
+    fn width_overrides_replace_the_default_constraints() {
+        let mut app = AppState {
+            tab: Tab {
+                selected_columns: vec![SelectItem::Key, SelectItem::Offset],
+                column_widths: vec![(SelectItem::Key, 44)],
+                selected_col: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            column_constraint(&SelectItem::Key, &app.tab.column_widths),
+            Constraint::Length(44)
+        );
+        assert_eq!(
+            column_constraint(&SelectItem::Offset, &app.tab.column_widths),
+            Constraint::Length(12)
+        );
+
+        // Narrowing starts from the default and stops at the minimum
+        app.adjust_column_width(-2);
+        assert_eq!(
+            column_constraint(&SelectItem::Offset, &app.tab.column_widths),
+            Constraint::Length(10)
+        );
+        app.adjust_column_width(-100);
+        assert_eq!(
+            column_constraint(&SelectItem::Offset, &app.tab.column_widths),
+            Constraint::Length(4)
+        );
+    }
+
+    #[test]
+    fn folded_paths_render_placeholders() {
+        let v: serde_json::Value =
+            serde_json::from_str(r#"{"a":{"x":1},"items":[{"id":1},{"id":2},[]],"z":{}}"#).unwrap();
+        let collapsed: HashSet<Vec<String>> =
+            [vec!["a".to_string()], vec!["items".into(), "1".into()]]
+                .into_iter()
+                .collect();
+        let tree = json_tree(&v, &[], &collapsed, &Theme::default());
+        let text: Vec<String> = tree
+            .lines
+            .iter()
+            .map(|l| l.spans.iter().map(|s| s.content.as_ref()).collect())
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "{",
+                "  \"a\": {...},",
+                "  \"items\":",
+                "  [",
+                "    {",
+                "      \"id\": 1",
+                "    },",
+                "    {...},",
+                "    []",
+                "  ],",
+                "  \"z\":",
+                "  {}",
+                "}",
+            ]
+        );
+        // Folded lines keep their node so they can be unfolded; empty
+        // containers and leaves have nothing to fold
+        let path = |p: &[&str]| Some(p.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+        assert_eq!(tree.nodes[0], path(&[]));
+        assert_eq!(tree.nodes[1], path(&["a"]));
+        assert_eq!(tree.nodes[2], path(&["items"]));
+        assert_eq!(tree.nodes[3], path(&["items"]));
+        assert_eq!(tree.nodes[4], path(&["items", "0"]));
+        assert_eq!(tree.nodes[5], None);
+        assert_eq!(tree.nodes[7], path(&["items", "1"]));
+        assert_eq!(tree.nodes[8], None);
+        assert_eq!(tree.nodes[10], None);
+        assert_eq!(tree.nodes[11], None);
+    }
+
+    #[test]
+    fn where_needles_split_detail_spans() {
+        let ast = crate::query::parse_query(
+            "SELECT value FROM t WHERE value->msg CONTAINS 'err' \
+             AND (key LIKE 'ord\\_%-eu%' OR NOT value CONTAINS 'skip')",
+        )
+        .unwrap();
+        let needles = ast.r#where.unwrap().highlight_needles();
+        assert_eq!(needles, ["err", "ord_", "-eu"]);
+
+        let line = Line::from(vec![
+            Span::styled("\"msg\"", Style::default().fg(Color::Green)),
+            Span::raw(": "),
+            Span::styled("\"an error, ord_1-eu\"", Style::default().fg(Color::Yellow)),
+        ]);
+        // A match may cross span boundaries; overlapping ones merge
+        let marked = mark_needles(
+            line,
+            &[
+                "g\": \"a".to_string(),
+                "rror".to_string(),
+                "err".to_string(),
+            ],
+        );
+        let parts: Vec<(&str, bool)> = marked
+            .spans
+            .iter()
+            .map(|s| {
+                (
+                    s.content.as_ref(),
+                    s.style.add_modifier.contains(Modifier::REVERSED),
+                )
+            })
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                ("\"ms", false),
+                ("g\"", true),
+                (": ", true),
+                ("\"a", true),
+                ("n ", false),
+                ("error", true),
+                (", ord_1-eu\"", false),
+            ]
+        );
+        assert_eq!(marked.spans[5].style.fg, Some(Color::Yellow));
+
+        // Case-sensitive, like CONTAINS
+        let plain = Line::from("ERROR");
+        assert_eq!(mark_needles(plain.clone(), &needles), plain);
+    }
+
+    #[test]
+    fn results_header_shows_column_aliases() {
+        use ratatui::{buffer::Buffer, widgets::Widget};
+        let app = AppState {
+            tab: Tab {
+                selected_columns: vec![SelectItem::Partition, SelectItem::Key],
+                column_aliases: vec![(SelectItem::Key, "order_id".to_string())],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let area = Rect::new(0, 0, 40, 1);
+        let mut buf = Buffer::empty(area);
+        Widget::render(results_table(&app, &[0, 1]), area, &mut buf);
+        let header: String = buf.content().iter().map(|c| c.symbol()).collect();
+        assert!(header.contains("Partition"));
+        assert!(header.contains("order_id"));
+        assert!(!header.contains("Key"));
+    }
+
+    #[test]
+    fn topics_results_table_shows_partition_counts() {
+        use ratatui::{buffer::Buffer, widgets::Widget};
+        let app = AppState {
+            tab: Tab {
+                results_mode: ResultsMode::TopicList,
+                topics_with_partitions: vec![
+                    ("orders".to_string(), 12),
+                    ("payments".to_string(), 3),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let area = Rect::new(0, 0, 40, 5);
+        let mut buf = Buffer::empty(area);
+        Widget::render(topics_results_table(&app), area, &mut buf);
+        let line = |y: u16| -> String {
+            (0..area.width)
+                .map(|x| buf[(x, y)].symbol().to_string())
+                .collect()
+        };
+        assert!(line(1).contains("Topic") && line(1).contains("Partitions"));
+        assert!(line(2).contains("orders") && line(2).contains("12"));
+        assert!(line(3).contains("payments") && line(3).contains("3"));
+    }
+
+    #[test]
+    fn new_rows_fade_out_over_the_highlight_window() {
+        use std::time::Duration;
+        let bg = |ms: u64| new_row_style(Duration::from_millis(ms)).and_then(|s| s.bg);
+        let fresh = bg(0);
+        let older = bg(NEW_ROW_HIGHLIGHT.as_millis() as u64 / 2);
+        let oldest = bg(NEW_ROW_HIGHLIGHT.as_millis() as u64 - 1);
+        assert!(fresh.is_some() && older.is_some() && oldest.is_some());
+        assert_ne!(fresh, older);
+        assert_ne!(older, oldest);
+        assert_eq!(bg(NEW_ROW_HIGHLIGHT.as_millis() as u64), None);
+    }
+
+    fn line_text(line: &Line) -> String {
+        line.spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn sparkline_scales_to_largest_sample() {
+        assert_eq!(sparkline(&[0, 1, 2, 4, 8]), "▁▂▃▅█");
+        assert_eq!(sparkline(&[0, 0, 0]), "▁▁▁");
+        assert_eq!(sparkline(&[5, 5]), "██");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed_fields() {
+        let base = serde_json::json!({
+            "order": {"id": 7, "status": "new", "note": "x"},
+            "items": [1, 2]
+        });
+        let other = serde_json::json!({
+            "order": {"id": 7, "status": "paid", "paid_at": "2024-01-01"},
+            "items": [1, 2]
+        });
+        let rendered: Vec<String> = json_diff_lines(&base, &other)
+            .iter()
+            .map(line_text)
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "- order.note = \"x\"",
+                "+ order.paid_at = \"2024-01-01\"",
+                "~ order.status: \"new\" -> \"paid\"",
+            ]
+        );
+        let same: Vec<String> = json_diff_lines(&base, &base)
+            .iter()
+            .map(line_text)
+            .collect();
+        assert_eq!(same, vec!["(no differences)"]);
+    }
+
+    #[test]
+    fn pinned_split_keeps_a_third_for_the_value_region() {
+        // Wide terminal: pinned columns get their natural width
+        assert_eq!(pinned_split(120, 54), (54, 66));
+        // Narrow terminal: pinned region is capped so the value keeps a third
+        assert_eq!(pinned_split(60, 54), (40, 20));
+        assert_eq!(pinned_split(0, 54), (0, 0));
+    }
+
+    #[test]
+    fn header_rows_show_text_and_hex_for_binary() {
+        let headers = vec![
+            MessageHeader {
+                key: "content-type".to_string(),
+                value: Some(b"application/json".to_vec()),
+            },
+            MessageHeader {
+                key: "trace".to_string(),
+                value: Some(vec![0x00, 0xff, 0x10]),
+            },
+            MessageHeader {
+                key: "empty".to_string(),
+                value: None,
+            },
+        ];
+        assert_eq!(
+            header_rows(&headers),
+            vec![
+                ("content-type".to_string(), "application/json".to_string()),
+                ("trace".to_string(), "0x00ff10 (3 bytes)".to_string()),
+                ("empty".to_string(), "null".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn flat_rendering_lists_sorted_leaf_paths() {
+        let v = serde_json::json!({
+            "payload": {"method": "PUT", "tags": ["a", "b"], "meta": {}},
+            "code": 42,
+            "ok": true,
+            "none": null
+        });
+        let rendered: Vec<String> = json_to_flat_lines(&v, &Theme::default())
+            .iter()
+            .map(line_text)
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "code = 42",
+                "none = null",
+                "ok = true",
+                "payload.meta = {}",
+                "payload.method = \"PUT\"",
+                "payload.tags[0] = \"a\"",
+                "payload.tags[1] = \"b\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn hidden_columns_leave_the_scroll_width_estimate() {
+        let mut app = AppState {
+            tab: Tab {
+                rows: vec![MessageEnvelope::at(0).with_value("{\"a\":1}")],
+                selected_columns: vec![SelectItem::Partition, SelectItem::Key, SelectItem::Value],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let all = estimate_table_content_width(&app);
+        app.toggle_column_visible(1);
+        assert_eq!(
+            estimate_table_content_width(&app),
+            all - 1 - usize::from(column_width(&app.tab.column_widths, SelectItem::Key))
+        );
+        assert!(has_value_column(&app));
+        app.toggle_column_visible(2);
+        assert!(!has_value_column(&app));
+    }
+
+    #[test]
+    fn flat_rendering_of_scalar_root_has_no_path() {
+        let rendered: Vec<String> = json_to_flat_lines(&serde_json::json!("x"), &Theme::default())
+            .iter()
+            .map(line_text)
+            .collect();
+        assert_eq!(rendered, vec!["\"x\""]);
+    }
+}